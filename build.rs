@@ -4,6 +4,7 @@ use gl_generator::{Registry, Api, Profile, Fallbacks};
 use std::env;
 use std::fs::File;
 use std::path::PathBuf;
+use std::process::Command;
 
 fn main() {
     let target = env::var("TARGET").unwrap();
@@ -11,6 +12,36 @@ fn main() {
 
     println!("cargo:rerun-if-changed=build.rs");
 
+    // X11 windows need an API to create their OpenGL context
+    if env::var("CARGO_FEATURE_X11").is_ok() && env::var("CARGO_FEATURE_GLX").is_err() &&
+       env::var("CARGO_FEATURE_EGL").is_err()
+    {
+        panic!("The `x11` feature requires the `glx` feature, the `egl` feature, or both");
+    }
+    println!("cargo:rerun-if-env-changed=GLUTIN_EGL_LINK_KIND");
+    println!("cargo:rerun-if-env-changed=GLUTIN_EGL_LIB_DIR");
+
+    // with the `static-egl` feature, EGL and GLES are linked at compile-time instead of being
+    // loaded at runtime
+    let static_egl = env::var("CARGO_FEATURE_STATIC_EGL").is_ok() && !target.contains("android")
+                     && !target.contains("ios");
+
+    if static_egl {
+        // `static` or `dylib`
+        let kind = env::var("GLUTIN_EGL_LINK_KIND").unwrap_or_else(|_| "dylib".to_owned());
+        if let Ok(dir) = env::var("GLUTIN_EGL_LIB_DIR") {
+            println!("cargo:rustc-link-search=native={}", dir);
+        }
+
+        let (egl, gles) = if target.contains("windows") {
+            ("libEGL", "libGLESv2")
+        } else {
+            ("EGL", "GLESv2")
+        };
+        println!("cargo:rustc-link-lib={}={}", kind, egl);
+        println!("cargo:rustc-link-lib={}={}", kind, gles);
+    }
+
     if target.contains("windows") {
         let mut file = File::create(&dest.join("wgl_bindings.rs")).unwrap();
         Registry::new(Api::Wgl, (1, 0), Profile::Core, Fallbacks::All, [])
@@ -25,9 +56,12 @@ fn main() {
                           "WGL_ARB_context_flush_control",
                           "WGL_ARB_extensions_string",
                           "WGL_ARB_framebuffer_sRGB",
+                          "WGL_ARB_make_current_read",
                           "WGL_ARB_multisample",
+                          "WGL_ARB_pbuffer",
                           "WGL_ARB_pixel_format",
                           "WGL_ARB_pixel_format_float",
+                          "WGL_ARB_render_texture",
                           "WGL_EXT_create_context_es2_profile",
                           "WGL_EXT_extensions_string",
                           "WGL_EXT_framebuffer_sRGB",
@@ -36,7 +70,7 @@ fn main() {
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
 
         let mut file = File::create(&dest.join("egl_bindings.rs")).unwrap();
-        Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
+        let registry = Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
@@ -47,11 +81,21 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
-                      ])
-            .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
+                          "EGL_KHR_partial_update",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
+                      ]);
+        if static_egl {
+            registry.write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
+        } else {
+            registry.write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
+        }
     }
 
-    if target.contains("linux") || target.contains("dragonfly") || target.contains("freebsd") || target.contains("openbsd") {
+    if target.contains("linux") || target.contains("dragonfly") || target.contains("freebsd") ||
+       target.contains("openbsd") || target.contains("netbsd")
+    {
         let mut file = File::create(&dest.join("glx_bindings.rs")).unwrap();
         Registry::new(Api::Glx, (1, 4), Profile::Core, Fallbacks::All, [])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
@@ -67,12 +111,13 @@ fn main() {
                           "GLX_EXT_framebuffer_sRGB",
                           "GLX_ARB_multisample",
                           "GLX_EXT_swap_control",
-                          "GLX_SGI_swap_control"
+                          "GLX_SGI_swap_control",
+                          "GLX_MESA_query_renderer",
                       ])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
 
         let mut file = File::create(&dest.join("egl_bindings.rs")).unwrap();
-        Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
+        let registry = Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
@@ -83,8 +128,16 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
-                      ])
-            .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
+                          "EGL_KHR_partial_update",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
+                      ]);
+        if static_egl {
+            registry.write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
+        } else {
+            registry.write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
+        }
     }
 
     if target.contains("android") {
@@ -100,6 +153,10 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
+                          "EGL_KHR_partial_update",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
                       ])
             .write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
     }
@@ -117,6 +174,10 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
+                          "EGL_KHR_partial_update",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
                       ])
             .write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
 
@@ -125,10 +186,57 @@ fn main() {
             .write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
     }
 
+    if target.contains("haiku") && env::var("CARGO_FEATURE_HAIKU").is_ok() {
+        // the `BGLView` API is C++ only
+        let cxx = env::var("CXX").unwrap_or_else(|_| "c++".to_owned());
+        let object = dest.join("glview.o");
+        let status = Command::new(&cxx)
+                             .args(&["-c", "-fPIC", "-O2", "src/api/haiku/glview.cpp", "-o"])
+                             .arg(&object)
+                             .status()
+                             .expect("Failed to run the C++ compiler");
+        assert!(status.success(), "Failed to compile src/api/haiku/glview.cpp");
+
+        let status = Command::new("ar").arg("crs").arg(dest.join("libglutin_haiku.a"))
+                                       .arg(&object).status().expect("Failed to run ar");
+        assert!(status.success(), "Failed to archive src/api/haiku/glview.cpp");
+
+        println!("cargo:rerun-if-changed=src/api/haiku/glview.cpp");
+        println!("cargo:rustc-link-search=native={}", dest.display());
+        println!("cargo:rustc-link-lib=static=glutin_haiku");
+        println!("cargo:rustc-link-lib=dylib=be");
+        println!("cargo:rustc-link-lib=dylib=GL");
+        println!("cargo:rustc-link-lib=dylib=stdc++");
+    }
+
     if target.contains("darwin") {
         let mut file = File::create(&dest.join("gl_bindings.rs")).unwrap();
         Registry::new(Api::Gl, (3, 2), Profile::Core, Fallbacks::All, ["GL_EXT_framebuffer_object"])
             .write_bindings(gl_generator::GlobalGenerator, &mut file).unwrap();
+
+        // for ANGLE
+        let mut file = File::create(&dest.join("egl_bindings.rs")).unwrap();
+        let registry = Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
+                          "EGL_KHR_create_context",
+                          "EGL_EXT_create_context_robustness",
+                          "EGL_KHR_create_context_no_error",
+                          "EGL_KHR_platform_x11",
+                          "EGL_KHR_platform_android",
+                          "EGL_KHR_platform_gbm",
+                          "EGL_EXT_platform_base",
+                          "EGL_EXT_platform_x11",
+                          "EGL_MESA_platform_gbm",
+                          "EGL_EXT_platform_device",
+                          "EGL_KHR_partial_update",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
+                      ]);
+        if static_egl {
+            registry.write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
+        } else {
+            registry.write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
+        }
     }
 
     // TODO: only build the bindings below if we run tests/examples
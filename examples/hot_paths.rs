@@ -0,0 +1,81 @@
+//! Measures the functions that a render loop calls every frame, and checks that they don't
+//! allocate. Exits with an error if one of them does, so that it can be run to catch
+//! regressions.
+//!
+//! Only the Rust allocator is counted, so the allocations of the drivers and of the system
+//! libraries, which use `malloc` directly, aren't seen.
+
+#[cfg(target_os = "android")]
+#[macro_use]
+extern crate android_glue;
+
+extern crate glutin;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+#[cfg(target_os = "android")]
+android_start!(main);
+
+const ITERATIONS: u32 = 1000;
+
+/// Counts the allocations of the process.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Calls `f` repeatedly, prints its average duration and returns false if it allocated.
+fn measure<F>(name: &str, mut f: F) -> bool where F: FnMut() {
+    // the first calls may fill caches
+    f();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    let nanos = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
+    println!("{:<20} {:>10} ns/call {:>10} allocations", name, nanos / ITERATIONS as u64,
+             allocations);
+    allocations == 0
+}
+
+fn main() {
+    let window = glutin::WindowBuilder::new().build().unwrap();
+    let _ = unsafe { window.make_current() };
+
+    let mut ok = true;
+    ok &= measure("make_current", || { let _ = unsafe { window.make_current() }; });
+    ok &= measure("get_proc_address", || { window.get_proc_address("glClear"); });
+    ok &= measure("swap_buffers", || { let _ = window.swap_buffers(); });
+    ok &= measure("poll_events", || for _ in window.poll_events() {});
+
+    if !ok {
+        println!("some of the functions allocated");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,41 @@
+/* The C interface of glutin, built with the `capi` feature. See `src/capi.rs`. */
+
+#ifndef GLUTIN_H
+#define GLUTIN_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A window or a headless context. The functions fail when it's NULL, and so does
+   glutin_get_proc_address when `name` is NULL and glutin_get_inner_size when `width` or
+   `height` is. */
+typedef struct GlutinContext GlutinContext;
+
+/* The message of the last failure in this thread, or NULL. Valid until the next failure. */
+const char *glutin_last_error(void);
+
+/* Return NULL on failure. `title` may be NULL. */
+GlutinContext *glutin_window_new(uint32_t width, uint32_t height, const char *title);
+GlutinContext *glutin_headless_new(uint32_t width, uint32_t height);
+void glutin_destroy(GlutinContext *context);
+
+/* Return 0 on success. */
+int glutin_make_current(GlutinContext *context);
+int glutin_swap_buffers(GlutinContext *context);
+int glutin_set_swap_interval(GlutinContext *context, int interval);
+int glutin_get_inner_size(GlutinContext *context, uint32_t *width, uint32_t *height);
+
+/* Return 1 or 0. */
+int glutin_is_current(GlutinContext *context);
+int glutin_poll_events(GlutinContext *context);
+
+void *glutin_get_proc_address(GlutinContext *context, const char *name);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -24,6 +24,9 @@ pub type ANativeWindow = raw::c_void;
 extern {
     pub fn ANativeWindow_getHeight(window: *const ANativeWindow) -> libc::int32_t;
     pub fn ANativeWindow_getWidth(window: *const ANativeWindow) -> libc::int32_t;
+    pub fn ANativeWindow_setBuffersGeometry(window: *const ANativeWindow, width: libc::int32_t,
+                                            height: libc::int32_t, format: libc::int32_t)
+                                            -> libc::int32_t;
 }
 
 /**
@@ -70,6 +73,14 @@ pub struct ANativeActivityCallbacks {
     pub onLowMemory: extern fn(*mut ANativeActivity),
 }
 
+pub const AWINDOW_FLAG_SECURE: libc::uint32_t = 0x00002000;
+
+#[link(name = "android")]
+extern {
+    pub fn ANativeActivity_setWindowFlags(activity: *mut ANativeActivity,
+                                          addFlags: libc::uint32_t, removeFlags: libc::uint32_t);
+}
+
 /**
  * looper.h
  */
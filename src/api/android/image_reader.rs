@@ -0,0 +1,180 @@
+//! An `AImageReader` whose images are bound to an external texture, which is what a Java
+//! `SurfaceTexture` does. See `os::android::TextureSurface`.
+//!
+//! The functions of the reader are in `libmediandk.so`, which only has them since Android 8.0,
+//! so the library is loaded the first time a reader is created instead of being linked.
+
+use std::cell::Cell;
+use std::io;
+use std::mem;
+use std::os::raw::c_void;
+use std::path::Path;
+use std::ptr;
+use std::sync::Mutex;
+
+use shared_library::dynamic_library::DynamicLibrary;
+
+use ContextError;
+use CreationError;
+
+const AMEDIA_OK: i32 = 0;
+const AMEDIA_IMGREADER_NO_BUFFER_AVAILABLE: i32 = -30001;
+// the opaque format of the decoders and of the camera, which only the GPU can read
+const AIMAGE_FORMAT_PRIVATE: i32 = 0x22;
+const AHARDWAREBUFFER_USAGE_GPU_SAMPLED_IMAGE: u64 = 1 << 8;
+// the image bound to the texture, the one being acquired, and one for the producer
+const MAX_IMAGES: i32 = 3;
+
+#[derive(Copy, Clone)]
+struct Functions {
+    new_with_usage: extern "C" fn(i32, i32, i32, u64, i32, *mut *mut c_void) -> i32,
+    get_window: extern "C" fn(*mut c_void, *mut *mut c_void) -> i32,
+    acquire_latest_image: extern "C" fn(*mut c_void, *mut *mut c_void) -> i32,
+    delete: extern "C" fn(*mut c_void),
+    get_hardware_buffer: extern "C" fn(*const c_void, *mut *mut c_void) -> i32,
+    delete_image: extern "C" fn(*mut c_void),
+}
+
+lazy_static! {
+    // `None` until a reader is first created, `Some(None)` if the library or one of the
+    // functions couldn't be loaded
+    static ref MEDIA: Mutex<Option<Option<(DynamicLibrary, Functions)>>> = Mutex::new(None);
+}
+
+/// An `AImageReader` that produces hardware buffers for the GPU.
+pub struct ImageReader {
+    functions: Functions,
+    reader: *mut c_void,
+    window: *mut c_void,
+    // the image whose buffer is bound to the texture, which the reader must not reuse
+    image: Cell<*mut c_void>,
+}
+
+impl ImageReader {
+    pub fn new(dimensions: (u32, u32)) -> Result<ImageReader, CreationError> {
+        let functions = match load() {
+            Some(functions) => functions,
+            None => return Err(CreationError::NotSupported),
+        };
+
+        let mut reader = ptr::null_mut();
+        let status = (functions.new_with_usage)(dimensions.0 as i32, dimensions.1 as i32,
+                                                AIMAGE_FORMAT_PRIVATE,
+                                                AHARDWAREBUFFER_USAGE_GPU_SAMPLED_IMAGE,
+                                                MAX_IMAGES, &mut reader);
+        if status != AMEDIA_OK {
+            return Err(CreationError::OsError(format!("AImageReader_newWithUsage failed: {}",
+                                                      status)));
+        }
+
+        // the window belongs to the reader
+        let mut window = ptr::null_mut();
+        let status = (functions.get_window)(reader, &mut window);
+        if status != AMEDIA_OK {
+            (functions.delete)(reader);
+            return Err(CreationError::OsError(format!("AImageReader_getWindow failed: {}",
+                                                      status)));
+        }
+
+        Ok(ImageReader {
+            functions: functions,
+            reader: reader,
+            window: window,
+            image: Cell::new(ptr::null_mut()),
+        })
+    }
+
+    /// Returns the `ANativeWindow` that the producer renders into.
+    #[inline]
+    pub fn get_native_window(&self) -> *mut c_void {
+        self.window
+    }
+
+    /// Calls `bind` with the hardware buffer of the latest image, if the producer has queued
+    /// one since the last call, and returns whether it did. The previous image goes back to
+    /// the producer once `bind` has succeeded.
+    pub fn acquire_latest<F>(&self, bind: F) -> Result<bool, ContextError>
+        where F: FnOnce(*mut c_void) -> Result<(), ContextError>
+    {
+        let mut image = ptr::null_mut();
+        match (self.functions.acquire_latest_image)(self.reader, &mut image) {
+            AMEDIA_OK => (),
+            AMEDIA_IMGREADER_NO_BUFFER_AVAILABLE => return Ok(false),
+            status => return Err(error(format!("AImageReader_acquireLatestImage failed: {}",
+                                               status))),
+        }
+
+        let mut buffer = ptr::null_mut();
+        let status = (self.functions.get_hardware_buffer)(image, &mut buffer);
+        if status != AMEDIA_OK {
+            (self.functions.delete_image)(image);
+            return Err(error(format!("AImage_getHardwareBuffer failed: {}", status)));
+        }
+
+        if let Err(err) = bind(buffer) {
+            (self.functions.delete_image)(image);
+            return Err(err);
+        }
+
+        let previous = self.image.replace(image);
+        if !previous.is_null() {
+            (self.functions.delete_image)(previous);
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for ImageReader {
+    fn drop(&mut self) {
+        let image = self.image.get();
+        if !image.is_null() {
+            (self.functions.delete_image)(image);
+        }
+        (self.functions.delete)(self.reader);
+    }
+}
+
+#[inline]
+fn error(message: String) -> ContextError {
+    ContextError::IoError(io::Error::new(io::ErrorKind::Other, message))
+}
+
+fn load() -> Option<Functions> {
+    let mut media = MEDIA.lock().unwrap();
+    if media.is_none() {
+        *media = Some(load_library());
+    }
+    media.as_ref().unwrap().as_ref().map(|&(_, functions)| functions)
+}
+
+fn load_library() -> Option<(DynamicLibrary, Functions)> {
+    let library = match DynamicLibrary::open(Some(Path::new("libmediandk.so"))) {
+        Ok(library) => library,
+        Err(_err) => {
+            debug_log!("libmediandk.so couldn't be loaded: {}", _err);
+            return None;
+        },
+    };
+
+    let functions = unsafe {
+        let load = |name| library.symbol::<c_void>(name).unwrap_or(ptr::null_mut());
+        let symbols = [load("AImageReader_newWithUsage"), load("AImageReader_getWindow"),
+                       load("AImageReader_acquireLatestImage"), load("AImageReader_delete"),
+                       load("AImage_getHardwareBuffer"), load("AImage_delete")];
+        if symbols.iter().any(|s| s.is_null()) {
+            debug_log!("the AImageReader functions couldn't be loaded");
+            return None;
+        }
+
+        Functions {
+            new_with_usage: mem::transmute(symbols[0]),
+            get_window: mem::transmute(symbols[1]),
+            acquire_latest_image: mem::transmute(symbols[2]),
+            delete: mem::transmute(symbols[3]),
+            get_hardware_buffer: mem::transmute(symbols[4]),
+            delete_image: mem::transmute(symbols[5]),
+        }
+    };
+
+    Some((library, functions))
+}
@@ -13,8 +13,14 @@ use events::{Touch, TouchPhase};
 use std::collections::VecDeque;
 
 use Api;
+use ConfigLimits;
+use RendererInfo;
+use Rect;
+use HdrMetadata;
 use ContextError;
 use CursorState;
+use EglPlatform;
+use FailedVersion;
 use GlAttributes;
 use GlContext;
 use GlRequest;
@@ -23,8 +29,12 @@ use PixelFormatRequirements;
 use WindowAttributes;
 use native_monitor::NativeMonitorId;
 
+use api::Drawable;
 use api::egl;
 use api::egl::Context as EglContext;
+use presenter::SwapFn;
+
+pub use api::egl::PBuffer;
 
 pub struct Window {
     context: EglContext,
@@ -35,6 +45,7 @@ pub struct Window {
 pub struct MonitorId;
 
 mod ffi;
+pub mod image_reader;
 
 #[inline]
 pub fn get_available_monitors() -> VecDeque<MonitorId> {
@@ -66,7 +77,10 @@ impl MonitorId {
 }
 
 #[derive(Clone, Default)]
-pub struct PlatformSpecificWindowBuilderAttributes;
+pub struct PlatformSpecificWindowBuilderAttributes {
+    pub protected_content: bool,
+    pub secure_activity: Option<*mut ffi::ANativeActivity>,
+}
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
@@ -121,7 +135,8 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 
 impl Window {
     pub fn new(win_attribs: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               opengl: &GlAttributes<&Window>,
+               pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
         use std::{mem, ptr};
@@ -137,9 +152,30 @@ impl Window {
             return Err(OsError(format!("Android's native window is null")));
         }
 
+        // the window must be secure before its surface is created, otherwise the content of a
+        // protected surface is shown black even on secure outputs
+        if let Some(activity) = pl_attribs.secure_activity {
+            unsafe { ffi::ANativeActivity_setWindowFlags(activity, ffi::AWINDOW_FLAG_SECURE, 0) };
+        }
+
         let context = try!(EglContext::new(egl::ffi::egl::Egl, pf_reqs, &opengl,
-                                           egl::NativeDisplay::Android)
-                                                .and_then(|p| p.finish(native_window as *const _)));
+                                           egl::NativeDisplay::Android, EglPlatform::Auto)
+                                                .and_then(|p| {
+            // the buffers of the window get the format of the config, otherwise a 16 bits
+            // config renders into 32 bits buffers, or the other way around, and the buffers of a
+            // transparent window must have the alpha channel of the config for SurfaceFlinger to
+            // blend the window with what is behind it
+            let format = p.get_native_visual_id();
+            let res = unsafe {
+                ffi::ANativeWindow_setBuffersGeometry(native_window, 0, 0, format)
+            };
+            if res != 0 && win_attribs.transparent {
+                return Err(CreationError::TransparencyNotSupported);
+            }
+
+            try!(p.with_protected_content(pl_attribs.protected_content))
+                .finish(native_window as *const _)
+        }));
 
         let (tx, rx) = channel();
         android_glue::add_sender(tx);
@@ -252,14 +288,69 @@ impl Window {
         1.0
     }
 
+    /// Variable refresh rates aren't reported on this platform.
+    #[inline]
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        None
+    }
+
+    /// The buffers can only be swapped by the thread where the context is current on this
+    /// platform.
+    #[inline]
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        None
+    }
+
+    #[inline]
+    pub unsafe fn import_texture_hardware_buffer(&self, buffer: *mut libc::c_void)
+                                                 -> Result<u32, ContextError>
+    {
+        self.context.import_texture_hardware_buffer(buffer)
+    }
+
+    #[inline]
+    pub unsafe fn import_external_texture_hardware_buffer(&self, buffer: *mut libc::c_void)
+                                                          -> Result<u32, ContextError>
+    {
+        self.context.import_external_texture_hardware_buffer(buffer)
+    }
+
+    #[inline]
+    pub unsafe fn update_external_texture_hardware_buffer(&self, texture: u32,
+                                                          buffer: *mut libc::c_void)
+                                                          -> Result<(), ContextError>
+    {
+        self.context.update_external_texture_hardware_buffer(texture, buffer)
+    }
+
+    #[inline]
+    pub fn create_external_texture(&self) -> Result<u32, ContextError> {
+        self.context.create_external_texture()
+    }
+
     #[inline]
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
         unimplemented!();
     }
+
+    #[inline]
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        self.context.create_pbuffer(dimensions)
+    }
+
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        let draw = try!(draw.map(|w| Ok(&w.context), |p| Ok(p), |x| match *x {}));
+        let read = try!(read.map(|w| Ok(&w.context), |p| Ok(p), |x| match *x {}));
+        self.context.make_current_with_surfaces(draw, read)
+    }
 }
 
+// the receiver of the events can't be shared between threads, which makes the window `!Sync`
 unsafe impl Send for Window {}
-unsafe impl Sync for Window {}
 
 impl GlContext for Window {
     #[inline]
@@ -291,6 +382,56 @@ impl GlContext for Window {
     fn get_pixel_format(&self) -> PixelFormat {
         self.get_pixel_format()
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.context.get_failed_versions()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.context.get_config_limits()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.context.get_renderer_info()
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.context.make_not_current()
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        self.context.destroy_context()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.context.is_back_buffer_preserved()
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        self.context.is_front_buffer_rendering()
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.context.set_damage_region(rects)
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        self.context.set_hdr_metadata(metadata)
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        self.context.set_swap_interval(interval)
+    }
 }
 
 #[derive(Clone)]
@@ -314,14 +455,38 @@ impl HeadlessContext {
     {
         let opengl = opengl.clone().map_sharing(|c| &c.0);
         let context = try!(EglContext::new(egl::ffi::egl::Egl, pf_reqs, &opengl,
-                           egl::NativeDisplay::Android));
+                           egl::NativeDisplay::Android, EglPlatform::Auto));
         let context = try!(context.finish_pbuffer(dimensions));     // TODO: 
         Ok(HeadlessContext(context))
     }
-}
 
-unsafe impl Send for HeadlessContext {}
-unsafe impl Sync for HeadlessContext {}
+    /// Chooses the config like `new` does, without creating the context.
+    pub fn query_config_limits(pf_reqs: &PixelFormatRequirements,
+                               opengl: &GlAttributes<&HeadlessContext>,
+                               _: &PlatformSpecificHeadlessBuilderAttributes)
+                               -> Result<ConfigLimits, CreationError>
+    {
+        let opengl = opengl.clone().map_sharing(|c| &c.0);
+        let prototype = try!(EglContext::new(egl::ffi::egl::Egl, pf_reqs, &opengl,
+                                             egl::NativeDisplay::Android, EglPlatform::Auto));
+        Ok(prototype.get_config_limits())
+    }
+
+    #[inline]
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        self.0.create_pbuffer(dimensions)
+    }
+
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        let draw = try!(draw.map(|w| Ok(&w.context), |p| Ok(p), |x| match *x {}));
+        let read = try!(read.map(|w| Ok(&w.context), |p| Ok(p), |x| match *x {}));
+        self.0.make_current_with_surfaces(draw, read)
+    }
+}
 
 impl GlContext for HeadlessContext {
     #[inline]
@@ -353,4 +518,54 @@ impl GlContext for HeadlessContext {
     fn get_pixel_format(&self) -> PixelFormat {
         self.0.get_pixel_format()
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.0.get_failed_versions()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.0.get_config_limits()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.0.get_renderer_info()
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.0.make_not_current()
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        self.0.destroy_context()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.0.is_back_buffer_preserved()
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        self.0.is_front_buffer_rendering()
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.0.set_damage_region(rects)
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        self.0.set_hdr_metadata(metadata)
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        self.0.set_swap_interval(interval)
+    }
 }
@@ -1,4 +1,5 @@
-#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+           target_os = "openbsd", target_os = "netbsd"))]
 #![allow(unused_variables, dead_code)]
 
 use libc;
@@ -0,0 +1,171 @@
+//! OpenGL ES contexts created by ANGLE on top of Metal, for windows that request OpenGL ES.
+//!
+//! ANGLE isn't part of the system, its `libEGL.dylib` is loaded at runtime like the EGL
+//! implementations of the other platforms, see `Library::Egl`.
+
+use CreationError;
+use GlAttributes;
+use PixelFormatRequirements;
+
+use cocoa::base::id;
+
+#[cfg(feature = "egl")]
+pub use api::egl::Context;
+
+#[cfg(feature = "egl")]
+use EglPlatform;
+#[cfg(feature = "egl")]
+use objc::runtime::YES;
+#[cfg(feature = "egl")]
+use api::egl;
+#[cfg(feature = "egl")]
+use api::egl::ffi::egl::Egl;
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+use api::dlopen;
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+use libraries::{self, Library};
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+use std::ffi::CString;
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+use std::os::raw::{c_int, c_void};
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+use std::ptr;
+
+/// Stupid wrapper because `*const libc::c_void` doesn't implement `Sync`.
+#[cfg(feature = "egl")]
+struct EglWrapper(Egl);
+#[cfg(feature = "egl")]
+unsafe impl Sync for EglWrapper {}
+
+#[cfg(feature = "egl")]
+lazy_static! {
+    // The EGL implementation of ANGLE, if it can be found.
+    static ref EGL: Option<EglWrapper> = load_egl().map(EglWrapper);
+}
+
+/// Creates an OpenGL ES context that renders into the layer of `view`.
+///
+/// Returns `NotSupported` if sharing is requested, as the context can't share its objects with
+/// the NSOpenGLContext of another window.
+#[cfg(feature = "egl")]
+pub fn create_context<T>(view: id, pf_reqs: &PixelFormatRequirements,
+                         opengl: &GlAttributes<T>) -> Result<Context, CreationError>
+{
+    if opengl.sharing.is_some() {
+        return Err(CreationError::NotSupported);
+    }
+
+    let egl = match *EGL {
+        Some(ref egl) => egl.0.clone(),
+        None => return Err(CreationError::NotSupported),
+    };
+
+    // ANGLE presents into a `CAMetalLayer` that it adds to this layer
+    let layer = unsafe {
+        let _: () = msg_send![view, setWantsLayer:YES];
+        let layer: id = msg_send![view, layer];
+        layer
+    };
+
+    let opengl = opengl.clone().map_sharing(|_| unreachable!());
+    let prototype = try!(Context::new(egl, pf_reqs, &opengl, egl::NativeDisplay::AngleMetal,
+                                      EglPlatform::Auto));
+    prototype.finish(layer as *const _)
+}
+
+#[cfg(not(feature = "egl"))]
+#[inline]
+pub fn create_context<T>(_: id, _: &PixelFormatRequirements, _: &GlAttributes<T>)
+                         -> Result<Context, CreationError>
+{
+    Err(CreationError::NotSupported)
+}
+
+/// Returns the EGL implementation that was linked at compile-time.
+#[cfg(all(feature = "egl", feature = "static-egl"))]
+#[inline]
+fn load_egl() -> Option<Egl> {
+    Some(Egl)
+}
+
+// TODO: use something safer than raw "dlopen"
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+fn load_egl() -> Option<Egl> {
+    if let Some(loader) = libraries::egl_loader() {
+        return Some(Egl::load_with(|sym| loader(sym)));
+    }
+
+    // `libEGL.dylib` finds the `libGLESv2.dylib` of ANGLE next to it, but a user-provided one
+    // has to be loaded first
+    open_library(Library::GlesV2, dlopen::RTLD_NOW | dlopen::RTLD_GLOBAL);
+
+    let libegl = open_library(Library::Egl, dlopen::RTLD_NOW);
+
+    if libegl.is_null() {
+        None
+    } else {
+        Some(Egl::load_with(|sym| {
+            let sym = CString::new(sym).unwrap();
+            unsafe { dlopen::dlsym(libegl, sym.as_ptr()) }
+        }))
+    }
+}
+
+/// Opens the first library that can be loaded among the candidates for `library`.
+///
+/// Returns null if none of them could be loaded.
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+fn open_library(library: Library, flags: c_int) -> *mut c_void {
+    for path in libraries::candidates(library) {
+        let path = match CString::new(path.as_os_str().as_bytes()) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+
+        let lib = unsafe { dlopen::dlopen(path.as_ptr(), flags) };
+        if !lib.is_null() {
+            return lib;
+        }
+    }
+
+    ptr::null_mut()
+}
+
+/// Without the `egl` feature, no window ever has an ANGLE context.
+#[cfg(not(feature = "egl"))]
+pub enum Context {}
+
+#[cfg(not(feature = "egl"))]
+impl ::GlContext for Context {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ::ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ::ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> ::PixelFormat {
+        match *self {}
+    }
+}
@@ -0,0 +1,139 @@
+//! Waits for the refresh of a display with a `CVDisplayLink`, which follows the actual refresh
+//! rate of the display, including ProMotion displays.
+
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+type CVDisplayLinkRef = *mut c_void;
+type CVReturn = i32;
+type CVOptionFlags = u64;
+type CGDirectDisplayID = u32;
+
+type CVDisplayLinkOutputCallback = extern fn(display_link: CVDisplayLinkRef,
+                                             now: *const c_void,
+                                             output_time: *const c_void,
+                                             flags_in: CVOptionFlags,
+                                             flags_out: *mut CVOptionFlags,
+                                             context: *mut c_void) -> CVReturn;
+
+#[allow(non_upper_case_globals)]
+const kCVReturnSuccess: CVReturn = 0;
+
+#[link(name = "CoreVideo", kind = "framework")]
+extern {
+    fn CVDisplayLinkCreateWithCGDisplay(display: CGDirectDisplayID,
+                                        display_link: *mut CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkSetCurrentCGDisplay(display_link: CVDisplayLinkRef,
+                                        display: CGDirectDisplayID) -> CVReturn;
+    fn CVDisplayLinkSetOutputCallback(display_link: CVDisplayLinkRef,
+                                      callback: CVDisplayLinkOutputCallback,
+                                      context: *mut c_void) -> CVReturn;
+    fn CVDisplayLinkStart(display_link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkStop(display_link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkRelease(display_link: CVDisplayLinkRef);
+}
+
+/// A display link never fires while the display is asleep, so waits give up after this.
+const MAX_WAIT_MS: u64 = 100;
+
+/// Counts the refreshes of the display, shared with the thread of the display link.
+struct Refreshes {
+    count: Mutex<u64>,
+    condvar: Condvar,
+}
+
+pub struct DisplayLink {
+    link: CVDisplayLinkRef,
+    display: CGDirectDisplayID,
+    refreshes: Box<Refreshes>,
+}
+
+impl DisplayLink {
+    pub fn new(display: CGDirectDisplayID) -> Result<DisplayLink, String> {
+        let refreshes = Box::new(Refreshes {
+            count: Mutex::new(0),
+            condvar: Condvar::new(),
+        });
+
+        unsafe {
+            let mut link = ptr::null_mut();
+            if CVDisplayLinkCreateWithCGDisplay(display, &mut link) != kCVReturnSuccess {
+                return Err(format!("CVDisplayLinkCreateWithCGDisplay failed"));
+            }
+
+            // the link is released by `drop` if the next calls fail
+            let display_link = DisplayLink {
+                link: link,
+                display: display,
+                refreshes: refreshes,
+            };
+
+            let context = &*display_link.refreshes as *const Refreshes as *mut c_void;
+            if CVDisplayLinkSetOutputCallback(link, refresh_callback,
+                                              context) != kCVReturnSuccess
+            {
+                return Err(format!("CVDisplayLinkSetOutputCallback failed"));
+            }
+            if CVDisplayLinkStart(link) != kCVReturnSuccess {
+                return Err(format!("CVDisplayLinkStart failed"));
+            }
+
+            Ok(display_link)
+        }
+    }
+
+    /// Follows the refreshes of `display` from now on, for windows that moved to another screen.
+    pub fn set_display(&mut self, display: CGDirectDisplayID) -> Result<(), String> {
+        if display == self.display {
+            return Ok(());
+        }
+
+        unsafe {
+            if CVDisplayLinkSetCurrentCGDisplay(self.link, display) != kCVReturnSuccess {
+                return Err(format!("CVDisplayLinkSetCurrentCGDisplay failed"));
+            }
+        }
+
+        self.display = display;
+        Ok(())
+    }
+
+    /// Blocks until the next refresh of the display.
+    pub fn wait(&self) {
+        let mut count = self.refreshes.count.lock().unwrap();
+        let start = *count;
+
+        while *count == start {
+            let (guard, timeout) = self.refreshes.condvar
+                                       .wait_timeout(count, Duration::from_millis(MAX_WAIT_MS))
+                                       .unwrap();
+            count = guard;
+
+            if timeout.timed_out() {
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for DisplayLink {
+    fn drop(&mut self) {
+        unsafe {
+            // stopping waits for the callback to return, which must not outlive `refreshes`
+            CVDisplayLinkStop(self.link);
+            CVDisplayLinkRelease(self.link);
+        }
+    }
+}
+
+extern fn refresh_callback(_: CVDisplayLinkRef, _: *const c_void, _: *const c_void,
+                           _: CVOptionFlags, _: *mut CVOptionFlags, context: *mut c_void)
+                           -> CVReturn
+{
+    let refreshes = unsafe { &*(context as *const Refreshes) };
+    *refreshes.count.lock().unwrap() += 1;
+    refreshes.condvar.notify_all();
+    kCVReturnSuccess
+}
@@ -1,3 +1,4 @@
+use ConfigLimits;
 use ContextError;
 use CreationError;
 use CreationError::OsError;
@@ -5,13 +6,13 @@ use GlAttributes;
 use GlContext;
 use PixelFormatRequirements;
 
-use core_foundation::base::TCFType;
-use core_foundation::string::CFString;
-use core_foundation::bundle::{CFBundleGetBundleWithIdentifier, CFBundleGetFunctionPointerForName};
 use cocoa::base::{id, nil};
 use cocoa::appkit::*;
 use PixelFormat;
 use api::cocoa::helpers;
+use api::cocoa::PBuffer;
+use api::cocoa::Window;
+use api::Drawable;
 
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
@@ -47,6 +48,33 @@ impl HeadlessContext {
 
         Ok(headless)
     }
+
+    /// The pixel formats of CGL can't be queried without a context.
+    #[inline]
+    pub fn query_config_limits(_: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+                               _: &PlatformSpecificHeadlessBuilderAttributes)
+                               -> Result<ConfigLimits, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
 }
 
 impl GlContext for HeadlessContext {
@@ -61,16 +89,8 @@ impl GlContext for HeadlessContext {
     }
 
     #[inline]
-    fn get_proc_address(&self, _addr: &str) -> *const () {
-        let symbol_name: CFString = _addr.parse().unwrap();
-        let framework_name: CFString = "com.apple.opengl".parse().unwrap();
-        let framework = unsafe {
-            CFBundleGetBundleWithIdentifier(framework_name.as_concrete_TypeRef())
-        };
-        let symbol = unsafe {
-            CFBundleGetFunctionPointerForName(framework, symbol_name.as_concrete_TypeRef())
-        };
-        symbol as *const ()
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        helpers::get_proc_address(addr)
     }
 
     #[inline]
@@ -90,5 +110,6 @@ impl GlContext for HeadlessContext {
     }
 }
 
+// an NSOpenGLContext can be moved to another thread, but using it from two threads at once
+// requires `CGLLockContext`
 unsafe impl Send for HeadlessContext {}
-unsafe impl Sync for HeadlessContext {}
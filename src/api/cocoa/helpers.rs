@@ -5,7 +5,34 @@ use GlProfile;
 use GlRequest;
 use PixelFormatRequirements;
 use ReleaseBehavior;
+use c_str::with_c_str;
 use cocoa::appkit::*;
+use libc;
+
+use std::ptr;
+
+/// The OpenGL framework, opened once so that `get_proc_address` doesn't look up the bundle and
+/// create a `CFString` for every function.
+struct OpenGlFramework(*mut libc::c_void);
+// the handle is never closed, and `dlsym` can be called from any thread
+unsafe impl Send for OpenGlFramework {}
+unsafe impl Sync for OpenGlFramework {}
+
+lazy_static! {
+    static ref OPENGL_FRAMEWORK: OpenGlFramework = OpenGlFramework(unsafe {
+        libc::dlopen(b"/System/Library/Frameworks/OpenGL.framework/OpenGL\0".as_ptr() as *const _,
+                     libc::RTLD_LAZY | libc::RTLD_GLOBAL)
+    });
+}
+
+/// Returns the address of an OpenGL function, or null if the framework doesn't have it.
+pub fn get_proc_address(addr: &str) -> *const () {
+    if OPENGL_FRAMEWORK.0.is_null() {
+        return ptr::null();
+    }
+
+    with_c_str(addr, |addr| unsafe { libc::dlsym(OPENGL_FRAMEWORK.0, addr) as *const _ })
+}
 
 pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&T>)
     -> Result<Vec<u32>, CreationError> {
@@ -43,8 +70,8 @@ pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttri
     // full color size and hope for the best. Another hiccup is that
     // `NSOpenGLPFAColorSize` also includes `NSOpenGLPFAAlphaSize`,
     // so we have to account for that as well.
-    let alpha_depth = pf_reqs.alpha_bits.unwrap_or(8);
-    let color_depth = pf_reqs.color_bits.unwrap_or(24) + alpha_depth;
+    let alpha_depth = pf_reqs.alpha_bits.min().unwrap_or(8);
+    let color_depth = pf_reqs.color_bits.min().unwrap_or(24) + alpha_depth;
 
     // TODO: handle hardware_accelerated parameter of pf_reqs
 
@@ -53,8 +80,8 @@ pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttri
         NSOpenGLPFAClosestPolicy as u32,
         NSOpenGLPFAColorSize as u32, color_depth as u32,
         NSOpenGLPFAAlphaSize as u32, alpha_depth as u32,
-        NSOpenGLPFADepthSize as u32, pf_reqs.depth_bits.unwrap_or(24) as u32,
-        NSOpenGLPFAStencilSize as u32, pf_reqs.stencil_bits.unwrap_or(8) as u32,
+        NSOpenGLPFADepthSize as u32, pf_reqs.depth_bits.min().unwrap_or(24) as u32,
+        NSOpenGLPFAStencilSize as u32, pf_reqs.stencil_bits.min().unwrap_or(8) as u32,
         NSOpenGLPFAOpenGLProfile as u32, profile,
     ];
 
@@ -74,7 +101,7 @@ pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttri
         attributes.push(NSOpenGLPFAColorFloat as u32);
     }
 
-    pf_reqs.multisampling.map(|samples| {
+    pf_reqs.color_samples().map(|samples| {
         attributes.push(NSOpenGLPFAMultisample as u32);
         attributes.push(NSOpenGLPFASampleBuffers as u32); attributes.push(1);
         attributes.push(NSOpenGLPFASamples as u32); attributes.push(samples as u32);
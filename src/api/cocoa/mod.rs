@@ -4,20 +4,33 @@ use {CreationError, Event, MouseCursor, CursorState};
 use CreationError::OsError;
 use libc;
 
+use Api;
+use ConfigLimits;
+use RendererInfo;
 use ContextError;
+use FailedVersion;
 use GlAttributes;
 use GlContext;
+use GlRequest;
+use HdrMetadata;
 use PixelFormat;
 use PixelFormatRequirements;
+use Rect;
 use Robustness;
 use WindowAttributes;
+use check_swap_interval;
+use is_packed_depth_stencil;
 use native_monitor::NativeMonitorId;
-use os::macos::ActivationPolicy;
+use os::macos::{ActivationPolicy, ColorSpace};
+use api::Drawable;
+use presenter::SwapFn;
 
 use objc::runtime::{Class, Object, Sel, BOOL, YES, NO};
 use objc::declare::ClassDecl;
 
 use cgl::{CGLEnable, kCGLCECrashOnRemovedFunctions, CGLSetParameter, kCGLCPSurfaceOpacity};
+use cgl::{CGLContextObj, CGLPixelFormatObj, CGLCreateContext, CGLDestroyContext};
+use cgl::{CGLGetShareGroup, kCGLCPSwapInterval, kCGLNoError};
 
 use cocoa::base::{id, nil};
 use cocoa::foundation::{NSAutoreleasePool, NSArray, NSDate, NSDefaultRunLoopMode, NSPoint, NSRect};
@@ -26,23 +39,21 @@ use cocoa::appkit;
 use cocoa::appkit::*;
 use cocoa::appkit::NSEventSubtype::*;
 
-use core_foundation::base::TCFType;
-use core_foundation::string::CFString;
-use core_foundation::bundle::{CFBundle, CFBundleGetBundleWithIdentifier};
-use core_foundation::bundle::{CFBundleGetFunctionPointerForName};
+use core_foundation::bundle::CFBundle;
 
 use core_graphics::geometry::{CG_ZERO_POINT, CGRect, CGSize};
 use core_graphics::display::{CGAssociateMouseAndMouseCursorPosition, CGMainDisplayID, CGDisplayPixelsHigh, CGWarpMouseCursorPosition};
 use core_graphics::private::{CGSRegion, CGSSurface};
 
 use std::ffi::CStr;
+use std::io;
 use std::collections::VecDeque;
-use std::str::FromStr;
 use std::str::from_utf8;
 use std::sync::Mutex;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::env;
+use std::ptr;
 
 use events::ElementState;
 use events::{self, MouseButton, TouchPhase};
@@ -53,6 +64,8 @@ pub use self::headless::PlatformSpecificHeadlessBuilderAttributes;
 
 mod monitor;
 mod event;
+mod angle;
+mod display_link;
 mod headless;
 mod helpers;
 
@@ -64,6 +77,19 @@ const TITLEBAR_HEIGHT: f64 = 32.0;
 /// The corner radius for the window.
 const CORNER_RADIUS: CGFloat = 6.0;
 
+/// The number of pixels of the OpenGL surface per point of the view, which is 1 if the view
+/// doesn't want a surface at the native resolution of the screen.
+fn surface_scale_factor(window: id, view: id) -> f32 {
+    unsafe {
+        let best_resolution: BOOL = msg_send![view, wantsBestResolutionOpenGLSurface];
+        if best_resolution == NO {
+            1.0
+        } else {
+            NSWindow::backingScaleFactor(window) as f32
+        }
+    }
+}
+
 static mut shift_pressed: bool = false;
 static mut ctrl_pressed: bool = false;
 static mut win_pressed: bool = false;
@@ -110,13 +136,23 @@ impl WindowDelegate {
 
                 if let Some(handler) = state.resize_handler {
                     let rect = NSView::frame(*state.view);
-                    let scale_factor = NSWindow::backingScaleFactor(*state.window) as f32;
+                    let scale_factor = surface_scale_factor(*state.window, *state.view);
                     (handler)((scale_factor * rect.size.width as f32) as u32,
                               (scale_factor * rect.size.height as f32) as u32);
                 }
             }
         }
 
+        extern fn window_did_change_screen(this: &Object, _: Sel, _: id) {
+            unsafe {
+                let state: *mut c_void = *this.get_ivar("glutinState");
+                let state = &mut *(state as *mut DelegateState);
+
+                // the context may have to move to the renderer of the new screen
+                let _: () = msg_send![*state.context, update];
+            }
+        }
+
         extern fn window_did_become_key(this: &Object, _: Sel, _: id) {
             unsafe {
                 // TODO: center the cursor if the window had mouse grab when it
@@ -169,6 +205,11 @@ impl WindowDelegate {
                 window_should_close as extern fn(&Object, Sel, id) -> BOOL);
             decl.add_method(sel!(windowDidResize:),
                 window_did_resize as extern fn(&Object, Sel, id));
+            // a new backing scale changes the size of the surface, like a resize
+            decl.add_method(sel!(windowDidChangeBackingProperties:),
+                window_did_resize as extern fn(&Object, Sel, id));
+            decl.add_method(sel!(windowDidChangeScreen:),
+                window_did_change_screen as extern fn(&Object, Sel, id));
 
             decl.add_method(sel!(windowDidBecomeKey:),
                 window_did_become_key as extern fn(&Object, Sel, id));
@@ -217,18 +258,25 @@ impl Drop for WindowDelegate {
 pub struct PlatformSpecificWindowBuilderAttributes {
     pub activation_policy: ActivationPolicy,
     pub app_name: Option<String>,
+    pub color_space: Option<ColorSpace>,
+    pub disallow_hidpi: bool,
+    pub layer_backed: bool,
+    pub share_context: Option<CGLContextObj>,
 }
 
 pub struct Window {
     view: IdRef,
     window: IdRef,
+    /// Nil if the window has an ANGLE context instead.
     context: IdRef,
+    angle_context: Option<angle::Context>,
     pixel_format: PixelFormat,
     delegate: WindowDelegate,
+    display_link: Mutex<Option<display_link::DisplayLink>>,
 }
 
-unsafe impl Send for Window {}
-unsafe impl Sync for Window {}
+// AppKit objects must be used from the main thread, which makes the window neither `Send` nor
+// `Sync`
 
 #[derive(Clone)]
 pub struct WindowProxy;
@@ -341,16 +389,45 @@ impl Window {
         };
         let view = match Window::get_or_create_view(*window,
                                                     win_attribs.decorations,
-                                                    win_attribs.transparent) {
+                                                    win_attribs.transparent,
+                                                    !pl_attribs.disallow_hidpi) {
             Some(view) => view,
             None       => { return Err(OsError(format!("Couldn't create NSView"))); },
         };
 
+        if pl_attribs.layer_backed {
+            // AppKit then presents the context through an `NSOpenGLLayer`, a `CAOpenGLLayer`,
+            // instead of a surface on top of the window
+            unsafe {
+                let _: () = msg_send![*view, setWantsLayer:YES];
+            }
+        }
+
+        if let Some(color_space) = pl_attribs.color_space {
+            if !Window::set_color_space(*window, *view, color_space) {
+                return Err(OsError(format!("Couldn't set the color space to {:?}", color_space)));
+            }
+        }
+
+        // CGL doesn't provide OpenGL ES, which is provided by ANGLE on top of Metal instead
+        let angle_context = match opengl.version {
+            GlRequest::Specific(Api::OpenGlEs, _) | GlRequest::Range { api: Api::OpenGlEs, .. } => {
+                Some(try!(angle::create_context(*view, pf_reqs, opengl)))
+            },
+            _ => None,
+        };
+
         // TODO: perhaps we should return error from create_context so we can
         // determine the cause of failure and possibly recover?
-        let (context, pf) = match Window::create_context(*view, pf_reqs, opengl) {
-            Ok((context, pf)) => (context, pf),
-            Err(e) => { return Err(OsError(format!("Couldn't create OpenGL context: {}", e))); },
+        let (context, pf) = match angle_context {
+            Some(ref context) => (IdRef::new(nil), context.get_pixel_format()),
+            None => match Window::create_context(*view, pf_reqs, opengl, win_attribs.transparent,
+                                                 pl_attribs.share_context) {
+                Ok((context, pf)) => (context, pf),
+                Err(e) => {
+                    return Err(OsError(format!("Couldn't create OpenGL context: {}", e)));
+                },
+            },
         };
 
         let ds = DelegateState {
@@ -367,8 +444,10 @@ impl Window {
             view: view.clone(),
             window: window,
             context: context,
+            angle_context: angle_context,
             pixel_format: pf,
             delegate: WindowDelegate::new(ds),
+            display_link: Mutex::new(None),
         };
 
         unsafe {
@@ -506,6 +585,13 @@ impl Window {
                 NSWindow::setTitle_(*window, *title);
                 window.setAcceptsMouseMovedEvents_(YES);
 
+                if attrs.transparent {
+                    let color_class = Class::get("NSColor").unwrap();
+                    let clear: id = msg_send![color_class, clearColor];
+                    msg_send![*window, setBackgroundColor:clear];
+                    msg_send![*window, setOpaque:NO];
+                }
+
                 if screen.is_some() {
                     window.setLevel_(appkit::NSMainMenuWindowLevel as i64 + 1);
                 }
@@ -517,13 +603,16 @@ impl Window {
         }
     }
 
-    fn get_or_create_view(window: id, decorations: bool, transparent: bool) -> Option<IdRef> {
+    fn get_or_create_view(window: id, decorations: bool, transparent: bool, hidpi: bool)
+                          -> Option<IdRef> {
+        let hidpi = if hidpi { YES } else { NO };
+
         unsafe {
             // Note that transparent windows never have decorations.
             if decorations && !transparent {
                 let view = IdRef::new(NSView::alloc(nil).init());
                 return view.non_nil().map(|view| {
-                    view.setWantsBestResolutionOpenGLSurface_(YES);
+                    view.setWantsBestResolutionOpenGLSurface_(hidpi);
                     window.setContentView_(*view);
                     view
                 })
@@ -563,7 +652,7 @@ impl Window {
                                                               window_bounds.size.height));
             content_view = NSView::initWithFrame_(content_view, content_view_bounds);
             content_view.setAutoresizingMask_(NSViewWidthSizable | NSViewHeightSizable);
-            content_view.setWantsBestResolutionOpenGLSurface_(YES);
+            content_view.setWantsBestResolutionOpenGLSurface_(hidpi);
 
             let nondraggable_region_bounds =
                 NSRect::new(NSPoint::new(0., 0.),
@@ -581,7 +670,40 @@ impl Window {
         }
     }
 
-    fn create_context(view: id, pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>)
+    /// Returns false if the color space isn't available on this version of macOS.
+    fn set_color_space(window: id, view: id, color_space: ColorSpace) -> bool {
+        unsafe {
+            let color_space_class = Class::get("NSColorSpace").unwrap();
+            let constructor = match color_space {
+                ColorSpace::Srgb => sel!(sRGBColorSpace),
+                ColorSpace::DisplayP3 => sel!(displayP3ColorSpace),
+                ColorSpace::ExtendedSrgb => sel!(extendedSRGBColorSpace),
+            };
+
+            let responds: BOOL = msg_send![color_space_class, respondsToSelector:constructor];
+            if responds == NO {
+                return false;
+            }
+
+            let ns_color_space: id = msg_send![color_space_class, performSelector:constructor];
+            let _: () = msg_send![window, setColorSpace:ns_color_space];
+
+            if color_space == ColorSpace::ExtendedSrgb {
+                // without this, values outside of [0, 1] are clamped before reaching the window
+                let responds: BOOL = msg_send![view,
+                    respondsToSelector:sel!(setWantsExtendedDynamicRangeOpenGLSurface:)];
+                if responds == NO {
+                    return false;
+                }
+                let _: () = msg_send![view, setWantsExtendedDynamicRangeOpenGLSurface:YES];
+            }
+
+            true
+        }
+    }
+
+    fn create_context(view: id, pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>,
+                      transparent: bool, share_context: Option<CGLContextObj>)
                       -> Result<(IdRef, PixelFormat), CreationError>
     {
         let attributes = try!(helpers::build_nsattributes(pf_reqs, opengl));
@@ -591,7 +713,28 @@ impl Window {
             if let Some(pixelformat) = pixelformat.non_nil() {
 
                 // TODO: Add context sharing
-                let context = IdRef::new(NSOpenGLContext::alloc(nil).initWithFormat_shareContext_(*pixelformat, nil));
+                let context = match share_context {
+                    None => IdRef::new(NSOpenGLContext::alloc(nil).initWithFormat_shareContext_(*pixelformat, nil)),
+                    Some(share_context) => {
+                        // NSOpenGLContext can only share with another NSOpenGLContext, so the
+                        // context is created by CGL in the share group of `share_context`
+                        let cgl_pixelformat: CGLPixelFormatObj =
+                            msg_send![*pixelformat, CGLPixelFormatObj];
+                        let mut cgl_context = ptr::null_mut();
+                        if CGLCreateContext(cgl_pixelformat, share_context,
+                                            &mut cgl_context) != kCGLNoError
+                        {
+                            return Err(OsError(format!("CGLCreateContext failed")));
+                        }
+
+                        let context = NSOpenGLContext::alloc(nil)
+                                              .initWithCGLContextObj_(cgl_context as *mut _);
+                        let context = IdRef::new(context);
+                        // the NSOpenGLContext retains the CGL context, this releases our reference
+                        CGLDestroyContext(cgl_context);
+                        context
+                    },
+                };
 
                 if let Some(cxt) = context.non_nil() {
                     let pf = {
@@ -621,13 +764,30 @@ impl Window {
                                 None
                             },
                             srgb: true,
+                            implicit_resolve: false,
+                            packed_depth_stencil: is_packed_depth_stencil(
+                                get_attr(appkit::NSOpenGLPFADepthSize) as u8,
+                                get_attr(appkit::NSOpenGLPFAStencilSize) as u8),
+                            coverage_samples: None,
                         }
                     };
 
+                    // NSOpenGLPixelFormat picks the closest format, which may have more bits
+                    if !pf_reqs.bits_satisfied_by(&pf) {
+                        return Err(CreationError::NoAvailablePixelFormat);
+                    }
+                    try!(pf_reqs.check_strict(&pf));
+
                     NSOpenGLContext::setView_(*cxt, view);
                     let value = if opengl.vsync { 1 } else { 0 };
                     cxt.setValues_forParameter_(&value, appkit::NSOpenGLContextParameter::NSOpenGLCPSwapInterval);
 
+                    if transparent {
+                        // lets the alpha channel of the surface show the window behind
+                        let opacity = 0;
+                        cxt.setValues_forParameter_(&opacity, appkit::NSOpenGLContextParameter::NSOpenGLCPSurfaceOpacity);
+                    }
+
                     CGLEnable(cxt.CGLContextObj() as *mut _, kCGLCECrashOnRemovedFunctions);
 
                     Ok((cxt, pf))
@@ -840,6 +1000,56 @@ impl Window {
         *self.window as *mut libc::c_void
     }
 
+    /// Blocks until the next refresh of the screen that the window is on.
+    pub fn wait_for_vblank(&self) -> Result<(), ContextError> {
+        let display = self.get_display_id();
+        let mut display_link = self.display_link.lock().unwrap();
+
+        let result = if display_link.is_none() {
+            display_link::DisplayLink::new(display).map(|link| *display_link = Some(link))
+        } else {
+            display_link.as_mut().unwrap().set_display(display)
+        };
+
+        if let Err(err) = result {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other, err)));
+        }
+
+        display_link.as_ref().unwrap().wait();
+        Ok(())
+    }
+
+    /// Returns the `CGDirectDisplayID` of the screen of the window.
+    fn get_display_id(&self) -> u32 {
+        unsafe {
+            let screen: id = msg_send![*self.window, screen];
+            if screen == nil {
+                return CGMainDisplayID();
+            }
+
+            let key = IdRef::new(NSString::alloc(nil).init_str("NSScreenNumber"));
+            let device_description = appkit::NSScreen::deviceDescription(screen);
+            let value: id = msg_send![device_description, objectForKey:*key];
+            if value == nil {
+                return CGMainDisplayID();
+            }
+
+            let screen_number: NSUInteger = msg_send![value, unsignedIntegerValue];
+            screen_number as u32
+        }
+    }
+
+    #[inline]
+    pub fn get_cgl_share_group(&self) -> *mut libc::c_void {
+        if *self.context == nil {
+            return ptr::null_mut();
+        }
+
+        unsafe {
+            CGLGetShareGroup(self.context.CGLContextObj() as CGLContextObj) as *mut libc::c_void
+        }
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, callback: Option<fn(u32, u32)>) {
         self.delegate.state.resize_handler = callback;
@@ -906,11 +1116,36 @@ impl Window {
 
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
+        surface_scale_factor(*self.window, *self.view)
+    }
+
+    /// Returns whether the screen of the window has a variable refresh rate, like ProMotion
+    /// and adaptive-sync displays. `None` before macOS 12, which doesn't report it.
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
         unsafe {
-            NSWindow::backingScaleFactor(*self.window) as f32
+            let screen: id = msg_send![*self.window, screen];
+            if screen == nil {
+                return None;
+            }
+
+            let responds: BOOL = msg_send![screen, respondsToSelector:sel!(minimumRefreshInterval)];
+            if responds == NO {
+                return None;
+            }
+
+            let minimum: f64 = msg_send![screen, minimumRefreshInterval];
+            let maximum: f64 = msg_send![screen, maximumRefreshInterval];
+            Some(maximum > minimum)
         }
     }
 
+    /// The buffers can only be swapped by the thread where the context is current on this
+    /// platform.
+    #[inline]
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        None
+    }
+
     #[inline]
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
         let (window_x, window_y) = self.get_position().unwrap_or((0, 0));
@@ -927,11 +1162,68 @@ impl Window {
 
         Ok(())
     }
+
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
+}
+
+/// `NSOpenGLPixelBuffer` is deprecated, pbuffers aren't supported on OS/X.
+pub enum PBuffer {}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
 }
 
 impl GlContext for Window {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
+        if let Some(ref context) = self.angle_context {
+            return context.make_current();
+        }
+
         let _: () = msg_send![*self.context, update];
         self.context.makeCurrentContext();
         Ok(())
@@ -939,6 +1231,10 @@ impl GlContext for Window {
 
     #[inline]
     fn is_current(&self) -> bool {
+        if let Some(ref context) = self.angle_context {
+            return context.is_current();
+        }
+
         unsafe {
             let current = NSOpenGLContext::currentContext(nil);
             if current != nil {
@@ -950,20 +1246,39 @@ impl GlContext for Window {
         }
     }
 
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if let Some(ref context) = self.angle_context {
+            return context.make_not_current();
+        }
+
+        if self.is_current() {
+            NSOpenGLContext::clearCurrentContext(nil);
+        }
+        Ok(())
+    }
+
+    // NSOpenGLContext doesn't report failures, only the ANGLE context has errors to return
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        match self.angle_context {
+            Some(ref context) => context.destroy_context(),
+            None => Ok(()),
+        }
+    }
+
     fn get_proc_address(&self, addr: &str) -> *const () {
-        let symbol_name: CFString = FromStr::from_str(addr).unwrap();
-        let framework_name: CFString = FromStr::from_str("com.apple.opengl").unwrap();
-        let framework = unsafe {
-            CFBundleGetBundleWithIdentifier(framework_name.as_concrete_TypeRef())
-        };
-        let symbol = unsafe {
-            CFBundleGetFunctionPointerForName(framework, symbol_name.as_concrete_TypeRef())
-        };
-        symbol as *const _
+        if let Some(ref context) = self.angle_context {
+            return context.get_proc_address(addr);
+        }
+
+        helpers::get_proc_address(addr)
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
+        if let Some(ref context) = self.angle_context {
+            return context.swap_buffers();
+        }
+
         unsafe { 
             let pool = NSAutoreleasePool::new(nil);
             self.context.flushBuffer();
@@ -974,13 +1289,96 @@ impl GlContext for Window {
 
     #[inline]
     fn get_api(&self) -> ::Api {
-        ::Api::OpenGl
+        match self.angle_context {
+            Some(ref context) => context.get_api(),
+            None => ::Api::OpenGl,
+        }
     }
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
         self.pixel_format.clone()
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        match self.angle_context {
+            Some(ref context) => context.get_failed_versions(),
+            None => Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        match self.angle_context {
+            Some(ref context) => context.get_config_limits(),
+            // `NSOpenGLCPSwapInterval` is either on or off
+            None => ConfigLimits {
+                swap_interval_range: Some((0, 1)),
+                .. ConfigLimits::default()
+            },
+        }
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        match self.angle_context {
+            Some(ref context) => context.get_renderer_info(),
+            None => RendererInfo::default(),
+        }
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        match self.angle_context {
+            Some(ref context) => context.is_back_buffer_preserved(),
+            None => false,
+        }
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        match self.angle_context {
+            Some(ref context) => context.is_front_buffer_rendering(),
+            None => false,
+        }
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self.angle_context {
+            Some(ref context) => context.set_damage_region(rects),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        match self.angle_context {
+            Some(ref context) => context.set_hdr_metadata(metadata),
+            None => Err(ContextError::NotSupported("HDR metadata isn't supported by this backend")),
+        }
+    }
+
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        if let Some(ref context) = self.angle_context {
+            return context.set_swap_interval(interval);
+        }
+
+        // the compositor never shows a torn frame, so CGL has no equivalent of adaptive vsync
+        try!(check_swap_interval(&self.get_config_limits(), false, interval));
+
+        // `NSOpenGLCPSwapInterval` sets the same parameter, which takes effect at the next swap
+        let result = unsafe {
+            CGLSetParameter(self.context.CGLContextObj() as CGLContextObj, kCGLCPSwapInterval,
+                            &interval)
+        };
+        if result != kCGLNoError {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       format!("CGLSetParameter failed: {}", result))));
+        }
+        Ok(())
+    }
 }
 
 struct IdRef(id);
@@ -1,12 +1,18 @@
-#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+           target_os = "openbsd", target_os = "netbsd", target_os = "macos"))]
 #![allow(dead_code)]
 
 use std::os::raw::{c_void, c_char, c_int};
 
 pub const RTLD_LAZY: c_int = 0x001;
 pub const RTLD_NOW: c_int = 0x002;
+#[cfg(not(target_os = "macos"))]
+pub const RTLD_GLOBAL: c_int = 0x100;
+#[cfg(target_os = "macos")]
+pub const RTLD_GLOBAL: c_int = 0x8;
 
-#[link="dl"]
+// the BSDs provide the dynamic loader in the libc
+#[cfg_attr(target_os = "linux", link(name = "dl"))]
 extern {
     pub fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
     pub fn dlerror() -> *mut c_char;
@@ -0,0 +1,54 @@
+//! The libraries of the client APIs, for the EGL implementations whose `eglGetProcAddress`
+//! only returns extension functions.
+//!
+//! Before EGL 1.5 and `EGL_KHR_get_all_proc_addresses`, the core functions of OpenGL ES and
+//! OpenGL must be looked up in the library that implements the API. It is loaded the first
+//! time a context of that API needs it, so that a binary which creates both kinds of contexts
+//! doesn't load the library of an API it never uses.
+
+use Api;
+use libraries;
+
+use shared_library::dynamic_library::DynamicLibrary;
+
+use std::ptr;
+use std::sync::Mutex;
+
+lazy_static! {
+    // `None` until the library is first needed, `Some(None)` if it couldn't be loaded
+    static ref GLES: Mutex<Option<Option<DynamicLibrary>>> = Mutex::new(None);
+    static ref GL: Mutex<Option<Option<DynamicLibrary>>> = Mutex::new(None);
+}
+
+/// Returns the address of `name` in the library of `api`, or null if it isn't there.
+pub fn get_proc_address(api: Api, name: &str) -> *const () {
+    let library = match api {
+        Api::OpenGlEs => &*GLES,
+        Api::OpenGl => &*GL,
+        Api::WebGl => return ptr::null(),
+    };
+
+    let mut library = library.lock().unwrap();
+    if library.is_none() {
+        *library = Some(load(api));
+    }
+
+    match *library {
+        Some(Some(ref library)) => unsafe {
+            library.symbol::<()>(name).map(|p| p as *const ()).unwrap_or(ptr::null())
+        },
+        _ => ptr::null(),
+    }
+}
+
+fn load(api: Api) -> Option<DynamicLibrary> {
+    for path in libraries::client_api_candidates(api) {
+        if let Ok(library) = DynamicLibrary::open(Some(&path)) {
+            debug_log!("loaded the {:?} library {:?}", api, path);
+            return Some(library);
+        }
+    }
+
+    debug_log!("none of the {:?} libraries could be loaded", api);
+    None
+}
@@ -0,0 +1,216 @@
+//! Sharing of initialized EGL displays between contexts.
+//!
+//! `eglInitialize` and `eglTerminate` are not reference-counted by EGL: terminating a display
+//! invalidates it for every context that uses it. Contexts therefore hold a `Display`, and the
+//! display is only terminated once the last of them is destroyed.
+
+use CreationError;
+use api::extensions::Extensions;
+use blob_cache;
+use leaks;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::CStr;
+use std::os::raw::{c_long, c_void};
+use std::{mem, ptr, slice};
+use std::sync::{Arc, Mutex, Weak};
+
+use super::ffi;
+
+lazy_static! {
+    // The initialized displays, keyed by their `EGLDisplay`.
+    //
+    // EGL returns the same `EGLDisplay` every time it is asked for the same native display and
+    // platform, so this is equivalent to keying by native display.
+    static ref DISPLAYS: Mutex<HashMap<usize, Weak<Display>>> = Mutex::new(HashMap::new());
+
+    // The displays that have been given the functions of the blob cache, which EGL only accepts
+    // once per display, even after it's terminated and initialized again.
+    static ref BLOB_CACHE_DISPLAYS: Mutex<HashSet<usize>> = Mutex::new(HashSet::new());
+}
+
+/// An initialized `EGLDisplay`. Calls `eglTerminate` when dropped.
+pub struct Display {
+    egl: ffi::egl::Egl,
+    display: ffi::egl::types::EGLDisplay,
+    version: (ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+    extensions: Extensions,
+    all_proc_addresses: bool,
+}
+
+unsafe impl Send for Display {}
+unsafe impl Sync for Display {}
+
+impl Display {
+    /// Initializes `display`, or returns the existing `Display` if another context already
+    /// initialized it.
+    pub fn initialize(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay)
+                      -> Result<Arc<Display>, CreationError>
+    {
+        let mut displays = DISPLAYS.lock().unwrap();
+
+        if let Some(existing) = displays.get(&(display as usize)).and_then(|d| d.upgrade()) {
+            return Ok(existing);
+        }
+
+        let version = unsafe {
+            let mut major: ffi::egl::types::EGLint = mem::uninitialized();
+            let mut minor: ffi::egl::types::EGLint = mem::uninitialized();
+
+            if egl.Initialize(display, &mut major, &mut minor) == 0 {
+                return Err(CreationError::OsError(format!("eglInitialize failed")))
+            }
+
+            (major, minor)
+        };
+
+        // the list of extensions supported by the client once initialized is different from the
+        // list of extensions obtained without a display
+        let extensions = if version >= (1, 2) {
+            unsafe { query_extensions(egl, display) }
+        } else {
+            Extensions::default()
+        };
+
+        if extensions.contains("EGL_ANDROID_blob_cache") {
+            unsafe { register_blob_cache(egl, display) };
+        }
+
+        let all_proc_addresses = unsafe {
+            returns_all_proc_addresses(egl, &extensions, version)
+        };
+
+        let shared = Arc::new(Display {
+            egl: egl.clone(),
+            display: display,
+            version: version,
+            extensions: extensions,
+            all_proc_addresses: all_proc_addresses,
+        });
+
+        displays.insert(display as usize, Arc::downgrade(&shared));
+        Ok(shared)
+    }
+
+    /// Returns the version of EGL supported by the display.
+    #[inline]
+    pub fn version(&self) -> (ffi::egl::types::EGLint, ffi::egl::types::EGLint) {
+        self.version
+    }
+
+    /// Returns the extensions of the display, which are only parsed once.
+    #[inline]
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns true if `eglGetProcAddress` returns the core functions of the client APIs too,
+    /// and not only the extension functions.
+    #[inline]
+    pub fn returns_all_proc_addresses(&self) -> bool {
+        self.all_proc_addresses
+    }
+}
+
+/// Returns the extensions of `display`, or the client extensions for `EGL_NO_DISPLAY`. The list
+/// is empty if `eglQueryString` fails.
+pub unsafe fn query_extensions(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay)
+                               -> Extensions
+{
+    let p = egl.QueryString(display, ffi::egl::EXTENSIONS as i32);
+    if p.is_null() {
+        Extensions::default()
+    } else {
+        Extensions::parse(&CStr::from_ptr(p).to_string_lossy())
+    }
+}
+
+/// Gives the functions of `set_egl_blob_cache` to the driver, if the application has set them.
+/// Called every time the display is initialized, but only gives them the first time.
+unsafe fn register_blob_cache(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay) {
+    use super::ffi::blob_cache::SetBlobCacheFuncsANDROID;
+
+    if blob_cache::callbacks().is_none() {
+        return;
+    }
+    if !BLOB_CACHE_DISPLAYS.lock().unwrap().insert(display as usize) {
+        return;
+    }
+
+    let f = egl.GetProcAddress(b"eglSetBlobCacheFuncsANDROID\0".as_ptr() as *const _)
+        as *const c_void;
+    if f.is_null() {
+        return;
+    }
+    let set_blob_cache_funcs: SetBlobCacheFuncsANDROID = mem::transmute(f);
+    set_blob_cache_funcs(display, set_blob, get_blob);
+}
+
+extern "system" fn set_blob(key: *const c_void, key_size: c_long, value: *const c_void,
+                            value_size: c_long)
+{
+    if let Some((_, set)) = blob_cache::callbacks() {
+        unsafe {
+            set(slice::from_raw_parts(key as *const u8, key_size as usize),
+                slice::from_raw_parts(value as *const u8, value_size as usize));
+        }
+    }
+}
+
+/// Returns the size of the blob, which the driver asks again with a large enough buffer if
+/// `value_size` is too small. Zero means that there is no blob for the key.
+extern "system" fn get_blob(key: *const c_void, key_size: c_long, value: *mut c_void,
+                            value_size: c_long) -> c_long
+{
+    let blob = match blob_cache::callbacks() {
+        Some((get, _)) => unsafe {
+            get(slice::from_raw_parts(key as *const u8, key_size as usize))
+        },
+        None => None,
+    };
+
+    match blob {
+        Some(blob) => {
+            if blob.len() <= value_size as usize {
+                unsafe { ptr::copy_nonoverlapping(blob.as_ptr(), value as *mut u8, blob.len()) };
+            }
+            blob.len() as c_long
+        },
+        None => 0,
+    }
+}
+
+/// The Android loader always returns the core functions, whatever the version of EGL.
+unsafe fn returns_all_proc_addresses(egl: &ffi::egl::Egl, extensions: &Extensions,
+                                     version: (ffi::egl::types::EGLint, ffi::egl::types::EGLint))
+                                     -> bool
+{
+    if cfg!(target_os = "android") || version >= (1, 5) ||
+       extensions.contains("EGL_KHR_get_all_proc_addresses")
+    {
+        return true;
+    }
+
+    // before EGL 1.5, querying the client extensions without a display is an error if
+    // `EGL_EXT_client_extensions` isn't supported, which only returns null
+    query_extensions(egl, ffi::egl::NO_DISPLAY).contains("EGL_KHR_client_get_all_proc_addresses")
+}
+
+impl Drop for Display {
+    fn drop(&mut self) {
+        let mut displays = DISPLAYS.lock().unwrap();
+
+        // another context may have re-registered the display between the moment the last
+        // reference was released and now, in which case it must stay initialized
+        let still_used = displays.get(&(self.display as usize))
+                                 .map(|d| d.upgrade().is_some())
+                                 .unwrap_or(false);
+        if still_used {
+            return;
+        }
+
+        displays.remove(&(self.display as usize));
+        leaks::check_display(self.display as usize);
+        unsafe { self.egl.Terminate(self.display); }
+    }
+}
@@ -20,6 +20,112 @@ pub mod egl {
     include!(concat!(env!("OUT_DIR"), "/egl_bindings.rs"));
 }
 
+/// Tokens of `EGL_EXT_surface_SMPTE2086_metadata` and `EGL_EXT_surface_CTA861_3_metadata`, which
+/// are more recent than the registry used to generate the bindings.
+pub mod hdr_metadata {
+    pub const SMPTE2086_DISPLAY_PRIMARY_RX_EXT: u32 = 0x3341;
+    pub const SMPTE2086_DISPLAY_PRIMARY_RY_EXT: u32 = 0x3342;
+    pub const SMPTE2086_DISPLAY_PRIMARY_GX_EXT: u32 = 0x3343;
+    pub const SMPTE2086_DISPLAY_PRIMARY_GY_EXT: u32 = 0x3344;
+    pub const SMPTE2086_DISPLAY_PRIMARY_BX_EXT: u32 = 0x3345;
+    pub const SMPTE2086_DISPLAY_PRIMARY_BY_EXT: u32 = 0x3346;
+    pub const SMPTE2086_WHITE_POINT_X_EXT: u32 = 0x3347;
+    pub const SMPTE2086_WHITE_POINT_Y_EXT: u32 = 0x3348;
+    pub const SMPTE2086_MAX_LUMINANCE_EXT: u32 = 0x3349;
+    pub const SMPTE2086_MIN_LUMINANCE_EXT: u32 = 0x334A;
+    pub const CTA861_3_MAX_CONTENT_LIGHT_LEVEL_EXT: u32 = 0x3360;
+    pub const CTA861_3_MAX_FRAME_AVERAGE_LEVEL_EXT: u32 = 0x3361;
+    pub const METADATA_SCALING_EXT: u32 = 50000;
+}
+
+/// Tokens of `EGL_ANGLE_platform_angle` and `EGL_ANGLE_platform_angle_metal`, which are not
+/// part of the Khronos registry.
+pub mod angle {
+    pub const PLATFORM_ANGLE_ANGLE: u32 = 0x3202;
+    pub const PLATFORM_ANGLE_TYPE_ANGLE: u32 = 0x3203;
+    pub const PLATFORM_ANGLE_TYPE_METAL_ANGLE: u32 = 0x3489;
+    pub const PLATFORM_ANGLE_NATIVE_PLATFORM_TYPE_ANGLE: u32 = 0x348F;
+}
+
+/// Tokens of `EGL_EXT_platform_xcb`, which is more recent than the registry used to generate
+/// the bindings.
+pub mod xcb {
+    pub const PLATFORM_XCB_EXT: u32 = 0x31DC;
+    pub const PLATFORM_XCB_SCREEN_EXT: u32 = 0x31DE;
+}
+
+/// Tokens of `EGL_KHR_fence_sync` and `EGL_ANDROID_native_fence_sync`, whose functions are
+/// loaded with `eglGetProcAddress` because the bindings don't include them.
+pub mod native_fence {
+    pub const SYNC_NATIVE_FENCE_ANDROID: u32 = 0x3144;
+    pub const SYNC_NATIVE_FENCE_FD_ANDROID: u32 = 0x3145;
+    pub const NO_NATIVE_FENCE_FD_ANDROID: i32 = -1;
+    pub const SYNC_FLUSH_COMMANDS_BIT_KHR: i32 = 0x0001;
+
+    use std::os::raw::c_void;
+
+    pub type CreateSyncKHR = extern "system" fn(*const c_void, u32, *const i32) -> *const c_void;
+    pub type ClientWaitSyncKHR = extern "system" fn(*const c_void, *const c_void, i32, u64)
+                                                    -> i32;
+    pub type DestroySyncKHR = extern "system" fn(*const c_void, *const c_void) -> u32;
+    pub type DupNativeFenceFDANDROID = extern "system" fn(*const c_void, *const c_void) -> i32;
+}
+
+/// The function of `EGL_ANDROID_blob_cache`, loaded with `eglGetProcAddress` because the
+/// bindings don't include it.
+pub mod blob_cache {
+    use libc::c_long;
+    use std::os::raw::c_void;
+
+    pub type SetBlobFuncANDROID = extern "system" fn(*const c_void, c_long, *const c_void, c_long);
+    pub type GetBlobFuncANDROID = extern "system" fn(*const c_void, c_long, *mut c_void, c_long)
+                                                     -> c_long;
+    pub type SetBlobCacheFuncsANDROID = extern "system" fn(*const c_void, SetBlobFuncANDROID,
+                                                           GetBlobFuncANDROID);
+}
+
+/// Tokens of `EGL_KHR_image_base` and of the extensions that create images from textures and
+/// from the buffers of other processes, whose functions are loaded with `eglGetProcAddress`
+/// because the bindings don't include them.
+pub mod image {
+    pub const IMAGE_PRESERVED_KHR: i32 = 0x30D2;
+    pub const GL_TEXTURE_2D_KHR: u32 = 0x30B1;
+    #[cfg(target_os = "android")]
+    pub const NATIVE_BUFFER_ANDROID: u32 = 0x3140;
+    pub const LINUX_DMA_BUF_EXT: u32 = 0x3270;
+    pub const LINUX_DRM_FOURCC_EXT: i32 = 0x3271;
+    pub const DRM_FORMAT_MOD_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+    /// The OpenGL targets of the textures created from images. External textures need
+    /// `GL_OES_EGL_image_external`, and are sampled with `samplerExternalOES`, which converts
+    /// YUV formats to RGB.
+    pub const GL_TEXTURE_2D: u32 = 0x0DE1;
+    pub const GL_TEXTURE_EXTERNAL_OES: u32 = 0x8D65;
+
+    /// The file descriptor, offset, pitch and low and high bits of the modifier of each plane.
+    /// The fourth plane and the modifiers need `EGL_EXT_image_dma_buf_import_modifiers`.
+    pub const DMA_BUF_PLANE_ATTRIBS: [[i32; 5]; 4] = [
+        [0x3272, 0x3273, 0x3274, 0x3443, 0x3444],
+        [0x3275, 0x3276, 0x3277, 0x3445, 0x3446],
+        [0x3278, 0x3279, 0x327A, 0x3447, 0x3448],
+        [0x3440, 0x3441, 0x3442, 0x3449, 0x344A],
+    ];
+
+    use std::os::raw::{c_int, c_void};
+
+    pub type CreateImageKHR = extern "system" fn(*const c_void, *const c_void, u32, *const c_void,
+                                                 *const i32) -> *const c_void;
+    pub type DestroyImageKHR = extern "system" fn(*const c_void, *const c_void) -> u32;
+    pub type ExportDMABUFImageQueryMESA = extern "system" fn(*const c_void, *const c_void,
+                                                             *mut c_int, *mut c_int, *mut u64)
+                                                             -> u32;
+    pub type ExportDMABUFImageMESA = extern "system" fn(*const c_void, *const c_void, *mut c_int,
+                                                        *mut i32, *mut i32) -> u32;
+    #[cfg(target_os = "android")]
+    pub type GetNativeClientBufferANDROID = extern "system" fn(*const c_void) -> *const c_void;
+    /// `glEGLImageTargetTexture2DOES` of `GL_OES_EGL_image`.
+    pub type EGLImageTargetTexture2DOES = extern "system" fn(u32, *const c_void);
+}
+
 pub type khronos_utime_nanoseconds_t = khronos_uint64_t;
 pub type khronos_uint64_t = libc::uint64_t;
 pub type khronos_ssize_t = libc::c_long;
@@ -33,5 +139,9 @@ pub type EGLNativeWindowType = winapi::HWND;
 pub type EGLNativeWindowType = *const libc::c_void;
 #[cfg(target_os = "android")]
 pub type EGLNativeWindowType = *const libc::c_void;
-#[cfg(any(target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#[cfg(any(target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd",
+          target_os = "netbsd"))]
+pub type EGLNativeWindowType = *const libc::c_void;
+/// The `CALayer` of the view, for ANGLE.
+#[cfg(target_os = "macos")]
 pub type EGLNativeWindowType = *const libc::c_void;
@@ -1,34 +1,86 @@
 #![cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
-           target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+           target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd",
+           target_os = "netbsd", target_os = "macos"))]
 #![allow(unused_variables)]
 
+use ConfigLimits;
 use ContextError;
+use ContextPriority;
 use CreationError;
+use EglPlatform;
+use FailedVersion;
 use GlAttributes;
 use GlContext;
 use GlRequest;
+use HdrMetadata;
 use PixelFormat;
 use PixelFormatRequirements;
+use Rect;
 use ReleaseBehavior;
+use RendererInfo;
 use Robustness;
 use Api;
-
-use std::ffi::{CStr, CString};
+use NativeError;
+use check_swap_interval;
+use create_with_fallbacks;
+use is_packed_depth_stencil;
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+          target_os = "openbsd", target_os = "netbsd"))]
+use {DmaBufPlane, DmaBufTexture};
+use api::Drawable;
+use api::extensions::Extensions;
+use c_str::with_c_str;
+use config_cache;
+use current;
+use gl_version;
+use leaks;
+use quirks::{self, Quirk};
+
+use std::ffi::CStr;
 use std::os::raw::{c_void, c_int};
-use std::{mem, ptr};
+use std::{io, mem, ptr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use self::display::Display;
 
+mod client;
+mod display;
 pub mod ffi;
 
+/// From `EGL_NV_coverage_sample`, which the bindings aren't generated for.
+const COVERAGE_BUFFERS_NV: c_int = 0x30E0;
+const COVERAGE_SAMPLES_NV: c_int = 0x30E1;
+
+/// From `EGL_EXT_protected_content`, which the bindings aren't generated for either.
+const PROTECTED_CONTENT_EXT: c_int = 0x32C0;
+
+/// From `EGL_KHR_mutable_render_buffer`.
+const MUTABLE_RENDER_BUFFER_BIT_KHR: c_int = 0x1000;
+
+/// From `EGL_IMG_context_priority`.
+const CONTEXT_PRIORITY_LEVEL_IMG: c_int = 0x3100;
+const CONTEXT_PRIORITY_HIGH_IMG: c_int = 0x3101;
+const CONTEXT_PRIORITY_MEDIUM_IMG: c_int = 0x3102;
+const CONTEXT_PRIORITY_LOW_IMG: c_int = 0x3103;
+
 /// Specifies the type of display passed as `native_display`.
 pub enum NativeDisplay {
-    /// `None` means `EGL_DEFAULT_DISPLAY`.
-    X11(Option<ffi::EGLNativeDisplayType>),
+    /// `None` means `EGL_DEFAULT_DISPLAY`, followed by the screen. `None` means the default
+    /// screen. The screen is ignored if the EGL implementation lacks `eglGetPlatformDisplay`.
+    X11(Option<ffi::EGLNativeDisplayType>, Option<c_int>),
+    /// An `xcb_connection_t`, followed by the screen like for `X11`. Only usable with
+    /// `EGL_EXT_platform_xcb`.
+    Xcb(ffi::EGLNativeDisplayType, Option<c_int>),
     /// `None` means `EGL_DEFAULT_DISPLAY`.
     Gbm(Option<ffi::EGLNativeDisplayType>),
     /// `EGL_DEFAULT_DISPLAY` is mandatory for Android.
     Android,
     // TODO: should be `EGLDeviceEXT`
     Device(ffi::EGLNativeDisplayType),
+    /// The Metal renderer of ANGLE, through `EGL_ANGLE_platform_angle_metal`. Falls back to
+    /// `EGL_DEFAULT_DISPLAY` if the EGL implementation doesn't support it.
+    AngleMetal,
     /// Don't specify any display type. Useful on windows. `None` means `EGL_DEFAULT_DISPLAY`.
     Other(Option<ffi::EGLNativeDisplayType>),
 }
@@ -36,60 +88,146 @@ pub enum NativeDisplay {
 pub struct Context {
     egl: ffi::egl::Egl,
     display: ffi::egl::types::EGLDisplay,
+    shared_display: Arc<Display>,
     context: ffi::egl::types::EGLContext,
-    surface: ffi::egl::types::EGLSurface,
+    // only replaced by `recreate_surface`, in the thread where the context is current, and
+    // null if the surface couldn't be created again
+    surface: AtomicPtr<c_void>,
+    // the window of `surface`, or `None` for the pbuffer of a headless context
+    native_window: Option<ffi::EGLNativeWindowType>,
+    config_id: ffi::egl::types::EGLConfig,
     api: Api,
     pixel_format: PixelFormat,
+    config_limits: ConfigLimits,
+    renderer_info: RendererInfo,
+    quirks: Vec<Quirk>,
+    back_buffer_preserved: bool,
+    front_buffer_rendering: bool,
+    partial_update: bool,
+    smpte2086_metadata: bool,
+    cta861_3_metadata: bool,
+    native_fence_sync: bool,
+    // the window surface is created again with the same attributes
+    protected_content: bool,
+    failed_versions: Vec<FailedVersion>,
+    // set by `destroy_context`, after which the destructor has nothing left to do
+    destroyed: AtomicBool,
+    tracker: leaks::Tracker,
 }
 
-#[cfg(target_os = "android")]
+// with static bindings, there is no way to know whether `eglGetPlatformDisplay` is available
+#[cfg(any(target_os = "android", feature = "static-egl"))]
 #[inline]
-fn get_native_display(egl: &ffi::egl::Egl,
-                      native_display: NativeDisplay) -> *const c_void {
-    unsafe { egl.GetDisplay(ffi::egl::DEFAULT_DISPLAY as *mut _) }
+fn get_native_display(egl: &ffi::egl::Egl, native_display: NativeDisplay,
+                      platform: EglPlatform) -> Result<*const c_void, CreationError>
+{
+    match platform {
+        EglPlatform::Auto | EglPlatform::Unspecified => Ok(get_legacy_display(egl, native_display)),
+        // `eglGetDisplay` always returns an Android display on Android
+        EglPlatform::Android if cfg!(target_os = "android") => {
+            Ok(get_legacy_display(egl, native_display))
+        },
+        _ => Err(CreationError::OsError(format!("The EGL platform {:?} is not supported",
+                                                platform))),
+    }
 }
 
-#[cfg(not(target_os = "android"))]
-fn get_native_display(egl: &ffi::egl::Egl,
-                      native_display: NativeDisplay) -> *const c_void {
-    // the first step is to query the list of extensions without any display, if supported
-    let dp_extensions = unsafe {
-        let p = egl.QueryString(ffi::egl::NO_DISPLAY, ffi::egl::EXTENSIONS as i32);
-
-        // this possibility is available only with EGL 1.5 or EGL_EXT_platform_base, otherwise
-        // `eglQueryString` returns an error
-        if p.is_null() {
-            vec![]
-        } else {
-            let p = CStr::from_ptr(p);
-            let list = String::from_utf8(p.to_bytes().to_vec()).unwrap_or_else(|_| format!(""));
-            list.split(' ').map(|e| e.to_string()).collect::<Vec<_>>()
-        }
+#[cfg(not(any(target_os = "android", feature = "static-egl")))]
+fn get_native_display(egl: &ffi::egl::Egl, native_display: NativeDisplay,
+                      platform: EglPlatform) -> Result<*const c_void, CreationError>
+{
+    let matches_native_display = match (platform, &native_display) {
+        (EglPlatform::Auto, _) | (EglPlatform::Unspecified, _) => true,
+        (EglPlatform::X11, &NativeDisplay::X11(..)) => true,
+        (EglPlatform::Xcb, &NativeDisplay::Xcb(..)) => true,
+        (EglPlatform::Gbm, &NativeDisplay::Gbm(_)) => true,
+        (EglPlatform::Device, &NativeDisplay::Device(_)) => true,
+        (EglPlatform::Android, &NativeDisplay::Android) => true,
+        (EglPlatform::Angle, &NativeDisplay::X11(..)) => true,
+        _ => false,
     };
 
-    let has_dp_extension = |e: &str| dp_extensions.iter().find(|s| s == &e).is_some();
+    if !matches_native_display {
+        return Err(CreationError::OsError(format!("The EGL platform {:?} can't be used with \
+                                                   this kind of window", platform)));
+    }
 
-    match native_display {
+    if platform == EglPlatform::Unspecified {
+        return Ok(get_legacy_display(egl, native_display));
+    }
+
+    // the first step is to query the list of extensions without any display, if supported
+    //
+    // this possibility is available only with EGL 1.5 or EGL_EXT_platform_base, otherwise
+    // `eglQueryString` returns an error
+    let dp_extensions = unsafe { display::query_extensions(egl, ffi::egl::NO_DISPLAY) };
+
+    let has_dp_extension = |e: &str| dp_extensions.contains(e);
+
+    let display = match native_display {
         // Note: Some EGL implementations are missing the `eglGetPlatformDisplay(EXT)` symbol
         //       despite reporting `EGL_EXT_platform_base`. I'm pretty sure this is a bug.
         //       Therefore we detect whether the symbol is loaded in addition to checking for
         //       extensions.
-        NativeDisplay::X11(display) if has_dp_extension("EGL_KHR_platform_x11") &&
-                                       egl.GetPlatformDisplay.is_loaded() =>
+        // ANGLE doesn't implement the X11 platform of EGL, it wraps it in its own
+        NativeDisplay::X11(display, _) if (platform == EglPlatform::Angle ||
+                                           platform == EglPlatform::Auto) &&
+                                          has_dp_extension("EGL_ANGLE_platform_angle") &&
+                                          egl.GetPlatformDisplayEXT.is_loaded() =>
+        {
+            let d = display.unwrap_or(ffi::egl::DEFAULT_DISPLAY as *const _);
+            let attribs = [
+                ffi::angle::PLATFORM_ANGLE_NATIVE_PLATFORM_TYPE_ANGLE as c_int,
+                ffi::egl::PLATFORM_X11_EXT as c_int,
+                ffi::egl::NONE as c_int,
+            ];
+            unsafe { egl.GetPlatformDisplayEXT(ffi::angle::PLATFORM_ANGLE_ANGLE, d as *mut _,
+                                               attribs.as_ptr()) }
+        },
+
+        NativeDisplay::X11(display, screen) if has_dp_extension("EGL_KHR_platform_x11") &&
+                                               egl.GetPlatformDisplay.is_loaded() =>
         {
             let d = display.unwrap_or(ffi::egl::DEFAULT_DISPLAY as *const _);
-            // TODO: `PLATFORM_X11_SCREEN_KHR`
+            let attribs = match screen {
+                Some(screen) => vec![ffi::egl::PLATFORM_X11_SCREEN_KHR as ffi::egl::types::EGLAttrib,
+                                     screen as ffi::egl::types::EGLAttrib,
+                                     ffi::egl::NONE as ffi::egl::types::EGLAttrib],
+                None => vec![ffi::egl::NONE as ffi::egl::types::EGLAttrib],
+            };
             unsafe { egl.GetPlatformDisplay(ffi::egl::PLATFORM_X11_KHR, d as *mut _,
-                                            ptr::null()) }
+                                            attribs.as_ptr()) }
         },
 
-        NativeDisplay::X11(display) if has_dp_extension("EGL_EXT_platform_x11") &&
-                                       egl.GetPlatformDisplayEXT.is_loaded() =>
+        NativeDisplay::X11(display, screen) if has_dp_extension("EGL_EXT_platform_x11") &&
+                                               egl.GetPlatformDisplayEXT.is_loaded() =>
         {
             let d = display.unwrap_or(ffi::egl::DEFAULT_DISPLAY as *const _);
-            // TODO: `PLATFORM_X11_SCREEN_EXT`
+            let attribs = match screen {
+                Some(screen) => vec![ffi::egl::PLATFORM_X11_SCREEN_EXT as c_int, screen,
+                                     ffi::egl::NONE as c_int],
+                None => vec![ffi::egl::NONE as c_int],
+            };
             unsafe { egl.GetPlatformDisplayEXT(ffi::egl::PLATFORM_X11_EXT, d as *mut _,
-                                               ptr::null()) }
+                                               attribs.as_ptr()) }
+        },
+
+        NativeDisplay::Xcb(connection, screen) if has_dp_extension("EGL_EXT_platform_xcb") &&
+                                                  egl.GetPlatformDisplayEXT.is_loaded() =>
+        {
+            let attribs = match screen {
+                Some(screen) => vec![ffi::xcb::PLATFORM_XCB_SCREEN_EXT as c_int, screen,
+                                     ffi::egl::NONE as c_int],
+                None => vec![ffi::egl::NONE as c_int],
+            };
+            unsafe { egl.GetPlatformDisplayEXT(ffi::xcb::PLATFORM_XCB_EXT, connection as *mut _,
+                                               attribs.as_ptr()) }
+        },
+
+        // `eglGetDisplay` can't tell an XCB connection from an Xlib display
+        NativeDisplay::Xcb(..) => {
+            return Err(CreationError::OsError(format!("The EGL implementation doesn't support \
+                                                       EGL_EXT_platform_xcb")));
         },
 
         NativeDisplay::Gbm(display) if has_dp_extension("EGL_KHR_platform_gbm") &&
@@ -124,13 +262,77 @@ fn get_native_display(egl: &ffi::egl::Egl,
                                             ptr::null()) }
         },
 
-        NativeDisplay::X11(Some(display)) | NativeDisplay::Gbm(Some(display)) |
+        NativeDisplay::AngleMetal if has_dp_extension("EGL_ANGLE_platform_angle") &&
+                                     egl.GetPlatformDisplayEXT.is_loaded() =>
+        {
+            let attribs = [
+                ffi::angle::PLATFORM_ANGLE_TYPE_ANGLE as c_int,
+                ffi::angle::PLATFORM_ANGLE_TYPE_METAL_ANGLE as c_int,
+                ffi::egl::NONE as c_int,
+            ];
+            unsafe { egl.GetPlatformDisplayEXT(ffi::angle::PLATFORM_ANGLE_ANGLE,
+                                               ffi::egl::DEFAULT_DISPLAY as *mut _,
+                                               attribs.as_ptr()) }
+        },
+
+        // an explicit platform was requested, we don't let the implementation guess it
+        _ if platform != EglPlatform::Auto => {
+            return Err(CreationError::OsError(format!("The EGL platform {:?} is not supported",
+                                                      platform)));
+        },
+
+        native_display => get_legacy_display(egl, native_display),
+    };
+
+    Ok(display)
+}
+
+/// Looks up `name` with `eglGetProcAddress`, after looking it up in the library of `api` if
+/// the EGL implementation of `display` only returns the extension functions.
+fn get_proc_address(egl: &ffi::egl::Egl, display: &Display, api: Api, name: &str) -> *const () {
+    if !display.returns_all_proc_addresses() {
+        let address = client::get_proc_address(api, name);
+        if !address.is_null() {
+            return address;
+        }
+    }
+
+    with_c_str(name, |name| unsafe { egl.GetProcAddress(name) as *const _ })
+}
+
+/// Describes an error code returned by `eglGetError` after `function` failed.
+fn native_error(function: &'static str, code: u32) -> NativeError {
+    let name = match code {
+        ffi::egl::NOT_INITIALIZED => "EGL_NOT_INITIALIZED",
+        ffi::egl::BAD_ACCESS => "EGL_BAD_ACCESS",
+        ffi::egl::BAD_ALLOC => "EGL_BAD_ALLOC",
+        ffi::egl::BAD_ATTRIBUTE => "EGL_BAD_ATTRIBUTE",
+        ffi::egl::BAD_CONFIG => "EGL_BAD_CONFIG",
+        ffi::egl::BAD_CONTEXT => "EGL_BAD_CONTEXT",
+        ffi::egl::BAD_CURRENT_SURFACE => "EGL_BAD_CURRENT_SURFACE",
+        ffi::egl::BAD_DISPLAY => "EGL_BAD_DISPLAY",
+        ffi::egl::BAD_MATCH => "EGL_BAD_MATCH",
+        ffi::egl::BAD_NATIVE_PIXMAP => "EGL_BAD_NATIVE_PIXMAP",
+        ffi::egl::BAD_NATIVE_WINDOW => "EGL_BAD_NATIVE_WINDOW",
+        ffi::egl::BAD_PARAMETER => "EGL_BAD_PARAMETER",
+        ffi::egl::BAD_SURFACE => "EGL_BAD_SURFACE",
+        ffi::egl::CONTEXT_LOST => "EGL_CONTEXT_LOST",
+        _ => return NativeError { function: function, code: code, name: None },
+    };
+
+    NativeError { function: function, code: code, name: Some(name) }
+}
+
+/// Calls `eglGetDisplay`, which lets the implementation guess the platform of the display.
+fn get_legacy_display(egl: &ffi::egl::Egl, native_display: NativeDisplay) -> *const c_void {
+    match native_display {
+        NativeDisplay::X11(Some(display), _) | NativeDisplay::Gbm(Some(display)) |
         NativeDisplay::Device(display) | NativeDisplay::Other(Some(display)) => {
             unsafe { egl.GetDisplay(display as *mut _) }
         }
 
-        NativeDisplay::X11(None) | NativeDisplay::Gbm(None) |
-        NativeDisplay::Android | NativeDisplay::Other(None) => {
+        NativeDisplay::X11(None, _) | NativeDisplay::Gbm(None) | NativeDisplay::Android |
+        NativeDisplay::Xcb(..) | NativeDisplay::AngleMetal | NativeDisplay::Other(None) => {
             unsafe { egl.GetDisplay(ffi::egl::DEFAULT_DISPLAY as *mut _) }
         },
     }
@@ -143,41 +345,36 @@ impl Context {
     ///
     /// To finish the process, you must call `.finish(window)` on the `ContextPrototype`.
     pub fn new<'a>(egl: ffi::egl::Egl, pf_reqs: &PixelFormatRequirements,
-                   opengl: &'a GlAttributes<&'a Context>, native_display: NativeDisplay)
+                   opengl: &'a GlAttributes<&'a Context>, native_display: NativeDisplay,
+                   platform: EglPlatform)
                    -> Result<ContextPrototype<'a>, CreationError>
     {
-        if opengl.sharing.is_some() {
-            unimplemented!()
-        }
-
         // calling `eglGetDisplay` or equivalent
-        let display = get_native_display(&egl, native_display);
+        let display = try!(get_native_display(&egl, native_display, platform));
 
         if display.is_null() {
             return Err(CreationError::OsError("Could not create EGL display object".to_string()));
         }
 
-        let egl_version = unsafe {
-            let mut major: ffi::egl::types::EGLint = mem::uninitialized();
-            let mut minor: ffi::egl::types::EGLint = mem::uninitialized();
-
-            if egl.Initialize(display, &mut major, &mut minor) == 0 {
-                return Err(CreationError::OsError(format!("eglInitialize failed")))
-            }
-
-            (major, minor)
-        };
+        // calling `eglInitialize`, unless another context already did it
+        let shared_display = try!(Display::initialize(&egl, display));
+        let egl_version = shared_display.version();
 
-        // the list of extensions supported by the client once initialized is different from the
-        // list of extensions obtained earlier
-        let extensions = if egl_version >= (1, 2) {
-            let p = unsafe { CStr::from_ptr(egl.QueryString(display, ffi::egl::EXTENSIONS as i32)) };
-            let list = String::from_utf8(p.to_bytes().to_vec()).unwrap_or_else(|_| format!(""));
-            list.split(' ').map(|e| e.to_string()).collect::<Vec<_>>()
+        let extensions = shared_display.extensions();
 
-        } else {
-            vec![]
+        // the workarounds for the driver are applied to the pixel format
+        let quirks = unsafe {
+            let string = |name: u32| {
+                let p = egl.QueryString(display, name as i32);
+                if p.is_null() {
+                    format!("")
+                } else {
+                    CStr::from_ptr(p).to_string_lossy().into_owned()
+                }
+            };
+            quirks::active_quirks(&string(ffi::egl::VENDOR), &string(ffi::egl::VERSION))
         };
+        let pf_reqs = &try!(quirks::apply_to_pixel_format(&quirks, pf_reqs));
 
         // binding the right API and choosing the version
         let (version, api) = unsafe {
@@ -226,70 +423,1154 @@ impl Context {
                         (Some(opengles_version), Api::OpenGlEs)
                     }
                 },
+                // the fbconfig is chosen for the oldest version, the context creation then tries
+                // the versions of the range
+                GlRequest::Range { api: Api::OpenGlEs, min, .. } => {
+                    if egl_version >= (1, 2) {
+                        if egl.BindAPI(ffi::egl::OPENGL_ES_API) == 0 {
+                            return Err(CreationError::OpenGlVersionNotSupported);
+                        }
+                    }
+                    (Some(min), Api::OpenGlEs)
+                },
+                GlRequest::Range { api: Api::OpenGl, min, .. } => {
+                    if egl_version < (1, 4) {
+                        return Err(CreationError::OpenGlVersionNotSupported);
+                    }
+                    if egl.BindAPI(ffi::egl::OPENGL_API) == 0 {
+                        return Err(CreationError::OpenGlVersionNotSupported);
+                    }
+                    (Some(min), Api::OpenGl)
+                },
+                GlRequest::Range { .. } => return Err(CreationError::OpenGlVersionNotSupported),
+            }
+        };
+
+        let config = unsafe {
+            choose_fbconfig(&egl, display, &egl_version, extensions, api, version, pf_reqs,
+                            &quirks)
+        };
+        let (api, version, (config_id, pixel_format)) = match (config, opengl.version) {
+            (Ok(config), _) => (api, version, config),
+
+            // the implementation provides the OpenGL API but no config can render with it
+            (Err(_), GlRequest::GlThenGles { opengles_version, .. }) if api == Api::OpenGl => {
+                unsafe {
+                    if egl.BindAPI(ffi::egl::OPENGL_ES_API) == 0 {
+                        return Err(CreationError::OpenGlVersionNotSupported);
+                    }
+
+                    let config = try!(choose_fbconfig(&egl, display, &egl_version, extensions,
+                                                      Api::OpenGlEs, Some(opengles_version),
+                                                      pf_reqs, &quirks));
+                    (Api::OpenGlEs, Some(opengles_version), config)
+                }
+            },
+
+            (Err(err), _) => return Err(err),
+        };
+
+        let config_limits = unsafe { get_config_limits(&egl, display, config_id) };
+        let renderer_info = unsafe { get_renderer_info(&egl, display) };
+
+        Ok(ContextPrototype {
+            opengl: opengl,
+            egl: egl,
+            display: display,
+            shared_display: shared_display,
+            egl_version: egl_version,
+            api: api,
+            version: version,
+            config_id: config_id,
+            pixel_format: pixel_format,
+            config_limits: config_limits,
+            renderer_info: renderer_info,
+            quirks: quirks,
+            preserve_back_buffer: pf_reqs.preserve_back_buffer,
+            front_buffer_rendering: pf_reqs.front_buffer_rendering,
+            protected_content: false,
+        })
+    }
+}
+
+impl Context {
+    /// Creates a pbuffer surface with the config of this context.
+    ///
+    /// Returns `CreationError::NotSupported` if the config can't be used for pbuffers.
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        unsafe {
+            let mut surface_type = 0;
+            if self.egl.GetConfigAttrib(self.display, self.config_id,
+                                        ffi::egl::SURFACE_TYPE as ffi::egl::types::EGLint,
+                                        &mut surface_type) == 0
+            {
+                return Err(CreationError::OsError(format!("eglGetConfigAttrib failed")));
+            }
+            if surface_type & ffi::egl::PBUFFER_BIT as ffi::egl::types::EGLint == 0 ||
+               self.quirks.contains(&Quirk::NoPbuffers)
+            {
+                return Err(CreationError::NotSupported);
+            }
+
+            let attrs = surface_attributes(Some(dimensions), self.pixel_format.srgb, false);
+            let surface = self.egl.CreatePbufferSurface(self.display, self.config_id,
+                                                        attrs.as_ptr());
+            if surface.is_null() {
+                return Err(CreationError::OsError(format!("eglCreatePbufferSurface failed")));
+            }
+
+            Ok(PBuffer {
+                egl: self.egl.clone(),
+                display: self.display,
+                shared_display: self.shared_display.clone(),
+                context: self.context,
+                surface: surface,
+                api: self.api,
+                pixel_format: self.pixel_format.clone(),
+                tracker: leaks::Tracker::new("EGL pbuffer", surface as usize,
+                                             self.display as usize),
+            })
+        }
+    }
+
+    /// Creates a surface that renders into a native pixmap with the config of this context.
+    ///
+    /// Returns `CreationError::NotSupported` if the config can't be used for pixmaps.
+    ///
+    /// # Unsafety
+    ///
+    /// The pixmap must continue to exist as long as the resulting surface exists, and its depth
+    /// must match the one of the config.
+    pub unsafe fn create_pixmap_surface(&self, pixmap: ffi::EGLNativePixmapType)
+                                        -> Result<PixmapSurface, CreationError>
+    {
+        let mut surface_type = 0;
+        if self.egl.GetConfigAttrib(self.display, self.config_id,
+                                    ffi::egl::SURFACE_TYPE as ffi::egl::types::EGLint,
+                                    &mut surface_type) == 0
+        {
+            return Err(CreationError::OsError(format!("eglGetConfigAttrib failed")));
+        }
+        if surface_type & ffi::egl::PIXMAP_BIT as ffi::egl::types::EGLint == 0 {
+            return Err(CreationError::NotSupported);
+        }
+
+        let attrs = surface_attributes(None, self.pixel_format.srgb, false);
+        let surface = self.egl.CreatePixmapSurface(self.display, self.config_id, pixmap,
+                                                   attrs.as_ptr());
+        if surface.is_null() {
+            return Err(CreationError::OsError(format!("eglCreatePixmapSurface failed")));
+        }
+
+        Ok(PixmapSurface {
+            egl: self.egl.clone(),
+            display: self.display,
+            shared_display: self.shared_display.clone(),
+            context: self.context,
+            surface: surface,
+            api: self.api,
+            pixel_format: self.pixel_format.clone(),
+            tracker: leaks::Tracker::new("EGL pixmap surface", surface as usize,
+                                         self.display as usize),
+        })
+    }
+
+    /// Makes the context current, drawing into `draw` and reading from `read`.
+    ///
+    /// The surfaces of other contexts must have been created on the same display, with a
+    /// compatible config.
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Context, PBuffer, PixmapSurface>,
+                                             read: Drawable<Context, PBuffer, PixmapSurface>)
+                                             -> Result<(), ContextError>
+    {
+        let draw = try!(self.surface_for(draw));
+        let read = try!(self.surface_for(read));
+        if current::is_current(self.context as usize, draw as usize, read as usize) {
+            return Ok(());
+        }
+
+        let ret = self.egl.MakeCurrent(self.display, draw, read, self.context);
+
+        if ret == 0 {
+            current::forget();
+            match self.egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
+                ffi::egl::BAD_MATCH => {
+                    Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                             "the surfaces can't be used together")))
+                },
+                err => Err(ContextError::NativeError(native_error("eglMakeCurrent", err))),
+            }
+
+        } else {
+            current::set(self.context as usize, draw as usize, read as usize);
+            Ok(())
+        }
+    }
+
+    /// Creates a native fence that signals once the GPU has executed the commands submitted so
+    /// far, including the last swap, and returns its file descriptor, which belongs to the
+    /// caller. This is what a compositor or another API waits for with explicit
+    /// synchronization.
+    ///
+    /// The context must be current. Needs `EGL_ANDROID_native_fence_sync`.
+    pub fn create_native_fence_fd(&self) -> Result<c_int, ContextError> {
+        use self::ffi::native_fence::*;
+
+        if !self.native_fence_sync {
+            return Err(ContextError::NotSupported(
+                       "the EGL implementation doesn't support native fences"));
+        }
+        if !self.is_current() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "the context must be current to create a fence")));
+        }
+
+        unsafe {
+            let load = |name: &[u8]| {
+                self.egl.GetProcAddress(name.as_ptr() as *const _) as *const c_void
+            };
+            let functions = [load(b"eglCreateSyncKHR\0"), load(b"eglClientWaitSyncKHR\0"),
+                             load(b"eglDestroySyncKHR\0"), load(b"eglDupNativeFenceFDANDROID\0")];
+            if functions.iter().any(|f| f.is_null()) {
+                return Err(ContextError::NotSupported(
+                           "the native fence functions couldn't be loaded"));
+            }
+            let create: CreateSyncKHR = mem::transmute(functions[0]);
+            let client_wait: ClientWaitSyncKHR = mem::transmute(functions[1]);
+            let destroy: DestroySyncKHR = mem::transmute(functions[2]);
+            let dup: DupNativeFenceFDANDROID = mem::transmute(functions[3]);
+
+            let attribs = [
+                SYNC_NATIVE_FENCE_FD_ANDROID as i32, NO_NATIVE_FENCE_FD_ANDROID,
+                ffi::egl::NONE as i32,
+            ];
+            let sync = create(self.display, SYNC_NATIVE_FENCE_ANDROID, attribs.as_ptr());
+            if sync.is_null() {
+                let err = self.egl.GetError() as u32;
+                return Err(ContextError::NativeError(native_error("eglCreateSyncKHR", err)));
+            }
+
+            // the file descriptor only exists once the fence has been flushed
+            client_wait(self.display, sync, SYNC_FLUSH_COMMANDS_BIT_KHR, 0);
+            let fd = dup(self.display, sync);
+            let err = self.egl.GetError() as u32;
+            destroy(self.display, sync);
+
+            if fd == NO_NATIVE_FENCE_FD_ANDROID {
+                return Err(ContextError::NativeError(native_error("eglDupNativeFenceFDANDROID",
+                                                                  err)));
+            }
+            Ok(fd)
+        }
+    }
+
+    /// Exports a 2D texture of this context as dma-bufs, which another process imports with
+    /// `import_texture_dma_buf` once it has received their file descriptors. The file
+    /// descriptors belong to the caller.
+    ///
+    /// The context must be current. Needs `EGL_KHR_gl_texture_2D_image` and
+    /// `EGL_MESA_image_dma_buf_export`.
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd"))]
+    pub fn export_texture_dma_buf(&self, texture: u32, dimensions: (u32, u32))
+                                  -> Result<DmaBufTexture, ContextError>
+    {
+        use self::ffi::image::*;
+
+        let extensions = self.shared_display.extensions();
+        if !extensions.contains("EGL_KHR_gl_texture_2D_image") ||
+           !extensions.contains("EGL_MESA_image_dma_buf_export")
+        {
+            return Err(ContextError::NotSupported(
+                       "the EGL implementation can't export textures as dma-bufs"));
+        }
+        if !self.is_current() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "the context must be current to export a texture")));
+        }
+
+        unsafe {
+            let (create_image, destroy_image) = try!(self.load_image_functions());
+            let load = |name: &[u8]| {
+                self.egl.GetProcAddress(name.as_ptr() as *const _) as *const c_void
+            };
+            let functions = [load(b"eglExportDMABUFImageQueryMESA\0"),
+                             load(b"eglExportDMABUFImageMESA\0")];
+            if functions.iter().any(|f| f.is_null()) {
+                return Err(ContextError::NotSupported(
+                           "the dma-buf export functions couldn't be loaded"));
+            }
+            let query: ExportDMABUFImageQueryMESA = mem::transmute(functions[0]);
+            let export: ExportDMABUFImageMESA = mem::transmute(functions[1]);
+
+            let attribs = [IMAGE_PRESERVED_KHR, ffi::egl::TRUE as i32, ffi::egl::NONE as i32];
+            let image = create_image(self.display, self.context, GL_TEXTURE_2D_KHR,
+                                     texture as usize as *const c_void, attribs.as_ptr());
+            if image.is_null() {
+                let err = self.egl.GetError() as u32;
+                return Err(ContextError::NativeError(native_error("eglCreateImageKHR", err)));
+            }
+
+            // the modifiers are returned for each plane, so the number of planes comes first
+            let (mut fourcc, mut planes) = (0, 0);
+            let mut modifiers = [DRM_FORMAT_MOD_INVALID; 4];
+            let mut fds = [-1; 4];
+            let (mut strides, mut offsets) = ([0; 4], [0; 4]);
+            let exported = query(self.display, image, &mut fourcc, &mut planes,
+                                 ptr::null_mut()) != 0 &&
+                           planes >= 1 && planes <= 4 &&
+                           query(self.display, image, &mut fourcc, &mut planes,
+                                 modifiers.as_mut_ptr()) != 0 &&
+                           export(self.display, image, fds.as_mut_ptr(), strides.as_mut_ptr(),
+                                  offsets.as_mut_ptr()) != 0;
+            let err = self.egl.GetError() as u32;
+            destroy_image(self.display, image);
+
+            if !exported {
+                for &fd in fds.iter().filter(|&&fd| fd >= 0) {
+                    libc::close(fd);
+                }
+                return Err(ContextError::NativeError(native_error("eglExportDMABUFImageMESA",
+                                                                  err)));
+            }
+
+            Ok(DmaBufTexture {
+                dimensions: dimensions,
+                fourcc: fourcc as u32,
+                modifier: if modifiers[0] == DRM_FORMAT_MOD_INVALID {
+                    None
+                } else {
+                    Some(modifiers[0])
+                },
+                planes: (0 .. planes as usize).map(|i| DmaBufPlane {
+                    fd: fds[i],
+                    offset: offsets[i] as u32,
+                    stride: strides[i] as u32,
+                }).collect(),
+            })
+        }
+    }
+
+    /// Creates a 2D texture with the content of dma-bufs, for example exported by another
+    /// process with `export_texture_dma_buf`, and returns its name. The file descriptors still
+    /// belong to the caller, which can close them afterwards.
+    ///
+    /// The context must be current. Needs `EGL_EXT_image_dma_buf_import`, and
+    /// `EGL_EXT_image_dma_buf_import_modifiers` for a modifier or a fourth plane.
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd"))]
+    #[inline]
+    pub fn import_texture_dma_buf(&self, texture: &DmaBufTexture) -> Result<u32, ContextError> {
+        self.import_dma_buf(texture, ffi::image::GL_TEXTURE_2D)
+    }
+
+    /// Like `import_texture_dma_buf`, with an external texture instead, which is how the
+    /// frames of VA-API and V4L2 decoders are sampled without converting them from YUV first.
+    ///
+    /// Also needs `GL_OES_EGL_image_external`.
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd"))]
+    #[inline]
+    pub fn import_external_texture_dma_buf(&self, texture: &DmaBufTexture)
+                                           -> Result<u32, ContextError>
+    {
+        self.import_dma_buf(texture, ffi::image::GL_TEXTURE_EXTERNAL_OES)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd"))]
+    fn import_dma_buf(&self, texture: &DmaBufTexture, target: u32) -> Result<u32, ContextError> {
+        use self::ffi::image::*;
+
+        let extensions = self.shared_display.extensions();
+        let modifiers = extensions.contains("EGL_EXT_image_dma_buf_import_modifiers");
+        if !extensions.contains("EGL_EXT_image_dma_buf_import") ||
+           (texture.modifier.is_some() || texture.planes.len() > 3) && !modifiers
+        {
+            return Err(ContextError::NotSupported(
+                       "the EGL implementation can't import these dma-bufs"));
+        }
+        if texture.planes.is_empty() || texture.planes.len() > 4 {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "a dma-buf texture has between one and four planes")));
+        }
+        if !self.is_current() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "the context must be current to import a texture")));
+        }
+
+        let mut attribs = vec![
+            ffi::egl::WIDTH as i32, texture.dimensions.0 as i32,
+            ffi::egl::HEIGHT as i32, texture.dimensions.1 as i32,
+            LINUX_DRM_FOURCC_EXT, texture.fourcc as i32,
+        ];
+        for (plane, names) in texture.planes.iter().zip(DMA_BUF_PLANE_ATTRIBS.iter()) {
+            attribs.extend_from_slice(&[names[0], plane.fd, names[1], plane.offset as i32,
+                                        names[2], plane.stride as i32]);
+            if let Some(modifier) = texture.modifier {
+                attribs.extend_from_slice(&[names[3], modifier as u32 as i32,
+                                            names[4], (modifier >> 32) as u32 as i32]);
+            }
+        }
+        attribs.push(ffi::egl::NONE as i32);
+
+        unsafe {
+            let (create_image, destroy_image) = try!(self.load_image_functions());
+            let image = create_image(self.display, ffi::egl::NO_CONTEXT, LINUX_DMA_BUF_EXT,
+                                     ptr::null(), attribs.as_ptr());
+            if image.is_null() {
+                let err = self.egl.GetError() as u32;
+                return Err(ContextError::NativeError(native_error("eglCreateImageKHR", err)));
+            }
+
+            // the texture keeps the buffers alive after the image is destroyed
+            let texture = self.create_texture_from_image(image, target, None);
+            destroy_image(self.display, image);
+            texture
+        }
+    }
+
+    /// Creates a 2D texture with the content of an `AHardwareBuffer`, which other processes
+    /// can share with `AHardwareBuffer_sendHandleToUnixSocket`, and returns its name.
+    ///
+    /// The context must be current. Needs `EGL_ANDROID_get_native_client_buffer` and
+    /// `EGL_ANDROID_image_native_buffer`.
+    ///
+    /// # Unsafety
+    ///
+    /// `buffer` must be a valid `AHardwareBuffer`. The texture holds a reference to it.
+    #[cfg(target_os = "android")]
+    #[inline]
+    pub unsafe fn import_texture_hardware_buffer(&self, buffer: *mut c_void)
+                                                 -> Result<u32, ContextError>
+    {
+        self.import_hardware_buffer(buffer, ffi::image::GL_TEXTURE_2D, None)
+    }
+
+    /// Like `import_texture_hardware_buffer`, with an external texture instead, which is
+    /// needed for the YUV buffers of `MediaCodec` and of the camera.
+    ///
+    /// Also needs `GL_OES_EGL_image_external`.
+    #[cfg(target_os = "android")]
+    #[inline]
+    pub unsafe fn import_external_texture_hardware_buffer(&self, buffer: *mut c_void)
+                                                          -> Result<u32, ContextError>
+    {
+        self.import_hardware_buffer(buffer, ffi::image::GL_TEXTURE_EXTERNAL_OES, None)
+    }
+
+    /// Replaces the storage of an external texture with an `AHardwareBuffer`, like
+    /// `SurfaceTexture.updateTexImage` does with the latest frame. The texture is left bound to
+    /// `GL_TEXTURE_EXTERNAL_OES` of the active texture unit.
+    ///
+    /// # Unsafety
+    ///
+    /// `buffer` must be a valid `AHardwareBuffer`.
+    #[cfg(target_os = "android")]
+    #[inline]
+    pub unsafe fn update_external_texture_hardware_buffer(&self, texture: u32,
+                                                          buffer: *mut c_void)
+                                                          -> Result<(), ContextError>
+    {
+        self.import_hardware_buffer(buffer, ffi::image::GL_TEXTURE_EXTERNAL_OES, Some(texture))
+            .map(|_| ())
+    }
+
+    #[cfg(target_os = "android")]
+    unsafe fn import_hardware_buffer(&self, buffer: *mut c_void, target: u32,
+                                     texture: Option<u32>) -> Result<u32, ContextError>
+    {
+        use self::ffi::image::*;
+
+        let extensions = self.shared_display.extensions();
+        if !extensions.contains("EGL_ANDROID_get_native_client_buffer") ||
+           !extensions.contains("EGL_ANDROID_image_native_buffer")
+        {
+            return Err(ContextError::NotSupported(
+                       "the EGL implementation can't import hardware buffers"));
+        }
+        if !self.is_current() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "the context must be current to import a texture")));
+        }
+
+        let (create_image, destroy_image) = try!(self.load_image_functions());
+        let get_client_buffer = self.egl.GetProcAddress(
+            b"eglGetNativeClientBufferANDROID\0".as_ptr() as *const _) as *const c_void;
+        if get_client_buffer.is_null() {
+            return Err(ContextError::NotSupported(
+                       "eglGetNativeClientBufferANDROID couldn't be loaded"));
+        }
+        let get_client_buffer: GetNativeClientBufferANDROID = mem::transmute(get_client_buffer);
+
+        let client_buffer = get_client_buffer(buffer);
+        if client_buffer.is_null() {
+            let err = self.egl.GetError() as u32;
+            return Err(ContextError::NativeError(native_error("eglGetNativeClientBufferANDROID",
+                                                              err)));
+        }
+
+        let attribs = [IMAGE_PRESERVED_KHR, ffi::egl::TRUE as i32, ffi::egl::NONE as i32];
+        let image = create_image(self.display, ffi::egl::NO_CONTEXT, NATIVE_BUFFER_ANDROID,
+                                 client_buffer, attribs.as_ptr());
+        if image.is_null() {
+            let err = self.egl.GetError() as u32;
+            return Err(ContextError::NativeError(native_error("eglCreateImageKHR", err)));
+        }
+
+        let texture = self.create_texture_from_image(image, target, texture);
+        destroy_image(self.display, image);
+        texture
+    }
+
+    /// Creates an external texture without storage, and returns its name. It's left bound to
+    /// `GL_TEXTURE_EXTERNAL_OES` of the active texture unit.
+    ///
+    /// This is the texture that an Android `SurfaceTexture` is created with: each
+    /// `updateTexImage` then binds the latest frame of the `Surface` of the video decoder or of
+    /// the camera to it, without copying it.
+    ///
+    /// The context must be current. Needs `GL_OES_EGL_image_external`.
+    pub fn create_external_texture(&self) -> Result<u32, ContextError> {
+        if !self.is_current() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "the context must be current to create a texture")));
+        }
+
+        unsafe {
+            try!(self.check_external_textures());
+
+            let load = |name| GlContext::get_proc_address(self, name) as *const c_void;
+            let functions = [load("glGenTextures"), load("glBindTexture")];
+            if functions.iter().any(|f| f.is_null()) {
+                return Err(ContextError::NotSupported("the texture functions couldn't be loaded"));
+            }
+            let gen_textures: extern "system" fn(i32, *mut u32) = mem::transmute(functions[0]);
+            let bind_texture: extern "system" fn(u32, u32) = mem::transmute(functions[1]);
+
+            // the default filters and wrap modes of external textures are the only ones that
+            // every driver supports, linear without mipmaps and clamped to the edges
+            let mut texture = 0;
+            gen_textures(1, &mut texture);
+            bind_texture(ffi::image::GL_TEXTURE_EXTERNAL_OES, texture);
+            Ok(texture)
+        }
+    }
+
+    /// Returns an error if the current context doesn't support `GL_OES_EGL_image_external`.
+    unsafe fn check_external_textures(&self) -> Result<(), ContextError> {
+        let get_string = GlContext::get_proc_address(self, "glGetString") as *const c_void;
+        if get_string.is_null() ||
+           !gl_version::has_extension(mem::transmute(get_string), "GL_OES_EGL_image_external")
+        {
+            return Err(ContextError::NotSupported(
+                       "the context doesn't support GL_OES_EGL_image_external"));
+        }
+        Ok(())
+    }
+
+    /// Loads `eglCreateImageKHR` and `eglDestroyImageKHR`.
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd", target_os = "android"))]
+    unsafe fn load_image_functions(&self)
+        -> Result<(ffi::image::CreateImageKHR, ffi::image::DestroyImageKHR), ContextError>
+    {
+        if !self.shared_display.extensions().contains("EGL_KHR_image_base") {
+            return Err(ContextError::NotSupported(
+                       "the EGL implementation doesn't support EGL_KHR_image_base"));
+        }
+
+        let load = |name: &[u8]| {
+            self.egl.GetProcAddress(name.as_ptr() as *const _) as *const c_void
+        };
+        let functions = [load(b"eglCreateImageKHR\0"), load(b"eglDestroyImageKHR\0")];
+        if functions.iter().any(|f| f.is_null()) {
+            return Err(ContextError::NotSupported("the EGLImage functions couldn't be loaded"));
+        }
+        Ok((mem::transmute(functions[0]), mem::transmute(functions[1])))
+    }
+
+    /// Creates a texture whose storage is `image` with `glEGLImageTargetTexture2DOES`, or
+    /// replaces the storage of `texture`, and leaves it bound to `target` of the active texture
+    /// unit.
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd", target_os = "android"))]
+    unsafe fn create_texture_from_image(&self, image: *const c_void, target: u32,
+                                        texture: Option<u32>) -> Result<u32, ContextError>
+    {
+        use self::ffi::image::EGLImageTargetTexture2DOES;
+
+        if target == ffi::image::GL_TEXTURE_EXTERNAL_OES {
+            try!(self.check_external_textures());
+        }
+
+        let load = |name| GlContext::get_proc_address(self, name) as *const c_void;
+        let functions = [load("glGenTextures"), load("glBindTexture"), load("glDeleteTextures"),
+                         load("glGetError"), load("glEGLImageTargetTexture2DOES")];
+        if functions.iter().any(|f| f.is_null()) {
+            return Err(ContextError::NotSupported(
+                       "glEGLImageTargetTexture2DOES couldn't be loaded"));
+        }
+        let gen_textures: extern "system" fn(i32, *mut u32) = mem::transmute(functions[0]);
+        let bind_texture: extern "system" fn(u32, u32) = mem::transmute(functions[1]);
+        let delete_textures: extern "system" fn(i32, *const u32) = mem::transmute(functions[2]);
+        let get_error: extern "system" fn() -> u32 = mem::transmute(functions[3]);
+        let image_target: EGLImageTargetTexture2DOES = mem::transmute(functions[4]);
+
+        // the errors of the previous commands aren't ours, a lost context may keep reporting
+        // some though
+        for _ in 0 .. 8 {
+            if get_error() == 0 {
+                break;
+            }
+        }
+
+        let (texture, created) = match texture {
+            Some(texture) => (texture, false),
+            None => {
+                let mut texture = 0;
+                gen_textures(1, &mut texture);
+                (texture, true)
+            },
+        };
+        bind_texture(target, texture);
+        image_target(target, image);
+        if get_error() != 0 {
+            if created {
+                delete_textures(1, &texture);
+            }
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "the driver can't use the image as a texture")));
+        }
+        Ok(texture)
+    }
+
+    #[inline]
+    fn surface(&self) -> ffi::egl::types::EGLSurface {
+        self.surface.load(Ordering::Acquire) as ffi::egl::types::EGLSurface
+    }
+
+    unsafe fn make_current_impl(&self) -> Result<(), ContextError> {
+        let surface = self.surface();
+        if surface.is_null() {
+            return Err(ContextError::SurfaceLost);
+        }
+        if current::is_current(self.context as usize, surface as usize, surface as usize) {
+            return Ok(());
+        }
+
+        let ret = self.egl.MakeCurrent(self.display, surface, surface, self.context);
+
+        if ret == 0 {
+            current::forget();
+            match self.egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
+                ffi::egl::BAD_SURFACE | ffi::egl::BAD_NATIVE_WINDOW => {
+                    return Err(ContextError::SurfaceLost)
+                },
+                err => Err(ContextError::NativeError(native_error("eglMakeCurrent", err))),
+            }
+
+        } else {
+            current::set(self.context as usize, surface as usize, surface as usize);
+            Ok(())
+        }
+    }
+
+    fn swap_buffers_impl(&self) -> Result<(), ContextError> {
+        let ret = unsafe {
+            self.egl.SwapBuffers(self.display, self.surface())
+        };
+
+        if ret == 0 {
+            match unsafe { self.egl.GetError() } as u32 {
+                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
+                ffi::egl::BAD_SURFACE | ffi::egl::BAD_NATIVE_WINDOW => {
+                    return Err(ContextError::SurfaceLost)
+                },
+                err => Err(ContextError::NativeError(native_error("eglSwapBuffers", err))),
+            }
+
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Replaces the surface with a new one for the same native window, after the driver has
+    /// reported that the surface is invalid.
+    ///
+    /// Returns false if there is no native window, or if the new surface can't be created
+    /// either, which happens when the native window has really been destroyed. The context then
+    /// has no surface, and the next swap or `make_current` tries again.
+    fn recreate_surface(&self) -> bool {
+        let native_window = match self.native_window {
+            Some(w) => w,
+            None => return false,
+        };
+
+        unsafe {
+            // a native window can only have one surface at a time, so the old surface is
+            // released and destroyed before the new one is created
+            let was_current = self.is_current();
+            if was_current {
+                current::forget();
+                self.egl.MakeCurrent(self.display, ffi::egl::NO_SURFACE, ffi::egl::NO_SURFACE,
+                                     ffi::egl::NO_CONTEXT);
+            }
+            let old = self.surface.swap(ptr::null_mut(), Ordering::AcqRel);
+            if !old.is_null() {
+                current::forget_object(old as usize);
+                self.egl.DestroySurface(self.display, old as ffi::egl::types::EGLSurface);
+            }
+
+            let attributes = surface_attributes(None, self.pixel_format.srgb,
+                                                self.protected_content);
+            let surface = self.egl.CreateWindowSurface(self.display, self.config_id,
+                                                       native_window, attributes.as_ptr());
+            if surface.is_null() {
+                debug_log!("the EGL surface can't be created again: {:#x}", self.egl.GetError());
+                return false;
+            }
+            debug_log!("the EGL surface has been created again");
+
+            if self.back_buffer_preserved {
+                self.egl.SurfaceAttrib(self.display, surface,
+                                       ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                                       ffi::egl::BUFFER_PRESERVED as ffi::egl::types::EGLint);
+            }
+            if self.front_buffer_rendering {
+                self.egl.SurfaceAttrib(self.display, surface,
+                                       ffi::egl::RENDER_BUFFER as ffi::egl::types::EGLint,
+                                       ffi::egl::SINGLE_BUFFER as ffi::egl::types::EGLint);
+            }
+
+            self.surface.store(surface as *mut c_void, Ordering::Release);
+            if was_current {
+                if self.egl.MakeCurrent(self.display, surface, surface, self.context) != 0 {
+                    current::set(self.context as usize, surface as usize, surface as usize);
+                }
+            }
+        }
+        true
+    }
+
+    fn surface_for(&self, drawable: Drawable<Context, PBuffer, PixmapSurface>)
+                   -> Result<ffi::egl::types::EGLSurface, ContextError>
+    {
+        let (display, surface) = match drawable {
+            Drawable::Default => return Ok(self.surface()),
+            Drawable::Window(context) => (context.display, context.surface()),
+            Drawable::PBuffer(pbuffer) => (pbuffer.display, pbuffer.surface),
+            Drawable::Pixmap(pixmap) => (pixmap.display, pixmap.surface),
+        };
+
+        if display != self.display {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                            "the surface uses another display")));
+        }
+
+        Ok(surface)
+    }
+}
+
+impl GlContext for Context {
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match self.make_current_impl() {
+            // some drivers invalidate the surface of a window that is still alive
+            Err(ContextError::SurfaceLost) if self.recreate_surface() => {
+                self.make_current_impl()
+            },
+            result => result,
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe { self.egl.GetCurrentContext() == self.context }
+    }
+
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        current::forget();
+        if self.egl.MakeCurrent(self.display, ffi::egl::NO_SURFACE, ffi::egl::NO_SURFACE,
+                                ffi::egl::NO_CONTEXT) == 0
+        {
+            let err = self.egl.GetError() as u32;
+            return Err(ContextError::NativeError(native_error("eglMakeCurrent", err)));
+        }
+        Ok(())
+    }
+
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        if self.destroyed.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        try!(self.make_not_current());
+        // the destructor must not try again, even if the driver refuses
+        self.destroyed.store(true, Ordering::Release);
+        self.tracker.release();
+        let surface = self.surface();
+        current::forget_object(self.context as usize);
+        current::forget_object(surface as usize);
+
+        // the context is destroyed even if its surface couldn't be
+        let surface_result = if surface.is_null() {
+            Ok(())
+        } else if self.egl.DestroySurface(self.display, surface) == 0 {
+            let err = self.egl.GetError() as u32;
+            Err(ContextError::NativeError(native_error("eglDestroySurface", err)))
+        } else {
+            Ok(())
+        };
+        if self.egl.DestroyContext(self.display, self.context) == 0 {
+            let err = self.egl.GetError() as u32;
+            return Err(ContextError::NativeError(native_error("eglDestroyContext", err)));
+        }
+        surface_result
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        get_proc_address(&self.egl, &self.shared_display, self.api, addr)
+    }
+
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match self.swap_buffers_impl() {
+            // the frame is lost with the old surface, but the next one is drawn into the new one
+            Err(ContextError::SurfaceLost) if self.recreate_surface() => {
+                self.swap_buffers_impl()
+            },
+            result => result,
+        }
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.api
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.failed_versions.clone()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.config_limits.clone()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.renderer_info.clone()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.back_buffer_preserved
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        self.front_buffer_rendering
+    }
+
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        try!(check_swap_interval(&self.config_limits, false, interval));
+
+        // applies to the surface that is current in this thread
+        if unsafe { self.egl.SwapInterval(self.display, interval) } == 0 {
+            let err = unsafe { self.egl.GetError() } as u32;
+            return Err(ContextError::NativeError(native_error("eglSwapInterval", err)));
+        }
+        Ok(())
+    }
+
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        if !self.partial_update {
+            return Ok(());
+        }
+
+        // this is called every frame, usually with a few rects that are converted on the stack
+        let mut stack = [0; 64];
+        let mut heap;
+        let attribs = if rects.len() * 4 <= stack.len() {
+            &mut stack[..rects.len() * 4]
+        } else {
+            heap = vec![0; rects.len() * 4];
+            &mut heap[..]
+        };
+        for (rect, attrib) in rects.iter().zip(attribs.chunks_mut(4)) {
+            attrib[0] = rect.x as ffi::egl::types::EGLint;
+            attrib[1] = rect.y as ffi::egl::types::EGLint;
+            attrib[2] = rect.width as ffi::egl::types::EGLint;
+            attrib[3] = rect.height as ffi::egl::types::EGLint;
+        }
+
+        let ret = unsafe {
+            self.egl.SetDamageRegionKHR(self.display, self.surface(), attribs.as_mut_ptr(),
+                                        rects.len() as ffi::egl::types::EGLint)
+        };
+
+        if ret == 0 {
+            match unsafe { self.egl.GetError() } as u32 {
+                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
+                ffi::egl::BAD_ACCESS => {
+                    Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                        "the damage region must be set once per frame, before rendering")))
+                },
+                err => {
+                    Err(ContextError::NativeError(native_error("eglSetDamageRegionKHR", err)))
+                },
+            }
+
+        } else {
+            Ok(())
+        }
+    }
+
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        use self::ffi::hdr_metadata::*;
+
+        let has_light_levels = metadata.max_content_light_level.is_some() ||
+                               metadata.max_frame_average_light_level.is_some();
+        if !self.smpte2086_metadata || (has_light_levels && !self.cta861_3_metadata) {
+            return Err(ContextError::NotSupported(
+                       "the EGL implementation doesn't support this HDR metadata"));
+        }
+
+        // the values are passed as integers multiplied by `EGL_METADATA_SCALING_EXT`
+        let scale = |value: f32| (value * METADATA_SCALING_EXT as f32).round() as c_int;
+
+        let mut attribs = vec![
+            (SMPTE2086_DISPLAY_PRIMARY_RX_EXT, metadata.display_primaries[0].0),
+            (SMPTE2086_DISPLAY_PRIMARY_RY_EXT, metadata.display_primaries[0].1),
+            (SMPTE2086_DISPLAY_PRIMARY_GX_EXT, metadata.display_primaries[1].0),
+            (SMPTE2086_DISPLAY_PRIMARY_GY_EXT, metadata.display_primaries[1].1),
+            (SMPTE2086_DISPLAY_PRIMARY_BX_EXT, metadata.display_primaries[2].0),
+            (SMPTE2086_DISPLAY_PRIMARY_BY_EXT, metadata.display_primaries[2].1),
+            (SMPTE2086_WHITE_POINT_X_EXT, metadata.white_point.0),
+            (SMPTE2086_WHITE_POINT_Y_EXT, metadata.white_point.1),
+            (SMPTE2086_MAX_LUMINANCE_EXT, metadata.max_luminance),
+            (SMPTE2086_MIN_LUMINANCE_EXT, metadata.min_luminance),
+        ];
+        if let Some(level) = metadata.max_content_light_level {
+            attribs.push((CTA861_3_MAX_CONTENT_LIGHT_LEVEL_EXT, level));
+        }
+        if let Some(level) = metadata.max_frame_average_light_level {
+            attribs.push((CTA861_3_MAX_FRAME_AVERAGE_LEVEL_EXT, level));
+        }
+
+        for (attrib, value) in attribs {
+            let ret = unsafe {
+                self.egl.SurfaceAttrib(self.display, self.surface(), attrib as c_int,
+                                       scale(value))
+            };
+
+            if ret == 0 {
+                return match unsafe { self.egl.GetError() } as u32 {
+                    ffi::egl::CONTEXT_LOST => Err(ContextError::ContextLost),
+                    err => Err(ContextError::NativeError(native_error("eglSurfaceAttrib", err))),
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// EGL handles can be used from any thread, and the fields that change are atomic
+unsafe impl Send for Context {}
+unsafe impl Sync for Context {}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        if self.destroyed.load(Ordering::Acquire) {
+            return;
+        }
+
+        let surface = self.surface();
+        current::forget_object(self.context as usize);
+        current::forget_object(surface as usize);
+        unsafe {
+            // we don't call MakeCurrent(0, 0) because we are not sure that the context
+            // is still the current one
+            self.egl.DestroyContext(self.display, self.context);
+            if !surface.is_null() {
+                self.egl.DestroySurface(self.display, surface);
+            }
+            // the display is terminated when `shared_display` is dropped, if no other context
+            // uses it
+        }
+    }
+}
+
+/// A pbuffer surface that is drawn with the context it has been created from.
+///
+/// Note: must be destroyed before its context.
+pub struct PBuffer {
+    egl: ffi::egl::Egl,
+    display: ffi::egl::types::EGLDisplay,
+    shared_display: Arc<Display>,
+    context: ffi::egl::types::EGLContext,
+    surface: ffi::egl::types::EGLSurface,
+    api: Api,
+    pixel_format: PixelFormat,
+    // only dropped, which forgets the object
+    #[allow(dead_code)]
+    tracker: leaks::Tracker,
+}
+
+impl GlContext for PBuffer {
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let surface = self.surface as usize;
+        if current::is_current(self.context as usize, surface, surface) {
+            return Ok(());
+        }
+
+        let ret = self.egl.MakeCurrent(self.display, self.surface, self.surface, self.context);
+
+        if ret == 0 {
+            current::forget();
+            match self.egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
+                err => Err(ContextError::NativeError(native_error("eglMakeCurrent", err))),
+            }
+
+        } else {
+            current::set(self.context as usize, surface, surface);
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe {
+            self.egl.GetCurrentContext() == self.context &&
+            self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint) == self.surface
+        }
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        get_proc_address(&self.egl, &self.shared_display, self.api, addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        // pbuffers have no back buffer, `eglSwapBuffers` has no effect on them
+        Ok(())
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.api
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+}
+
+unsafe impl Send for PBuffer {}
+unsafe impl Sync for PBuffer {}
+
+impl Drop for PBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            // the surface isn't destroyed before it stops being current
+            if self.is_current() {
+                current::forget();
+                self.egl.MakeCurrent(self.display, ffi::egl::NO_SURFACE, ffi::egl::NO_SURFACE,
+                                     ffi::egl::NO_CONTEXT);
             }
-        };
+            current::forget_object(self.surface as usize);
 
-        let (config_id, pixel_format) = unsafe {
-            try!(choose_fbconfig(&egl, display, &egl_version, api, version, pf_reqs))
-        };
+            self.egl.DestroySurface(self.display, self.surface);
+        }
+    }
+}
 
-        Ok(ContextPrototype {
-            opengl: opengl,
-            egl: egl,
-            display: display,
-            egl_version: egl_version,
-            extensions: extensions,
-            api: api,
-            version: version,
-            config_id: config_id,
-            pixel_format: pixel_format,
-        })
+/// A pixmap surface that is drawn with the context it has been created from.
+///
+/// Note: must be destroyed before its context.
+pub struct PixmapSurface {
+    egl: ffi::egl::Egl,
+    display: ffi::egl::types::EGLDisplay,
+    shared_display: Arc<Display>,
+    context: ffi::egl::types::EGLContext,
+    surface: ffi::egl::types::EGLSurface,
+    api: Api,
+    pixel_format: PixelFormat,
+    // only dropped, which forgets the object
+    #[allow(dead_code)]
+    tracker: leaks::Tracker,
+}
+
+impl PixmapSurface {
+    /// Returns the dimensions of the pixmap, as reported by `eglQuerySurface`.
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        unsafe {
+            let (mut width, mut height) = (0, 0);
+            self.egl.QuerySurface(self.display, self.surface,
+                                  ffi::egl::WIDTH as ffi::egl::types::EGLint, &mut width);
+            self.egl.QuerySurface(self.display, self.surface,
+                                  ffi::egl::HEIGHT as ffi::egl::types::EGLint, &mut height);
+            (width as u32, height as u32)
+        }
     }
 }
 
-impl GlContext for Context {
+impl GlContext for PixmapSurface {
     unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let surface = self.surface as usize;
+        if current::is_current(self.context as usize, surface, surface) {
+            return Ok(());
+        }
+
         let ret = self.egl.MakeCurrent(self.display, self.surface, self.surface, self.context);
 
         if ret == 0 {
+            current::forget();
             match self.egl.GetError() as u32 {
                 ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
-                err => panic!("eglMakeCurrent failed (eglGetError returned 0x{:x})", err)
+                err => Err(ContextError::NativeError(native_error("eglMakeCurrent", err))),
             }
 
         } else {
+            current::set(self.context as usize, surface, surface);
             Ok(())
         }
     }
 
     #[inline]
     fn is_current(&self) -> bool {
-        unsafe { self.egl.GetCurrentContext() == self.context }
+        unsafe {
+            self.egl.GetCurrentContext() == self.context &&
+            self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint) == self.surface
+        }
     }
 
+    #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
-        let addr = CString::new(addr.as_bytes()).unwrap();
-        let addr = addr.as_ptr();
-        unsafe {
-            self.egl.GetProcAddress(addr) as *const _
-        }
+        get_proc_address(&self.egl, &self.shared_display, self.api, addr)
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
-        let ret = unsafe {
-            self.egl.SwapBuffers(self.display, self.surface)
-        };
-
-        if ret == 0 {
-            match unsafe { self.egl.GetError() } as u32 {
-                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
-                err => panic!("eglSwapBuffers failed (eglGetError returned 0x{:x})", err)
-            }
-
-        } else {
-            Ok(())
-        }
+        // pixmaps are single-buffered, this only makes the rendering visible to the native API
+        unsafe { self.egl.WaitClient(); }
+        Ok(())
     }
 
     #[inline]
@@ -303,17 +1584,20 @@ impl GlContext for Context {
     }
 }
 
-unsafe impl Send for Context {}
-unsafe impl Sync for Context {}
+unsafe impl Send for PixmapSurface {}
+unsafe impl Sync for PixmapSurface {}
 
-impl Drop for Context {
+impl Drop for PixmapSurface {
     fn drop(&mut self) {
         unsafe {
-            // we don't call MakeCurrent(0, 0) because we are not sure that the context
-            // is still the current one
-            self.egl.DestroyContext(self.display, self.context);
+            if self.is_current() {
+                current::forget();
+                self.egl.MakeCurrent(self.display, ffi::egl::NO_SURFACE, ffi::egl::NO_SURFACE,
+                                     ffi::egl::NO_CONTEXT);
+            }
+            current::forget_object(self.surface as usize);
+
             self.egl.DestroySurface(self.display, self.surface);
-            self.egl.Terminate(self.display);
         }
     }
 }
@@ -322,15 +1606,42 @@ pub struct ContextPrototype<'a> {
     opengl: &'a GlAttributes<&'a Context>,
     egl: ffi::egl::Egl,
     display: ffi::egl::types::EGLDisplay,
+    shared_display: Arc<Display>,
     egl_version: (ffi::egl::types::EGLint, ffi::egl::types::EGLint),
-    extensions: Vec<String>,
     api: Api,
     version: Option<(u8, u8)>,
     config_id: ffi::egl::types::EGLConfig,
     pixel_format: PixelFormat,
+    config_limits: ConfigLimits,
+    renderer_info: RendererInfo,
+    quirks: Vec<Quirk>,
+    preserve_back_buffer: bool,
+    front_buffer_rendering: bool,
+    protected_content: bool,
 }
 
 impl<'a> ContextPrototype<'a> {
+    /// Makes the context and its window surface protected, which lets them read and write the
+    /// protected buffers of the DRM video decoders. The content of such a surface is only
+    /// displayed on secure outputs, and is black in screenshots and screen recordings.
+    ///
+    /// Returns `CreationError::NotSupported` without `EGL_EXT_protected_content`.
+    pub fn with_protected_content(mut self, protected: bool)
+                                  -> Result<ContextPrototype<'a>, CreationError>
+    {
+        if protected && !self.shared_display.extensions().contains("EGL_EXT_protected_content") {
+            return Err(CreationError::NotSupported);
+        }
+        self.protected_content = protected;
+        Ok(self)
+    }
+
+    /// Returns the limits of the chosen config, before the context is created.
+    #[inline]
+    pub fn get_config_limits(&self) -> ConfigLimits {
+        self.config_limits.clone()
+    }
+
     pub fn get_native_visual_id(&self) -> ffi::egl::types::EGLint {
         let mut value = unsafe { mem::uninitialized() };
         let ret = unsafe { self.egl.GetConfigAttrib(self.display, self.config_id,
@@ -344,23 +1655,21 @@ impl<'a> ContextPrototype<'a> {
                   -> Result<Context, CreationError>
     {
         let surface = unsafe {
+            let attributes = surface_attributes(None, self.pixel_format.srgb,
+                                                self.protected_content);
             let surface = self.egl.CreateWindowSurface(self.display, self.config_id, native_window,
-                                                       ptr::null());
+                                                       attributes.as_ptr());
             if surface.is_null() {
                 return Err(CreationError::OsError(format!("eglCreateWindowSurface failed")))
             }
             surface
         };
 
-        self.finish_impl(surface)
+        self.finish_impl(surface, Some(native_window))
     }
 
     pub fn finish_pbuffer(self, dimensions: (u32, u32)) -> Result<Context, CreationError> {
-        let attrs = &[
-            ffi::egl::WIDTH as c_int, dimensions.0 as c_int,
-            ffi::egl::HEIGHT as c_int, dimensions.1 as c_int,
-            ffi::egl::NONE as c_int,
-        ];
+        let attrs = surface_attributes(Some(dimensions), self.pixel_format.srgb, false);
 
         let surface = unsafe {
             let surface = self.egl.CreatePbufferSurface(self.display, self.config_id,
@@ -371,74 +1680,182 @@ impl<'a> ContextPrototype<'a> {
             surface
         };
 
-        self.finish_impl(surface)
+        self.finish_impl(surface, None)
     }
 
-    fn finish_impl(self, surface: ffi::egl::types::EGLSurface)
+    fn finish_impl(self, surface: ffi::egl::types::EGLSurface,
+                   native_window: Option<ffi::EGLNativeWindowType>)
                    -> Result<Context, CreationError>
     {
-        let context = unsafe {
-            if let Some(version) = self.version {
-                try!(create_context(&self.egl, self.display, &self.egl_version,
-                                    &self.extensions, self.api, version, self.config_id,
-                                    self.opengl.debug, self.opengl.robustness))
+        // objects can only be shared between the contexts of a display
+        let share = match self.opengl.sharing {
+            Some(ctxt) if ctxt.display != self.display => {
+                return Err(CreationError::OsError(format!("the context to share with belongs \
+                                                           to another EGL display")));
+            },
+            Some(ctxt) => ctxt.context,
+            None => ffi::egl::NO_CONTEXT,
+        };
 
+        let (context, failed_versions) = unsafe {
+            let create = |version| {
+                create_context(&self.egl, self.display, &self.egl_version,
+                               self.shared_display.extensions(),
+                               self.api, version, self.config_id, self.opengl.debug,
+                               self.opengl.robustness, self.opengl.priority,
+                               self.protected_content, share)
+            };
+
+            if let GlRequest::Range { .. } = self.opengl.version {
+                try!(self.opengl.version.create_in_range(create))
+            } else if let Some(version) = self.version {
+                (try!(create(version)), Vec::new())
             } else if self.api == Api::OpenGlEs {
-                if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
-                                                 &self.extensions, self.api, (2, 0), self.config_id,
-                                                 self.opengl.debug, self.opengl.robustness)
-                {
-                    ctxt
-                } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
-                                                        &self.extensions, self.api, (1, 0),
-                                                        self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
-                {
-                    ctxt
-                } else {
-                    return Err(CreationError::OpenGlVersionNotSupported);
-                }
-
+                try!(create_with_fallbacks(self.api, &[(2, 0), (1, 0)], create))
             } else {
-                if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
-                                                 &self.extensions, self.api, (3, 2), self.config_id,
-                                                 self.opengl.debug, self.opengl.robustness)
-                {
-                    ctxt
-                } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
-                                                        &self.extensions, self.api, (3, 1),
-                                                        self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
-                {
-                    ctxt
-                } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
-                                                        &self.extensions, self.api, (1, 0),
-                                                        self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
-                {
-                    ctxt
-                } else {
-                    return Err(CreationError::OpenGlVersionNotSupported);
-                }
+                try!(create_with_fallbacks(self.api, &[(3, 2), (3, 1), (1, 0)], create))
             }
         };
 
+        let back_buffer_preserved = unsafe { self.set_swap_behavior(surface) };
+        let front_buffer_rendering = unsafe { self.set_render_buffer(surface) };
+
+        let extensions = self.shared_display.extensions();
+        let partial_update = extensions.contains("EGL_KHR_partial_update");
+        let smpte2086_metadata = extensions.contains("EGL_EXT_surface_SMPTE2086_metadata");
+        let cta861_3_metadata = extensions.contains("EGL_EXT_surface_CTA861_3_metadata");
+        let native_fence_sync = extensions.contains("EGL_ANDROID_native_fence_sync");
+
+        // EGL doesn't report the version of Mesa, only its OpenGL version string does
+        let mut renderer_info = self.renderer_info;
+        if renderer_info.vendor.as_ref().map(|v| v.contains("Mesa")).unwrap_or(false) {
+            renderer_info.mesa_version = unsafe {
+                query_mesa_version(&self.egl, self.display, surface, context)
+            };
+        }
+
         Ok(Context {
             egl: self.egl,
             display: self.display,
+            shared_display: self.shared_display,
             context: context,
-            surface: surface,
+            surface: AtomicPtr::new(surface as *mut c_void),
+            native_window: native_window,
+            config_id: self.config_id,
             api: self.api,
             pixel_format: self.pixel_format,
+            config_limits: self.config_limits,
+            renderer_info: renderer_info,
+            quirks: self.quirks,
+            back_buffer_preserved: back_buffer_preserved,
+            front_buffer_rendering: front_buffer_rendering,
+            partial_update: partial_update,
+            smpte2086_metadata: smpte2086_metadata,
+            cta861_3_metadata: cta861_3_metadata,
+            native_fence_sync: native_fence_sync,
+            protected_content: self.protected_content,
+            failed_versions: failed_versions,
+            destroyed: AtomicBool::new(false),
+            tracker: leaks::Tracker::new("EGL context", context as usize,
+                                         self.display as usize),
         })
     }
+
+    /// Requests `EGL_BUFFER_PRESERVED` on `surface` if the back buffer should be preserved and
+    /// the config allows it, then returns whether the surface preserves it.
+    unsafe fn set_swap_behavior(&self, surface: ffi::egl::types::EGLSurface) -> bool {
+        // `EGL_SWAP_BEHAVIOR` appeared in EGL 1.2, and the config bit that allows changing it
+        // in EGL 1.4
+        if self.egl_version < (1, 2) {
+            return false;
+        }
+
+        if self.preserve_back_buffer && self.egl_version >= (1, 4) {
+            let mut surface_type = 0;
+            self.egl.GetConfigAttrib(self.display, self.config_id,
+                                     ffi::egl::SURFACE_TYPE as ffi::egl::types::EGLint,
+                                     &mut surface_type);
+            if surface_type & ffi::egl::SWAP_BEHAVIOR_PRESERVED_BIT as ffi::egl::types::EGLint
+                != 0
+            {
+                self.egl.SurfaceAttrib(self.display, surface,
+                                       ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                                       ffi::egl::BUFFER_PRESERVED as ffi::egl::types::EGLint);
+            }
+        }
+
+        // some implementations preserve the back buffer by default
+        let mut behavior = 0;
+        if self.egl.QuerySurface(self.display, surface,
+                                 ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                                 &mut behavior) == 0
+        {
+            return false;
+        }
+        behavior == ffi::egl::BUFFER_PRESERVED as ffi::egl::types::EGLint
+    }
+
+    /// Requests `EGL_SINGLE_BUFFER` on `surface` if the window should render into its front
+    /// buffer and the config allows it, then returns whether it was requested. The surface
+    /// switches to the front buffer at the first `eglSwapBuffers`.
+    unsafe fn set_render_buffer(&self, surface: ffi::egl::types::EGLSurface) -> bool {
+        if !self.front_buffer_rendering ||
+           !self.shared_display.extensions().contains("EGL_KHR_mutable_render_buffer")
+        {
+            return false;
+        }
+
+        let mut surface_type = 0;
+        self.egl.GetConfigAttrib(self.display, self.config_id,
+                                 ffi::egl::SURFACE_TYPE as ffi::egl::types::EGLint,
+                                 &mut surface_type);
+        if surface_type & MUTABLE_RENDER_BUFFER_BIT_KHR == 0 {
+            return false;
+        }
+
+        self.egl.SurfaceAttrib(self.display, surface,
+                               ffi::egl::RENDER_BUFFER as ffi::egl::types::EGLint,
+                               ffi::egl::SINGLE_BUFFER as ffi::egl::types::EGLint) != 0
+    }
 }
 
 unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
                           egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
-                          api: Api, version: Option<(u8, u8)>, reqs: &PixelFormatRequirements)
+                          extensions: &Extensions, api: Api, version: Option<(u8, u8)>,
+                          reqs: &PixelFormatRequirements, quirks: &[Quirk])
                           -> Result<(ffi::egl::types::EGLConfig, PixelFormat), CreationError>
 {
+    // the bit of the requested API in `EGL_RENDERABLE_TYPE` and `EGL_CONFORMANT`, which don't
+    // exist before EGL 1.3
+    let api_bit = match (api, version) {
+        (Api::OpenGlEs, Some((3, _))) => {
+            if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
+            Some(ffi::egl::OPENGL_ES3_BIT)
+        },
+        (Api::OpenGlEs, Some((2, _))) => {
+            if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
+            Some(ffi::egl::OPENGL_ES2_BIT)
+        },
+        (Api::OpenGlEs, Some((1, _))) => {
+            if egl_version >= &(1, 3) { Some(ffi::egl::OPENGL_ES_BIT) } else { None }
+        },
+        (Api::OpenGlEs, _) => unimplemented!(),
+        (Api::OpenGl, _) => {
+            if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
+            Some(ffi::egl::OPENGL_BIT)
+        },
+        (_, _) => unimplemented!(),
+    };
+
+    if reqs.conformant && api_bit.is_none() {
+        return Err(CreationError::NoAvailablePixelFormat);
+    }
+
+    let coverage = extensions.contains("EGL_NV_coverage_sample");
+    // sRGB is requested when the surfaces are created, the configs don't have it
+    let srgb = reqs.srgb &&
+               (egl_version >= &(1, 5) || extensions.contains("EGL_KHR_gl_colorspace"));
+
     let descriptor = {
         let mut out: Vec<c_int> = Vec::with_capacity(37);
 
@@ -450,52 +1867,42 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
         out.push(ffi::egl::SURFACE_TYPE as c_int);
         // TODO: Some versions of Mesa report a BAD_ATTRIBUTE error
         // if we ask for PBUFFER_BIT as well as WINDOW_BIT
-        out.push((ffi::egl::WINDOW_BIT) as c_int);
-
-        match (api, version) {
-            (Api::OpenGlEs, Some((3, _))) => {
-                if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
-                out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                out.push(ffi::egl::OPENGL_ES3_BIT as c_int);
-                out.push(ffi::egl::CONFORMANT as c_int);
-                out.push(ffi::egl::OPENGL_ES3_BIT as c_int);
-            },
-            (Api::OpenGlEs, Some((2, _))) => {
-                if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
-                out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                out.push(ffi::egl::OPENGL_ES2_BIT as c_int);
-                out.push(ffi::egl::CONFORMANT as c_int);
-                out.push(ffi::egl::OPENGL_ES2_BIT as c_int);
-            },
-            (Api::OpenGlEs, Some((1, _))) => {
-                if egl_version >= &(1, 3) {
-                    out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                    out.push(ffi::egl::OPENGL_ES_BIT as c_int);
-                    out.push(ffi::egl::CONFORMANT as c_int);
-                    out.push(ffi::egl::OPENGL_ES_BIT as c_int);
-                }
+        let mut surface_type = ffi::egl::WINDOW_BIT as c_int;
+        if reqs.preserve_back_buffer && reqs.strict {
+            // the swap behavior of the surface can't be changed without this bit
+            if egl_version < &(1, 4) { return Err(CreationError::NoAvailablePixelFormat); }
+            surface_type |= ffi::egl::SWAP_BEHAVIOR_PRESERVED_BIT as c_int;
+        }
+        if reqs.front_buffer_rendering && reqs.strict {
+            // same for the render buffer
+            if !extensions.contains("EGL_KHR_mutable_render_buffer") {
+                return Err(CreationError::NoAvailablePixelFormat);
+            }
+            surface_type |= MUTABLE_RENDER_BUFFER_BIT_KHR;
+        }
+        out.push(surface_type);
+
+        if let Some(api_bit) = api_bit {
+            out.push(ffi::egl::RENDERABLE_TYPE as c_int);
+            out.push(api_bit as c_int);
+            out.push(ffi::egl::CONFORMANT as c_int);
+            out.push(api_bit as c_int);
+        }
+
+        match (reqs.hardware_accelerated, reqs.allow_caveat) {
+            (Some(true), _) | (None, false) => {
+                out.push(ffi::egl::CONFIG_CAVEAT as c_int);
+                out.push(ffi::egl::NONE as c_int);
             },
-            (Api::OpenGlEs, _) => unimplemented!(),
-            (Api::OpenGl, _) => {
-                if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
-                out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                out.push(ffi::egl::OPENGL_BIT as c_int);
-                out.push(ffi::egl::CONFORMANT as c_int);
-                out.push(ffi::egl::OPENGL_BIT as c_int);
+            (Some(false), true) => {
+                out.push(ffi::egl::CONFIG_CAVEAT as c_int);
+                out.push(ffi::egl::SLOW_CONFIG as c_int);
             },
-            (_, _) => unimplemented!(),
-        };
-
-        if let Some(hardware_accelerated) = reqs.hardware_accelerated {
-            out.push(ffi::egl::CONFIG_CAVEAT as c_int);
-            out.push(if hardware_accelerated {
-                ffi::egl::NONE as c_int
-            } else {
-                ffi::egl::SLOW_CONFIG as c_int
-            });
+            (Some(false), false) => return Err(CreationError::NoAvailablePixelFormat),
+            (None, true) => (),
         }
 
-        if let Some(color) = reqs.color_bits {
+        if let Some(color) = reqs.color_bits.min() {
             out.push(ffi::egl::RED_SIZE as c_int);
             out.push((color / 3) as c_int);
             out.push(ffi::egl::GREEN_SIZE as c_int);
@@ -504,17 +1911,17 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
             out.push((color / 3 + if color % 3 == 2 { 1 } else { 0 }) as c_int);
         }
 
-        if let Some(alpha) = reqs.alpha_bits {
+        if let Some(alpha) = reqs.alpha_bits.min() {
             out.push(ffi::egl::ALPHA_SIZE as c_int);
             out.push(alpha as c_int);
         }
 
-        if let Some(depth) = reqs.depth_bits {
+        if let Some(depth) = reqs.depth_bits.min() {
             out.push(ffi::egl::DEPTH_SIZE as c_int);
             out.push(depth as c_int);
         }
 
-        if let Some(stencil) = reqs.stencil_bits {
+        if let Some(stencil) = reqs.stencil_bits.min() {
             out.push(ffi::egl::STENCIL_SIZE as c_int);
             out.push(stencil as c_int);
         }
@@ -523,17 +1930,26 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
             return Err(CreationError::NoAvailablePixelFormat);
         }
 
-        if let Some(multisampling) = reqs.multisampling {
+        if let Some(multisampling) = reqs.color_samples() {
             out.push(ffi::egl::SAMPLES as c_int);
             out.push(multisampling as c_int);
         }
 
+        // without the extension, the multisampled configs with the color samples are chosen
+        match reqs.coverage_samples {
+            Some(samples) if coverage && samples.coverage > samples.color => {
+                out.push(COVERAGE_BUFFERS_NV);
+                out.push(1);
+                out.push(COVERAGE_SAMPLES_NV);
+                out.push(samples.coverage as c_int);
+            },
+            _ => (),
+        }
+
         if reqs.stereoscopy {
             return Err(CreationError::NoAvailablePixelFormat);
         }
 
-        // FIXME: srgb is not taken into account
-
         match reqs.release_behavior {
             ReleaseBehavior::Flush => (),
             ReleaseBehavior::None => {
@@ -546,17 +1962,208 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
         out
     };
 
+    debug_log!("eglChooseConfig attributes: {:?}", descriptor);
+
+    let cache_key = config_cache_key(egl, display, api, version, reqs, quirks, &descriptor);
+    if let Some(ref key) = cache_key {
+        if let Some(cached) = load_cached_config(egl, display, key, reqs, api_bit, coverage, srgb) {
+            return Ok(cached);
+        }
+    }
+
     // calling `eglChooseConfig`
-    let mut config_id = mem::uninitialized();
-    let mut num_configs = mem::uninitialized();
-    if egl.ChooseConfig(display, descriptor.as_ptr(), &mut config_id, 1, &mut num_configs) == 0 {
+    let mut num_configs = 0;
+    if egl.ChooseConfig(display, descriptor.as_ptr(), ptr::null_mut(), 0, &mut num_configs) == 0 {
         return Err(CreationError::OsError(format!("eglChooseConfig failed")));
     }
     if num_configs == 0 {
+        debug_log!("eglChooseConfig didn't return any config");
         return Err(CreationError::NoAvailablePixelFormat);
     }
 
-    // analyzing each config
+    let mut configs = Vec::with_capacity(num_configs as usize);
+    if egl.ChooseConfig(display, descriptor.as_ptr(), configs.as_mut_ptr(), num_configs,
+                        &mut num_configs) == 0
+    {
+        return Err(CreationError::OsError(format!("eglChooseConfig failed")));
+    }
+    configs.set_len(num_configs as usize);
+
+    debug_log!("eglChooseConfig returned {} configs", num_configs);
+
+    // EGL only filters the configs by minimum number of bits, so we pick the best one that
+    // also matches the exact requirements, or the first one if neither the quirks of the driver
+    // nor the scoring function rank them. Reading every attribute of every config is slow with
+    // some drivers, so the cheap checks reject the configs before the pixel format is read.
+    let mut best: Option<(i32, ffi::egl::types::EGLConfig, PixelFormat)> = None;
+    for config_id in configs {
+        if !config_has_bits(egl, display, config_id, reqs) {
+            continue;
+        }
+
+        if reqs.conformant && !is_config_conformant(egl, display, config_id, api_bit.unwrap()) {
+            debug_log!("skipping non-conformant EGL config {:?}", config_id);
+            continue;
+        }
+
+        let desc = try!(get_config_pixel_format(egl, display, config_id, coverage, srgb));
+
+        // a strict request skips the inexact configs, so that a lower ranked exact one wins
+        if reqs.strict && !reqs.is_satisfied_by(&desc) {
+            debug_log!("skipping EGL config {:?}, not exact: {:?}", config_id, desc);
+            continue;
+        }
+
+        let mut visual_id = 0;
+        egl.GetConfigAttrib(display, config_id,
+                            ffi::egl::NATIVE_VISUAL_ID as ffi::egl::types::EGLint, &mut visual_id);
+        let score = quirks::score_pixel_format(quirks, reqs, &desc, visual_id != 0);
+        let score = match reqs.score(&desc, score) {
+            Some(score) => score,
+            None => {
+                debug_log!("EGL config {:?} rejected by the scoring function", config_id);
+                continue;
+            },
+        };
+        debug_log!("EGL config {:?} has score {}: {:?}", config_id, score, desc);
+
+        if best.as_ref().map(|&(s, _, _)| score > s).unwrap_or(true) {
+            best = Some((score, config_id, desc));
+        }
+    }
+
+    match best {
+        Some((_score, config_id, desc)) => {
+            debug_log!("selected EGL config {:?} with score {}: {:?}", config_id, _score, desc);
+
+            if let Some(key) = cache_key {
+                let mut id = 0;
+                let attrib = ffi::egl::CONFIG_ID as ffi::egl::types::EGLint;
+                if egl.GetConfigAttrib(display, config_id, attrib, &mut id) != 0 {
+                    config_cache::store(&key, id);
+                }
+            }
+            Ok((config_id, desc))
+        },
+        None => {
+            debug_log!("none of the EGL configs has the exact number of bits requested");
+            Err(CreationError::NoAvailablePixelFormat)
+        },
+    }
+}
+
+/// Returns the key of the config chosen for these requirements in the config cache, or `None`
+/// if it must not be cached.
+unsafe fn config_cache_key(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay, api: Api,
+                           version: Option<(u8, u8)>, reqs: &PixelFormatRequirements,
+                           quirks: &[Quirk], descriptor: &[c_int]) -> Option<String>
+{
+    if reqs.scoring.is_some() || config_cache::get_egl_config_cache_dir().is_none() {
+        return None;
+    }
+
+    let string = |name: u32| {
+        let p = egl.QueryString(display, name as i32);
+        if p.is_null() {
+            format!("")
+        } else {
+            CStr::from_ptr(p).to_string_lossy().into_owned()
+        }
+    };
+
+    // the quirks change the scores of the configs, and can be changed with `add_rule` and
+    // `set_quirk_enabled` without updating the driver
+    Some(format!("{}\n{}\n{:?} {:?}\n{:?}\n{:?}\n{:?}", string(ffi::egl::VENDOR),
+                 string(ffi::egl::VERSION), api, version, reqs, quirks, descriptor))
+}
+
+/// Returns the config stored in the config cache for `key`, if it still fulfills the
+/// requirements.
+unsafe fn load_cached_config(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                             key: &str, reqs: &PixelFormatRequirements,
+                             api_bit: Option<ffi::egl::types::EGLenum>, coverage: bool,
+                             srgb: bool) -> Option<(ffi::egl::types::EGLConfig, PixelFormat)>
+{
+    let id = match config_cache::load(key) {
+        Some(id) => id,
+        None => return None,
+    };
+
+    // the other attributes are ignored when `EGL_CONFIG_ID` is given
+    let attributes = [ffi::egl::CONFIG_ID as c_int, id, ffi::egl::NONE as c_int];
+    let mut config_id = ptr::null();
+    let mut num_configs = 0;
+    if egl.ChooseConfig(display, attributes.as_ptr(), &mut config_id, 1, &mut num_configs) == 0 ||
+       num_configs != 1
+    {
+        return None;
+    }
+
+    if !config_has_bits(egl, display, config_id, reqs) ||
+       (reqs.conformant && !is_config_conformant(egl, display, config_id, api_bit.unwrap()))
+    {
+        return None;
+    }
+
+    let desc = match get_config_pixel_format(egl, display, config_id, coverage, srgb) {
+        Ok(desc) => desc,
+        Err(_) => return None,
+    };
+    if reqs.check_strict(&desc).is_err() {
+        return None;
+    }
+
+    debug_log!("using the cached EGL config {:?}: {:?}", config_id, desc);
+    Some((config_id, desc))
+}
+
+/// Returns true if the sizes of the buffers of `config_id` fulfill the exact requirements,
+/// which `eglChooseConfig` doesn't check. A size that can't be read counts as zero.
+unsafe fn config_has_bits(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                          config_id: ffi::egl::types::EGLConfig,
+                          reqs: &PixelFormatRequirements) -> bool
+{
+    let attrib = |attr: u32| {
+        let mut value = 0;
+        egl.GetConfigAttrib(display, config_id, attr as ffi::egl::types::EGLint, &mut value);
+        value as u8
+    };
+
+    reqs.color_bits.accepts(attrib(ffi::egl::RED_SIZE) + attrib(ffi::egl::GREEN_SIZE) +
+                            attrib(ffi::egl::BLUE_SIZE)) &&
+    reqs.alpha_bits.accepts(attrib(ffi::egl::ALPHA_SIZE)) &&
+    reqs.depth_bits.accepts(attrib(ffi::egl::DEPTH_SIZE)) &&
+    reqs.stencil_bits.accepts(attrib(ffi::egl::STENCIL_SIZE))
+}
+
+/// Returns true if `config_id` is conformant for the API of `api_bit`, according to both
+/// `EGL_CONFORMANT` and the deprecated `EGL_NON_CONFORMANT_CONFIG` caveat.
+unsafe fn is_config_conformant(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                               config_id: ffi::egl::types::EGLConfig,
+                               api_bit: ffi::egl::types::EGLenum) -> bool
+{
+    let mut conformant = 0;
+    if egl.GetConfigAttrib(display, config_id, ffi::egl::CONFORMANT as ffi::egl::types::EGLint,
+                           &mut conformant) == 0
+    {
+        return false;
+    }
+
+    let mut caveat = 0;
+    if egl.GetConfigAttrib(display, config_id,
+                           ffi::egl::CONFIG_CAVEAT as ffi::egl::types::EGLint, &mut caveat) == 0
+    {
+        return false;
+    }
+
+    (conformant as ffi::egl::types::EGLenum & api_bit) != 0 &&
+        caveat as ffi::egl::types::EGLenum != ffi::egl::NON_CONFORMANT_CONFIG
+}
+
+unsafe fn get_config_pixel_format(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                                  config_id: ffi::egl::types::EGLConfig, coverage: bool,
+                                  srgb: bool) -> Result<PixelFormat, CreationError>
+{
     macro_rules! attrib {
         ($egl:expr, $display:expr, $config:expr, $attr:expr) => (
             {
@@ -571,40 +2178,228 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
         )
     };
 
-    let desc = PixelFormat {
+    let depth_bits = attrib!(egl, display, config_id, ffi::egl::DEPTH_SIZE) as u8;
+    let stencil_bits = attrib!(egl, display, config_id, ffi::egl::STENCIL_SIZE) as u8;
+    let red_bits = attrib!(egl, display, config_id, ffi::egl::RED_SIZE) as u8;
+    let green_bits = attrib!(egl, display, config_id, ffi::egl::GREEN_SIZE) as u8;
+    let blue_bits = attrib!(egl, display, config_id, ffi::egl::BLUE_SIZE) as u8;
+
+    Ok(PixelFormat {
         hardware_accelerated: attrib!(egl, display, config_id, ffi::egl::CONFIG_CAVEAT)
                                       != ffi::egl::SLOW_CONFIG as i32,
-        color_bits: attrib!(egl, display, config_id, ffi::egl::RED_SIZE) as u8 +
-                    attrib!(egl, display, config_id, ffi::egl::BLUE_SIZE) as u8 +
-                    attrib!(egl, display, config_id, ffi::egl::GREEN_SIZE) as u8,
+        color_bits: red_bits + green_bits + blue_bits,
         alpha_bits: attrib!(egl, display, config_id, ffi::egl::ALPHA_SIZE) as u8,
-        depth_bits: attrib!(egl, display, config_id, ffi::egl::DEPTH_SIZE) as u8,
-        stencil_bits: attrib!(egl, display, config_id, ffi::egl::STENCIL_SIZE) as u8,
+        depth_bits: depth_bits,
+        stencil_bits: stencil_bits,
         stereoscopy: false,
         double_buffer: true,
         multisampling: match attrib!(egl, display, config_id, ffi::egl::SAMPLES) {
             0 | 1 => None,
             a => Some(a as u16),
         },
-        srgb: false,        // TODO: use EGL_KHR_gl_colorspace to know that
+        // the drivers only have sRGB surfaces with 8 bits per channel
+        srgb: srgb && red_bits == 8 && green_bits == 8 && blue_bits == 8,
+        implicit_resolve: false,
+        packed_depth_stencil: is_packed_depth_stencil(depth_bits, stencil_bits),
+        coverage_samples: if coverage &&
+                             attrib!(egl, display, config_id, COVERAGE_BUFFERS_NV) != 0
+        {
+            Some(attrib!(egl, display, config_id, COVERAGE_SAMPLES_NV) as u16)
+        } else {
+            None
+        },
+    })
+}
+
+/// Reads the limits of a config.
+unsafe fn get_config_limits(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                            config_id: ffi::egl::types::EGLConfig) -> ConfigLimits
+{
+    let attrib = |attr: u32| {
+        let mut value = 0;
+        if egl.GetConfigAttrib(display, config_id, attr as ffi::egl::types::EGLint,
+                               &mut value) == 0
+        {
+            None
+        } else {
+            Some(value)
+        }
+    };
+
+    let max_width = attrib(ffi::egl::MAX_PBUFFER_WIDTH);
+    let max_height = attrib(ffi::egl::MAX_PBUFFER_HEIGHT);
+    let min_interval = attrib(ffi::egl::MIN_SWAP_INTERVAL);
+    let max_interval = attrib(ffi::egl::MAX_SWAP_INTERVAL);
+
+    ConfigLimits {
+        max_pbuffer_dimensions: match (max_width, max_height) {
+            (Some(w), Some(h)) => Some((w as u32, h as u32)),
+            _ => None,
+        },
+        max_pbuffer_pixels: attrib(ffi::egl::MAX_PBUFFER_PIXELS).map(|p| p as u32),
+        swap_interval_range: match (min_interval, max_interval) {
+            (Some(min), Some(max)) => Some((min as u32, max as u32)),
+            _ => None,
+        },
+        native_renderable: attrib(ffi::egl::NATIVE_RENDERABLE).map(|r| r != 0),
+    }
+}
+
+/// Reads what EGL tells about the renderer of a display. EGL has no equivalent of most of the
+/// fields of `RendererInfo`, only the vendor and the DRM device are known.
+unsafe fn get_renderer_info(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay)
+                            -> RendererInfo
+{
+    let vendor = egl.QueryString(display, ffi::egl::VENDOR as i32);
+
+    RendererInfo {
+        vendor: if vendor.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(vendor).to_string_lossy().into_owned())
+        },
+        device: get_drm_device_file(egl, display),
+        .. RendererInfo::default()
+    }
+}
+
+/// Reads the version of Mesa in the `GL_VERSION` string of `context`, which is made current for
+/// that. The context that was current before is restored.
+unsafe fn query_mesa_version(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                             surface: ffi::egl::types::EGLSurface,
+                             context: ffi::egl::types::EGLContext) -> Option<(u32, u32, u32)>
+{
+    const GL_VERSION: u32 = 0x1F02;
+
+    let previous_display = egl.GetCurrentDisplay();
+    let previous_draw = egl.GetCurrentSurface(ffi::egl::DRAW as i32);
+    let previous_read = egl.GetCurrentSurface(ffi::egl::READ as i32);
+    let previous_context = egl.GetCurrentContext();
+
+    if egl.MakeCurrent(display, surface, surface, context) == 0 {
+        return None;
+    }
+
+    let get_string = egl.GetProcAddress(b"glGetString\0".as_ptr() as *const _) as *const c_void;
+    let version = if get_string.is_null() {
+        None
+    } else {
+        let get_string: extern "system" fn(u32) -> *const u8 = mem::transmute(get_string);
+        let version = get_string(GL_VERSION);
+        if version.is_null() {
+            None
+        } else {
+            parse_mesa_version(&CStr::from_ptr(version as *const _).to_string_lossy())
+        }
+    };
+
+    if previous_context.is_null() {
+        egl.MakeCurrent(display, ffi::egl::NO_SURFACE, ffi::egl::NO_SURFACE,
+                        ffi::egl::NO_CONTEXT);
+    } else {
+        egl.MakeCurrent(previous_display, previous_draw, previous_read, previous_context);
+    }
+
+    version
+}
+
+/// Parses the version that follows `Mesa` in an OpenGL version string, for example
+/// `4.6 (Compatibility Profile) Mesa 23.1.0-devel`.
+fn parse_mesa_version(version: &str) -> Option<(u32, u32, u32)> {
+    let start = match version.find("Mesa ") {
+        Some(pos) => pos + 5,
+        None => return None,
     };
 
-    Ok((config_id, desc))
+    let numbers = version[start ..].split(|c: char| !c.is_digit(10) && c != '.')
+                                   .next().unwrap_or("");
+    let mut numbers = numbers.split('.').map(|n| n.parse::<u32>().ok());
+
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(Some(major)), Some(Some(minor)), patch) => {
+            Some((major, minor, patch.and_then(|p| p).unwrap_or(0)))
+        },
+        _ => None,
+    }
+}
+
+// with static bindings, there is no way to know whether the device functions are available
+#[cfg(any(target_os = "android", feature = "static-egl"))]
+unsafe fn get_drm_device_file(_: &ffi::egl::Egl, _: ffi::egl::types::EGLDisplay)
+                              -> Option<String>
+{
+    None
+}
+
+/// Returns the path of the DRM device of the display, through `EGL_EXT_device_query` and
+/// `EGL_EXT_device_drm`.
+#[cfg(not(any(target_os = "android", feature = "static-egl")))]
+unsafe fn get_drm_device_file(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay)
+                              -> Option<String>
+{
+    let client_extensions = display::query_extensions(egl, ffi::egl::NO_DISPLAY);
+    if !client_extensions.contains("EGL_EXT_device_query") ||
+       !egl.QueryDisplayAttribEXT.is_loaded() || !egl.QueryDeviceStringEXT.is_loaded()
+    {
+        return None;
+    }
+
+    let mut device = 0;
+    if egl.QueryDisplayAttribEXT(display, ffi::egl::DEVICE_EXT as i32, &mut device) == 0 {
+        return None;
+    }
+    let device = device as ffi::egl::types::EGLDeviceEXT;
+
+    let extensions = egl.QueryDeviceStringEXT(device, ffi::egl::EXTENSIONS as i32);
+    if extensions.is_null() ||
+       !Extensions::parse(&CStr::from_ptr(extensions).to_string_lossy())
+                    .contains("EGL_EXT_device_drm")
+    {
+        return None;
+    }
+
+    let file = egl.QueryDeviceStringEXT(device, ffi::egl::DRM_DEVICE_FILE_EXT as i32);
+    if file.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(file).to_string_lossy().into_owned())
+    }
+}
+
+/// Returns the attributes of the `eglCreate*Surface` functions, with the dimensions of the
+/// surface for pbuffers.
+fn surface_attributes(dimensions: Option<(u32, u32)>, srgb: bool, protected_content: bool)
+                      -> Vec<c_int>
+{
+    let mut attributes = Vec::with_capacity(9);
+    if let Some((width, height)) = dimensions {
+        attributes.extend_from_slice(&[ffi::egl::WIDTH as c_int, width as c_int,
+                                       ffi::egl::HEIGHT as c_int, height as c_int]);
+    }
+    if srgb {
+        attributes.push(ffi::egl::GL_COLORSPACE as c_int);
+        attributes.push(ffi::egl::GL_COLORSPACE_SRGB as c_int);
+    }
+    if protected_content {
+        attributes.push(PROTECTED_CONTENT_EXT);
+        attributes.push(ffi::egl::TRUE as c_int);
+    }
+    attributes.push(ffi::egl::NONE as c_int);
+    attributes
 }
 
 unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
                          egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
-                         extensions: &[String], api: Api, version: (u8, u8),
+                         extensions: &Extensions, api: Api, version: (u8, u8),
                          config_id: ffi::egl::types::EGLConfig, gl_debug: bool,
-                         gl_robustness: Robustness)
+                         gl_robustness: Robustness, priority: Option<ContextPriority>,
+                         protected_content: bool, share: ffi::egl::types::EGLContext)
                          -> Result<ffi::egl::types::EGLContext, CreationError>
 {
     let mut context_attributes = Vec::with_capacity(10);
     let mut flags = 0;
 
-    if egl_version >= &(1, 5) || extensions.iter().find(|s| s == &"EGL_KHR_create_context")
-                                                  .is_some()
-    {
+    if egl_version >= &(1, 5) || extensions.contains("EGL_KHR_create_context") {
         context_attributes.push(ffi::egl::CONTEXT_MAJOR_VERSION as i32);
         context_attributes.push(version.0 as i32);
         context_attributes.push(ffi::egl::CONTEXT_MINOR_VERSION as i32);
@@ -612,15 +2407,13 @@ unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDispl
 
         // handling robustness
         let supports_robustness = egl_version >= &(1, 5) ||
-                                  extensions.iter()
-                                            .find(|s| s == &"EGL_EXT_create_context_robustness")
-                                            .is_some();
+                                  extensions.contains("EGL_EXT_create_context_robustness");
 
         match gl_robustness {
             Robustness::NotRobust => (),
 
             Robustness::NoError => {
-                if extensions.iter().find(|s| s == &"EGL_KHR_create_context_no_error").is_some() {
+                if extensions.contains("EGL_KHR_create_context_no_error") {
                     context_attributes.push(ffi::egl::CONTEXT_OPENGL_NO_ERROR_KHR as c_int);
                     context_attributes.push(1);
                 }
@@ -696,15 +2489,37 @@ unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDispl
         context_attributes.push(version.0 as i32);
     }
 
+    // the priority is only a hint, the drivers that don't grant it ignore it
+    if let Some(priority) = priority {
+        if extensions.contains("EGL_IMG_context_priority") {
+            context_attributes.push(CONTEXT_PRIORITY_LEVEL_IMG);
+            context_attributes.push(match priority {
+                ContextPriority::Low => CONTEXT_PRIORITY_LOW_IMG,
+                ContextPriority::Medium => CONTEXT_PRIORITY_MEDIUM_IMG,
+                ContextPriority::High => CONTEXT_PRIORITY_HIGH_IMG,
+            });
+        }
+    }
+
+    // a protected surface can only be made current with a protected context
+    if protected_content {
+        context_attributes.push(PROTECTED_CONTENT_EXT);
+        context_attributes.push(ffi::egl::TRUE as i32);
+    }
+
     context_attributes.push(ffi::egl::NONE as i32);
 
-    let context = egl.CreateContext(display, config_id, ptr::null(),
-                                    context_attributes.as_ptr());
+    debug_log!("eglCreateContext attributes for {:?} {:?}: {:?}", api, version,
+               context_attributes);
+
+    let context = egl.CreateContext(display, config_id, share, context_attributes.as_ptr());
 
     if context.is_null() {
-        match egl.GetError() as u32 {
+        let error = egl.GetError() as u32;
+        debug_log!("eglCreateContext failed: 0x{:x}", error);
+        match error {
             ffi::egl::BAD_ATTRIBUTE => return Err(CreationError::OpenGlVersionNotSupported),
-            e => panic!("eglCreateContext failed: 0x{:x}", e),
+            e => return Err(CreationError::NativeError(native_error("eglCreateContext", e))),
         }
     }
 
@@ -1,6 +1,5 @@
 #![cfg(target_os = "emscripten")]
 
-use std::ffi::CString;
 use libc;
 use Api;
 use Event;
@@ -12,6 +11,9 @@ use GlContext;
 use MouseCursor;
 use PixelFormat;
 use PixelFormatRequirements;
+use api::Drawable;
+use c_str::with_c_str;
+use presenter::SwapFn;
 use WindowAttributes;
 
 use std::collections::VecDeque;
@@ -225,10 +227,76 @@ impl Window {
         1.0
     }
 
+    /// Variable refresh rates aren't reported on this platform.
+    #[inline]
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        None
+    }
+
+    /// The buffers can only be swapped by the thread where the context is current on this
+    /// platform.
+    #[inline]
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        None
+    }
+
     #[inline]
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
         Ok(())
     }
+
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
+}
+
+/// WebGL has no pbuffers, use framebuffer objects instead.
+pub enum PBuffer {}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
 }
 
 impl GlContext for Window {
@@ -245,12 +313,7 @@ impl GlContext for Window {
     }
 
     fn get_proc_address(&self, addr: &str) -> *const () {
-        let addr = CString::new(addr).unwrap();
-
-        unsafe {
-            // FIXME: if `as_ptr()` is used, then wrong data is passed to emscripten
-            ffi::emscripten_GetProcAddress(addr.into_raw() as *const _) as *const _
-        }
+        with_c_str(addr, |addr| unsafe { ffi::emscripten_GetProcAddress(addr) as *const _ })
     }
 
     #[inline]
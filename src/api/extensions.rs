@@ -0,0 +1,21 @@
+//! The extensions supported by a display, parsed once from their space-separated list.
+
+use std::collections::HashSet;
+
+/// A set of extension names, as returned by `eglQueryString(EGL_EXTENSIONS)`,
+/// `glXQueryExtensionsString` or `wglGetExtensionsStringARB`.
+#[derive(Clone, Debug, Default)]
+pub struct Extensions(HashSet<String>);
+
+impl Extensions {
+    /// Parses a list of extension names, separated by spaces.
+    pub fn parse(list: &str) -> Extensions {
+        Extensions(list.split(' ').filter(|e| !e.is_empty()).map(|e| e.to_owned()).collect())
+    }
+
+    /// Returns true if the extension is in the set.
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+}
@@ -1,7 +1,10 @@
-#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+           target_os = "openbsd", target_os = "netbsd"))]
 
+use ConfigLimits;
 use ContextError;
 use CreationError;
+use FailedVersion;
 use GlAttributes;
 use GlContext;
 use GlProfile;
@@ -10,60 +13,112 @@ use Api;
 use PixelFormat;
 use PixelFormatRequirements;
 use ReleaseBehavior;
+use RendererInfo;
 use Robustness;
+use NativeError;
+use check_swap_interval;
+use create_with_fallbacks;
+use is_packed_depth_stencil;
+use api::Drawable;
+use api::extensions::Extensions;
+use c_str::with_c_str;
+use current;
+use leaks;
+use presenter::SwapFn;
+use quirks::{self, Quirk};
 
 use libc;
 use libc::c_int;
-use std::ffi::{CStr, CString};
-use std::{mem, ptr, slice};
+use std::ffi::CStr;
+use std::{io, mem, ptr, slice};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use api::x11::ffi;
 
 use platform::Window as PlatformWindow;
 
+/// From `GLX_NV_multisample_coverage`, which the bindings aren't generated for.
+const COLOR_SAMPLES_NV: c_int = 0x20B3;
+
 pub struct Context {
     glx: ffi::glx::Glx,
     display: *mut ffi::Display,
     window: ffi::Window,
     context: ffi::GLXContext,
+    fb_config: ffi::glx::types::GLXFBConfig,
     pixel_format: PixelFormat,
-}
-
-// TODO: remove me
-fn with_c_str<F, T>(s: &str, f: F) -> T where F: FnOnce(*const libc::c_char) -> T {
-    use std::ffi::CString;
-    let c_str = CString::new(s.as_bytes().to_vec()).unwrap();
-    f(c_str.as_ptr())
+    config_limits: ConfigLimits,
+    renderer_info: RendererInfo,
+    quirks: Vec<Quirk>,
+    failed_versions: Vec<FailedVersion>,
+    extra_functions: ffi::glx_extra::Glx,
+    // `GLX_EXT_swap_control` and `GLX_EXT_swap_control_tear`
+    swap_control: bool,
+    adaptive_vsync: bool,
+    // set by `destroy_context`, after which the destructor has nothing left to do
+    destroyed: AtomicBool,
+    tracker: leaks::Tracker,
 }
 
 impl Context {
-    pub fn new<'a>(glx: ffi::glx::Glx, xlib: &ffi::Xlib, pf_reqs: &PixelFormatRequirements,
-                   opengl: &'a GlAttributes<&'a Context>, display: *mut ffi::Display,
-                   screen_id: libc::c_int) -> Result<ContextPrototype<'a>, CreationError>
-    {
-        // This is completely ridiculous, but VirtualBox's OpenGL driver needs some call handled by
-        // *it* (i.e. not Mesa) to occur before anything else can happen. That is because
-        // VirtualBox's OpenGL driver is going to apply binary patches to Mesa in the DLL
-        // constructor and until it's loaded it won't have a chance to do that.
-        //
-        // The easiest way to do this is to just call `glXQueryVersion()` before doing anything
-        // else. See: https://www.virtualbox.org/ticket/8293
-        let (mut major, mut minor) = (0, 0);
-        unsafe {
-            glx.QueryVersion(display as *mut _, &mut major, &mut minor);
-        }
+    #[inline]
+    pub fn get_fb_config(&self) -> ffi::glx::types::GLXFBConfig {
+        self.fb_config
+    }
 
-        // loading the list of extensions
-        let extensions = unsafe {
-            let extensions = glx.QueryExtensionsString(display as *mut _, screen_id);
-            let extensions = CStr::from_ptr(extensions).to_bytes().to_vec();
-            String::from_utf8(extensions).unwrap()
+    /// Returns a function that swaps the buffers of the window from any thread, which GLX
+    /// allows because `glXSwapBuffers` only needs the drawable. The Xlib display must outlive
+    /// the function.
+    pub fn get_swapper(&self) -> SwapFn {
+        let swapper = Swapper {
+            glx: self.glx.clone(),
+            display: self.display,
+            window: self.window,
         };
 
+        Box::new(move || {
+            unsafe { swapper.glx.SwapBuffers(swapper.display as *mut _, swapper.window); }
+            Ok(())
+        })
+    }
+
+    /// Returns false if the rendering goes through the X server, which limits it to OpenGL 1.4.
+    #[inline]
+    pub fn is_direct(&self) -> bool {
+        unsafe { self.glx.IsDirect(self.display as *mut _, self.context) != 0 }
+    }
+
+    /// `extensions` are the GLX extensions of the screen, as returned by
+    /// `XConnection::glx_extensions`, which must be called before any other GLX function.
+    pub fn new<'a>(glx: ffi::glx::Glx, xlib: &ffi::Xlib, extensions: Arc<Extensions>,
+                   pf_reqs: &PixelFormatRequirements, opengl: &'a GlAttributes<&'a Context>,
+                   display: *mut ffi::Display, screen_id: libc::c_int, transparent: bool)
+                   -> Result<ContextPrototype<'a>, CreationError>
+    {
+        // the workarounds for the driver are applied to the pixel format
+        let quirks = unsafe {
+            let string = |name: u32| {
+                let p = glx.GetClientString(display as *mut _, name as c_int);
+                if p.is_null() {
+                    format!("")
+                } else {
+                    CStr::from_ptr(p).to_string_lossy().into_owned()
+                }
+            };
+            quirks::active_quirks(&string(ffi::glx::VENDOR), &string(ffi::glx::VERSION))
+        };
+        let pf_reqs = &try!(quirks::apply_to_pixel_format(&quirks, pf_reqs));
+
         // finding the pixel format we want
         let (fb_config, pixel_format) = unsafe {
-            try!(choose_fbconfig(&glx, &extensions, xlib, display, screen_id, pf_reqs)
-                                          .map_err(|_| CreationError::NoAvailablePixelFormat))
+            try!(choose_fbconfig(&glx, &extensions, xlib, display, screen_id, pf_reqs,
+                                 transparent)
+                                          .map_err(|_| if transparent {
+                                              CreationError::TransparencyNotSupported
+                                          } else {
+                                              CreationError::NoAvailablePixelFormat
+                                          }))
         };
 
         // getting the visual infos
@@ -77,25 +132,154 @@ impl Context {
             vi_copy
         };
 
+        let config_limits = unsafe { get_config_limits(&glx, display, fb_config) };
+
         Ok(ContextPrototype {
             glx: glx,
             extensions: extensions,
             opengl: opengl,
             display: display,
+            screen_id: screen_id,
             fb_config: fb_config,
             visual_infos: unsafe { mem::transmute(visual_infos) },
             pixel_format: pixel_format,
+            config_limits: config_limits,
+            quirks: quirks,
         })
     }
 }
 
+impl Context {
+    /// Creates a pbuffer with the framebuffer config of this context.
+    ///
+    /// Returns `CreationError::NotSupported` if the config can't be used for pbuffers.
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        unsafe {
+            let mut drawable_type = 0;
+            self.glx.GetFBConfigAttrib(self.display as *mut _, self.fb_config,
+                                       ffi::glx::DRAWABLE_TYPE as c_int, &mut drawable_type);
+            if drawable_type & ffi::glx::PBUFFER_BIT as c_int == 0 ||
+               self.quirks.contains(&Quirk::NoPbuffers)
+            {
+                return Err(CreationError::NotSupported);
+            }
+
+            let attributes = [
+                ffi::glx::PBUFFER_WIDTH as c_int, dimensions.0 as c_int,
+                ffi::glx::PBUFFER_HEIGHT as c_int, dimensions.1 as c_int,
+                ffi::glx::PRESERVED_CONTENTS as c_int, 1,
+                0
+            ];
+
+            let pbuffer = self.glx.CreatePbuffer(self.display as *mut _, self.fb_config,
+                                                 attributes.as_ptr());
+            if pbuffer == 0 {
+                return Err(CreationError::OsError(format!("glXCreatePbuffer failed")));
+            }
+
+            Ok(PBuffer {
+                glx: self.glx.clone(),
+                display: self.display,
+                pbuffer: pbuffer,
+                context: self.context,
+                pixel_format: self.pixel_format.clone(),
+                tracker: leaks::Tracker::new("GLX pbuffer", pbuffer as usize,
+                                             self.display as usize),
+            })
+        }
+    }
+
+    /// Creates a GLX pixmap that renders into `pixmap` with the framebuffer config of this
+    /// context.
+    ///
+    /// Returns `CreationError::NotSupported` if the config can't be used for pixmaps.
+    ///
+    /// # Unsafety
+    ///
+    /// The pixmap must continue to exist as long as the resulting surface exists, and its depth
+    /// must match the one of the config.
+    pub unsafe fn create_pixmap_surface(&self, pixmap: ffi::Pixmap)
+                                        -> Result<PixmapSurface, CreationError>
+    {
+        let mut drawable_type = 0;
+        self.glx.GetFBConfigAttrib(self.display as *mut _, self.fb_config,
+                                   ffi::glx::DRAWABLE_TYPE as c_int, &mut drawable_type);
+        if drawable_type & ffi::glx::PIXMAP_BIT as c_int == 0 {
+            return Err(CreationError::NotSupported);
+        }
+
+        let glx_pixmap = self.glx.CreatePixmap(self.display as *mut _, self.fb_config, pixmap,
+                                               ptr::null());
+        if glx_pixmap == 0 {
+            return Err(CreationError::OsError(format!("glXCreatePixmap failed")));
+        }
+
+        Ok(PixmapSurface {
+            glx: self.glx.clone(),
+            display: self.display,
+            pixmap: glx_pixmap,
+            context: self.context,
+            pixel_format: self.pixel_format.clone(),
+            tracker: leaks::Tracker::new("GLX pixmap", glx_pixmap as usize,
+                                         self.display as usize),
+        })
+    }
+
+    /// Makes the context current, drawing into `draw` and reading from `read`.
+    ///
+    /// The drawables of other contexts must have been created on the same display, with a
+    /// compatible framebuffer config.
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Context, PBuffer, PixmapSurface>,
+                                             read: Drawable<Context, PBuffer, PixmapSurface>)
+                                             -> Result<(), ContextError>
+    {
+        let draw = try!(self.drawable_for(draw));
+        let read = try!(self.drawable_for(read));
+        if current::is_current(self.context as usize, draw as usize, read as usize) {
+            return Ok(());
+        }
+
+        let res = self.glx.MakeContextCurrent(self.display as *mut _, draw, read, self.context);
+        if res == 0 {
+            current::forget();
+            return Err(ContextError::NativeError(native_error("glXMakeContextCurrent")));
+        }
+        current::set(self.context as usize, draw as usize, read as usize);
+        Ok(())
+    }
+
+    fn drawable_for(&self, drawable: Drawable<Context, PBuffer, PixmapSurface>)
+                    -> Result<ffi::glx::types::GLXDrawable, ContextError>
+    {
+        let (display, drawable) = match drawable {
+            Drawable::Default => return Ok(self.window),
+            Drawable::Window(context) => (context.display, context.window),
+            Drawable::PBuffer(pbuffer) => (pbuffer.display, pbuffer.pbuffer),
+            Drawable::Pixmap(pixmap) => (pixmap.display, pixmap.pixmap),
+        };
+
+        if display != self.display {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                            "the surface uses another display")));
+        }
+
+        Ok(drawable)
+    }
+}
+
 impl GlContext for Context {
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        // TODO: glutin needs some internal changes for proper error recovery
+        let window = self.window as usize;
+        if current::is_current(self.context as usize, window, window) {
+            return Ok(());
+        }
+
         let res = self.glx.MakeCurrent(self.display as *mut _, self.window, self.context);
         if res == 0 {
-            panic!("glx::MakeCurrent failed");
+            current::forget();
+            return Err(ContextError::NativeError(native_error("glXMakeCurrent")));
         }
+        current::set(self.context as usize, window, window);
         Ok(())
     }
 
@@ -104,12 +288,36 @@ impl GlContext for Context {
         unsafe { self.glx.GetCurrentContext() == self.context }
     }
 
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        current::forget();
+        if self.glx.MakeCurrent(self.display as *mut _, 0, ptr::null()) == 0 {
+            return Err(ContextError::NativeError(native_error("glXMakeCurrent")));
+        }
+        Ok(())
+    }
+
+    /// The errors of `glXDestroyContext` are X errors, which the caller must collect.
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        if self.destroyed.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        try!(self.make_not_current());
+        self.destroyed.store(true, Ordering::Release);
+        self.tracker.release();
+        current::forget_object(self.context as usize);
+        self.glx.DestroyContext(self.display as *mut _, self.context);
+        Ok(())
+    }
+
     fn get_proc_address(&self, addr: &str) -> *const () {
-        let addr = CString::new(addr.as_bytes()).unwrap();
-        let addr = addr.as_ptr();
-        unsafe {
+        with_c_str(addr, |addr| unsafe {
             self.glx.GetProcAddress(addr as *const _) as *const _
-        }
+        })
     }
 
     #[inline]
@@ -128,31 +336,255 @@ impl GlContext for Context {
     fn get_pixel_format(&self) -> PixelFormat {
         self.pixel_format.clone()
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.failed_versions.clone()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.config_limits.clone()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.renderer_info.clone()
+    }
+
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        // `glXSwapIntervalSGI` can't disable vsync, and only takes effect after the next swap
+        if !self.swap_control {
+            return Err(ContextError::NotSupported(
+                       "the swap interval can't be changed without GLX_EXT_swap_control"));
+        }
+        try!(check_swap_interval(&self.config_limits, self.adaptive_vsync, interval));
+
+        unsafe {
+            self.extra_functions.SwapIntervalEXT(self.display as *mut _, self.window, interval);
+        }
+        Ok(())
+    }
 }
 
+// the display connection is thread-safe after `XInitThreads`, and the fields that change are
+// atomic
 unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
+/// What `glXSwapBuffers` needs, see `Context::get_swapper`.
+struct Swapper {
+    glx: ffi::glx::Glx,
+    display: *mut ffi::Display,
+    window: ffi::Window,
+}
+
+unsafe impl Send for Swapper {}
+
 impl Drop for Context {
     fn drop(&mut self) {
+        if self.destroyed.load(Ordering::Acquire) {
+            return;
+        }
+
         unsafe {
             if self.is_current() {
+                current::forget();
                 self.glx.MakeCurrent(self.display as *mut _, 0, ptr::null_mut());
             }
 
+            current::forget_object(self.context as usize);
             self.glx.DestroyContext(self.display as *mut _, self.context);
         }
     }
 }
 
+/// A pbuffer that is drawn with the context it has been created from.
+///
+/// Note: must be destroyed before its context.
+pub struct PBuffer {
+    glx: ffi::glx::Glx,
+    display: *mut ffi::Display,
+    pbuffer: ffi::glx::types::GLXPbuffer,
+    context: ffi::GLXContext,
+    pixel_format: PixelFormat,
+    // only dropped, which forgets the object
+    #[allow(dead_code)]
+    tracker: leaks::Tracker,
+}
+
+impl GlContext for PBuffer {
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let pbuffer = self.pbuffer as usize;
+        if current::is_current(self.context as usize, pbuffer, pbuffer) {
+            return Ok(());
+        }
+
+        // TODO: glutin needs some internal changes for proper error recovery
+        let res = self.glx.MakeContextCurrent(self.display as *mut _, self.pbuffer, self.pbuffer,
+                                              self.context);
+        if res == 0 {
+            current::forget();
+            return Err(ContextError::NativeError(native_error("glXMakeContextCurrent")));
+        }
+        current::set(self.context as usize, pbuffer, pbuffer);
+        Ok(())
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe {
+            self.glx.GetCurrentContext() == self.context &&
+            self.glx.GetCurrentDrawable() == self.pbuffer
+        }
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        with_c_str(addr, |addr| unsafe {
+            self.glx.GetProcAddress(addr as *const _) as *const _
+        })
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        unsafe { self.glx.SwapBuffers(self.display as *mut _, self.pbuffer); }
+        Ok(())
+    }
+
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        ::Api::OpenGl
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+}
+
+unsafe impl Send for PBuffer {}
+unsafe impl Sync for PBuffer {}
+
+impl Drop for PBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            if self.is_current() {
+                current::forget();
+                self.glx.MakeContextCurrent(self.display as *mut _, 0, 0, ptr::null_mut());
+            }
+
+            current::forget_object(self.pbuffer as usize);
+            self.glx.DestroyPbuffer(self.display as *mut _, self.pbuffer);
+        }
+    }
+}
+
+/// A GLX pixmap that is drawn with the context it has been created from.
+///
+/// Note: must be destroyed before its context.
+pub struct PixmapSurface {
+    glx: ffi::glx::Glx,
+    display: *mut ffi::Display,
+    pixmap: ffi::glx::types::GLXPixmap,
+    context: ffi::GLXContext,
+    pixel_format: PixelFormat,
+    // only dropped, which forgets the object
+    #[allow(dead_code)]
+    tracker: leaks::Tracker,
+}
+
+impl PixmapSurface {
+    /// Returns the dimensions of the pixmap, as reported by `glXQueryDrawable`.
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        unsafe {
+            let (mut width, mut height) = (0, 0);
+            self.glx.QueryDrawable(self.display as *mut _, self.pixmap,
+                                   ffi::glx::WIDTH as c_int, &mut width);
+            self.glx.QueryDrawable(self.display as *mut _, self.pixmap,
+                                   ffi::glx::HEIGHT as c_int, &mut height);
+            (width, height)
+        }
+    }
+}
+
+impl GlContext for PixmapSurface {
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let pixmap = self.pixmap as usize;
+        if current::is_current(self.context as usize, pixmap, pixmap) {
+            return Ok(());
+        }
+
+        // TODO: glutin needs some internal changes for proper error recovery
+        let res = self.glx.MakeContextCurrent(self.display as *mut _, self.pixmap, self.pixmap,
+                                              self.context);
+        if res == 0 {
+            current::forget();
+            return Err(ContextError::NativeError(native_error("glXMakeContextCurrent")));
+        }
+        current::set(self.context as usize, pixmap, pixmap);
+        Ok(())
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe {
+            self.glx.GetCurrentContext() == self.context &&
+            self.glx.GetCurrentDrawable() == self.pixmap
+        }
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        with_c_str(addr, |addr| unsafe {
+            self.glx.GetProcAddress(addr as *const _) as *const _
+        })
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        // pixmaps are single-buffered, this only makes the rendering visible to X requests
+        unsafe { self.glx.WaitGL(); }
+        Ok(())
+    }
+
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        ::Api::OpenGl
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+}
+
+unsafe impl Send for PixmapSurface {}
+unsafe impl Sync for PixmapSurface {}
+
+impl Drop for PixmapSurface {
+    fn drop(&mut self) {
+        unsafe {
+            if self.is_current() {
+                current::forget();
+                self.glx.MakeContextCurrent(self.display as *mut _, 0, 0, ptr::null_mut());
+            }
+
+            current::forget_object(self.pixmap as usize);
+            self.glx.DestroyPixmap(self.display as *mut _, self.pixmap);
+        }
+    }
+}
+
 pub struct ContextPrototype<'a> {
     glx: ffi::glx::Glx,
-    extensions: String,
+    extensions: Arc<Extensions>,
     opengl: &'a GlAttributes<&'a Context>,
     display: *mut ffi::Display,
+    screen_id: libc::c_int,
     fb_config: ffi::glx::types::GLXFBConfig,
     visual_infos: ffi::XVisualInfo,
     pixel_format: PixelFormat,
+    config_limits: ConfigLimits,
+    quirks: Vec<Quirk>,
 }
 
 impl<'a> ContextPrototype<'a> {
@@ -175,42 +607,25 @@ impl<'a> ContextPrototype<'a> {
         });
 
         // creating GL context
-        let context = match self.opengl.version {
-            GlRequest::Latest => {
-                if let Ok(ctxt) = create_context(&self.glx, &extra_functions, &self.extensions, (3, 2),
-                                                 self.opengl.profile, self.opengl.debug,
-                                                 self.opengl.robustness, share,
-                                                 self.display, self.fb_config, &self.visual_infos)
-                {
-                    ctxt
-                } else if let Ok(ctxt) = create_context(&self.glx, &extra_functions, &self.extensions,
-                                                        (3, 1), self.opengl.profile,
-                                                        self.opengl.debug,
-                                                        self.opengl.robustness, share, self.display,
-                                                        self.fb_config, &self.visual_infos)
-                {
-                    ctxt
+        let create = |version| {
+            create_context(&self.glx, &extra_functions, &self.extensions, version,
+                           self.opengl.profile, self.opengl.debug, self.opengl.robustness, share,
+                           self.display, self.fb_config, &self.visual_infos)
+        };
 
-                } else {
-                    try!(create_context(&self.glx, &extra_functions, &self.extensions, (1, 0),
-                                        self.opengl.profile, self.opengl.debug,
-                                        self.opengl.robustness,
-                                        share, self.display, self.fb_config, &self.visual_infos))
-                }
-            },
-            GlRequest::Specific(Api::OpenGl, (major, minor)) => {
-                try!(create_context(&self.glx, &extra_functions, &self.extensions, (major, minor),
-                                    self.opengl.profile, self.opengl.debug,
-                                    self.opengl.robustness, share, self.display, self.fb_config,
-                                    &self.visual_infos))
+        let (context, failed_versions) = match self.opengl.version {
+            GlRequest::Latest => {
+                try!(create_with_fallbacks(Api::OpenGl, &[(3, 2), (3, 1), (1, 0)], create))
             },
+            GlRequest::Specific(Api::OpenGl, version) => (try!(create(version)), Vec::new()),
             GlRequest::Specific(_, _) => panic!("Only OpenGL is supported"),
-            GlRequest::GlThenGles { opengl_version: (major, minor), .. } => {
-                try!(create_context(&self.glx, &extra_functions, &self.extensions, (major, minor),
-                                    self.opengl.profile, self.opengl.debug,
-                                    self.opengl.robustness, share, self.display, self.fb_config,
-                                    &self.visual_infos))
+            GlRequest::GlThenGles { opengl_version, .. } => {
+                (try!(create(opengl_version)), Vec::new())
+            },
+            GlRequest::Range { api: Api::OpenGl, .. } => {
+                try!(self.opengl.version.create_in_range(create))
             },
+            GlRequest::Range { .. } => panic!("Only OpenGL is supported"),
         };
 
         // vsync
@@ -256,27 +671,58 @@ impl<'a> ContextPrototype<'a> {
             }*/
 
             unsafe { self.glx.MakeCurrent(self.display as *mut _, 0, ptr::null()) };
+            current::forget();
         }
 
+        // the maximum swap interval is a property of the drawable with GLX
+        let mut config_limits = self.config_limits;
+        if self.extensions.contains("GLX_EXT_swap_control") {
+            let mut max_interval = 0;
+            unsafe {
+                self.glx.QueryDrawable(self.display as *mut _, window,
+                                       ffi::glx_extra::MAX_SWAP_INTERVAL_EXT as c_int,
+                                       &mut max_interval);
+            }
+            config_limits.swap_interval_range = Some((0, max_interval));
+        }
+
+        let renderer_info = if self.extensions.contains("GLX_MESA_query_renderer") {
+            unsafe { query_renderer_info(&extra_functions, self.display, self.screen_id) }
+        } else {
+            RendererInfo::default()
+        };
+
         Ok(Context {
             glx: self.glx,
             display: self.display,
             window: window,
             context: context,
+            fb_config: self.fb_config,
             pixel_format: self.pixel_format,
+            config_limits: config_limits,
+            renderer_info: renderer_info,
+            quirks: self.quirks,
+            failed_versions: failed_versions,
+            extra_functions: extra_functions,
+            swap_control: self.extensions.contains("GLX_EXT_swap_control"),
+            adaptive_vsync: self.extensions.contains("GLX_EXT_swap_control_tear"),
+            destroyed: AtomicBool::new(false),
+            tracker: leaks::Tracker::new("GLX context", context as usize,
+                                         self.display as usize),
         })
     }
 }
 
-fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, extensions: &str,
-                  version: (u8, u8), profile: Option<GlProfile>, debug: bool,
-                  robustness: Robustness, share: ffi::GLXContext, display: *mut ffi::Display,
+fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx,
+                  extensions: &Extensions, version: (u8, u8), profile: Option<GlProfile>,
+                  debug: bool, robustness: Robustness, share: ffi::GLXContext,
+                  display: *mut ffi::Display,
                   fb_config: ffi::glx::types::GLXFBConfig,
                   visual_infos: &ffi::XVisualInfo)
                   -> Result<ffi::GLXContext, CreationError>
 {
     unsafe {
-        let context = if extensions.split(' ').find(|&i| i == "GLX_ARB_create_context").is_some() {
+        let context = if extensions.contains("GLX_ARB_create_context") {
             let mut attributes = Vec::with_capacity(9);
 
             attributes.push(ffi::glx_extra::CONTEXT_MAJOR_VERSION_ARB as c_int);
@@ -300,7 +746,7 @@ fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, ex
                 let mut flags = 0;
 
                 // robustness
-                if extensions.split(' ').find(|&i| i == "GLX_ARB_create_context_robustness").is_some() {
+                if extensions.contains("GLX_ARB_create_context_robustness") {
                     match robustness {
                         Robustness::RobustNoResetNotification | Robustness::TryRobustNoResetNotification => {
                             attributes.push(ffi::glx_extra::CONTEXT_RESET_NOTIFICATION_STRATEGY_ARB as c_int);
@@ -336,10 +782,13 @@ fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, ex
 
             attributes.push(0);
 
+            debug_log!("glXCreateContextAttribsARB attributes: {:?}", attributes);
+
             extra_functions.CreateContextAttribsARB(display as *mut _, fb_config, share, 1,
                                                     attributes.as_ptr())
 
         } else {
+            debug_log!("GLX_ARB_create_context is not supported, using glXCreateContext");
             let visual_infos: *const ffi::XVisualInfo = visual_infos;
             glx.CreateContext(display as *mut _, visual_infos as *mut _, share, 1)
         };
@@ -353,10 +802,93 @@ fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, ex
     }
 }
 
+/// Describes the failure of a GLX function. GLX reports the code through the X error handler,
+/// the windows fill it in from there.
+#[inline]
+fn native_error(function: &'static str) -> NativeError {
+    NativeError { function: function, code: 0, name: None }
+}
+
+/// Reads the description of the renderer of the screen with `GLX_MESA_query_renderer`.
+unsafe fn query_renderer_info(extra_functions: &ffi::glx_extra::Glx, display: *mut ffi::Display,
+                              screen_id: libc::c_int) -> RendererInfo
+{
+    // the values of the first renderer, which is the one that the contexts use
+    let integers = |attrib: u32| {
+        let mut values = [0; 3];
+        if extra_functions.QueryRendererIntegerMESA(display as *mut _, screen_id, 0,
+                                                    attrib as c_int, values.as_mut_ptr()) != 0
+        {
+            Some(values)
+        } else {
+            None
+        }
+    };
+
+    // `0.0` means that the profile isn't supported
+    let version = |attrib: u32| {
+        integers(attrib).and_then(|v| if v[0] == 0 { None } else { Some((v[0] as u8, v[1] as u8)) })
+    };
+
+    let string = |attrib: u32| {
+        let value = extra_functions.QueryRendererStringMESA(display as *mut _, screen_id, 0,
+                                                            attrib as c_int);
+        if value.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(value).to_string_lossy().into_owned())
+        }
+    };
+
+    RendererInfo {
+        vendor: string(ffi::glx_extra::RENDERER_VENDOR_ID_MESA),
+        device: string(ffi::glx_extra::RENDERER_DEVICE_ID_MESA),
+        vendor_id: integers(ffi::glx_extra::RENDERER_VENDOR_ID_MESA).map(|v| v[0]),
+        device_id: integers(ffi::glx_extra::RENDERER_DEVICE_ID_MESA).map(|v| v[0]),
+        video_memory: integers(ffi::glx_extra::RENDERER_VIDEO_MEMORY_MESA).map(|v| v[0]),
+        unified_memory: integers(ffi::glx_extra::RENDERER_UNIFIED_MEMORY_ARCHITECTURE_MESA)
+                            .map(|v| v[0] != 0),
+        accelerated: integers(ffi::glx_extra::RENDERER_ACCELERATED_MESA).map(|v| v[0] != 0),
+        max_core_profile_version:
+            version(ffi::glx_extra::RENDERER_OPENGL_CORE_PROFILE_VERSION_MESA),
+        max_compatibility_profile_version:
+            version(ffi::glx_extra::RENDERER_OPENGL_COMPATIBILITY_PROFILE_VERSION_MESA),
+        max_gles2_version: version(ffi::glx_extra::RENDERER_OPENGL_ES2_PROFILE_VERSION_MESA),
+        // only Mesa implements the extension, the version of the renderer is the one of Mesa
+        mesa_version: integers(ffi::glx_extra::RENDERER_VERSION_MESA).map(|v| (v[0], v[1], v[2])),
+    }
+}
+
 /// Enumerates all available FBConfigs
-unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xlib,
+/// Reads the limits of a framebuffer config.
+unsafe fn get_config_limits(glx: &ffi::glx::Glx, display: *mut ffi::Display,
+                            fb_config: ffi::glx::types::GLXFBConfig) -> ConfigLimits
+{
+    let attrib = |attr: u32| {
+        let mut value = 0;
+        if glx.GetFBConfigAttrib(display as *mut _, fb_config, attr as c_int, &mut value) != 0 {
+            None
+        } else {
+            Some(value)
+        }
+    };
+
+    ConfigLimits {
+        max_pbuffer_dimensions: match (attrib(ffi::glx::MAX_PBUFFER_WIDTH),
+                                       attrib(ffi::glx::MAX_PBUFFER_HEIGHT))
+        {
+            (Some(w), Some(h)) => Some((w as u32, h as u32)),
+            _ => None,
+        },
+        max_pbuffer_pixels: attrib(ffi::glx::MAX_PBUFFER_PIXELS).map(|p| p as u32),
+        swap_interval_range: None,
+        native_renderable: attrib(ffi::glx::X_RENDERABLE).map(|r| r != 0),
+    }
+}
+
+unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &Extensions, xlib: &ffi::Xlib,
                           display: *mut ffi::Display, screen_id: libc::c_int,
-                          reqs: &PixelFormatRequirements)
+                          reqs: &PixelFormatRequirements, transparent: bool)
                           -> Result<(ffi::glx::types::GLXFBConfig, PixelFormat), ()>
 {
     let descriptor = {
@@ -373,7 +905,7 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
 
         out.push(ffi::glx::RENDER_TYPE as c_int);
         if reqs.float_color_buffer {
-            if extensions.split(' ').find(|&i| i == "GLX_ARB_fbconfig_float").is_some() {
+            if extensions.contains("GLX_ARB_fbconfig_float") {
                 out.push(ffi::glx_extra::RGBA_FLOAT_BIT_ARB as c_int);
             } else {
                 return Err(());
@@ -382,7 +914,7 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
             out.push(ffi::glx::RGBA_BIT as c_int);
         }
 
-        if let Some(color) = reqs.color_bits {
+        if let Some(color) = reqs.color_bits.min() {
             out.push(ffi::glx::RED_SIZE as c_int);
             out.push((color / 3) as c_int);
             out.push(ffi::glx::GREEN_SIZE as c_int);
@@ -391,17 +923,17 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
             out.push((color / 3 + if color % 3 == 2 { 1 } else { 0 }) as c_int);
         }
 
-        if let Some(alpha) = reqs.alpha_bits {
+        if let Some(alpha) = reqs.alpha_bits.min() {
             out.push(ffi::glx::ALPHA_SIZE as c_int);
             out.push(alpha as c_int);
         }
 
-        if let Some(depth) = reqs.depth_bits {
+        if let Some(depth) = reqs.depth_bits.min() {
             out.push(ffi::glx::DEPTH_SIZE as c_int);
             out.push(depth as c_int);
         }
 
-        if let Some(stencil) = reqs.stencil_bits {
+        if let Some(stencil) = reqs.stencil_bits.min() {
             out.push(ffi::glx::STENCIL_SIZE as c_int);
             out.push(stencil as c_int);
         }
@@ -410,12 +942,27 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         out.push(ffi::glx::DOUBLEBUFFER as c_int);
         out.push(if double_buffer { 1 } else { 0 });
 
-        if let Some(multisampling) = reqs.multisampling {
-            if extensions.split(' ').find(|&i| i == "GLX_ARB_multisample").is_some() {
+        // with the extension, `GLX_SAMPLES_ARB` is the number of coverage samples, otherwise
+        // the multisampled configs with the color samples are chosen
+        let coverage = match reqs.coverage_samples {
+            Some(samples) if samples.coverage > samples.color &&
+                             extensions.contains("GLX_NV_multisample_coverage") => Some(samples),
+            _ => None,
+        };
+
+        if let Some(multisampling) = reqs.color_samples() {
+            if extensions.contains("GLX_ARB_multisample") {
                 out.push(ffi::glx_extra::SAMPLE_BUFFERS_ARB as c_int);
                 out.push(if multisampling == 0 { 0 } else { 1 });
                 out.push(ffi::glx_extra::SAMPLES_ARB as c_int);
-                out.push(multisampling as c_int);
+                match coverage {
+                    Some(samples) => {
+                        out.push(samples.coverage as c_int);
+                        out.push(COLOR_SAMPLES_NV);
+                        out.push(samples.color as c_int);
+                    },
+                    None => out.push(multisampling as c_int),
+                }
             } else {
                 return Err(());
             }
@@ -425,10 +972,10 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         out.push(if reqs.stereoscopy { 1 } else { 0 });
 
         if reqs.srgb {
-            if extensions.split(' ').find(|&i| i == "GLX_ARB_framebuffer_sRGB").is_some() {
+            if extensions.contains("GLX_ARB_framebuffer_sRGB") {
                 out.push(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int);
                 out.push(1);
-            } else if extensions.split(' ').find(|&i| i == "GLX_EXT_framebuffer_sRGB").is_some() {
+            } else if extensions.contains("GLX_EXT_framebuffer_sRGB") {
                 out.push(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int);
                 out.push(1);
             } else {
@@ -439,7 +986,7 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         match reqs.release_behavior {
             ReleaseBehavior::Flush => (),
             ReleaseBehavior::None => {
-                if extensions.split(' ').find(|&i| i == "GLX_ARB_context_flush_control").is_some() {
+                if extensions.contains("GLX_ARB_context_flush_control") {
                     out.push(ffi::glx_extra::CONTEXT_RELEASE_BEHAVIOR_ARB as c_int);
                     out.push(ffi::glx_extra::CONTEXT_RELEASE_BEHAVIOR_NONE_ARB as c_int);
                 }
@@ -447,24 +994,98 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         }
 
         out.push(ffi::glx::CONFIG_CAVEAT as c_int);
-        out.push(ffi::glx::DONT_CARE as c_int);
+        out.push(if reqs.allow_caveat { ffi::glx::DONT_CARE } else { ffi::glx::NONE } as c_int);
 
         out.push(0);
         out
     };
 
+    debug_log!("glXChooseFBConfig attributes: {:?}", descriptor);
+
     // calling glXChooseFBConfig
-    let fb_config = {
-        let mut num_configs = 1;
+    let fb_configs = {
+        let mut num_configs = 0;
         let result = glx.ChooseFBConfig(display as *mut _, screen_id, descriptor.as_ptr(),
                                         &mut num_configs);
         if result.is_null() { return Err(()); }
-        if num_configs == 0 { return Err(()); }
-        let val = *result;
+        let configs = slice::from_raw_parts(result, num_configs as usize).to_vec();
         (xlib.XFree)(result as *mut _);
-        val
+        debug_log!("glXChooseFBConfig returned {} configs", num_configs);
+        configs
     };
 
+    // GLX only filters the configs by minimum number of bits, so we pick the best one that
+    // also matches the exact requirements, or the first one without a scoring function
+    let mut best: Option<(i32, ffi::glx::types::GLXFBConfig, PixelFormat)> = None;
+    for fb_config in fb_configs {
+        let pf_desc = get_fbconfig_pixel_format(glx, extensions, display, fb_config);
+
+        if !reqs.bits_satisfied_by(&pf_desc) {
+            continue;
+        }
+
+        // a strict request skips the inexact configs, so that a lower ranked exact one wins
+        if reqs.strict && !reqs.is_satisfied_by(&pf_desc) {
+            debug_log!("skipping GLXFBConfig {:?}, not exact: {:?}", fb_config, pf_desc);
+            continue;
+        }
+
+        if transparent && !has_argb_visual(glx, xlib, display, fb_config) {
+            continue;
+        }
+
+        if reqs.conformant && is_fbconfig_non_conformant(glx, display, fb_config) {
+            debug_log!("skipping non-conformant GLXFBConfig {:?}", fb_config);
+            continue;
+        }
+
+        let score = match reqs.score(&pf_desc, 0) {
+            Some(score) => score,
+            None => {
+                debug_log!("GLXFBConfig {:?} rejected by the scoring function", fb_config);
+                continue;
+            },
+        };
+        debug_log!("GLXFBConfig {:?} has score {}: {:?}", fb_config, score, pf_desc);
+        if best.as_ref().map(|&(s, _, _)| score > s).unwrap_or(true) {
+            best = Some((score, fb_config, pf_desc));
+        }
+    }
+
+    let (_, fb_config, pf_desc) = try!(best.ok_or(()));
+    debug_log!("selected GLXFBConfig {:?}: {:?}", fb_config, pf_desc);
+    Ok((fb_config, pf_desc))
+}
+
+/// Returns true if `fb_config` has the `GLX_NON_CONFORMANT_CONFIG` caveat.
+unsafe fn is_fbconfig_non_conformant(glx: &ffi::glx::Glx, display: *mut ffi::Display,
+                                     fb_config: ffi::glx::types::GLXFBConfig) -> bool
+{
+    let mut caveat = 0;
+    glx.GetFBConfigAttrib(display as *mut _, fb_config, ffi::glx::CONFIG_CAVEAT as c_int,
+                          &mut caveat);
+    caveat as u32 == ffi::glx::NON_CONFORMANT_CONFIG
+}
+
+/// Returns true if the visual of `fb_config` has an alpha channel, which compositing window
+/// managers use to blend the window with what is behind it.
+unsafe fn has_argb_visual(glx: &ffi::glx::Glx, xlib: &ffi::Xlib, display: *mut ffi::Display,
+                          fb_config: ffi::glx::types::GLXFBConfig) -> bool
+{
+    let vi = glx.GetVisualFromFBConfig(display as *mut _, fb_config);
+    if vi.is_null() {
+        return false;
+    }
+
+    let depth = (*(vi as *const ffi::XVisualInfo)).depth;
+    (xlib.XFree)(vi as *mut _);
+    depth == 32
+}
+
+unsafe fn get_fbconfig_pixel_format(glx: &ffi::glx::Glx, extensions: &Extensions,
+                                    display: *mut ffi::Display,
+                                    fb_config: ffi::glx::types::GLXFBConfig) -> PixelFormat
+{
     let get_attrib = |attrib: c_int| -> i32 {
         let mut value = 0;
         glx.GetFBConfigAttrib(display as *mut _, fb_config, attrib, &mut value);
@@ -472,25 +1093,44 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         value
     };
 
-    let pf_desc = PixelFormat {
+    let depth_bits = get_attrib(ffi::glx::DEPTH_SIZE as c_int) as u8;
+    let stencil_bits = get_attrib(ffi::glx::STENCIL_SIZE as c_int) as u8;
+
+    // with `GLX_NV_multisample_coverage`, `GLX_SAMPLES_ARB` counts the coverage samples
+    let (color_samples, coverage_samples) =
+        if get_attrib(ffi::glx::SAMPLE_BUFFERS as c_int) == 0 {
+            (0, 0)
+        } else if extensions.contains("GLX_NV_multisample_coverage") {
+            (get_attrib(COLOR_SAMPLES_NV), get_attrib(ffi::glx::SAMPLES as c_int))
+        } else {
+            let samples = get_attrib(ffi::glx::SAMPLES as c_int);
+            (samples, samples)
+        };
+
+    PixelFormat {
         hardware_accelerated: get_attrib(ffi::glx::CONFIG_CAVEAT as c_int) !=
                                                             ffi::glx::SLOW_CONFIG as c_int,
         color_bits: get_attrib(ffi::glx::RED_SIZE as c_int) as u8 +
                     get_attrib(ffi::glx::GREEN_SIZE as c_int) as u8 +
                     get_attrib(ffi::glx::BLUE_SIZE as c_int) as u8,
         alpha_bits: get_attrib(ffi::glx::ALPHA_SIZE as c_int) as u8,
-        depth_bits: get_attrib(ffi::glx::DEPTH_SIZE as c_int) as u8,
-        stencil_bits: get_attrib(ffi::glx::STENCIL_SIZE as c_int) as u8,
+        depth_bits: depth_bits,
+        stencil_bits: stencil_bits,
         stereoscopy: get_attrib(ffi::glx::STEREO as c_int) != 0,
         double_buffer: get_attrib(ffi::glx::DOUBLEBUFFER as c_int) != 0,
-        multisampling: if get_attrib(ffi::glx::SAMPLE_BUFFERS as c_int) != 0 {
-            Some(get_attrib(ffi::glx::SAMPLES as c_int) as u16)
+        multisampling: if color_samples != 0 {
+            Some(color_samples as u16)
         } else {
             None
         },
         srgb: get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int) != 0 ||
               get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int) != 0,
-    };
-
-    Ok((fb_config, pf_desc))
+        implicit_resolve: false,
+        packed_depth_stencil: is_packed_depth_stencil(depth_bits, stencil_bits),
+        coverage_samples: if coverage_samples > color_samples {
+            Some(coverage_samples as u16)
+        } else {
+            None
+        },
+    }
 }
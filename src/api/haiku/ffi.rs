@@ -0,0 +1,53 @@
+#![allow(dead_code)]
+
+use libc;
+
+// options of `BGLView`, from `GLView.h`
+pub const BGL_RGB: libc::c_ulong = 0;
+pub const BGL_DOUBLE: libc::c_ulong = 2;
+pub const BGL_ACCUM: libc::c_ulong = 4;
+pub const BGL_ALPHA: libc::c_ulong = 8;
+pub const BGL_DEPTH: libc::c_ulong = 16;
+pub const BGL_STENCIL: libc::c_ulong = 512;
+
+// events returned by `glutin_haiku_window_next_event`, must match `glview.cpp`
+pub const EVENT_NONE: libc::c_int = 0;
+pub const EVENT_CLOSED: libc::c_int = 1;
+pub const EVENT_RESIZED: libc::c_int = 2;
+pub const EVENT_MOVED: libc::c_int = 3;
+pub const EVENT_FOCUSED: libc::c_int = 4;
+pub const EVENT_UNFOCUSED: libc::c_int = 5;
+pub const EVENT_AWAKENED: libc::c_int = 6;
+
+/// A `BWindow` that contains a `BGLView` covering its whole area.
+pub type GlutinWindow = libc::c_void;
+
+/// A `BMessenger` that targets a `GlutinWindow`, and stays valid after the window is destroyed.
+pub type GlutinMessenger = libc::c_void;
+
+extern {
+    pub fn glutin_haiku_window_new(title: *const libc::c_char, width: libc::c_int,
+                                   height: libc::c_int, gl_type: libc::c_ulong,
+                                   visible: libc::c_int) -> *mut GlutinWindow;
+    pub fn glutin_haiku_window_destroy(window: *mut GlutinWindow);
+    pub fn glutin_haiku_lock_gl(window: *mut GlutinWindow);
+    pub fn glutin_haiku_unlock_gl(window: *mut GlutinWindow);
+    pub fn glutin_haiku_swap_buffers(window: *mut GlutinWindow);
+    pub fn glutin_haiku_get_proc_address(window: *mut GlutinWindow, name: *const libc::c_char)
+                                         -> *mut libc::c_void;
+    pub fn glutin_haiku_window_set_title(window: *mut GlutinWindow, title: *const libc::c_char);
+    pub fn glutin_haiku_window_show(window: *mut GlutinWindow);
+    pub fn glutin_haiku_window_hide(window: *mut GlutinWindow);
+    pub fn glutin_haiku_window_get_frame(window: *mut GlutinWindow, x: *mut libc::c_int,
+                                         y: *mut libc::c_int, width: *mut libc::c_int,
+                                         height: *mut libc::c_int);
+    pub fn glutin_haiku_window_move_to(window: *mut GlutinWindow, x: libc::c_int, y: libc::c_int);
+    pub fn glutin_haiku_window_resize_to(window: *mut GlutinWindow, width: libc::c_int,
+                                         height: libc::c_int);
+    pub fn glutin_haiku_window_next_event(window: *mut GlutinWindow, a: *mut libc::c_int,
+                                          b: *mut libc::c_int) -> libc::c_int;
+    pub fn glutin_haiku_messenger_new(window: *mut GlutinWindow) -> *mut GlutinMessenger;
+    pub fn glutin_haiku_messenger_destroy(messenger: *mut GlutinMessenger);
+    pub fn glutin_haiku_messenger_wakeup(messenger: *mut GlutinMessenger);
+    pub fn glutin_haiku_screen_get_size(width: *mut libc::c_int, height: *mut libc::c_int);
+}
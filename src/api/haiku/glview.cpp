@@ -0,0 +1,268 @@
+// C interface to the `BWindow` and `BGLView` classes of the Haiku API, which are only usable from
+// C++. Compiled by the build script when targetting Haiku.
+
+#include <Application.h>
+#include <GLView.h>
+#include <Messenger.h>
+#include <Screen.h>
+#include <Window.h>
+#include <OS.h>
+
+#include <string.h>
+
+namespace {
+
+// events that `glutin_haiku_window_next_event` reports, must match `ffi.rs`
+enum {
+    EVENT_NONE = 0,
+    EVENT_CLOSED = 1,
+    EVENT_RESIZED = 2,
+    EVENT_MOVED = 3,
+    EVENT_FOCUSED = 4,
+    EVENT_UNFOCUSED = 5,
+    EVENT_AWAKENED = 6,
+};
+
+// the message that `glutin_haiku_messenger_wakeup` sends to the window
+const uint32 WAKEUP_MESSAGE = 'glwk';
+
+struct PendingEvent {
+    int kind;
+    int a;
+    int b;
+};
+
+const int MAX_PENDING_EVENTS = 64;
+
+// the application object must exist before any window is created, and its message loop runs in
+// its own thread because the calling thread belongs to the user
+thread_id application_thread = -1;
+
+int32 run_application(void*) {
+    new BApplication("application/x-vnd.glutin");
+    be_app->Run();
+    delete be_app;
+    return 0;
+}
+
+void ensure_application() {
+    if (application_thread >= 0 || be_app != NULL) {
+        return;
+    }
+
+    application_thread = spawn_thread(run_application, "glutin application",
+                                      B_NORMAL_PRIORITY, NULL);
+    resume_thread(application_thread);
+
+    while (be_app == NULL || be_app->IsLaunching()) {
+        snooze(10000);
+    }
+}
+
+class GlutinWindow : public BWindow {
+public:
+    GlutinWindow(BRect frame, const char* title)
+        : BWindow(frame, title, B_TITLED_WINDOW, B_ASYNCHRONOUS_CONTROLS),
+          view(NULL), count(0), sem(create_sem(1, "glutin events"))
+    {
+    }
+
+    virtual ~GlutinWindow() {
+        delete_sem(sem);
+    }
+
+    virtual bool QuitRequested() {
+        // the window is destroyed by its owner, which only happens when the user drops it
+        push(EVENT_CLOSED, 0, 0);
+        return false;
+    }
+
+    virtual void FrameResized(float width, float height) {
+        BWindow::FrameResized(width, height);
+        push(EVENT_RESIZED, (int) width + 1, (int) height + 1);
+    }
+
+    virtual void FrameMoved(BPoint origin) {
+        BWindow::FrameMoved(origin);
+        push(EVENT_MOVED, (int) origin.x, (int) origin.y);
+    }
+
+    virtual void WindowActivated(bool active) {
+        BWindow::WindowActivated(active);
+        push(active ? EVENT_FOCUSED : EVENT_UNFOCUSED, 0, 0);
+    }
+
+    virtual void MessageReceived(BMessage* message) {
+        if (message->what == WAKEUP_MESSAGE) {
+            push(EVENT_AWAKENED, 0, 0);
+        } else {
+            BWindow::MessageReceived(message);
+        }
+    }
+
+    void push(int kind, int a, int b) {
+        acquire_sem(sem);
+        if (count < MAX_PENDING_EVENTS) {
+            events[count].kind = kind;
+            events[count].a = a;
+            events[count].b = b;
+            count += 1;
+        }
+        release_sem(sem);
+    }
+
+    int pop(int* a, int* b) {
+        acquire_sem(sem);
+        int kind = EVENT_NONE;
+        if (count > 0) {
+            kind = events[0].kind;
+            *a = events[0].a;
+            *b = events[0].b;
+            memmove(events, events + 1, (count - 1) * sizeof(PendingEvent));
+            count -= 1;
+        }
+        release_sem(sem);
+        return kind;
+    }
+
+    BGLView* view;
+
+private:
+    PendingEvent events[MAX_PENDING_EVENTS];
+    int count;
+    sem_id sem;
+};
+
+}
+
+extern "C" {
+
+void* glutin_haiku_window_new(const char* title, int width, int height, unsigned long gl_type,
+                              int visible)
+{
+    ensure_application();
+
+    BRect frame(100, 100, 100 + width - 1, 100 + height - 1);
+    GlutinWindow* window = new GlutinWindow(frame, title);
+
+    BGLView* view = new BGLView(window->Bounds(), "glutin", B_FOLLOW_ALL_SIDES, 0, gl_type);
+    window->AddChild(view);
+    window->view = view;
+
+    if (visible) {
+        window->Show();
+    } else {
+        // a window must be shown once for its looper to start
+        window->Hide();
+        window->Show();
+    }
+
+    return window;
+}
+
+void glutin_haiku_window_destroy(void* window) {
+    GlutinWindow* w = (GlutinWindow*) window;
+    if (w->Lock()) {
+        w->Quit();
+    }
+}
+
+void glutin_haiku_lock_gl(void* window) {
+    ((GlutinWindow*) window)->view->LockGL();
+}
+
+void glutin_haiku_unlock_gl(void* window) {
+    ((GlutinWindow*) window)->view->UnlockGL();
+}
+
+void glutin_haiku_swap_buffers(void* window) {
+    ((GlutinWindow*) window)->view->SwapBuffers();
+}
+
+void* glutin_haiku_get_proc_address(void* window, const char* name) {
+    return ((GlutinWindow*) window)->view->GetGLProcAddress(name);
+}
+
+void glutin_haiku_window_set_title(void* window, const char* title) {
+    GlutinWindow* w = (GlutinWindow*) window;
+    if (w->Lock()) {
+        w->SetTitle(title);
+        w->Unlock();
+    }
+}
+
+void glutin_haiku_window_show(void* window) {
+    GlutinWindow* w = (GlutinWindow*) window;
+    if (w->Lock()) {
+        if (w->IsHidden()) {
+            w->Show();
+        }
+        w->Unlock();
+    }
+}
+
+void glutin_haiku_window_hide(void* window) {
+    GlutinWindow* w = (GlutinWindow*) window;
+    if (w->Lock()) {
+        if (!w->IsHidden()) {
+            w->Hide();
+        }
+        w->Unlock();
+    }
+}
+
+void glutin_haiku_window_get_frame(void* window, int* x, int* y, int* width, int* height) {
+    GlutinWindow* w = (GlutinWindow*) window;
+    if (w->Lock()) {
+        BRect frame = w->Frame();
+        *x = (int) frame.left;
+        *y = (int) frame.top;
+        *width = (int) frame.Width() + 1;
+        *height = (int) frame.Height() + 1;
+        w->Unlock();
+    }
+}
+
+void glutin_haiku_window_move_to(void* window, int x, int y) {
+    GlutinWindow* w = (GlutinWindow*) window;
+    if (w->Lock()) {
+        w->MoveTo(x, y);
+        w->Unlock();
+    }
+}
+
+void glutin_haiku_window_resize_to(void* window, int width, int height) {
+    GlutinWindow* w = (GlutinWindow*) window;
+    if (w->Lock()) {
+        w->ResizeTo(width - 1, height - 1);
+        w->Unlock();
+    }
+}
+
+int glutin_haiku_window_next_event(void* window, int* a, int* b) {
+    return ((GlutinWindow*) window)->pop(a, b);
+}
+
+void* glutin_haiku_messenger_new(void* window) {
+    return new BMessenger((GlutinWindow*) window);
+}
+
+void glutin_haiku_messenger_destroy(void* messenger) {
+    delete (BMessenger*) messenger;
+}
+
+void glutin_haiku_messenger_wakeup(void* messenger) {
+    // fails without any harm if the window has been destroyed
+    ((BMessenger*) messenger)->SendMessage(WAKEUP_MESSAGE);
+}
+
+void glutin_haiku_screen_get_size(int* width, int* height) {
+    // a `BScreen` needs the application object
+    ensure_application();
+
+    BRect frame = BScreen(B_MAIN_SCREEN_ID).Frame();
+    *width = (int) frame.Width() + 1;
+    *height = (int) frame.Height() + 1;
+}
+
+}
@@ -0,0 +1,470 @@
+#![cfg(target_os = "haiku")]
+
+//! Haiku backend. The API of Haiku is C++ only, therefore the `BWindow` and the `BGLView` that
+//! provides its OpenGL context are created by `glview.cpp`, which the build script compiles.
+
+use libc;
+use Api;
+use ContextError;
+use CreationError;
+use CursorState;
+use Event;
+use GlAttributes;
+use GlContext;
+use GlRequest;
+use MouseCursor;
+use PixelFormat;
+use PixelFormatRequirements;
+use WindowAttributes;
+use api::Drawable;
+use c_str::with_c_str;
+use presenter::SwapFn;
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::{ptr, thread};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod ffi;
+
+thread_local! {
+    // `BGLView` contexts are made current by locking them, this is the one locked by the thread
+    static CURRENT_WINDOW: Cell<*mut ffi::GlutinWindow> = Cell::new(ptr::null_mut());
+}
+
+#[derive(Clone, Default)]
+pub struct PlatformSpecificWindowBuilderAttributes;
+
+pub struct Window {
+    window: *mut ffi::GlutinWindow,
+    pixel_format: PixelFormat,
+}
+
+unsafe impl Send for Window {}
+unsafe impl Sync for Window {}
+
+pub struct PollEventsIterator<'a> {
+    window: &'a Window,
+}
+
+impl<'a> Iterator for PollEventsIterator<'a> {
+    type Item = Event;
+
+    #[inline]
+    fn next(&mut self) -> Option<Event> {
+        self.window.next_event()
+    }
+}
+
+pub struct WaitEventsIterator<'a> {
+    window: &'a Window,
+}
+
+impl<'a> Iterator for WaitEventsIterator<'a> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        // the events are queued by the looper thread of the window, which can't wake us up
+        loop {
+            if let Some(ev) = self.window.next_event() {
+                return Some(ev);
+            }
+
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WindowProxy {
+    messenger: Arc<Messenger>,
+}
+
+// the `BMessenger` is only read to send messages, which the kernel ports make thread-safe
+struct Messenger(*mut ffi::GlutinMessenger);
+
+unsafe impl Send for Messenger {}
+unsafe impl Sync for Messenger {}
+
+impl Drop for Messenger {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ffi::glutin_haiku_messenger_destroy(self.0) }
+    }
+}
+
+impl WindowProxy {
+    #[inline]
+    pub fn wakeup_event_loop(&self) {
+        unsafe { ffi::glutin_haiku_messenger_wakeup(self.messenger.0) }
+    }
+}
+
+#[derive(Clone)]
+pub struct MonitorId;
+
+#[inline]
+pub fn get_available_monitors() -> VecDeque<MonitorId> {
+    let mut list = VecDeque::new();
+    list.push_back(MonitorId);
+    list
+}
+
+#[inline]
+pub fn get_primary_monitor() -> MonitorId {
+    MonitorId
+}
+
+impl MonitorId {
+    #[inline]
+    pub fn get_name(&self) -> Option<String> {
+        Some("Primary Monitor".to_owned())
+    }
+
+    #[inline]
+    pub fn get_native_identifier(&self) -> ::native_monitor::NativeMonitorId {
+        ::native_monitor::NativeMonitorId::Unavailable
+    }
+
+    #[inline]
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        let (mut width, mut height) = (0, 0);
+        unsafe { ffi::glutin_haiku_screen_get_size(&mut width, &mut height) };
+        (width as u32, height as u32)
+    }
+}
+
+impl Window {
+    pub fn new(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
+               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               -> Result<Window, CreationError>
+    {
+        // `BGLView` provides whatever desktop OpenGL version Mesa supports
+        match opengl.version {
+            GlRequest::Specific(Api::OpenGl, _) | GlRequest::Latest |
+            GlRequest::GlThenGles { .. } | GlRequest::Range { api: Api::OpenGl, .. } => (),
+            GlRequest::Specific(_, _) | GlRequest::Range { .. } => {
+                return Err(CreationError::OpenGlVersionNotSupported)
+            },
+        }
+
+        if opengl.sharing.is_some() || window.transparent {
+            return Err(CreationError::NotSupported);
+        }
+
+        if pf_reqs.float_color_buffer || pf_reqs.stereoscopy || pf_reqs.srgb ||
+           pf_reqs.color_samples().unwrap_or(0) != 0
+        {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+
+        let alpha = pf_reqs.alpha_bits.min().unwrap_or(0) != 0;
+        let depth = pf_reqs.depth_bits.min().unwrap_or(0) != 0;
+        let stencil = pf_reqs.stencil_bits.min().unwrap_or(0) != 0;
+        let double_buffer = pf_reqs.double_buffer.unwrap_or(true);
+
+        let mut gl_type = ffi::BGL_RGB;
+        if double_buffer { gl_type |= ffi::BGL_DOUBLE; }
+        if alpha { gl_type |= ffi::BGL_ALPHA; }
+        if depth { gl_type |= ffi::BGL_DEPTH; }
+        if stencil { gl_type |= ffi::BGL_STENCIL; }
+
+        // the view can't be queried, these are the sizes of the buffers that Mesa allocates
+        let pixel_format = PixelFormat {
+            hardware_accelerated: false,
+            color_bits: 24,
+            alpha_bits: if alpha { 8 } else { 0 },
+            depth_bits: if depth { 24 } else { 0 },
+            stencil_bits: if stencil { 8 } else { 0 },
+            stereoscopy: false,
+            double_buffer: double_buffer,
+            multisampling: None,
+            srgb: false,
+            implicit_resolve: false,
+            packed_depth_stencil: depth && stencil,
+            coverage_samples: None,
+        };
+
+        if !pf_reqs.bits_satisfied_by(&pixel_format) {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+        try!(pf_reqs.check_strict(&pixel_format));
+
+        let (width, height) = window.dimensions.unwrap_or((800, 600));
+        let title = CString::new(window.title.replace('\0', "")).unwrap();
+
+        let handle = unsafe {
+            ffi::glutin_haiku_window_new(title.as_ptr(), width as libc::c_int,
+                                         height as libc::c_int, gl_type,
+                                         if window.visible { 1 } else { 0 })
+        };
+
+        if handle.is_null() {
+            return Err(CreationError::OsError(format!("Failed to create the BGLView")));
+        }
+
+        Ok(Window {
+            window: handle,
+            pixel_format: pixel_format,
+        })
+    }
+
+    fn next_event(&self) -> Option<Event> {
+        unsafe {
+            let (mut a, mut b) = (0, 0);
+
+            match ffi::glutin_haiku_window_next_event(self.window, &mut a, &mut b) {
+                ffi::EVENT_CLOSED => Some(Event::Closed),
+                ffi::EVENT_RESIZED => Some(Event::Resized(a as u32, b as u32)),
+                ffi::EVENT_MOVED => Some(Event::Moved(a as i32, b as i32)),
+                ffi::EVENT_FOCUSED => Some(Event::Focused(true)),
+                ffi::EVENT_UNFOCUSED => Some(Event::Focused(false)),
+                ffi::EVENT_AWAKENED => Some(Event::Awakened),
+                _ => None,
+            }
+        }
+    }
+
+    #[inline]
+    pub fn set_title(&self, title: &str) {
+        if let Ok(title) = CString::new(title) {
+            unsafe { ffi::glutin_haiku_window_set_title(self.window, title.as_ptr()); }
+        }
+    }
+
+    #[inline]
+    pub fn show(&self) {
+        unsafe { ffi::glutin_haiku_window_show(self.window); }
+    }
+
+    #[inline]
+    pub fn hide(&self) {
+        unsafe { ffi::glutin_haiku_window_hide(self.window); }
+    }
+
+    fn get_frame(&self) -> (i32, i32, u32, u32) {
+        unsafe {
+            let (mut x, mut y, mut width, mut height) = (0, 0, 0, 0);
+            ffi::glutin_haiku_window_get_frame(self.window, &mut x, &mut y, &mut width,
+                                               &mut height);
+            (x as i32, y as i32, width as u32, height as u32)
+        }
+    }
+
+    #[inline]
+    pub fn get_position(&self) -> Option<(i32, i32)> {
+        let (x, y, _, _) = self.get_frame();
+        Some((x, y))
+    }
+
+    #[inline]
+    pub fn set_position(&self, x: i32, y: i32) {
+        unsafe {
+            ffi::glutin_haiku_window_move_to(self.window, x as libc::c_int, y as libc::c_int);
+        }
+    }
+
+    #[inline]
+    pub fn get_inner_size(&self) -> Option<(u32, u32)> {
+        let (_, _, width, height) = self.get_frame();
+        Some((width, height))
+    }
+
+    #[inline]
+    pub fn get_outer_size(&self) -> Option<(u32, u32)> {
+        // the decorations are drawn by the app_server outside of the frame
+        self.get_inner_size()
+    }
+
+    #[inline]
+    pub fn set_inner_size(&self, width: u32, height: u32) {
+        unsafe {
+            ffi::glutin_haiku_window_resize_to(self.window, width as libc::c_int,
+                                               height as libc::c_int);
+        }
+    }
+
+    #[inline]
+    pub fn poll_events(&self) -> PollEventsIterator {
+        PollEventsIterator {
+            window: self,
+        }
+    }
+
+    #[inline]
+    pub fn wait_events(&self) -> WaitEventsIterator {
+        WaitEventsIterator {
+            window: self,
+        }
+    }
+
+    #[inline]
+    pub fn create_window_proxy(&self) -> WindowProxy {
+        let messenger = unsafe { ffi::glutin_haiku_messenger_new(self.window) };
+        WindowProxy { messenger: Arc::new(Messenger(messenger)) }
+    }
+
+    #[inline]
+    pub fn platform_display(&self) -> *mut libc::c_void {
+        ptr::null_mut()
+    }
+
+    /// Returns the `BWindow`.
+    #[inline]
+    pub fn platform_window(&self) -> *mut libc::c_void {
+        self.window
+    }
+
+    #[inline]
+    pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_cursor(&self, _: MouseCursor) {
+    }
+
+    #[inline]
+    pub fn set_cursor_state(&self, _: CursorState) -> Result<(), String> {
+        Ok(())
+    }
+
+    #[inline]
+    pub fn hidpi_factor(&self) -> f32 {
+        1.0
+    }
+
+    /// Variable refresh rates aren't reported on this platform.
+    #[inline]
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        None
+    }
+
+    /// The buffers can only be swapped by the thread where the context is current on this
+    /// platform.
+    #[inline]
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        None
+    }
+
+    #[inline]
+    pub fn set_cursor_position(&self, _: i32, _: i32) -> Result<(), ()> {
+        Err(())
+    }
+
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
+}
+
+/// `BGLView` can only render into a window, there are no pbuffers on Haiku.
+pub enum PBuffer {}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+}
+
+impl GlContext for Window {
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        CURRENT_WINDOW.with(|current| {
+            let previous = current.get();
+            if previous == self.window {
+                return;
+            }
+
+            if !previous.is_null() {
+                ffi::glutin_haiku_unlock_gl(previous);
+            }
+
+            ffi::glutin_haiku_lock_gl(self.window);
+            current.set(self.window);
+        });
+
+        Ok(())
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        CURRENT_WINDOW.with(|current| current.get() == self.window)
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        with_c_str(addr, |addr| unsafe {
+            ffi::glutin_haiku_get_proc_address(self.window, addr) as *const _
+        })
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        unsafe { ffi::glutin_haiku_swap_buffers(self.window); }
+        Ok(())
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        Api::OpenGl
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        unsafe {
+            CURRENT_WINDOW.with(|current| {
+                if current.get() == self.window {
+                    ffi::glutin_haiku_unlock_gl(self.window);
+                    current.set(ptr::null_mut());
+                }
+            });
+
+            ffi::glutin_haiku_window_destroy(self.window);
+        }
+    }
+}
@@ -73,8 +73,11 @@ use objc::runtime::{Class, BOOL, YES, NO };
 
 use native_monitor::NativeMonitorId;
 use { Api, PixelFormat, CreationError, GlContext, CursorState, MouseCursor, Event };
+use c_str::with_c_str;
 use { PixelFormatRequirements, GlAttributes, WindowAttributes, ContextError };
 use CreationError::OsError;
+use api::Drawable;
+use presenter::SwapFn;
 
 mod delegate;
 use self::delegate::{ create_delegate_class, create_view_class };
@@ -109,7 +112,9 @@ pub struct MonitorId;
 
 pub struct Window {
     eagl_context: id,
-    delegate_state: *mut DelegateState
+    delegate_state: *mut DelegateState,
+    // opened once, so that `get_proc_address` doesn't call `dlopen`
+    gles_library: *mut libc::c_void,
 }
 
 #[derive(Clone)]
@@ -194,9 +199,14 @@ impl Window {
 
                 let context = Window::create_context();
 
+                let path = CString::new("/System/Library/Frameworks/OpenGLES.framework/OpenGLES")
+                                   .unwrap();
+                let gles_library = dlopen(path.as_ptr(), RTLD_LAZY | RTLD_GLOBAL);
+
                 let mut window = Window {
                     eagl_context: context,
-                    delegate_state: state
+                    delegate_state: state,
+                    gles_library: gles_library,
                 };
 
                 window.init_context(builder);
@@ -360,6 +370,28 @@ impl Window {
         unsafe { (&*self.delegate_state) }.scale
     }
 
+    /// Returns whether the screen adapts its refresh rate, which is the case of ProMotion
+    /// displays. `None` before iOS 10.3, which doesn't report the maximum frame rate.
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        unsafe {
+            let screen: id = msg_send![Class::get("UIScreen").unwrap(), mainScreen];
+            let responds: BOOL = msg_send![screen, respondsToSelector:sel!(maximumFramesPerSecond)];
+            if responds == NO {
+                return None;
+            }
+
+            let maximum: libc::c_long = msg_send![screen, maximumFramesPerSecond];
+            Some(maximum > 60)
+        }
+    }
+
+    /// The buffers can only be swapped by the thread where the context is current on this
+    /// platform.
+    #[inline]
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        None
+    }
+
     #[inline]
     pub fn set_cursor_position(&self, _x: i32, _y: i32) -> Result<(), ()> {
         unimplemented!();
@@ -370,6 +402,58 @@ impl Window {
         WindowProxy
     }
 
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
+}
+
+/// EAGL has no pbuffers, use framebuffer objects instead.
+pub enum PBuffer {}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
 }
 
 impl GlContext for Window {
@@ -389,12 +473,7 @@ impl GlContext for Window {
     }
 
     fn get_proc_address(&self, addr: &str) -> *const () {
-        let addr_c = CString::new(addr).unwrap();
-        let path = CString::new("/System/Library/Frameworks/OpenGLES.framework/OpenGLES").unwrap();
-        unsafe {
-            let lib = dlopen(path.as_ptr(), RTLD_LAZY | RTLD_GLOBAL);
-            dlsym(lib, addr_c.as_ptr()) as *const _
-        }
+        with_c_str(addr, |addr| unsafe { dlsym(self.gles_library, addr) as *const _ })
     }
 
     #[inline]
@@ -1,12 +1,66 @@
 pub mod android;
+#[cfg(feature = "osmesa")]
 pub mod caca;
 pub mod cocoa;
 pub mod dlopen;
+#[cfg(any(feature = "egl", target_os = "android"))]
 pub mod egl;
 pub mod emscripten;
+#[cfg(any(feature = "egl", feature = "glx", feature = "wgl", target_os = "android"))]
+pub mod extensions;
+#[cfg(feature = "haiku")]
+pub mod haiku;
+#[cfg(feature = "glx")]
 pub mod glx;
+#[cfg(feature = "osmesa")]
 pub mod osmesa;
+#[cfg(feature = "wgl")]
 pub mod wgl;
 pub mod win32;
+#[cfg(feature = "x11")]
 pub mod x11;
 pub mod ios;
+
+use ContextError;
+
+/// The platform objects behind a `Surface`, as passed down to the backends.
+pub enum Drawable<'a, W: 'a, P: 'a, X: 'a = NoPixmap> {
+    /// The surface of the context itself.
+    Default,
+    /// The surface of another window.
+    Window(&'a W),
+    /// A pbuffer.
+    PBuffer(&'a P),
+    /// A surface that renders into a native pixmap.
+    Pixmap(&'a X),
+}
+
+/// The pixmap surface of the platforms and backends that don't have any.
+pub enum NoPixmap {}
+
+impl<'a, W, P, X> Drawable<'a, W, P, X> {
+    /// Converts the window, the pbuffer or the pixmap to the type of the layer below, which
+    /// fails if they don't use the same backend as the context.
+    pub fn map<W2, P2, X2, FW, FP, FX>(self, window: FW, pbuffer: FP, pixmap: FX)
+                                       -> Result<Drawable<'a, W2, P2, X2>, ContextError>
+        where FW: FnOnce(&'a W) -> Result<&'a W2, ContextError>,
+              FP: FnOnce(&'a P) -> Result<&'a P2, ContextError>,
+              FX: FnOnce(&'a X) -> Result<&'a X2, ContextError>
+    {
+        Ok(match self {
+            Drawable::Default => Drawable::Default,
+            Drawable::Window(w) => Drawable::Window(try!(window(w))),
+            Drawable::PBuffer(p) => Drawable::PBuffer(try!(pbuffer(p))),
+            Drawable::Pixmap(x) => Drawable::Pixmap(try!(pixmap(x))),
+        })
+    }
+
+    /// Returns true for `Drawable::Default`.
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        match *self {
+            Drawable::Default => true,
+            _ => false,
+        }
+    }
+}
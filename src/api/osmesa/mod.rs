@@ -1,4 +1,5 @@
-#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+           target_os = "openbsd", target_os = "netbsd"))]
 
 extern crate osmesa_sys;
 
@@ -12,9 +13,9 @@ use GlRequest;
 use PixelFormat;
 use PixelFormatRequirements;
 use Robustness;
+use c_str::with_c_str;
 use libc;
 use std::{mem, ptr};
-use std::ffi::CString;
 
 pub struct OsMesaContext {
     context: osmesa_sys::OSMesaContext,
@@ -87,6 +88,14 @@ impl OsMesaContext {
                 attribs.push(osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION);
                 attribs.push(minor as libc::c_int);
             },
+            // OSMesa creates the most recent version compatible with the one requested
+            GlRequest::Range { api: Api::OpenGl, min: (major, minor), .. } => {
+                attribs.push(osmesa_sys::OSMESA_CONTEXT_MAJOR_VERSION);
+                attribs.push(major as libc::c_int);
+                attribs.push(osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION);
+                attribs.push(minor as libc::c_int);
+            },
+            GlRequest::Range { .. } => return Err(OsMesaCreationError::NotSupported),
         }
 
         // attribs array must be NULL terminated.
@@ -147,8 +156,9 @@ impl GlContext for OsMesaContext {
 
     fn get_proc_address(&self, addr: &str) -> *const () {
         unsafe {
-            let c_str = CString::new(addr.as_bytes().to_vec()).unwrap();
-            mem::transmute(osmesa_sys::OSMesaGetProcAddress(mem::transmute(c_str.as_ptr())))
+            with_c_str(addr, |addr| {
+                mem::transmute(osmesa_sys::OSMesaGetProcAddress(mem::transmute(addr)))
+            })
         }
     }
 
@@ -175,5 +185,7 @@ impl Drop for OsMesaContext {
     }
 }
 
+// OSMesa refuses to make a context current in two threads at once, which is the only way to
+// reach the buffer from another thread
 unsafe impl Send for OsMesaContext {}
 unsafe impl Sync for OsMesaContext {}
@@ -1,8 +1,11 @@
 #![cfg(any(target_os = "windows"))]
 
+use ConfigLimits;
 use ContextError;
 use CreationError;
+use FailedVersion;
 use GlAttributes;
+use NativeError;
 use GlContext;
 use GlRequest;
 use GlProfile;
@@ -11,14 +14,25 @@ use PixelFormatRequirements;
 use ReleaseBehavior;
 use Robustness;
 use Api;
+use check_swap_interval;
+use is_packed_depth_stencil;
+use api::Drawable;
+use api::extensions::Extensions;
+use c_str::with_c_str;
+use current;
+use leaks;
+use presenter::SwapFn;
+
+use libraries::{self, Library};
 
 use self::make_current_guard::CurrentContextGuard;
 
-use std::ffi::{CStr, CString, OsStr};
+use std::ffi::{CStr, OsStr};
 use std::os::raw::{c_void, c_int};
 use std::os::windows::ffi::OsStrExt;
 use std::{mem, ptr};
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use winapi;
 use kernel32;
@@ -28,6 +42,13 @@ use gdi32;
 mod make_current_guard;
 mod gl;
 
+/// From `WGL_ARB_create_context_no_error`, which is missing from the registry of the generator.
+const CONTEXT_OPENGL_NO_ERROR_ARB: c_int = 0x31B3;
+
+/// From `WGL_NV_multisample_coverage`, which the bindings aren't generated for.
+const COVERAGE_SAMPLES_NV: c_int = 0x2042;
+const COLOR_SAMPLES_NV: c_int = 0x20B9;
+
 /// A WGL context.
 ///
 /// Note: should be destroyed before its window.
@@ -36,6 +57,15 @@ pub struct Context {
 
     hdc: winapi::HDC,
 
+    /// The WGL functions that are not guaranteed to be supported.
+    extra_functions: gl::wgl_extra::Wgl,
+
+    /// The WGL extensions supported by the driver, parsed once when the context is created.
+    extensions: Extensions,
+
+    /// The identifier of the pixel format set on `hdc`.
+    pixel_format_id: c_int,
+
     /// Binded to `opengl32.dll`.
     ///
     /// `wglGetProcAddress` returns null for GL 1.1 functions because they are
@@ -44,6 +74,21 @@ pub struct Context {
 
     /// The pixel format that has been used to create this context.
     pixel_format: PixelFormat,
+
+    /// The limits of the pixel format.
+    config_limits: ConfigLimits,
+
+    /// True if the pixel format copies the back buffer when swapping instead of exchanging it.
+    back_buffer_preserved: bool,
+
+    /// The version of OpenGL reported by the context.
+    gl_version: (u8, u8),
+
+    /// The versions of a `GlRequest::Range` that couldn't be created.
+    failed_versions: Vec<FailedVersion>,
+
+    /// Reports the context if it's never destroyed, with the `leak-detection` feature.
+    tracker: leaks::Tracker,
 }
 
 /// A simple wrapper that destroys the window when it is destroyed.
@@ -58,12 +103,18 @@ impl Drop for WindowWrapper {
     }
 }
 
-/// Wraps around a context so that it is destroyed when necessary.
-struct ContextWrapper(winapi::HGLRC);
+/// Wraps around a context so that it is destroyed when necessary. The flag is set once the
+/// context has been deleted by `destroy_context`.
+struct ContextWrapper(winapi::HGLRC, AtomicBool);
 
 impl Drop for ContextWrapper {
     #[inline]
     fn drop(&mut self) {
+        if self.1.load(Ordering::Acquire) {
+            return;
+        }
+
+        current::forget_object(self.0 as usize);
         unsafe {
             gl::wgl::DeleteContext(self.0 as *const _);
         }
@@ -75,11 +126,18 @@ impl Context {
     ///
     /// The window must **not** have had `SetPixelFormat` called on it.
     ///
+    /// If `swap_exchange` is true, a pixel format whose buffers are exchanged instead of copied
+    /// when swapping is preferred. This is what allows exclusive fullscreen presentation.
+    ///
+    /// If `composition` is true, the pixel format must be compatible with the composition of
+    /// the desktop window manager, which transparent and layered windows need.
+    ///
     /// # Unsafety
     ///
     /// The `window` must continue to exist as long as the resulting `Context` exists.
     pub unsafe fn new(pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<winapi::HGLRC>,
-                      window: winapi::HWND) -> Result<Context, CreationError>
+                      window: winapi::HWND, swap_exchange: bool, composition: bool)
+                      -> Result<Context, CreationError>
     {
         let hdc = user32::GetDC(window);
         if hdc.is_null() {
@@ -94,43 +152,92 @@ impl Context {
         // getting the list of the supported extensions
         let extensions = if extra_functions.GetExtensionsStringARB.is_loaded() {
             let data = extra_functions.GetExtensionsStringARB(hdc as *const _);
-            let data = CStr::from_ptr(data).to_bytes().to_vec();
-            String::from_utf8(data).unwrap()
+            Extensions::parse(&CStr::from_ptr(data).to_string_lossy())
 
         } else if extra_functions.GetExtensionsStringEXT.is_loaded() {
             let data = extra_functions.GetExtensionsStringEXT();
-            let data = CStr::from_ptr(data).to_bytes().to_vec();
-            String::from_utf8(data).unwrap()
+            Extensions::parse(&CStr::from_ptr(data).to_string_lossy())
 
         } else {
-            format!("")
+            Extensions::default()
         };
 
         // calling SetPixelFormat
-        let pixel_format = {
-            let (id, f) = if extensions.split(' ').find(|&i| i == "WGL_ARB_pixel_format")
-                                                  .is_some()
+        let (pixel_format_id, pixel_format) = {
+            let (id, f) = if extensions.contains("WGL_ARB_pixel_format")
             {
-                try!(choose_arb_pixel_format(&extra_functions, &extensions, hdc, pf_reqs)
-                                            .map_err(|_| CreationError::NoAvailablePixelFormat))
+                // the swap method is only a preference, exchanging the buffers wins over copying
+                // them to preserve the back buffer
+                let swap_method = if swap_exchange {
+                    Some(gl::wgl_extra::SWAP_EXCHANGE_ARB)
+                } else if pf_reqs.preserve_back_buffer {
+                    Some(gl::wgl_extra::SWAP_COPY_ARB)
+                } else {
+                    None
+                };
+                let format = match swap_method {
+                    Some(method) => choose_arb_pixel_format(&extra_functions, &extensions, hdc,
+                                                            pf_reqs, Some(method)),
+                    None => Err(()),
+                };
+                try!(format.or_else(|_| {
+                    choose_arb_pixel_format(&extra_functions, &extensions, hdc, pf_reqs, None)
+                }).map_err(|_| CreationError::NoAvailablePixelFormat))
             } else {
-                try!(choose_native_pixel_format(hdc, pf_reqs)
+                try!(choose_native_pixel_format(hdc, pf_reqs, swap_exchange, composition)
                                             .map_err(|_| CreationError::NoAvailablePixelFormat))
             };
 
+            try!(pf_reqs.check_strict(&f));
             try!(set_pixel_format(hdc, id));
-            f
+            (id, f)
         };
 
+        let config_limits = get_config_limits(&extra_functions, &extensions, hdc, pixel_format_id);
+
+        let back_buffer_preserved = is_swap_copy(&extra_functions, &extensions, hdc,
+                                                 pixel_format_id);
+        if pf_reqs.preserve_back_buffer && pf_reqs.strict && !back_buffer_preserved {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+
         // creating the OpenGL context
-        let context = try!(create_context(Some((&extra_functions, pf_reqs, opengl, &extensions)),
-                                          window, hdc));
+        let (context, failed_versions) = match opengl.version {
+            GlRequest::Range { api, .. } => {
+                try!(opengl.version.create_in_range(|version| {
+                    let opengl = GlAttributes {
+                        version: GlRequest::Specific(api, version),
+                        .. opengl.clone()
+                    };
+                    create_context(Some((&extra_functions, pf_reqs, &opengl, &extensions)),
+                                   window, hdc)
+                }))
+            },
+            _ => {
+                let context = try!(create_context(Some((&extra_functions, pf_reqs, opengl,
+                                                        &extensions)), window, hdc));
+                (context, Vec::new())
+            },
+        };
 
         // loading the opengl32 module
         let gl_library = try!(load_opengl32_dll());
 
+        let gl_version = try!(query_gl_version(gl_library, hdc, &context));
+
+        // without `WGL_ARB_create_context` the system silently returns whatever version the
+        // driver provides, which may be OpenGL 1.1
+        match opengl.version {
+            GlRequest::Specific(Api::OpenGl, version) |
+            GlRequest::GlThenGles { opengl_version: version, .. } |
+            GlRequest::Range { api: Api::OpenGl, min: version, .. } if gl_version < version => {
+                return Err(CreationError::OpenGlVersionNotSupported);
+            },
+            _ => ()
+        }
+
         // handling vsync
-        if extensions.split(' ').find(|&i| i == "WGL_EXT_swap_control").is_some() {
+        if extensions.contains("WGL_EXT_swap_control") {
             let _guard = try!(CurrentContextGuard::make_current(hdc, context.0));
 
             if extra_functions.SwapIntervalEXT(if opengl.vsync { 1 } else { 0 }) == 0 {
@@ -138,11 +245,20 @@ impl Context {
             }
         }
 
+        let tracker = leaks::Tracker::new("WGL context", context.0 as usize, 0);
         Ok(Context {
             context: context,
             hdc: hdc,
+            extra_functions: extra_functions,
+            extensions: extensions,
+            pixel_format_id: pixel_format_id,
             gl_library: gl_library,
             pixel_format: pixel_format,
+            config_limits: config_limits,
+            back_buffer_preserved: back_buffer_preserved,
+            gl_version: gl_version,
+            failed_versions: failed_versions,
+            tracker: tracker,
         })
     }
 
@@ -151,15 +267,175 @@ impl Context {
     pub fn get_hglrc(&self) -> winapi::HGLRC {
         self.context.0
     }
+
+    /// Returns a function that swaps the buffers of the window from any thread, which
+    /// `SwapBuffers` allows because it only needs the device context. The window must outlive
+    /// the function.
+    pub fn get_swapper(&self) -> SwapFn {
+        let hdc = DeviceContext(self.hdc);
+        Box::new(move || {
+            unsafe { gdi32::SwapBuffers(hdc.0) };
+            Ok(())
+        })
+    }
+
+    /// Returns the version of OpenGL reported by `glGetString(GL_VERSION)`.
+    ///
+    /// Drivers that don't provide an ICD, which is common on ARM64 Windows, only give access to
+    /// the OpenGL 1.1 software implementation of the system.
+    #[inline]
+    pub fn get_gl_version(&self) -> (u8, u8) {
+        self.gl_version
+    }
+
+    /// Creates a pbuffer with the pixel format of this context.
+    ///
+    /// Returns `CreationError::NotSupported` if `WGL_ARB_pbuffer` isn't available or if the
+    /// pixel format can't be used for pbuffers.
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        let has_extension = |e| self.extensions.contains(e);
+        if !has_extension("WGL_ARB_pbuffer") || !has_extension("WGL_ARB_pixel_format") {
+            return Err(CreationError::NotSupported);
+        }
+
+        unsafe {
+            let mut draw_to_pbuffer = 0;
+            self.extra_functions.GetPixelFormatAttribivARB(self.hdc as *const _,
+                                                           self.pixel_format_id, 0, 1,
+                                                           [gl::wgl_extra::DRAW_TO_PBUFFER_ARB
+                                                            as c_int].as_ptr(),
+                                                           &mut draw_to_pbuffer);
+            if draw_to_pbuffer == 0 {
+                return Err(CreationError::NotSupported);
+            }
+
+            // with `WGL_ARB_render_texture`, the pbuffer is created so that it can be bound as
+            // a texture if the pixel format allows it
+            let texture_format = if has_extension("WGL_ARB_render_texture") {
+                let get_info = |attrib: u32| {
+                    let mut value = 0;
+                    self.extra_functions.GetPixelFormatAttribivARB(self.hdc as *const _,
+                                                                   self.pixel_format_id, 0, 1,
+                                                                   [attrib as c_int].as_ptr(),
+                                                                   &mut value);
+                    value
+                };
+
+                if get_info(gl::wgl_extra::BIND_TO_TEXTURE_RGBA_ARB) != 0 {
+                    Some(gl::wgl_extra::TEXTURE_RGBA_ARB)
+                } else if get_info(gl::wgl_extra::BIND_TO_TEXTURE_RGB_ARB) != 0 {
+                    Some(gl::wgl_extra::TEXTURE_RGB_ARB)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            let mut attributes = Vec::with_capacity(5);
+            if let Some(texture_format) = texture_format {
+                attributes.push(gl::wgl_extra::TEXTURE_FORMAT_ARB as c_int);
+                attributes.push(texture_format as c_int);
+                attributes.push(gl::wgl_extra::TEXTURE_TARGET_ARB as c_int);
+                attributes.push(gl::wgl_extra::TEXTURE_2D_ARB as c_int);
+            }
+            attributes.push(0);
+
+            let pbuffer = self.extra_functions.CreatePbufferARB(self.hdc as *const _,
+                                                                self.pixel_format_id,
+                                                                dimensions.0 as c_int,
+                                                                dimensions.1 as c_int,
+                                                                attributes.as_ptr());
+            if pbuffer.is_null() {
+                return Err(CreationError::OsError(format!("wglCreatePbufferARB failed: {}",
+                                                  format!("{}", io::Error::last_os_error()))));
+            }
+
+            let hdc = self.extra_functions.GetPbufferDCARB(pbuffer);
+            if hdc.is_null() {
+                self.extra_functions.DestroyPbufferARB(pbuffer);
+                return Err(CreationError::OsError(format!("wglGetPbufferDCARB failed: {}",
+                                                  format!("{}", io::Error::last_os_error()))));
+            }
+
+            Ok(PBuffer {
+                extra_functions: self.extra_functions.clone(),
+                pbuffer: pbuffer,
+                hdc: hdc as winapi::HDC,
+                context: self.context.0,
+                gl_library: self.gl_library,
+                pixel_format: self.pixel_format.clone(),
+                render_texture: texture_format.is_some(),
+                tracker: leaks::Tracker::new("WGL pbuffer", pbuffer as usize, 0),
+            })
+        }
+    }
+
+    /// Makes the context current, drawing into `draw` and reading from `read`.
+    ///
+    /// The surfaces of other contexts must have a pixel format that is compatible with the one
+    /// of this context. Reading from another surface than the one drawn into requires
+    /// `WGL_ARB_make_current_read`.
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Context, PBuffer>,
+                                             read: Drawable<Context, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        let draw = self.hdc_for(draw);
+        let read = self.hdc_for(read);
+        if current::is_current(self.context.0 as usize, draw as usize, read as usize) {
+            return Ok(());
+        }
+
+        let ret = if draw == read {
+            gl::wgl::MakeCurrent(draw as *const _, self.context.0 as *const _)
+        } else {
+            if !self.extensions.contains("WGL_ARB_make_current_read") {
+                return Err(ContextError::NotSupported(
+                           "reading from another surface requires WGL_ARB_make_current_read"));
+            }
+
+            self.extra_functions.MakeContextCurrentARB(draw as *const _, read as *const _,
+                                                       self.context.0 as *const _)
+        };
+
+        if ret != 0 {
+            current::set(self.context.0 as usize, draw as usize, read as usize);
+            return Ok(());
+        }
+
+        current::forget();
+        if draw == read {
+            Err(ContextError::NativeError(last_error("wglMakeCurrent")))
+        } else {
+            Err(ContextError::NativeError(last_error("wglMakeContextCurrentARB")))
+        }
+    }
+
+    #[inline]
+    fn hdc_for(&self, drawable: Drawable<Context, PBuffer>) -> winapi::HDC {
+        match drawable {
+            Drawable::Default => self.hdc,
+            Drawable::Window(context) => context.hdc,
+            Drawable::PBuffer(pbuffer) => pbuffer.hdc,
+            Drawable::Pixmap(pixmap) => match *pixmap {},
+        }
+    }
 }
 
 impl GlContext for Context {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let hdc = self.hdc as usize;
+        if current::is_current(self.context.0 as usize, hdc, hdc) {
+            return Ok(());
+        }
+
         if gl::wgl::MakeCurrent(self.hdc as *const _, self.context.0 as *const _) != 0 {
+            current::set(self.context.0 as usize, hdc, hdc);
             Ok(())
         } else {
-            Err(ContextError::IoError(io::Error::last_os_error()))
+            current::forget();
+            Err(ContextError::NativeError(last_error("wglMakeCurrent")))
         }
     }
 
@@ -168,15 +444,39 @@ impl GlContext for Context {
         unsafe { gl::wgl::GetCurrentContext() == self.context.0 as *const c_void }
     }
 
-    fn get_proc_address(&self, addr: &str) -> *const () {
-        let addr = CString::new(addr.as_bytes()).unwrap();
-        let addr = addr.as_ptr();
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
 
-        unsafe {
+        current::forget();
+        if gl::wgl::MakeCurrent(ptr::null(), ptr::null()) == 0 {
+            return Err(ContextError::NativeError(last_error("wglMakeCurrent")));
+        }
+        Ok(())
+    }
+
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        if self.context.1.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        try!(self.make_not_current());
+        self.context.1.store(true, Ordering::Release);
+        self.tracker.release();
+        current::forget_object(self.context.0 as usize);
+        if gl::wgl::DeleteContext(self.context.0 as *const _) == 0 {
+            return Err(ContextError::NativeError(last_error("wglDeleteContext")));
+        }
+        Ok(())
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        with_c_str(addr, |addr| unsafe {
             let p = gl::wgl::GetProcAddress(addr) as *const _;
             if !p.is_null() { return p; }
             kernel32::GetProcAddress(self.gl_library, addr) as *const _
-        }
+        })
     }
 
     #[inline]
@@ -201,11 +501,184 @@ impl GlContext for Context {
     fn get_pixel_format(&self) -> PixelFormat {
         self.pixel_format.clone()
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.failed_versions.clone()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.config_limits.clone()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.back_buffer_preserved
+    }
+
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        if !self.extensions.contains("WGL_EXT_swap_control") {
+            return Err(ContextError::NotSupported(
+                       "the swap interval can't be changed without WGL_EXT_swap_control"));
+        }
+        try!(check_swap_interval(&self.config_limits,
+                                 self.extensions.contains("WGL_EXT_swap_control_tear"),
+                                 interval));
+
+        // applies to the context that is current in this thread
+        if unsafe { self.extra_functions.SwapIntervalEXT(interval) } == 0 {
+            return Err(ContextError::NativeError(last_error("wglSwapIntervalEXT")));
+        }
+        Ok(())
+    }
 }
 
+// WGL handles can be used from any thread, and the fields that change are atomic
 unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
+/// The device context of a window, see `Context::get_swapper`.
+struct DeviceContext(winapi::HDC);
+
+unsafe impl Send for DeviceContext {}
+
+/// A pbuffer that is drawn with the context it has been created from.
+///
+/// Note: must be destroyed before its context.
+pub struct PBuffer {
+    extra_functions: gl::wgl_extra::Wgl,
+    pbuffer: gl::wgl_extra::types::HPBUFFERARB,
+    hdc: winapi::HDC,
+    context: winapi::HGLRC,
+    gl_library: winapi::HMODULE,
+    pixel_format: PixelFormat,
+    /// True if the pbuffer has been created with a texture format of `WGL_ARB_render_texture`.
+    render_texture: bool,
+    /// Reports the pbuffer if it's never destroyed, with the `leak-detection` feature.
+    #[allow(dead_code)]
+    tracker: leaks::Tracker,
+}
+
+impl PBuffer {
+    /// Returns true if the pbuffer can be bound as a texture with `bind_to_texture`.
+    #[inline]
+    pub fn can_bind_to_texture(&self) -> bool {
+        self.render_texture
+    }
+
+    /// Binds the color buffer of the pbuffer to the `GL_TEXTURE_2D` texture that is bound in the
+    /// current context, with `wglBindTexImageARB`.
+    ///
+    /// The back buffer is used if the pixel format is double-buffered.
+    pub unsafe fn bind_to_texture(&self) -> Result<(), ContextError> {
+        if !self.render_texture {
+            return Err(ContextError::NotSupported(
+                       "the pbuffer has been created without WGL_ARB_render_texture"));
+        }
+
+        let buffer = if self.pixel_format.double_buffer {
+            gl::wgl_extra::BACK_LEFT_ARB
+        } else {
+            gl::wgl_extra::FRONT_LEFT_ARB
+        };
+
+        if self.extra_functions.BindTexImageARB(self.pbuffer, buffer as c_int) != 0 {
+            Ok(())
+        } else {
+            Err(ContextError::IoError(io::Error::last_os_error()))
+        }
+    }
+
+    /// Releases the color buffer bound with `bind_to_texture`, which must be done before
+    /// drawing into the pbuffer again.
+    pub unsafe fn release_from_texture(&self) -> Result<(), ContextError> {
+        if !self.render_texture {
+            return Ok(());
+        }
+
+        let buffer = if self.pixel_format.double_buffer {
+            gl::wgl_extra::BACK_LEFT_ARB
+        } else {
+            gl::wgl_extra::FRONT_LEFT_ARB
+        };
+
+        if self.extra_functions.ReleaseTexImageARB(self.pbuffer, buffer as c_int) != 0 {
+            Ok(())
+        } else {
+            Err(ContextError::IoError(io::Error::last_os_error()))
+        }
+    }
+}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let hdc = self.hdc as usize;
+        if current::is_current(self.context as usize, hdc, hdc) {
+            return Ok(());
+        }
+
+        if gl::wgl::MakeCurrent(self.hdc as *const _, self.context as *const _) != 0 {
+            current::set(self.context as usize, hdc, hdc);
+            Ok(())
+        } else {
+            current::forget();
+            Err(ContextError::NativeError(last_error("wglMakeCurrent")))
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe {
+            gl::wgl::GetCurrentContext() == self.context as *const c_void &&
+            gl::wgl::GetCurrentDC() == self.hdc as *const c_void
+        }
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        with_c_str(addr, |addr| unsafe {
+            let p = gl::wgl::GetProcAddress(addr) as *const _;
+            if !p.is_null() { return p; }
+            kernel32::GetProcAddress(self.gl_library, addr) as *const _
+        })
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        unsafe { gdi32::SwapBuffers(self.hdc) };
+        Ok(())
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        Api::OpenGl
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+}
+
+unsafe impl Send for PBuffer {}
+unsafe impl Sync for PBuffer {}
+
+impl Drop for PBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            if self.is_current() {
+                current::forget();
+                gl::wgl::MakeCurrent(ptr::null(), ptr::null());
+            }
+
+            current::forget_object(self.hdc as usize);
+            self.extra_functions.ReleasePbufferDCARB(self.pbuffer, self.hdc as *const _);
+            self.extra_functions.DestroyPbufferARB(self.pbuffer);
+        }
+    }
+}
+
 /// Creates an OpenGL context.
 ///
 /// If `extra` is `Some`, this function will attempt to use the latest WGL functions to create the
@@ -214,7 +687,7 @@ unsafe impl Sync for Context {}
 /// Otherwise, only the basic API will be used and the chances of `CreationError::NotSupported`
 /// being returned increase.
 unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequirements,
-                                        &GlAttributes<winapi::HGLRC>, &str)>,
+                                        &GlAttributes<winapi::HGLRC>, &Extensions)>,
                          _: winapi::HWND, hdc: winapi::HDC)
                          -> Result<ContextWrapper, CreationError>
 {
@@ -223,7 +696,7 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
     if let Some((extra_functions, pf_reqs, opengl, extensions)) = extra {
         share = opengl.sharing.unwrap_or(ptr::null_mut());
 
-        if extensions.split(' ').find(|&i| i == "WGL_ARB_create_context").is_some() {
+        if extensions.contains("WGL_ARB_create_context") {
             let mut attributes = Vec::new();
 
             match opengl.version {
@@ -235,8 +708,7 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
                     attributes.push(minor as c_int);
                 },
                 GlRequest::Specific(Api::OpenGlEs, (major, minor)) => {
-                    if extensions.split(' ').find(|&i| i == "WGL_EXT_create_context_es2_profile")
-                                            .is_some()
+                    if extensions.contains("WGL_EXT_create_context_es2_profile")
                     {
                         attributes.push(gl::wgl_extra::CONTEXT_PROFILE_MASK_ARB as c_int);
                         attributes.push(gl::wgl_extra::CONTEXT_ES2_PROFILE_BIT_EXT as c_int);
@@ -256,10 +728,12 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
                     attributes.push(gl::wgl_extra::CONTEXT_MINOR_VERSION_ARB as c_int);
                     attributes.push(minor as c_int);
                 },
+                // `Context::new` turns ranges into `Specific` requests
+                GlRequest::Range { .. } => return Err(CreationError::OpenGlVersionNotSupported),
             }
 
             if let Some(profile) = opengl.profile {
-                if extensions.split(' ').find(|&i| i == "WGL_ARB_create_context_profile").is_some()
+                if extensions.contains("WGL_ARB_create_context_profile")
                 {
                     let flag = match profile {
                         GlProfile::Compatibility =>
@@ -278,7 +752,7 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
                 let mut flags = 0;
 
                 // robustness
-                if extensions.split(' ').find(|&i| i == "WGL_ARB_create_context_robustness").is_some() {
+                if extensions.contains("WGL_ARB_create_context_robustness") {
                     match opengl.robustness {
                         Robustness::RobustNoResetNotification | Robustness::TryRobustNoResetNotification => {
                             attributes.push(gl::wgl_extra::CONTEXT_RESET_NOTIFICATION_STRATEGY_ARB as c_int);
@@ -291,7 +765,12 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
                             flags = flags | gl::wgl_extra::CONTEXT_ROBUST_ACCESS_BIT_ARB as c_int;
                         },
                         Robustness::NotRobust => (),
-                        Robustness::NoError => (),
+                        Robustness::NoError => {
+                            if extensions.contains("WGL_ARB_create_context_no_error") {
+                                attributes.push(CONTEXT_OPENGL_NO_ERROR_ARB);
+                                attributes.push(1);
+                            }
+                        },
                     }
                 } else {
                     match opengl.robustness {
@@ -314,6 +793,8 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
 
             attributes.push(0);
 
+            debug_log!("wglCreateContextAttribsARB attributes: {:?}", attributes);
+
             let ctxt = extra_functions.CreateContextAttribsARB(hdc as *const c_void,
                                                                share as *const c_void,
                                                                attributes.as_ptr());
@@ -322,10 +803,18 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
                 return Err(CreationError::OsError(format!("wglCreateContextAttribsARB failed: {}",
                                                       format!("{}", io::Error::last_os_error()))));
             } else {
-                return Ok(ContextWrapper(ctxt as winapi::HGLRC));
+                return Ok(ContextWrapper(ctxt as winapi::HGLRC, AtomicBool::new(false)));
             }
         }
 
+        // `wglCreateContext` can't create a robust context
+        match opengl.robustness {
+            Robustness::RobustNoResetNotification | Robustness::RobustLoseContextOnReset => {
+                return Err(CreationError::RobustnessNotSupported);
+            },
+            _ => ()
+        }
+
     } else {
         share = ptr::null_mut();
     }
@@ -343,13 +832,14 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
         }
     };
 
-    Ok(ContextWrapper(ctxt as winapi::HGLRC))
+    Ok(ContextWrapper(ctxt as winapi::HGLRC, AtomicBool::new(false)))
 }
 
 /// Chooses a pixel formats without using WGL.
 ///
 /// Gives less precise results than `enumerate_arb_pixel_formats`.
-unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequirements)
+unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequirements,
+                                     swap_exchange: bool, composition: bool)
                                      -> Result<(c_int, PixelFormat), ()>
 {
     // TODO: hardware acceleration is not handled
@@ -359,7 +849,7 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
         return Err(());
     }
 
-    match reqs.multisampling {
+    match reqs.color_samples() {
         Some(0) => (),
         None => (),
         Some(_) => return Err(())
@@ -394,25 +884,40 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
                 0
             };
 
-            winapi::PFD_DRAW_TO_WINDOW | winapi::PFD_SUPPORT_OPENGL | f1 | f2
+            // ChoosePixelFormat treats this flag as a hint
+            let f3 = if swap_exchange {
+                0x00000200      // FIXME: PFD_SWAP_EXCHANGE
+            } else if reqs.preserve_back_buffer {
+                winapi::PFD_SWAP_COPY
+            } else {
+                0
+            };
+
+            let f4 = if composition {
+                0x00008000      // FIXME: PFD_SUPPORT_COMPOSITION
+            } else {
+                0
+            };
+
+            winapi::PFD_DRAW_TO_WINDOW | winapi::PFD_SUPPORT_OPENGL | f1 | f2 | f3 | f4
         },
         iPixelType: winapi::PFD_TYPE_RGBA,
-        cColorBits: reqs.color_bits.unwrap_or(0),
+        cColorBits: reqs.color_bits.min().unwrap_or(0),
         cRedBits: 0,
         cRedShift: 0,
         cGreenBits: 0,
         cGreenShift: 0,
         cBlueBits: 0,
         cBlueShift: 0,
-        cAlphaBits: reqs.alpha_bits.unwrap_or(0),
+        cAlphaBits: reqs.alpha_bits.min().unwrap_or(0),
         cAlphaShift: 0,
         cAccumBits: 0,
         cAccumRedBits: 0,
         cAccumGreenBits: 0,
         cAccumBlueBits: 0,
         cAccumAlphaBits: 0,
-        cDepthBits: reqs.depth_bits.unwrap_or(0),
-        cStencilBits: reqs.stencil_bits.unwrap_or(0),
+        cDepthBits: reqs.depth_bits.min().unwrap_or(0),
+        cStencilBits: reqs.stencil_bits.min().unwrap_or(0),
         cAuxBuffers: 0,
         iLayerType: winapi::PFD_MAIN_PLANE,
         bReserved: 0,
@@ -424,6 +929,7 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
     // now querying
     let pf_id = gdi32::ChoosePixelFormat(hdc, &descriptor);
     if pf_id == 0 {
+        debug_log!("ChoosePixelFormat didn't return any pixel format");
         return Err(());
     }
 
@@ -443,6 +949,9 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
     if (output.dwFlags & winapi::PFD_SUPPORT_OPENGL) == 0 {
         return Err(());
     }
+    if composition && (output.dwFlags & 0x00008000 /* FIXME: PFD_SUPPORT_COMPOSITION */) == 0 {
+        return Err(());
+    }
     if output.iPixelType != winapi::PFD_TYPE_RGBA {
         return Err(());
     }
@@ -457,18 +966,14 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
         double_buffer: (output.dwFlags & winapi::PFD_DOUBLEBUFFER) != 0,
         multisampling: None,
         srgb: false,
+        implicit_resolve: false,
+        packed_depth_stencil: is_packed_depth_stencil(output.cDepthBits, output.cStencilBits),
+        coverage_samples: None,
     };
 
-    if pf_desc.alpha_bits < reqs.alpha_bits.unwrap_or(0) {
-        return Err(());
-    }
-    if pf_desc.depth_bits < reqs.depth_bits.unwrap_or(0) {
-        return Err(());
-    }
-    if pf_desc.stencil_bits < reqs.stencil_bits.unwrap_or(0) {
-        return Err(());
-    }
-    if pf_desc.color_bits < reqs.color_bits.unwrap_or(0) {
+    debug_log!("ChoosePixelFormat returned pixel format {}: {:?}", pf_id, pf_desc);
+
+    if !reqs.bits_satisfied_by(&pf_desc) {
         return Err(());
     }
     if let Some(req) = reqs.hardware_accelerated {
@@ -476,6 +981,9 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
             return Err(());
         }
     }
+    if !reqs.allow_caveat && !pf_desc.hardware_accelerated {
+        return Err(());
+    }
     if let Some(req) = reqs.double_buffer {
         if pf_desc.double_buffer != req {
             return Err(());
@@ -488,8 +996,9 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
 /// Enumerates the list of pixel formats by using extra WGL functions.
 ///
 /// Gives more precise results than `enumerate_native_pixel_formats`.
-unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
-                                  hdc: winapi::HDC, reqs: &PixelFormatRequirements)
+unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &Extensions,
+                                  hdc: winapi::HDC, reqs: &PixelFormatRequirements,
+                                  swap_method: Option<u32>)
                                   -> Result<(c_int, PixelFormat), ()>
 {
     let descriptor = {
@@ -503,7 +1012,7 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
 
         out.push(gl::wgl_extra::PIXEL_TYPE_ARB as c_int);
         if reqs.float_color_buffer {
-            if extensions.split(' ').find(|&i| i == "WGL_ARB_pixel_format_float").is_some() {
+            if extensions.contains("WGL_ARB_pixel_format_float") {
                 out.push(gl::wgl_extra::TYPE_RGBA_FLOAT_ARB as c_int);
             } else {
                 return Err(());
@@ -512,31 +1021,35 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
             out.push(gl::wgl_extra::TYPE_RGBA_ARB as c_int);
         }
 
-        if let Some(hardware_accelerated) = reqs.hardware_accelerated {
-            out.push(gl::wgl_extra::ACCELERATION_ARB as c_int);
-            out.push(if hardware_accelerated {
-                gl::wgl_extra::FULL_ACCELERATION_ARB as c_int
-            } else {
-                gl::wgl_extra::NO_ACCELERATION_ARB as c_int
-            });
+        match (reqs.hardware_accelerated, reqs.allow_caveat) {
+            (Some(true), _) | (None, false) => {
+                out.push(gl::wgl_extra::ACCELERATION_ARB as c_int);
+                out.push(gl::wgl_extra::FULL_ACCELERATION_ARB as c_int);
+            },
+            (Some(false), true) => {
+                out.push(gl::wgl_extra::ACCELERATION_ARB as c_int);
+                out.push(gl::wgl_extra::NO_ACCELERATION_ARB as c_int);
+            },
+            (Some(false), false) => return Err(()),
+            (None, true) => (),
         }
 
-        if let Some(color) = reqs.color_bits {
+        if let Some(color) = reqs.color_bits.min() {
             out.push(gl::wgl_extra::COLOR_BITS_ARB as c_int);
             out.push(color as c_int);
         }
 
-        if let Some(alpha) = reqs.alpha_bits {
+        if let Some(alpha) = reqs.alpha_bits.min() {
             out.push(gl::wgl_extra::ALPHA_BITS_ARB as c_int);
             out.push(alpha as c_int);
         }
 
-        if let Some(depth) = reqs.depth_bits {
+        if let Some(depth) = reqs.depth_bits.min() {
             out.push(gl::wgl_extra::DEPTH_BITS_ARB as c_int);
             out.push(depth as c_int);
         }
 
-        if let Some(stencil) = reqs.stencil_bits {
+        if let Some(stencil) = reqs.stencil_bits.min() {
             out.push(gl::wgl_extra::STENCIL_BITS_ARB as c_int);
             out.push(stencil as c_int);
         }
@@ -546,12 +1059,32 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         out.push(gl::wgl_extra::DOUBLE_BUFFER_ARB as c_int);
         out.push(if double_buffer { 1 } else { 0 });
 
-        if let Some(multisampling) = reqs.multisampling {
-            if extensions.split(' ').find(|&i| i == "WGL_ARB_multisample").is_some() {
+        if let Some(swap_method) = swap_method {
+            out.push(gl::wgl_extra::SWAP_METHOD_ARB as c_int);
+            out.push(swap_method as c_int);
+        }
+
+        // with the extension, `WGL_SAMPLES_ARB` is the number of coverage samples, otherwise
+        // the multisampled formats with the color samples are chosen
+        let coverage = match reqs.coverage_samples {
+            Some(samples) if samples.coverage > samples.color &&
+                             extensions.contains("WGL_NV_multisample_coverage") => Some(samples),
+            _ => None,
+        };
+
+        if let Some(multisampling) = reqs.color_samples() {
+            if extensions.contains("WGL_ARB_multisample") {
                 out.push(gl::wgl_extra::SAMPLE_BUFFERS_ARB as c_int);
                 out.push(if multisampling == 0 { 0 } else { 1 });
                 out.push(gl::wgl_extra::SAMPLES_ARB as c_int);
-                out.push(multisampling as c_int);
+                match coverage {
+                    Some(samples) => {
+                        out.push(samples.coverage as c_int);
+                        out.push(COLOR_SAMPLES_NV);
+                        out.push(samples.color as c_int);
+                    },
+                    None => out.push(multisampling as c_int),
+                }
             } else {
                 return Err(());
             }
@@ -561,10 +1094,10 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         out.push(if reqs.stereoscopy { 1 } else { 0 });
 
         if reqs.srgb {
-            if extensions.split(' ').find(|&i| i == "WGL_ARB_framebuffer_sRGB").is_some() {
+            if extensions.contains("WGL_ARB_framebuffer_sRGB") {
                 out.push(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int);
                 out.push(1);
-            } else if extensions.split(' ').find(|&i| i == "WGL_EXT_framebuffer_sRGB").is_some() {
+            } else if extensions.contains("WGL_EXT_framebuffer_sRGB") {
                 out.push(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int);
                 out.push(1);
             } else {
@@ -575,7 +1108,7 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         match reqs.release_behavior {
             ReleaseBehavior::Flush => (),
             ReleaseBehavior::None => {
-                if extensions.split(' ').find(|&i| i == "WGL_ARB_context_flush_control").is_some() {
+                if extensions.contains("WGL_ARB_context_flush_control") {
                     out.push(gl::wgl_extra::CONTEXT_RELEASE_BEHAVIOR_ARB as c_int);
                     out.push(gl::wgl_extra::CONTEXT_RELEASE_BEHAVIOR_NONE_ARB as c_int);
                 }
@@ -586,18 +1119,57 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         out
     };
 
-    let mut format_id = mem::uninitialized();
+    debug_log!("wglChoosePixelFormatARB attributes: {:?}", descriptor);
+
+    // the formats are sorted by the driver, 256 is more than any driver returns in practice
+    let mut format_ids: Vec<c_int> = vec![0; 256];
     let mut num_formats = mem::uninitialized();
-    if extra.ChoosePixelFormatARB(hdc as *const _, descriptor.as_ptr(), ptr::null(), 1,
-                                  &mut format_id, &mut num_formats) == 0
+    if extra.ChoosePixelFormatARB(hdc as *const _, descriptor.as_ptr(), ptr::null(),
+                                  format_ids.len() as winapi::UINT, format_ids.as_mut_ptr(),
+                                  &mut num_formats) == 0
     {
         return Err(());
     }
+    format_ids.truncate(num_formats as usize);
 
-    if num_formats == 0 {
-        return Err(());
+    // WGL only filters the formats by minimum number of bits, so we pick the best one that
+    // also matches the exact requirements, or the first one without a scoring function
+    let mut best: Option<(i32, c_int, PixelFormat)> = None;
+    for format_id in format_ids {
+        let pf_desc = get_arb_pixel_format(extra, extensions, hdc, format_id);
+
+        if !reqs.bits_satisfied_by(&pf_desc) {
+            continue;
+        }
+
+        // a strict request skips the inexact formats, so that a lower ranked exact one wins
+        if reqs.strict && !reqs.is_satisfied_by(&pf_desc) {
+            debug_log!("skipping pixel format {}, not exact: {:?}", format_id, pf_desc);
+            continue;
+        }
+
+        let score = match reqs.score(&pf_desc, 0) {
+            Some(score) => score,
+            None => {
+                debug_log!("pixel format {} rejected by the scoring function", format_id);
+                continue;
+            },
+        };
+        debug_log!("pixel format {} has score {}: {:?}", format_id, score, pf_desc);
+        if best.as_ref().map(|&(s, _, _)| score > s).unwrap_or(true) {
+            best = Some((score, format_id, pf_desc));
+        }
     }
 
+    let (_, format_id, pf_desc) = try!(best.ok_or(()));
+    debug_log!("selected pixel format {}: {:?}", format_id, pf_desc);
+    Ok((format_id, pf_desc))
+}
+
+/// Queries the description of a pixel format with `WGL_ARB_pixel_format`.
+unsafe fn get_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &Extensions,
+                               hdc: winapi::HDC, format_id: c_int) -> PixelFormat
+{
     let get_info = |attrib: u32| {
         let mut value = mem::uninitialized();
         extra.GetPixelFormatAttribivARB(hdc as *const _, format_id as c_int,
@@ -606,37 +1178,104 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         value as u32
     };
 
-    let pf_desc = PixelFormat {
+    let depth_bits = get_info(gl::wgl_extra::DEPTH_BITS_ARB) as u8;
+    let stencil_bits = get_info(gl::wgl_extra::STENCIL_BITS_ARB) as u8;
+
+    // with `WGL_NV_multisample_coverage`, `WGL_SAMPLES_ARB` counts the coverage samples
+    let (color_samples, coverage_samples) =
+        if !extensions.contains("WGL_ARB_multisample") {
+            (0, 0)
+        } else if extensions.contains("WGL_NV_multisample_coverage") {
+            (get_info(COLOR_SAMPLES_NV as u32), get_info(gl::wgl_extra::SAMPLES_ARB))
+        } else {
+            let samples = get_info(gl::wgl_extra::SAMPLES_ARB);
+            (samples, samples)
+        };
+
+    PixelFormat {
         hardware_accelerated: get_info(gl::wgl_extra::ACCELERATION_ARB) !=
                                                                 gl::wgl_extra::NO_ACCELERATION_ARB,
         color_bits: get_info(gl::wgl_extra::RED_BITS_ARB) as u8 + 
                     get_info(gl::wgl_extra::GREEN_BITS_ARB) as u8 +
                     get_info(gl::wgl_extra::BLUE_BITS_ARB) as u8,
         alpha_bits: get_info(gl::wgl_extra::ALPHA_BITS_ARB) as u8,
-        depth_bits: get_info(gl::wgl_extra::DEPTH_BITS_ARB) as u8,
-        stencil_bits: get_info(gl::wgl_extra::STENCIL_BITS_ARB) as u8,
+        depth_bits: depth_bits,
+        stencil_bits: stencil_bits,
         stereoscopy: get_info(gl::wgl_extra::STEREO_ARB) != 0,
         double_buffer: get_info(gl::wgl_extra::DOUBLE_BUFFER_ARB) != 0,
-        multisampling: {
-            if extensions.split(' ').find(|&i| i == "WGL_ARB_multisample").is_some() {
-                match get_info(gl::wgl_extra::SAMPLES_ARB) {
-                    0 => None,
-                    a => Some(a as u16),
-                }
-            } else {
-                None
-            }
+        multisampling: match color_samples {
+            0 => None,
+            a => Some(a as u16),
         },
-        srgb: if extensions.split(' ').find(|&i| i == "WGL_ARB_framebuffer_sRGB").is_some() {
+        srgb: if extensions.contains("WGL_ARB_framebuffer_sRGB") {
             get_info(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0
-        } else if extensions.split(' ').find(|&i| i == "WGL_EXT_framebuffer_sRGB").is_some() {
+        } else if extensions.contains("WGL_EXT_framebuffer_sRGB") {
             get_info(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT) != 0
         } else {
             false
         },
-    };
+        implicit_resolve: false,
+        packed_depth_stencil: is_packed_depth_stencil(depth_bits, stencil_bits),
+        coverage_samples: if coverage_samples > color_samples {
+            Some(coverage_samples as u16)
+        } else {
+            None
+        },
+    }
+}
 
-    Ok((format_id, pf_desc))
+/// Reads the limits of a pixel format. The pbuffer limits need `WGL_ARB_pbuffer`.
+unsafe fn get_config_limits(extra: &gl::wgl_extra::Wgl, extensions: &Extensions,
+                            hdc: winapi::HDC, format_id: c_int) -> ConfigLimits
+{
+    let mut limits = ConfigLimits::default();
+
+    if extensions.contains("WGL_ARB_pbuffer") &&
+       extensions.contains("WGL_ARB_pixel_format")
+    {
+        let get_info = |attrib: u32| {
+            let mut value = 0;
+            extra.GetPixelFormatAttribivARB(hdc as *const _, format_id, 0, 1,
+                                            [attrib as c_int].as_ptr(), &mut value);
+            value as u32
+        };
+
+        limits.max_pbuffer_dimensions = Some((get_info(gl::wgl_extra::MAX_PBUFFER_WIDTH_ARB),
+                                              get_info(gl::wgl_extra::MAX_PBUFFER_HEIGHT_ARB)));
+        limits.max_pbuffer_pixels = Some(get_info(gl::wgl_extra::MAX_PBUFFER_PIXELS_ARB));
+    }
+
+    // GDI is the native rendering API of Windows
+    let mut output: winapi::PIXELFORMATDESCRIPTOR = mem::zeroed();
+    if gdi32::DescribePixelFormat(hdc, format_id, mem::size_of::<winapi::PIXELFORMATDESCRIPTOR>()
+                                  as winapi::UINT, &mut output) != 0
+    {
+        limits.native_renderable = Some(output.dwFlags & winapi::PFD_SUPPORT_GDI != 0);
+    }
+
+    limits
+}
+
+/// Returns true if swapping the buffers of a pixel format copies the back buffer, which then
+/// keeps its content.
+unsafe fn is_swap_copy(extra: &gl::wgl_extra::Wgl, extensions: &Extensions,
+                       hdc: winapi::HDC, format_id: c_int) -> bool
+{
+    if extensions.contains("WGL_ARB_pixel_format") {
+        let mut value = 0;
+        extra.GetPixelFormatAttribivARB(hdc as *const _, format_id, 0, 1,
+                                        [gl::wgl_extra::SWAP_METHOD_ARB as c_int].as_ptr(),
+                                        &mut value);
+        return value as u32 == gl::wgl_extra::SWAP_COPY_ARB;
+    }
+
+    let mut output: winapi::PIXELFORMATDESCRIPTOR = mem::zeroed();
+    if gdi32::DescribePixelFormat(hdc, format_id, mem::size_of::<winapi::PIXELFORMATDESCRIPTOR>()
+                                  as winapi::UINT, &mut output) == 0
+    {
+        return false;
+    }
+    output.dwFlags & winapi::PFD_SWAP_COPY != 0
 }
 
 /// Calls `SetPixelFormat` on a window.
@@ -658,19 +1297,48 @@ unsafe fn set_pixel_format(hdc: winapi::HDC, id: c_int) -> Result<(), CreationEr
     Ok(())
 }
 
-/// Loads the `opengl32.dll` library.
+/// Makes the context current and queries the version of OpenGL it reports.
+unsafe fn query_gl_version(gl_library: winapi::HMODULE, hdc: winapi::HDC,
+                           context: &ContextWrapper) -> Result<(u8, u8), CreationError>
+{
+    const GL_VERSION: u32 = 0x1F02;
+
+    let get_string = kernel32::GetProcAddress(gl_library, b"glGetString\0".as_ptr() as *const _);
+    if get_string.is_null() {
+        return Err(CreationError::OsError(format!("glGetString is missing from opengl32.dll")));
+    }
+    let get_string: extern "system" fn(u32) -> *const u8 = mem::transmute(get_string);
+
+    let _guard = try!(CurrentContextGuard::make_current(hdc, context.0));
+
+    let version = get_string(GL_VERSION);
+    if version.is_null() {
+        return Err(CreationError::OsError(format!("glGetString(GL_VERSION) failed")));
+    }
+
+    // the string starts with `<major>.<minor>`, optionally followed by a release number and
+    // vendor-specific information
+    let version = CStr::from_ptr(version as *const _).to_string_lossy();
+    let mut numbers = version.split(|c: char| !c.is_digit(10))
+                             .filter_map(|n| n.parse::<u8>().ok());
+    Ok((numbers.next().unwrap_or(1), numbers.next().unwrap_or(0)))
+}
+
+/// Loads the `opengl32.dll` library, or the one provided by the user.
 unsafe fn load_opengl32_dll() -> Result<winapi::HMODULE, CreationError> {
-    let name = OsStr::new("opengl32.dll").encode_wide().chain(Some(0).into_iter())
-                                         .collect::<Vec<_>>();
+    for path in libraries::candidates(Library::Gl) {
+        let name = path.as_os_str().encode_wide().chain(Some(0).into_iter())
+                                   .collect::<Vec<_>>();
 
-    let lib = kernel32::LoadLibraryW(name.as_ptr());
+        let lib = kernel32::LoadLibraryW(name.as_ptr());
 
-    if lib.is_null() {
-        return Err(CreationError::OsError(format!("LoadLibrary function failed: {}",
-                                                  format!("{}", io::Error::last_os_error()))));
+        if !lib.is_null() {
+            return Ok(lib);
+        }
     }
 
-    Ok(lib)
+    Err(CreationError::OsError(format!("LoadLibrary function failed: {}",
+                                       format!("{}", io::Error::last_os_error()))))
 }
 
 /// Loads the WGL functions that are not guaranteed to be supported.
@@ -740,9 +1408,7 @@ unsafe fn load_extra_functions(window: winapi::HWND) -> Result<gl::wgl_extra::Wg
 
     // loading the extra WGL functions
     Ok(gl::wgl_extra::Wgl::load_with(|addr| {
-        let addr = CString::new(addr.as_bytes()).unwrap();
-        let addr = addr.as_ptr();
-        gl::wgl::GetProcAddress(addr) as *const c_void
+        with_c_str(addr, |addr| gl::wgl::GetProcAddress(addr) as *const c_void)
     }))
 }
 
@@ -787,3 +1453,18 @@ fn choose_dummy_pixel_format(hdc: winapi::HDC) -> Result<c_int, CreationError> {
 
     Ok(pf_id)
 }
+
+/// Describes the error returned by `GetLastError` after `function` failed.
+fn last_error(function: &'static str) -> NativeError {
+    let code = unsafe { kernel32::GetLastError() };
+    let name = match code {
+        6 => Some("ERROR_INVALID_HANDLE"),
+        170 => Some("ERROR_BUSY"),
+        2000 => Some("ERROR_INVALID_PIXEL_FORMAT"),
+        0xC0072095 => Some("ERROR_INVALID_VERSION_ARB"),
+        0xC0072096 => Some("ERROR_INVALID_PROFILE_ARB"),
+        _ => None,
+    };
+
+    NativeError { function: function, code: code, name: name }
+}
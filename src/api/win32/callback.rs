@@ -73,9 +73,14 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
                     Some(ref v) => v
                 };
 
-                let &ThreadLocalData { ref win, .. } = stored;
+                let &ThreadLocalData { ref win, ref window_state, .. } = stored;
 
                 if win == &window {
+                    // giving the desktop its display mode back
+                    let display_mode = fullscreen_display_mode(&window_state.lock().unwrap());
+                    if let Some((adapter, _)) = display_mode {
+                        apply_display_mode(&adapter, None);
+                    }
                     user32::PostQuitMessage(0);
                 }
             });
@@ -84,6 +89,31 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             0
         },
 
+        winapi::WM_ACTIVATEAPP => {
+            // the display mode of a fullscreen window must not stay active while another
+            // application is used, for example after alt-tab
+            //
+            // the lock on the window state is released before changing anything, because this
+            // sends messages to the window
+            let display_mode = CONTEXT_STASH.with(|context_stash| {
+                context_stash.borrow().as_ref().and_then(|cstash| {
+                    fullscreen_display_mode(&cstash.window_state.lock().unwrap())
+                })
+            });
+
+            if let Some((adapter, mode)) = display_mode {
+                if wparam != 0 {
+                    apply_display_mode(&adapter, Some(mode));
+                    user32::ShowWindow(window, winapi::SW_RESTORE);
+                } else {
+                    apply_display_mode(&adapter, None);
+                    user32::ShowWindow(window, winapi::SW_MINIMIZE);
+                }
+            }
+
+            user32::DefWindowProcW(window, msg, wparam, lparam)
+        },
+
         winapi::WM_ERASEBKGND => {
             1
         },
@@ -351,3 +381,28 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
         }
     }
 }
+
+/// Returns the adapter name of the monitor and the display mode of the window, if the window is
+/// fullscreen.
+fn fullscreen_display_mode(window_state: &WindowState)
+                           -> Option<(Vec<winapi::WCHAR>, winapi::DEVMODEW)>
+{
+    match (&window_state.attributes.monitor, window_state.display_mode) {
+        (&Some(ref monitor), Some(mode)) => Some((monitor.get_adapter_name().to_vec(), mode)),
+        _ => None,
+    }
+}
+
+/// Switches the monitor to `mode`, or back to the display mode of the desktop if `None`.
+unsafe fn apply_display_mode(adapter: &[winapi::WCHAR], mode: Option<winapi::DEVMODEW>) {
+    match mode {
+        Some(mut mode) => {
+            user32::ChangeDisplaySettingsExW(adapter.as_ptr(), &mut mode, ptr::null_mut(),
+                                             winapi::CDS_FULLSCREEN, ptr::null_mut());
+        },
+        None => {
+            user32::ChangeDisplaySettingsExW(adapter.as_ptr(), ptr::null_mut(), ptr::null_mut(),
+                                             0, ptr::null_mut());
+        },
+    }
+}
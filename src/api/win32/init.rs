@@ -15,11 +15,16 @@ use Api;
 use CreationError;
 use CreationError::OsError;
 use CursorState;
+#[cfg(feature = "egl")]
+use EglPlatform;
 use GlAttributes;
 use GlRequest;
 use PixelFormatRequirements;
+use PresentMode;
 use WindowAttributes;
 
+use platform::PlatformSpecificWindowBuilderAttributes;
+
 use std::ffi::{OsStr};
 use std::os::windows::ffi::OsStrExt;
 use std::sync::mpsc::channel;
@@ -27,15 +32,21 @@ use std::sync::mpsc::channel;
 use winapi;
 use kernel32;
 use dwmapi;
+use gdi32;
 use user32;
 
+#[cfg(feature = "wgl")]
 use api::wgl::Context as WglContext;
+#[cfg(feature = "egl")]
 use api::egl;
+#[cfg(feature = "egl")]
 use api::egl::Context as EglContext;
-use api::egl::ffi::egl::Egl;
+
+use super::Egl;
 
 #[derive(Clone)]
 pub enum RawContext {
+    #[cfg(feature = "egl")]
     Egl(egl::ffi::egl::types::EGLContext),
     Wgl(winapi::HGLRC),
 }
@@ -44,13 +55,15 @@ unsafe impl Send for RawContext {}
 unsafe impl Sync for RawContext {}
 
 pub fn new_window(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-                  opengl: &GlAttributes<RawContext>, egl: Option<&Egl>)
+                  opengl: &GlAttributes<RawContext>, egl: Option<&Egl>,
+                  pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                   -> Result<Window, CreationError>
 {
     let egl = egl.map(|e| e.clone());
     let window = window.clone();
     let pf_reqs = pf_reqs.clone();
     let opengl = opengl.clone();
+    let pl_attribs = pl_attribs.clone();
 
     // initializing variables to be sent to the task
 
@@ -64,7 +77,7 @@ pub fn new_window(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
     thread::spawn(move || {
         unsafe {
             // creating and sending the `Window`
-            match init(title, &window, &pf_reqs, &opengl, egl) {
+            match init(title, &window, &pf_reqs, &opengl, egl, &pl_attribs) {
                 Ok(w) => tx.send(Ok(w)).ok(),
                 Err(e) => {
                     tx.send(Err(e)).ok();
@@ -91,16 +104,30 @@ pub fn new_window(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
 }
 
 unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<RawContext>, egl: Option<Egl>)
+               opengl: &GlAttributes<RawContext>, egl: Option<Egl>,
+               pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
 {
-    let opengl = opengl.clone().map_sharing(|sharelists| {
+    let mut opengl = opengl.clone().map_sharing(|sharelists| {
         match sharelists {
             RawContext::Wgl(c) => c,
+            #[cfg(feature = "egl")]
             _ => unimplemented!()
         }
     });
 
+    // when the DWM is compositing, the swap interval waits for the vertical blank of the
+    // display and the DWM then presents the frame at the next one, so we pace the frames with
+    // `DwmFlush` instead
+    let dwm_flush = opengl.vsync && match pl_attribs.present_mode {
+        PresentMode::Auto => window.monitor.is_none() && super::is_dwm_composition_enabled(),
+        PresentMode::SwapInterval => false,
+        PresentMode::DwmFlush => super::is_dwm_composition_enabled(),
+    };
+    if dwm_flush {
+        opengl.vsync = false;
+    }
+
     // registering the window class
     let class_name = register_window_class();
 
@@ -113,10 +140,12 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
     // switching to fullscreen if necessary
     // this means adjusting the window's position so that it overlaps the right monitor,
     //  and change the monitor's resolution if necessary
-    if window.monitor.is_some() {
+    let display_mode = if window.monitor.is_some() {
         let monitor = window.monitor.as_ref().unwrap();
-        try!(switch_to_fullscreen(&mut rect, monitor));
-    }
+        Some(try!(switch_to_fullscreen(&mut rect, monitor)))
+    } else {
+        None
+    };
 
     // computing the style and extended style of the window
     let (ex_style, style) = if window.monitor.is_some() || window.decorations == false {
@@ -126,6 +155,12 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
             winapi::WS_OVERLAPPEDWINDOW | winapi::WS_CLIPSIBLINGS | winapi::WS_CLIPCHILDREN)
     };
 
+    let ex_style = if pl_attribs.layered {
+        ex_style | winapi::WS_EX_LAYERED
+    } else {
+        ex_style
+    };
+
     // adjusting the window coordinates using the style
     user32::AdjustWindowRectEx(&mut rect, style, 0, ex_style);
 
@@ -163,6 +198,16 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
                                        format!("{}", io::Error::last_os_error()))));
         }
 
+        // a layered window isn't drawn until its attributes are set, the per-pixel alpha then
+        // comes from the DWM (see below)
+        if pl_attribs.layered {
+            if user32::SetLayeredWindowAttributes(handle, 0, 255, 0x2 /* FIXME: LWA_ALPHA */) == 0 {
+                user32::DestroyWindow(handle);
+                return Err(OsError(format!("SetLayeredWindowAttributes function failed: {}",
+                                           format!("{}", io::Error::last_os_error()))));
+            }
+        }
+
         let hdc = user32::GetDC(handle);
         if hdc.is_null() {
             return Err(OsError(format!("GetDC function failed: {}",
@@ -173,41 +218,72 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
     };
 
     // creating the OpenGL context
+    let swap_exchange = pl_attribs.exclusive_fullscreen && window.monitor.is_some();
+    let composition = window.transparent;
     let context = match opengl.version {
-        GlRequest::Specific(Api::OpenGlEs, (_major, _minor)) => {
-            if let Some(egl) = egl {
-                if let Ok(c) = EglContext::new(egl, &pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),
-                                               egl::NativeDisplay::Other(Some(ptr::null())))
-                                                             .and_then(|p| p.finish(real_window.0))
-                {
-                    Context::Egl(c)
-
-                } else {
-                    try!(WglContext::new(&pf_reqs, &opengl, real_window.0)
-                                        .map(Context::Wgl))
-                }
-
+        GlRequest::Specific(Api::OpenGlEs, _) | GlRequest::Range { api: Api::OpenGlEs, .. } => {
+            if let Some(c) = create_egl_context(egl, &pf_reqs, &opengl, &real_window) {
+                c
             } else {
-                // falling back to WGL, which is always available
-                try!(WglContext::new(&pf_reqs, &opengl, real_window.0)
-                                    .map(Context::Wgl))
+                // falling back to WGL
+                try!(create_wgl_context(&pf_reqs, &opengl, &real_window, swap_exchange,
+                                        composition))
             }
         },
         _ => {
-            try!(WglContext::new(&pf_reqs, &opengl, real_window.0).map(Context::Wgl))
+            let wgl = create_wgl_context(&pf_reqs, &opengl, &real_window, swap_exchange,
+                                         composition);
+
+            // the OpenGL drivers of ARM64 Windows often only expose OpenGL 1.1, in which case
+            // OpenGL ES is provided through EGL instead (usually ANGLE on top of Direct3D)
+            let insufficient = match wgl {
+                Ok(ref c) => is_legacy_wgl_context(c),
+                Err(_) => true,
+            };
+
+            // the EGL contexts can't share their objects with a WGL context, so there is no
+            // fallback when sharing is requested
+            let fallback = match (insufficient, gles_fallback_request(opengl.version)) {
+                (true, Some(version)) if opengl.sharing.is_none() => {
+                    let opengl = GlAttributes { version: version, .. opengl.clone() };
+                    create_egl_context(egl, &pf_reqs, &opengl, &real_window)
+                },
+                _ => None,
+            };
+
+            match fallback {
+                Some(c) => c,
+                None => try!(wgl),
+            }
         }
     };
 
     // making the window transparent
+    //
+    // OpenGL can't render to a layered window, instead the DWM is asked to blend the window
+    // with what is behind it by using the alpha channel of the pixel format
     if window.transparent {
+        if !super::is_dwm_composition_enabled() {
+            return Err(CreationError::TransparencyNotSupported);
+        }
+
+        // on Windows 8 and later, an empty blur region is required for the alpha channel to
+        // be taken into account without any blur
+        let region = gdi32::CreateRectRgn(0, 0, -1, -1);
+
         let bb = winapi::DWM_BLURBEHIND {
-            dwFlags: 0x1, // FIXME: DWM_BB_ENABLE;
+            dwFlags: 0x1 | 0x2, // FIXME: DWM_BB_ENABLE | DWM_BB_BLURREGION;
             fEnable: 1,
-            hRgnBlur: ptr::null_mut(),
+            hRgnBlur: region,
             fTransitionOnMaximized: 0,
         };
 
-        dwmapi::DwmEnableBlurBehindWindow(real_window.0, &bb);
+        let result = dwmapi::DwmEnableBlurBehindWindow(real_window.0, &bb);
+        gdi32::DeleteObject(region as winapi::HGDIOBJ);
+
+        if result != winapi::S_OK {
+            return Err(CreationError::TransparencyNotSupported);
+        }
     }
 
     // calling SetForegroundWindow if fullscreen
@@ -219,7 +295,8 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
     let window_state = Arc::new(Mutex::new(WindowState {
         cursor: winapi::IDC_ARROW, // use arrow by default
         cursor_state: CursorState::Normal,
-        attributes: window.clone()
+        attributes: window.clone(),
+        display_mode: display_mode,
     }));
 
     // filling the CONTEXT_STASH task-local storage so that we can start receiving events
@@ -243,9 +320,85 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
         context: context,
         events_receiver: events_receiver,
         window_state: window_state,
+        dwm_flush: dwm_flush,
     })
 }
 
+/// Returns the request to use with EGL when WGL can't provide the requested version of OpenGL,
+/// or `None` if the request doesn't allow OpenGL ES.
+fn gles_fallback_request(version: GlRequest) -> Option<GlRequest> {
+    match version {
+        GlRequest::Latest => Some(GlRequest::Specific(Api::OpenGlEs, (2, 0))),
+        GlRequest::GlThenGles { opengles_version, .. } => {
+            Some(GlRequest::Specific(Api::OpenGlEs, opengles_version))
+        },
+        GlRequest::Specific(_, _) | GlRequest::Range { .. } => None,
+    }
+}
+
+/// Returns true if the context is an OpenGL 1.1 context, which is what the system provides
+/// when there is no hardware driver.
+#[cfg(feature = "wgl")]
+fn is_legacy_wgl_context(context: &Context) -> bool {
+    match *context {
+        Context::Wgl(ref c) => c.get_gl_version() <= (1, 1),
+        #[cfg(feature = "egl")]
+        Context::Egl(_) => false,
+    }
+}
+
+#[cfg(not(feature = "wgl"))]
+#[inline]
+fn is_legacy_wgl_context(_: &Context) -> bool {
+    false
+}
+
+/// Returns `None` if EGL is not available or if creating the context failed.
+#[cfg(feature = "egl")]
+unsafe fn create_egl_context(egl: Option<Egl>, pf_reqs: &PixelFormatRequirements,
+                             opengl: &GlAttributes<winapi::HGLRC>, window: &WindowWrapper)
+                             -> Option<Context>
+{
+    let egl = match egl {
+        Some(egl) => egl,
+        None => return None,
+    };
+
+    EglContext::new(egl, pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),
+                    egl::NativeDisplay::Other(Some(ptr::null())), EglPlatform::Auto)
+        .and_then(|p| p.finish(window.0))
+        .map(Context::Egl)
+        .ok()
+}
+
+#[cfg(not(feature = "egl"))]
+#[inline]
+unsafe fn create_egl_context(_: Option<Egl>, _: &PixelFormatRequirements,
+                             _: &GlAttributes<winapi::HGLRC>, _: &WindowWrapper)
+                             -> Option<Context>
+{
+    None
+}
+
+#[cfg(feature = "wgl")]
+#[inline]
+unsafe fn create_wgl_context(pf_reqs: &PixelFormatRequirements,
+                             opengl: &GlAttributes<winapi::HGLRC>, window: &WindowWrapper,
+                             swap_exchange: bool, composition: bool)
+                             -> Result<Context, CreationError>
+{
+    WglContext::new(pf_reqs, opengl, window.0, swap_exchange, composition).map(Context::Wgl)
+}
+
+#[cfg(not(feature = "wgl"))]
+#[inline]
+unsafe fn create_wgl_context(_: &PixelFormatRequirements, _: &GlAttributes<winapi::HGLRC>,
+                             _: &WindowWrapper, _: bool, _: bool)
+                             -> Result<Context, CreationError>
+{
+    Err(CreationError::NotSupported)
+}
+
 unsafe fn register_window_class() -> Vec<u16> {
     let class_name = OsStr::new("Window Class").encode_wide().chain(Some(0).into_iter())
                                                .collect::<Vec<_>>();
@@ -274,8 +427,9 @@ unsafe fn register_window_class() -> Vec<u16> {
     class_name
 }
 
+/// Returns the display mode that has been set on the monitor.
 unsafe fn switch_to_fullscreen(rect: &mut winapi::RECT, monitor: &MonitorId)
-                               -> Result<(), CreationError>
+                               -> Result<winapi::DEVMODEW, CreationError>
 {
     // adjusting the rect
     {
@@ -302,5 +456,5 @@ unsafe fn switch_to_fullscreen(rect: &mut winapi::RECT, monitor: &MonitorId)
         return Err(OsError(format!("ChangeDisplaySettings failed: {}", result)));
     }
 
-    Ok(())
+    Ok(screen_settings)
 }
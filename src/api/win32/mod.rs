@@ -11,8 +11,13 @@ use std::sync::{
 use std::sync::mpsc::Receiver;
 use libc;
 use ContextError;
+use ConfigLimits;
+use RendererInfo;
+use Rect;
+use HdrMetadata;
 use {CreationError, Event, MouseCursor};
 use CursorState;
+use FailedVersion;
 use GlAttributes;
 use GlContext;
 
@@ -20,6 +25,10 @@ use Api;
 use PixelFormat;
 use PixelFormatRequirements;
 use WindowAttributes;
+use api::Drawable;
+use presenter::SwapFn;
+
+use platform::PlatformSpecificWindowBuilderAttributes;
 
 pub use self::monitor::{MonitorId, get_available_monitors, get_primary_monitor};
 
@@ -27,9 +36,22 @@ use winapi;
 use user32;
 use kernel32;
 
+#[cfg(feature = "wgl")]
 use api::wgl::Context as WglContext;
+#[cfg(feature = "wgl")]
+use api::wgl::PBuffer as WglPBuffer;
+#[cfg(feature = "egl")]
 use api::egl::Context as EglContext;
-use api::egl::ffi::egl::Egl;
+#[cfg(feature = "egl")]
+use api::egl::PBuffer as EglPBuffer;
+
+#[cfg(feature = "egl")]
+pub use api::egl::ffi::egl::Egl;
+
+/// Takes the place of the EGL functions when glutin is built without the `egl` feature.
+#[cfg(not(feature = "egl"))]
+#[derive(Clone)]
+pub enum Egl {}
 
 use self::init::RawContext;
 
@@ -38,6 +60,19 @@ mod event;
 mod init;
 mod monitor;
 
+// missing from `dwmapi-sys`
+#[link(name = "dwmapi")]
+extern "system" {
+    fn DwmFlush() -> winapi::HRESULT;
+    fn DwmIsCompositionEnabled(pfEnabled: *mut winapi::BOOL) -> winapi::HRESULT;
+}
+
+/// Returns true if the desktop window manager is composing the windows.
+fn is_dwm_composition_enabled() -> bool {
+    let mut enabled = 0;
+    unsafe { DwmIsCompositionEnabled(&mut enabled) == winapi::S_OK && enabled != 0 }
+}
+
 lazy_static! {
     static ref WAKEUP_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::EventID".as_ptr() as *const i8) };
 }
@@ -50,7 +85,9 @@ pub type Cursor = *const winapi::wchar_t;
 pub struct WindowState {
     pub cursor: Cursor,
     pub cursor_state: CursorState,
-    pub attributes: WindowAttributes
+    pub attributes: WindowAttributes,
+    /// The display mode of the monitor, if the window is fullscreen.
+    pub display_mode: Option<winapi::DEVMODEW>,
 }
 
 /// The Win32 implementation of the main `Window` object.
@@ -66,16 +103,30 @@ pub struct Window {
 
     /// The current window state.
     window_state: Arc<Mutex<WindowState>>,
+
+    /// If true, `DwmFlush` is called after swapping the buffers instead of relying on the swap
+    /// interval.
+    dwm_flush: bool,
 }
 
 unsafe impl Send for Window {}
 unsafe impl Sync for Window {}
 
 enum Context {
+    #[cfg(feature = "egl")]
     Egl(EglContext),
+    #[cfg(feature = "wgl")]
     Wgl(WglContext),
 }
 
+/// A pbuffer created from the context of a window.
+pub enum PBuffer {
+    #[cfg(feature = "egl")]
+    Egl(EglPBuffer),
+    #[cfg(feature = "wgl")]
+    Wgl(WglPBuffer),
+}
+
 /// A simple wrapper that destroys the window when it is destroyed.
 // FIXME: remove `pub` (https://github.com/rust-lang/rust/issues/23585)
 #[doc(hidden)]
@@ -110,17 +161,20 @@ impl WindowProxy {
 impl Window {
     /// See the docs in the crate root file.
     pub fn new(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, egl: Option<&Egl>)
+               opengl: &GlAttributes<&Window>, egl: Option<&Egl>,
+               pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
         let opengl = opengl.clone().map_sharing(|sharing| {
             match sharing.context {
+                #[cfg(feature = "wgl")]
                 Context::Wgl(ref c) => RawContext::Wgl(c.get_hglrc()),
+                #[cfg(feature = "egl")]
                 Context::Egl(_) => unimplemented!(),        // FIXME:
             }
         });
 
-        init::new_window(window, pf_reqs, &opengl, egl)
+        init::new_window(window, pf_reqs, &opengl, egl, pl_attribs)
     }
 
     /// See the docs in the crate root file.
@@ -259,6 +313,62 @@ impl Window {
         self.window.0 as *mut libc::c_void
     }
 
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.create_pbuffer(dimensions).map(PBuffer::Wgl),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.create_pbuffer(dimensions).map(PBuffer::Egl),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => {
+                let draw = try!(draw.map(Window::wgl_context, PBuffer::as_wgl, |x| Ok(x)));
+                let read = try!(read.map(Window::wgl_context, PBuffer::as_wgl, |x| Ok(x)));
+                c.make_current_with_surfaces(draw, read)
+            },
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => {
+                let draw = try!(draw.map(Window::egl_context, PBuffer::as_egl, |x| match *x {}));
+                let read = try!(read.map(Window::egl_context, PBuffer::as_egl, |x| match *x {}));
+                c.make_current_with_surfaces(draw, read)
+            },
+        }
+    }
+
+    #[cfg(feature = "wgl")]
+    fn wgl_context(&self) -> Result<&WglContext, ContextError> {
+        match self.context {
+            Context::Wgl(ref c) => Ok(c),
+            #[cfg(feature = "egl")]
+            Context::Egl(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+
+    /// Returns the EGL context of the window, which is the only kind that the EGL contexts of
+    /// headless contexts can use.
+    #[cfg(feature = "egl")]
+    pub fn egl_context(&self) -> Result<&EglContext, ContextError> {
+        match self.context {
+            Context::Egl(ref c) => Ok(c),
+            #[cfg(feature = "wgl")]
+            Context::Wgl(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
     }
@@ -349,6 +459,32 @@ impl Window {
         1.0
     }
 
+    /// Returns a function that swaps the buffers from any thread, which only WGL allows.
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => {
+                let mut swap = c.get_swapper();
+                let dwm_flush = self.dwm_flush;
+                Some(Box::new(move || {
+                    try!(swap());
+                    if dwm_flush {
+                        unsafe { DwmFlush(); }
+                    }
+                    Ok(())
+                }))
+            },
+            #[cfg(feature = "egl")]
+            Context::Egl(_) => None,
+        }
+    }
+
+    /// Variable refresh rates aren't reported on this platform.
+    #[inline]
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        None
+    }
+
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
         let mut point = winapi::POINT {
             x: x,
@@ -373,7 +509,9 @@ impl GlContext for Window {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
         match self.context {
+            #[cfg(feature = "wgl")]
             Context::Wgl(ref c) => c.make_current(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref c) => c.make_current(),
         }
     }
@@ -381,7 +519,9 @@ impl GlContext for Window {
     #[inline]
     fn is_current(&self) -> bool {
         match self.context {
+            #[cfg(feature = "wgl")]
             Context::Wgl(ref c) => c.is_current(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref c) => c.is_current(),
         }
     }
@@ -389,23 +529,36 @@ impl GlContext for Window {
     #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
         match self.context {
+            #[cfg(feature = "wgl")]
             Context::Wgl(ref c) => c.get_proc_address(addr),
+            #[cfg(feature = "egl")]
             Context::Egl(ref c) => c.get_proc_address(addr),
         }
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
-        match self.context {
+        try!(match self.context {
+            #[cfg(feature = "wgl")]
             Context::Wgl(ref c) => c.swap_buffers(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref c) => c.swap_buffers(),
+        });
+
+        if self.dwm_flush {
+            // blocks until the desktop window manager has composed the frame
+            unsafe { DwmFlush(); }
         }
+
+        Ok(())
     }
 
     #[inline]
     fn get_api(&self) -> Api {
         match self.context {
+            #[cfg(feature = "wgl")]
             Context::Wgl(ref c) => c.get_api(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref c) => c.get_api(),
         }
     }
@@ -413,10 +566,236 @@ impl GlContext for Window {
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
         match self.context {
+            #[cfg(feature = "wgl")]
             Context::Wgl(ref c) => c.get_pixel_format(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref c) => c.get_pixel_format(),
         }
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.get_failed_versions(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.get_failed_versions(),
+        }
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.get_config_limits(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.get_config_limits(),
+        }
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.get_renderer_info(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.get_renderer_info(),
+        }
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.make_not_current(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.make_not_current(),
+        }
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.destroy_context(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.destroy_context(),
+        }
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.is_back_buffer_preserved(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.is_back_buffer_preserved(),
+        }
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.is_front_buffer_rendering(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.is_front_buffer_rendering(),
+        }
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.set_damage_region(rects),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.set_damage_region(rects),
+        }
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.set_hdr_metadata(metadata),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.set_hdr_metadata(metadata),
+        }
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        match self.context {
+            #[cfg(feature = "wgl")]
+            Context::Wgl(ref c) => c.set_swap_interval(interval),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref c) => c.set_swap_interval(interval),
+        }
+    }
+}
+
+impl PBuffer {
+    #[cfg(feature = "wgl")]
+    fn as_wgl(&self) -> Result<&WglPBuffer, ContextError> {
+        match *self {
+            PBuffer::Wgl(ref p) => Ok(p),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+
+    /// Returns the EGL pbuffer, which is the only kind that the EGL contexts of headless
+    /// contexts can use.
+    #[cfg(feature = "egl")]
+    pub fn as_egl(&self) -> Result<&EglPBuffer, ContextError> {
+        match *self {
+            PBuffer::Egl(ref p) => Ok(p),
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+
+    /// See the docs of `PBufferExt`.
+    #[inline]
+    pub fn can_bind_to_texture(&self) -> bool {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.can_bind_to_texture(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(_) => false,
+        }
+    }
+
+    /// See the docs of `PBufferExt`.
+    #[inline]
+    pub unsafe fn bind_to_texture(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.bind_to_texture(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(_) => {
+                Err(ContextError::NotSupported(
+                           "render-to-texture is only supported by WGL pbuffers"))
+            },
+        }
+    }
+
+    /// See the docs of `PBufferExt`.
+    #[inline]
+    pub unsafe fn release_from_texture(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.release_from_texture(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(_) => Ok(()),
+        }
+    }
+}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.make_current(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.make_current(),
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.is_current(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.is_current(),
+        }
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.get_proc_address(addr),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.get_proc_address(addr),
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.swap_buffers(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.get_api(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.get_api(),
+        }
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {
+            #[cfg(feature = "wgl")]
+            PBuffer::Wgl(ref p) => p.get_pixel_format(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.get_pixel_format(),
+        }
+    }
 }
 
 pub struct PollEventsIterator<'a> {
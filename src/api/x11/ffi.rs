@@ -4,6 +4,7 @@ pub use x11_dl::xf86vmode::*;
 pub use x11_dl::xlib::*;
 pub use x11_dl::xinput::*;
 pub use x11_dl::xinput2::*;
+pub use x11_dl::xrandr::*;
 
 pub use x11_dl::error::OpenError;
 
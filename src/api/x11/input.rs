@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
 
 use libc;
@@ -122,12 +123,14 @@ impl XInputEventHandler {
         }
     }
 
-    pub fn translate_key_event(&self, event: &mut ffi::XKeyEvent) -> Vec<Event> {
+    /// Appends the events of a key press or release to `translated_events`, which the window
+    /// reuses to avoid allocating for each key.
+    pub fn translate_key_event(&self, event: &mut ffi::XKeyEvent,
+                               translated_events: &mut VecDeque<Event>)
+    {
         use events::Event::{KeyboardInput, ReceivedCharacter};
         use events::ElementState::{Pressed, Released};
 
-        let mut translated_events = Vec::new();
-
         let state;
         if event.type_ == ffi::KeyPress {
             let raw_ev: *mut ffi::XKeyEvent = event;
@@ -139,20 +142,20 @@ impl XInputEventHandler {
 
         let mut kp_keysym = 0;
 
+        let mut buffer: [u8; 16] = unsafe { [mem::uninitialized(); 16] };
         let written = unsafe {
             use std::str;
 
-            let mut buffer: [u8; 16] = [mem::uninitialized(); 16];
             let raw_ev: *mut ffi::XKeyEvent = event;
             let count = (self.display.xlib.Xutf8LookupString)(self.ic, mem::transmute(raw_ev),
             mem::transmute(buffer.as_mut_ptr()),
             buffer.len() as libc::c_int, &mut kp_keysym, ptr::null_mut());
 
-            str::from_utf8(&buffer[..count as usize]).unwrap_or("").to_string()
+            str::from_utf8(&buffer[..count as usize]).unwrap_or("")
         };
 
         for chr in written.chars() {
-            translated_events.push(ReceivedCharacter(chr));
+            translated_events.push_back(ReceivedCharacter(chr));
         }
 
         let mut keysym = unsafe {
@@ -165,8 +168,7 @@ impl XInputEventHandler {
 
         let vkey = events::keycode_to_element(keysym as libc::c_uint);
 
-        translated_events.push(KeyboardInput(state, event.keycode as u8, vkey));
-        translated_events
+        translated_events.push_back(KeyboardInput(state, event.keycode as u8, vkey));
     }
 
     pub fn translate_event(&mut self, cookie: &ffi::XGenericEventCookie) -> Option<Event> {
@@ -1,7 +1,9 @@
-#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+           target_os = "openbsd", target_os = "netbsd"))]
 
 pub use self::monitor::{MonitorId, get_available_monitors, get_primary_monitor};
 pub use self::window::{Window, XWindow, PollEventsIterator, WaitEventsIterator, Context, WindowProxy};
+pub use self::window::{PBuffer, PixmapSurface};
 pub use self::xdisplay::{XConnection, XNotSupported, XError};
 
 pub mod ffi;
@@ -9,5 +11,6 @@ pub mod ffi;
 mod events;
 mod input;
 mod monitor;
+mod remote;
 mod window;
 mod xdisplay;
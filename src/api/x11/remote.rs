@@ -0,0 +1,48 @@
+//! Detection of the sessions where the OpenGL commands don't reach a local GPU directly.
+
+use std::env;
+
+/// Returns true if the X server of `DISPLAY` runs on another machine.
+///
+/// `ssh -X` forwards the display over TCP on `localhost`, with a display number of 10 or more,
+/// therefore a display on `localhost` is only considered remote inside an SSH session.
+pub fn is_remote_display() -> bool {
+    let display = match env::var("DISPLAY") {
+        Ok(d) => d,
+        Err(_) => return false,
+    };
+
+    // the launchd sockets of XQuartz are paths that contain `:`
+    if display.starts_with('/') {
+        return false;
+    }
+
+    let host = match display.rfind(':') {
+        Some(pos) => &display[.. pos],
+        None => return false,
+    };
+
+    match host {
+        "" | "unix" => false,
+        "localhost" | "127.0.0.1" | "::1" => {
+            env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_CLIENT").is_some()
+        },
+        _ => true,
+    }
+}
+
+/// Returns true if the process runs under `vglrun`, which renders on the GPU of this machine
+/// and sends the frames to the X server as images.
+pub fn is_virtualgl() -> bool {
+    if env::var("VGL_ISACTIVE").map(|v| v == "1").unwrap_or(false) {
+        return true;
+    }
+
+    env::var("LD_PRELOAD").map(|p| p.contains("libvglfaker")).unwrap_or(false)
+}
+
+/// Returns true if Mesa has been told to create indirect GLX contexts, which send the OpenGL
+/// commands through the X server like for a remote display.
+pub fn is_indirect_forced() -> bool {
+    env::var_os("LIBGL_ALWAYS_INDIRECT").map(|v| !v.is_empty()).unwrap_or(false)
+}
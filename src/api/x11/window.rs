@@ -1,10 +1,16 @@
 use {Event, MouseCursor};
 use CreationError;
+use ConfigLimits;
+use RendererInfo;
+use Rect;
+use HdrMetadata;
 use CreationError::OsError;
+use presenter::SwapFn;
+use FailedVersion;
 use libc;
 use std::borrow::Borrow;
 use std::{mem, ptr, cmp};
-use std::cell::Cell;
+use std::io;
 use std::sync::atomic::AtomicBool;
 use std::collections::VecDeque;
 use std::path::PathBuf;
@@ -16,35 +22,50 @@ use std::time::Duration;
 use Api;
 use ContextError;
 use CursorState;
+use DmaBufTexture;
+#[cfg(feature = "egl")]
+use EglPlatform;
 use GlAttributes;
 use GlContext;
 use GlRequest;
 use PixelFormat;
 use PixelFormatRequirements;
 use WindowAttributes;
+use api::Drawable;
+use c_str::with_c_str;
 
+#[cfg(feature = "glx")]
+use NativeError;
+#[cfg(feature = "glx")]
 use api::glx::Context as GlxContext;
+#[cfg(feature = "glx")]
+use api::glx::PBuffer as GlxPBuffer;
+#[cfg(feature = "glx")]
+use api::glx::PixmapSurface as GlxPixmapSurface;
+#[cfg(feature = "egl")]
 use api::egl;
+#[cfg(feature = "egl")]
 use api::egl::Context as EglContext;
+#[cfg(feature = "egl")]
+use api::egl::PBuffer as EglPBuffer;
+#[cfg(feature = "egl")]
+use api::egl::PixmapSurface as EglPixmapSurface;
 
 use platform::MonitorId as PlatformMonitorId;
+use platform::PlatformSpecificWindowBuilderAttributes;
 
 use super::input::XInputEventHandler;
+use super::remote;
 use super::{ffi};
 use super::{MonitorId, XConnection};
+#[cfg(feature = "glx")]
+use super::XError;
 
 // XOpenIM doesn't seem to be thread-safe
 lazy_static! {      // TODO: use a static mutex when that's possible, and put me back in my function
     static ref GLOBAL_XOPENIM_LOCK: Mutex<()> = Mutex::new(());
 }
 
-// TODO: remove me
-fn with_c_str<F, T>(s: &str, f: F) -> T where F: FnOnce(*const libc::c_char) -> T {
-    use std::ffi::CString;
-    let c_str = CString::new(s.as_bytes().to_vec()).unwrap();
-    f(c_str.as_ptr())
-}
-
 struct WindowProxyData {
     display: Arc<XConnection>,
     window: ffi::Window,
@@ -58,6 +79,7 @@ pub struct XWindow {
     pub context: Context,
     is_fullscreen: bool,
     screen_id: libc::c_int,
+    visual_infos: ffi::XVisualInfo,
     xf86_desk_mode: Option<ffi::XF86VidModeModeInfo>,
     ic: ffi::XIC,
     im: ffi::XIM,
@@ -66,11 +88,109 @@ pub struct XWindow {
 }
 
 pub enum Context {
+    #[cfg(feature = "glx")]
     Glx(GlxContext),
+    #[cfg(feature = "egl")]
     Egl(EglContext),
     None,
 }
 
+/// A pbuffer created from the context of a window.
+pub enum PBuffer {
+    #[cfg(feature = "glx")]
+    Glx(GlxPBuffer),
+    #[cfg(feature = "egl")]
+    Egl(EglPBuffer),
+}
+
+/// A surface that renders into an X pixmap with the context of a window.
+pub enum PixmapSurface {
+    #[cfg(feature = "glx")]
+    Glx(GlxPixmapSurface),
+    #[cfg(feature = "egl")]
+    Egl(EglPixmapSurface),
+}
+
+/// A context whose creation has started, but that doesn't have a window yet.
+enum Prototype<'a> {
+    #[cfg(feature = "glx")]
+    Glx(::api::glx::ContextPrototype<'a>),
+    #[cfg(feature = "egl")]
+    Egl(::api::egl::ContextPrototype<'a>),
+}
+
+impl<'a> Prototype<'a> {
+    /// Returns `None` if GLX is not available.
+    #[cfg(feature = "glx")]
+    fn new_glx(display: &Arc<XConnection>, pf_reqs: &PixelFormatRequirements,
+               opengl: &'a GlAttributes<&'a GlxContext>, screen_id: libc::c_int,
+               transparent: bool)
+               -> Result<Option<Prototype<'a>>, CreationError>
+    {
+        match display.glx() {
+            Some(glx) => {
+                let extensions = display.glx_extensions(&glx, screen_id);
+                let proto = try!(GlxContext::new(glx, &display.xlib, extensions, pf_reqs, opengl,
+                                                 display.display, screen_id, transparent));
+                Ok(Some(Prototype::Glx(proto)))
+            },
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "glx"))]
+    #[inline]
+    fn new_glx(_: &Arc<XConnection>, _: &PixelFormatRequirements, _: &'a GlAttributes<&'a ()>,
+               _: libc::c_int, _: bool) -> Result<Option<Prototype<'a>>, CreationError>
+    {
+        Ok(None)
+    }
+
+    /// Returns `None` if EGL is not available.
+    #[cfg(feature = "egl")]
+    fn new_egl(display: &Arc<XConnection>, pf_reqs: &PixelFormatRequirements,
+               opengl: &'a GlAttributes<&'a EglContext>, screen_id: libc::c_int,
+               pl_attribs: &PlatformSpecificWindowBuilderAttributes)
+               -> Result<Option<Prototype<'a>>, CreationError>
+    {
+        match display.egl() {
+            Some(egl) => {
+                // rendering through the connection of the toolkit if possible, and through the
+                // Xlib display of glutin otherwise
+                if let Some(connection) = pl_attribs.xcb_connection {
+                    let platform = pl_attribs.egl_platform;
+                    if platform == EglPlatform::Auto || platform == EglPlatform::Xcb {
+                        let native_display = egl::NativeDisplay::Xcb(connection as *const _,
+                                                                     Some(screen_id));
+                        match EglContext::new(egl.clone(), pf_reqs, opengl, native_display,
+                                              EglPlatform::Xcb)
+                        {
+                            Ok(proto) => return Ok(Some(Prototype::Egl(proto))),
+                            Err(err) => if platform == EglPlatform::Xcb { return Err(err) },
+                        }
+                    }
+                }
+
+                let native_display = egl::NativeDisplay::X11(Some(display.display as *const _),
+                                                             Some(screen_id));
+                let proto = try!(EglContext::new(egl, pf_reqs, opengl, native_display,
+                                                 pl_attribs.egl_platform));
+                Ok(Some(Prototype::Egl(proto)))
+            },
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "egl"))]
+    #[inline]
+    fn new_egl(_: &Arc<XConnection>, _: &PixelFormatRequirements, _: &'a GlAttributes<&'a ()>,
+               _: libc::c_int, _: &PlatformSpecificWindowBuilderAttributes)
+               -> Result<Option<Prototype<'a>>, CreationError>
+    {
+        Ok(None)
+    }
+}
+
 unsafe impl Send for XWindow {}
 unsafe impl Sync for XWindow {}
 
@@ -135,6 +255,51 @@ impl WindowProxy {
     }
 }
 
+/// Returns true if the error means that the requested version or API isn't available, in which
+/// case another one can be tried.
+fn is_version_failure(error: &CreationError) -> bool {
+    match *error {
+        CreationError::OpenGlVersionNotSupported | CreationError::NotSupported |
+        CreationError::NoAvailablePixelFormat => true,
+        _ => false,
+    }
+}
+
+/// Fills in the code of a failed GLX call with the X error that it produced.
+#[cfg(feature = "glx")]
+fn with_x_error_code(display: &XConnection, result: Result<(), ContextError>)
+                     -> Result<(), ContextError>
+{
+    match result {
+        Err(ContextError::NativeError(mut error)) => {
+            if let Err(x_error) = display.check_errors() {
+                set_x_error_code(&mut error, &x_error);
+            }
+            Err(ContextError::NativeError(error))
+        },
+        result => result,
+    }
+}
+
+#[cfg(feature = "glx")]
+fn set_x_error_code(error: &mut NativeError, x_error: &XError) {
+    error.code = x_error.error_code as u32;
+    error.name = match x_error.error_code {
+        1 => Some("BadRequest"),
+        2 => Some("BadValue"),
+        3 => Some("BadWindow"),
+        4 => Some("BadPixmap"),
+        8 => Some("BadMatch"),
+        9 => Some("BadDrawable"),
+        10 => Some("BadAccess"),
+        11 => Some("BadAlloc"),
+        16 => Some("BadLength"),
+        17 => Some("BadImplementation"),
+        // the codes of the GLX errors depend on the server
+        _ => None,
+    };
+}
+
 // XEvents of type GenericEvent store their actual data
 // in an XGenericEventCookie data structure. This is a wrapper
 // to extract the cookie from a GenericEvent XEvent and release
@@ -218,9 +383,9 @@ impl<'a> Iterator for PollEventsIterator<'a> {
                 ffi::ConfigureNotify => {
                     use events::Event::Resized;
                     let cfg_event: &ffi::XConfigureEvent = unsafe { mem::transmute(&xev) };
-                    let (current_width, current_height) = self.window.current_size.get();
-                    if current_width != cfg_event.width || current_height != cfg_event.height {
-                        self.window.current_size.set((cfg_event.width, cfg_event.height));
+                    let mut current_size = self.window.current_size.lock().unwrap();
+                    if *current_size != (cfg_event.width, cfg_event.height) {
+                        *current_size = (cfg_event.width, cfg_event.height);
                         return Some(Resized(cfg_event.width as u32, cfg_event.height as u32));
                     }
                 },
@@ -232,10 +397,9 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
                 ffi::KeyPress | ffi::KeyRelease => {
                     let mut event: &mut ffi::XKeyEvent = unsafe { mem::transmute(&mut xev) };
-                    let events = self.window.input_handler.lock().unwrap().translate_key_event(&mut event);
-                    for event in events {
-                        self.window.pending_events.lock().unwrap().push_back(event);
-                    }
+                    let input_handler = self.window.input_handler.lock().unwrap();
+                    let mut pending_events = self.window.pending_events.lock().unwrap();
+                    input_handler.translate_key_event(&mut event, &mut pending_events);
                 },
 
                 ffi::GenericEvent => {
@@ -299,17 +463,124 @@ pub struct Window {
     pub x: Arc<XWindow>,
     is_closed: AtomicBool,
     wm_delete_window: ffi::Atom,
-    current_size: Cell<(libc::c_int, libc::c_int)>,
+    current_size: Mutex<(libc::c_int, libc::c_int)>,
     /// Events that have been retreived with XLib but not dispatched with iterators yet
     pending_events: Mutex<VecDeque<Event>>,
     cursor_state: Mutex<CursorState>,
-    input_handler: Mutex<XInputEventHandler>
+    input_handler: Mutex<XInputEventHandler>,
+    /// Versions that failed before falling back to the API of the context.
+    failed_versions: Vec<FailedVersion>,
 }
 
 impl Window {
     pub fn new(display: &Arc<XConnection>, window_attrs: &WindowAttributes,
-               pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>)
+               pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>,
+               pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
+    {
+        let error = match Window::new_with_gles_fallback(display, window_attrs, pf_reqs, opengl,
+                                                         pl_attribs)
+        {
+            Ok(window) => return Ok(window),
+            Err(err) => err,
+        };
+
+        // indirect GLX contexts, which are the only ones available over a remote display, are
+        // limited to old versions of OpenGL
+        let indirect = remote::is_remote_display() || remote::is_indirect_forced();
+        let latest = match opengl.version { GlRequest::Latest => true, _ => false };
+        let version_failure = match error {
+            CreationError::NoVersionAvailable(_) => true,
+            ref error => is_version_failure(error),
+        };
+        if !pl_attribs.remote_fallback || !indirect || latest || !version_failure {
+            return Err(error);
+        }
+
+        let fallback = GlAttributes {
+            version: GlRequest::Latest,
+            profile: None,
+            .. opengl.clone()
+        };
+
+        let failures = match error {
+            CreationError::NoVersionAvailable(failures) => failures,
+            error => {
+                let (api, version) = match opengl.version {
+                    GlRequest::Specific(api, version) => (api, version),
+                    GlRequest::Range { api, min, .. } => (api, min),
+                    GlRequest::GlThenGles { opengl_version, .. } => (Api::OpenGl, opengl_version),
+                    GlRequest::Latest => unreachable!(),
+                };
+                vec![FailedVersion { api: api, version: version, reason: format!("{}", error) }]
+            },
+        };
+
+        match Window::new_with_api(display, window_attrs, pf_reqs, &fallback, pl_attribs) {
+            Ok(mut window) => {
+                window.failed_versions.extend(failures);
+                Ok(window)
+            },
+            Err(ref error) if is_version_failure(error) => {
+                Err(CreationError::NoVersionAvailable(failures))
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    fn new_with_gles_fallback(display: &Arc<XConnection>, window_attrs: &WindowAttributes,
+                              pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>,
+                              pl_attribs: &PlatformSpecificWindowBuilderAttributes)
+                              -> Result<Window, CreationError>
+    {
+        let (opengl_version, opengles_version) = match opengl.version {
+            GlRequest::GlThenGles { opengl_version, opengles_version } => {
+                (opengl_version, opengles_version)
+            },
+            _ => return Window::new_with_api(display, window_attrs, pf_reqs, opengl, pl_attribs),
+        };
+
+        // desktop OpenGL is often missing on ARM boards and in virtual machines, in which case
+        // OpenGL ES is created through EGL instead
+        let gl_error = match Window::new_with_api(display, window_attrs, pf_reqs, opengl,
+                                                  pl_attribs)
+        {
+            Ok(window) => return Ok(window),
+            // the other errors wouldn't be solved by OpenGL ES
+            Err(err) => if is_version_failure(&err) { err } else { return Err(err) },
+        };
+
+        let opengl_es = GlAttributes {
+            version: GlRequest::Specific(Api::OpenGlEs, opengles_version),
+            .. opengl.clone()
+        };
+
+        let gl_failure = FailedVersion {
+            api: Api::OpenGl,
+            version: opengl_version,
+            reason: format!("{}", gl_error),
+        };
+
+        match Window::new_with_api(display, window_attrs, pf_reqs, &opengl_es, pl_attribs) {
+            Ok(mut window) => {
+                window.failed_versions.push(gl_failure);
+                Ok(window)
+            },
+            Err(ref gles_error) if is_version_failure(gles_error) => {
+                Err(CreationError::NoVersionAvailable(vec![gl_failure, FailedVersion {
+                    api: Api::OpenGlEs,
+                    version: opengles_version,
+                    reason: format!("{}", gles_error),
+                }]))
+            },
+            Err(gles_error) => Err(gles_error),
+        }
+    }
+
+    fn new_with_api(display: &Arc<XConnection>, window_attrs: &WindowAttributes,
+                    pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>,
+                    pl_attribs: &PlatformSpecificWindowBuilderAttributes)
+                    -> Result<Window, CreationError>
     {
         let dimensions = {
 
@@ -329,8 +600,19 @@ impl Window {
 
         };
 
-        let screen_id = match window_attrs.monitor {
-            Some(PlatformMonitorId::X(MonitorId(_, monitor))) => monitor as i32,
+        // the visual is chosen among those of this screen, which is the screen of the parent for
+        // child windows
+        let screen_id = match (&window_attrs.monitor, &window_attrs.parent) {
+            (&Some(PlatformMonitorId::X(MonitorId(_, monitor))), _) => monitor as i32,
+            (_, &Some(ref parent)) => unsafe {
+                let mut attributes: ffi::XWindowAttributes = mem::zeroed();
+                if (display.xlib.XGetWindowAttributes)(display.display, parent.window as ffi::Window,
+                                                       &mut attributes) == 0
+                {
+                    return Err(OsError(format!("XGetWindowAttributes failed on the parent window")));
+                }
+                (display.xlib.XScreenNumberOfScreen)(attributes.screen)
+            },
             _ => unsafe { (display.xlib.XDefaultScreen)(display.display) },
         };
 
@@ -366,54 +648,79 @@ impl Window {
             }
         };
 
-        // start the context building process
-        enum Prototype<'a> {
-            Glx(::api::glx::ContextPrototype<'a>),
-            Egl(::api::egl::ContextPrototype<'a>),
-        }
-        let builder_clone_opengl_glx = opengl.clone().map_sharing(|_| unimplemented!());      // FIXME:
-        let builder_clone_opengl_egl = opengl.clone().map_sharing(|_| unimplemented!());      // FIXME:
+        // start the context building process, with the API of the context to share with if any
+        let (opengl_glx, opengl_egl) = match opengl.sharing.map(|w| &w.x.context) {
+            None => (Some(opengl.clone().map_sharing(|_| unreachable!())),
+                     Some(opengl.clone().map_sharing(|_| unreachable!()))),
+            #[cfg(feature = "glx")]
+            Some(&Context::Glx(ref c)) => (Some(opengl.clone().map_sharing(|_| c)), None),
+            #[cfg(feature = "egl")]
+            Some(&Context::Egl(ref c)) => (None, Some(opengl.clone().map_sharing(|_| c))),
+            Some(&Context::None) => {
+                return Err(OsError(format!("the window to share with has no context")));
+            },
+        };
+        let new_glx = |transparent| match opengl_glx {
+            Some(ref opengl) => Prototype::new_glx(display, pf_reqs, opengl, screen_id, transparent),
+            None => Ok(None),
+        };
+        let new_egl = || match opengl_egl {
+            Some(ref opengl) => Prototype::new_egl(display, pf_reqs, opengl, screen_id, pl_attribs),
+            None => Ok(None),
+        };
         let context = match opengl.version {
-            GlRequest::Latest | GlRequest::Specific(Api::OpenGl, _) | GlRequest::GlThenGles { .. } => {
+            GlRequest::Latest | GlRequest::Specific(Api::OpenGl, _) | GlRequest::GlThenGles { .. } |
+            GlRequest::Range { api: Api::OpenGl, .. } => {
                 // GLX should be preferred over EGL, otherwise crashes may occur
                 // on X11 – issue #314
-                if let Some(ref glx) = display.glx {
-                    Prototype::Glx(try!(GlxContext::new(glx.clone(), &display.xlib, pf_reqs, &builder_clone_opengl_glx, display.display, screen_id)))
-                } else if let Some(ref egl) = display.egl {
-                    Prototype::Egl(try!(EglContext::new(egl.clone(), pf_reqs, &builder_clone_opengl_egl, egl::NativeDisplay::X11(Some(display.display as *const _)))))
+                if let Some(p) = try!(new_glx(window_attrs.transparent)) {
+                    p
+                } else if let Some(p) = try!(new_egl()) {
+                    p
                 } else {
                     return Err(CreationError::NotSupported);
                 }
             },
-            GlRequest::Specific(Api::OpenGlEs, _) => {
-                if let Some(ref egl) = display.egl {
-                    Prototype::Egl(try!(EglContext::new(egl.clone(), pf_reqs, &builder_clone_opengl_egl, egl::NativeDisplay::X11(Some(display.display as *const _)))))
+            GlRequest::Specific(Api::OpenGlEs, _) | GlRequest::Range { api: Api::OpenGlEs, .. } => {
+                if let Some(p) = try!(new_egl()) {
+                    p
                 } else {
                     return Err(CreationError::NotSupported);
                 }
             },
-            GlRequest::Specific(_, _) => {
+            GlRequest::Specific(_, _) | GlRequest::Range { .. } => {
                 return Err(CreationError::NotSupported);
             },
         };
 
         // getting the `visual_infos` (a struct that contains information about the visual to use)
         let visual_infos = match context {
+            #[cfg(feature = "glx")]
             Prototype::Glx(ref p) => p.get_visual_infos().clone(),
+            #[cfg(feature = "egl")]
             Prototype::Egl(ref p) => {
                 unsafe {
                     let mut template: ffi::XVisualInfo = mem::zeroed();
                     template.visualid = p.get_native_visual_id() as ffi::VisualID;
+                    template.screen = screen_id;
 
                     let mut num_visuals = 0;
-                    let vi = (display.xlib.XGetVisualInfo)(display.display, ffi::VisualIDMask,
+                    let vi = (display.xlib.XGetVisualInfo)(display.display,
+                                                           ffi::VisualIDMask | ffi::VisualScreenMask,
                                                            &mut template, &mut num_visuals);
                     display.check_errors().expect("Failed to call XGetVisualInfo");
                     assert!(!vi.is_null());
                     assert!(num_visuals == 1);
 
-                    let vi_copy = ptr::read(vi as *const _);
+                    let vi_copy: ffi::XVisualInfo = ptr::read(vi as *const _);
                     (display.xlib.XFree)(vi as *mut _);
+
+                    // EGL doesn't let us filter the configs by visual, so all we can do is
+                    // check that the one that has been chosen has an alpha channel
+                    if window_attrs.transparent && vi_copy.depth != 32 {
+                        return Err(CreationError::TransparencyNotSupported);
+                    }
+
                     vi_copy
                 }
             },
@@ -423,7 +730,8 @@ impl Window {
         let parent = match window_attrs.parent {
             Some(ref w) => w.window as ffi::Window,
             None => {
-                let parent = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
+                // the window must be created on the screen its visual belongs to
+                let parent = unsafe { (display.xlib.XRootWindow)(display.display, screen_id) };
                 display.check_errors().expect("Failed to get root window");
                 parent
             }
@@ -638,11 +946,38 @@ impl Window {
 
         // finish creating the OpenGL context
         let context = match context {
-            Prototype::Glx(ctxt) => {
-                Context::Glx(try!(ctxt.finish(window)))
-            },
+            #[cfg(feature = "glx")]
+            Prototype::Glx(ctxt) => ctxt.finish(window).and_then(|ctxt| {
+                if pl_attribs.require_direct_rendering && !ctxt.is_direct() {
+                    return Err(OsError(format!("The GLX context uses indirect rendering")));
+                }
+                Ok(Context::Glx(ctxt))
+            }),
+            #[cfg(feature = "egl")]
             Prototype::Egl(ctxt) => {
-                Context::Egl(try!(ctxt.finish(window as *const libc::c_void)))
+                ctxt.finish(window as *const libc::c_void).map(Context::Egl)
+            },
+        };
+
+        let context = match context {
+            Ok(context) => context,
+            Err(err) => {
+                // destroying what has been created so far, as the caller may try again with
+                // another API
+                drop(XWindow {
+                    display: display.clone(),
+                    window: window,
+                    im: im,
+                    ic: ic,
+                    context: Context::None,
+                    screen_id: screen_id,
+                    visual_infos: visual_infos,
+                    is_fullscreen: is_fullscreen,
+                    xf86_desk_mode: xf86_desk_mode,
+                    colormap: cmap,
+                    window_proxy_data: Arc::new(Mutex::new(None)),
+                });
+                return Err(err);
             },
         };
 
@@ -664,6 +999,7 @@ impl Window {
                 ic: ic,
                 context: context,
                 screen_id: screen_id,
+                visual_infos: visual_infos,
                 is_fullscreen: is_fullscreen,
                 xf86_desk_mode: xf86_desk_mode,
                 colormap: cmap,
@@ -671,10 +1007,11 @@ impl Window {
             }),
             is_closed: AtomicBool::new(false),
             wm_delete_window: wm_delete_window,
-            current_size: Cell::new((0, 0)),
+            current_size: Mutex::new((0, 0)),
             pending_events: Mutex::new(VecDeque::new()),
             cursor_state: Mutex::new(CursorState::Normal),
-            input_handler: Mutex::new(XInputEventHandler::new(display, window, ic, window_attrs))
+            input_handler: Mutex::new(XInputEventHandler::new(display, window, ic, window_attrs)),
+            failed_versions: Vec::new(),
         };
 
         window.set_title(&window_attrs.title);
@@ -887,6 +1224,92 @@ impl Window {
         self.x.display.display as *mut libc::c_void
     }
 
+    /// Returns the visual that the window has been created with.
+    #[inline]
+    pub fn get_xlib_visual_info(&self) -> &ffi::XVisualInfo {
+        &self.x.visual_infos
+    }
+
+    /// Exports a fence of the commands submitted so far, which only EGL can do.
+    pub fn create_native_fence_fd(&self) -> Result<libc::c_int, ContextError> {
+        match self.x.context {
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.create_native_fence_fd(),
+            _ => Err(ContextError::NotSupported("native fences need an EGL context")),
+        }
+    }
+
+    /// Exports a texture as dma-bufs, which only EGL can do.
+    #[cfg_attr(not(feature = "egl"), allow(unused_variables))]
+    pub fn export_texture_dma_buf(&self, texture: u32, dimensions: (u32, u32))
+                                  -> Result<DmaBufTexture, ContextError>
+    {
+        match self.x.context {
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.export_texture_dma_buf(texture, dimensions),
+            _ => Err(ContextError::NotSupported("exporting textures needs an EGL context")),
+        }
+    }
+
+    /// Imports a texture from dma-bufs, which only EGL can do.
+    #[cfg_attr(not(feature = "egl"), allow(unused_variables))]
+    pub fn import_texture_dma_buf(&self, texture: &DmaBufTexture) -> Result<u32, ContextError> {
+        match self.x.context {
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.import_texture_dma_buf(texture),
+            _ => Err(ContextError::NotSupported("importing textures needs an EGL context")),
+        }
+    }
+
+    /// Imports an external texture from dma-bufs, which only EGL can do.
+    #[cfg_attr(not(feature = "egl"), allow(unused_variables))]
+    pub fn import_external_texture_dma_buf(&self, texture: &DmaBufTexture)
+                                           -> Result<u32, ContextError>
+    {
+        match self.x.context {
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.import_external_texture_dma_buf(texture),
+            _ => Err(ContextError::NotSupported("importing textures needs an EGL context")),
+        }
+    }
+
+    /// Returns whether the GLX context renders directly, or `None` if the context doesn't use
+    /// GLX.
+    #[inline]
+    pub fn is_direct(&self) -> Option<bool> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => Some(ctxt.is_direct()),
+            #[cfg(feature = "egl")]
+            Context::Egl(_) => None,
+            Context::None => None,
+        }
+    }
+
+    /// Returns true if the X server runs on another machine.
+    #[inline]
+    pub fn is_remote_display(&self) -> bool {
+        remote::is_remote_display()
+    }
+
+    /// Returns true if the process runs under VirtualGL.
+    #[inline]
+    pub fn is_virtualgl(&self) -> bool {
+        remote::is_virtualgl()
+    }
+
+    /// Returns the `GLXFBConfig` of the context, or `None` if the context doesn't use GLX.
+    #[inline]
+    pub fn get_glx_fbconfig(&self) -> Option<*mut libc::c_void> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => Some(ctxt.get_fb_config() as *mut libc::c_void),
+            #[cfg(feature = "egl")]
+            Context::Egl(_) => None,
+            Context::None => None,
+        }
+    }
+
     #[inline]
     pub fn get_xlib_window(&self) -> *mut libc::c_void {
         self.x.window as *mut libc::c_void
@@ -897,6 +1320,87 @@ impl Window {
         self.x.window as *mut libc::c_void
     }
 
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.create_pbuffer(dimensions).map(PBuffer::Glx),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.create_pbuffer(dimensions).map(PBuffer::Egl),
+            Context::None => Err(CreationError::NotSupported),
+        }
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer, PixmapSurface>,
+                                             read: Drawable<Window, PBuffer, PixmapSurface>)
+                                             -> Result<(), ContextError>
+    {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => {
+                let draw = try!(draw.map(Window::glx_context, PBuffer::as_glx,
+                                         PixmapSurface::as_glx));
+                let read = try!(read.map(Window::glx_context, PBuffer::as_glx,
+                                         PixmapSurface::as_glx));
+                with_x_error_code(&self.x.display, ctxt.make_current_with_surfaces(draw, read))
+            },
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => {
+                let draw = try!(draw.map(Window::egl_context, PBuffer::as_egl,
+                                         PixmapSurface::as_egl));
+                let read = try!(read.map(Window::egl_context, PBuffer::as_egl,
+                                         PixmapSurface::as_egl));
+                ctxt.make_current_with_surfaces(draw, read)
+            },
+            Context::None if draw.is_default() && read.is_default() => Ok(()),
+            Context::None => {
+                Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                         "the window has no context")))
+            },
+        }
+    }
+
+    #[cfg(feature = "glx")]
+    fn glx_context(&self) -> Result<&GlxContext, ContextError> {
+        match self.x.context {
+            Context::Glx(ref ctxt) => Ok(ctxt),
+            _ => Err(ContextError::NotSupported("the surface uses another API")),
+        }
+    }
+
+    #[cfg(feature = "egl")]
+    fn egl_context(&self) -> Result<&EglContext, ContextError> {
+        match self.x.context {
+            Context::Egl(ref ctxt) => Ok(ctxt),
+            _ => Err(ContextError::NotSupported("the surface uses another API")),
+        }
+    }
+
+    /// The pixmap must outlive the surface, and have the depth of the visual of the window.
+    pub unsafe fn create_pixmap_surface(&self, pixmap: ffi::Pixmap)
+                                        -> Result<PixmapSurface, CreationError>
+    {
+        let surface = match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => {
+                try!(ctxt.create_pixmap_surface(pixmap).map(PixmapSurface::Glx))
+            },
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => {
+                try!(ctxt.create_pixmap_surface(pixmap as *const libc::c_void)
+                         .map(PixmapSurface::Egl))
+            },
+            Context::None => return Err(CreationError::NotSupported),
+        };
+
+        // a pixmap with the wrong depth is only reported asynchronously by the X server
+        (self.x.display.xlib.XSync)(self.x.display.display, 0);
+        if let Err(e) = self.x.display.check_errors() {
+            return Err(OsError(format!("Failed to create the pixmap surface: {}", e)));
+        }
+
+        Ok(surface)
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
     }
@@ -1083,19 +1587,140 @@ impl Window {
         1.0
     }
 
+    /// Returns a function that swaps the buffers from any thread, which only GLX allows.
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => {
+                // the Xlib display must outlive the function
+                let display = self.x.display.clone();
+                let mut swap = ctxt.get_swapper();
+                Some(Box::new(move || {
+                    let _ = &display;
+                    swap()
+                }))
+            },
+            _ => None,
+        }
+    }
+
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
         unsafe {
             (self.x.display.xlib.XWarpPointer)(self.x.display.display, 0, self.x.window, 0, 0, 0, 0, x, y);
             self.x.display.check_errors().map_err(|_| ())
         }
     }
+
+    /// Returns whether the monitor that shows the center of the window supports a variable
+    /// refresh rate, according to the `vrr_capable` property of its RandR outputs. `None` if
+    /// Xrandr isn't available or if the drivers don't report it.
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        let xrandr = match self.x.display.xrandr {
+            Some(ref xrandr) => xrandr,
+            None => return None,
+        };
+
+        unsafe {
+            let display = self.x.display.display;
+            let xlib = &self.x.display.xlib;
+
+            // the atom only exists if a driver created the property
+            let atom = with_c_str("vrr_capable", |name| {
+                (xlib.XInternAtom)(display, name, ffi::True)
+            });
+            if atom == 0 {
+                return None;
+            }
+
+            let root = (xlib.XRootWindow)(display, self.x.screen_id);
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            (xlib.XTranslateCoordinates)(display, self.x.window, root, 0, 0, &mut x, &mut y,
+                                         &mut child);
+            let (width, height) = self.get_inner_size().unwrap_or((0, 0));
+            let center_x = x + width as libc::c_int / 2;
+            let center_y = y + height as libc::c_int / 2;
+
+            let resources = (xrandr.XRRGetScreenResourcesCurrent)(display, root);
+            if resources.is_null() {
+                return None;
+            }
+
+            let mut capable = None;
+            for i in 0 .. (*resources).ncrtc as isize {
+                let crtc_id = *(*resources).crtcs.offset(i);
+                let crtc = (xrandr.XRRGetCrtcInfo)(display, resources, crtc_id);
+                if crtc.is_null() {
+                    continue;
+                }
+
+                let contains_center = center_x >= (*crtc).x && center_y >= (*crtc).y &&
+                                      center_x < (*crtc).x + (*crtc).width as libc::c_int &&
+                                      center_y < (*crtc).y + (*crtc).height as libc::c_int;
+
+                if contains_center {
+                    // a monitor cloned on several outputs needs all of them to support it
+                    for j in 0 .. (*crtc).noutput as isize {
+                        let output = *(*crtc).outputs.offset(j);
+                        let output_capable = get_vrr_capable(&self.x.display, output, atom);
+                        if let Some(output_capable) = output_capable {
+                            capable = Some(capable.unwrap_or(true) && output_capable);
+                        }
+                    }
+                }
+
+                (xrandr.XRRFreeCrtcInfo)(crtc);
+
+                if contains_center {
+                    break;
+                }
+            }
+
+            (xrandr.XRRFreeScreenResources)(resources);
+            capable
+        }
+    }
+}
+
+/// Reads the `vrr_capable` property of a RandR output.
+unsafe fn get_vrr_capable(display: &XConnection, output: ffi::RROutput, atom: ffi::Atom)
+                          -> Option<bool>
+{
+    let xrandr = display.xrandr.as_ref().unwrap();
+
+    let mut actual_type = 0;
+    let mut actual_format = 0;
+    let mut num_items = 0;
+    let mut bytes_after = 0;
+    let mut data = ptr::null_mut();
+    if (xrandr.XRRGetOutputProperty)(display.display, output, atom, 0, 1, ffi::False, ffi::False,
+                                     ffi::AnyPropertyType as ffi::Atom, &mut actual_type,
+                                     &mut actual_format, &mut num_items, &mut bytes_after,
+                                     &mut data) != ffi::Success as libc::c_int
+    {
+        return None;
+    }
+
+    // the data of 32 bits properties is stored as longs by Xlib
+    let capable = if num_items == 1 && actual_format == 32 && !data.is_null() {
+        Some(*(data as *const c_long) != 0)
+    } else {
+        None
+    };
+
+    if !data.is_null() {
+        (display.xlib.XFree)(data as *mut _);
+    }
+
+    capable
 }
 
 impl GlContext for Window {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
         match self.x.context {
-            Context::Glx(ref ctxt) => ctxt.make_current(),
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => with_x_error_code(&self.x.display, ctxt.make_current()),
+            #[cfg(feature = "egl")]
             Context::Egl(ref ctxt) => ctxt.make_current(),
             Context::None => Ok(())
         }
@@ -1104,7 +1729,9 @@ impl GlContext for Window {
     #[inline]
     fn is_current(&self) -> bool {
         match self.x.context {
+            #[cfg(feature = "glx")]
             Context::Glx(ref ctxt) => ctxt.is_current(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref ctxt) => ctxt.is_current(),
             Context::None => panic!()
         }
@@ -1113,7 +1740,9 @@ impl GlContext for Window {
     #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
         match self.x.context {
+            #[cfg(feature = "glx")]
             Context::Glx(ref ctxt) => ctxt.get_proc_address(addr),
+            #[cfg(feature = "egl")]
             Context::Egl(ref ctxt) => ctxt.get_proc_address(addr),
             Context::None => ptr::null()
         }
@@ -1122,7 +1751,9 @@ impl GlContext for Window {
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         match self.x.context {
+            #[cfg(feature = "glx")]
             Context::Glx(ref ctxt) => ctxt.swap_buffers(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref ctxt) => ctxt.swap_buffers(),
             Context::None => Ok(())
         }
@@ -1131,7 +1762,9 @@ impl GlContext for Window {
     #[inline]
     fn get_api(&self) -> Api {
         match self.x.context {
+            #[cfg(feature = "glx")]
             Context::Glx(ref ctxt) => ctxt.get_api(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref ctxt) => ctxt.get_api(),
             Context::None => panic!()
         }
@@ -1140,9 +1773,330 @@ impl GlContext for Window {
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
         match self.x.context {
+            #[cfg(feature = "glx")]
             Context::Glx(ref ctxt) => ctxt.get_pixel_format(),
+            #[cfg(feature = "egl")]
             Context::Egl(ref ctxt) => ctxt.get_pixel_format(),
             Context::None => panic!()
         }
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        let context_failures = match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.get_failed_versions(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.get_failed_versions(),
+            Context::None => panic!()
+        };
+
+        let mut failed = self.failed_versions.clone();
+        failed.extend(context_failures);
+        failed
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.get_config_limits(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.get_config_limits(),
+            Context::None => ConfigLimits::default(),
+        }
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.get_renderer_info(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.get_renderer_info(),
+            Context::None => RendererInfo::default(),
+        }
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.make_not_current(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.make_not_current(),
+            Context::None => Ok(()),
+        }
+    }
+
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => {
+                self.x.display.ignore_error();
+                try!(with_x_error_code(&self.x.display, ctxt.destroy_context()));
+
+                // glXDestroyContext returns nothing, its errors only arrive after a round trip
+                (self.x.display.xlib.XSync)(self.x.display.display, 0);
+                match self.x.display.check_errors() {
+                    Ok(()) => Ok(()),
+                    Err(x_error) => {
+                        let mut error = NativeError {
+                            function: "glXDestroyContext",
+                            code: 0,
+                            name: None,
+                        };
+                        set_x_error_code(&mut error, &x_error);
+                        Err(ContextError::NativeError(error))
+                    },
+                }
+            },
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.destroy_context(),
+            Context::None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.is_back_buffer_preserved(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.is_back_buffer_preserved(),
+            Context::None => false,
+        }
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.is_front_buffer_rendering(),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.is_front_buffer_rendering(),
+            Context::None => false,
+        }
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.set_damage_region(rects),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.set_damage_region(rects),
+            Context::None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.set_hdr_metadata(metadata),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.set_hdr_metadata(metadata),
+            Context::None => {
+                Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                         "the window has no OpenGL context")))
+            },
+        }
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        match self.x.context {
+            #[cfg(feature = "glx")]
+            Context::Glx(ref ctxt) => ctxt.set_swap_interval(interval),
+            #[cfg(feature = "egl")]
+            Context::Egl(ref ctxt) => ctxt.set_swap_interval(interval),
+            Context::None => {
+                Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                         "the window has no OpenGL context")))
+            },
+        }
+    }
+}
+
+impl PBuffer {
+    #[cfg(feature = "glx")]
+    fn as_glx(&self) -> Result<&GlxPBuffer, ContextError> {
+        match *self {
+            PBuffer::Glx(ref p) => Ok(p),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+
+    #[cfg(feature = "egl")]
+    fn as_egl(&self) -> Result<&EglPBuffer, ContextError> {
+        match *self {
+            PBuffer::Egl(ref p) => Ok(p),
+            #[cfg(feature = "glx")]
+            PBuffer::Glx(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "glx")]
+            PBuffer::Glx(ref p) => p.make_current(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.make_current(),
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {
+            #[cfg(feature = "glx")]
+            PBuffer::Glx(ref p) => p.is_current(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.is_current(),
+        }
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        match *self {
+            #[cfg(feature = "glx")]
+            PBuffer::Glx(ref p) => p.get_proc_address(addr),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.get_proc_address(addr),
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "glx")]
+            PBuffer::Glx(ref p) => p.swap_buffers(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {
+            #[cfg(feature = "glx")]
+            PBuffer::Glx(ref p) => p.get_api(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.get_api(),
+        }
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {
+            #[cfg(feature = "glx")]
+            PBuffer::Glx(ref p) => p.get_pixel_format(),
+            #[cfg(feature = "egl")]
+            PBuffer::Egl(ref p) => p.get_pixel_format(),
+        }
+    }
+}
+
+impl PixmapSurface {
+    #[inline]
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        match *self {
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(ref p) => p.get_dimensions(),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(ref p) => p.get_dimensions(),
+        }
+    }
+
+    #[cfg(feature = "glx")]
+    fn as_glx(&self) -> Result<&GlxPixmapSurface, ContextError> {
+        match *self {
+            PixmapSurface::Glx(ref p) => Ok(p),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+
+    #[cfg(feature = "egl")]
+    fn as_egl(&self) -> Result<&EglPixmapSurface, ContextError> {
+        match *self {
+            PixmapSurface::Egl(ref p) => Ok(p),
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(_) => {
+                Err(ContextError::NotSupported("the surface uses another API"))
+            },
+        }
+    }
+}
+
+impl GlContext for PixmapSurface {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(ref p) => p.make_current(),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(ref p) => p.make_current(),
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(ref p) => p.is_current(),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(ref p) => p.is_current(),
+        }
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        match *self {
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(ref p) => p.get_proc_address(addr),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(ref p) => p.get_proc_address(addr),
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(ref p) => p.swap_buffers(),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(ref p) => p.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(ref p) => p.get_api(),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(ref p) => p.get_api(),
+        }
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {
+            #[cfg(feature = "glx")]
+            PixmapSurface::Glx(ref p) => p.get_pixel_format(),
+            #[cfg(feature = "egl")]
+            PixmapSurface::Egl(ref p) => p.get_pixel_format(),
+        }
+    }
 }
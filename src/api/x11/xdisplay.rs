@@ -1,14 +1,26 @@
 use std::ptr;
 use std::fmt;
 use std::error::Error;
+#[cfg(feature = "glx")]
+use std::collections::HashMap;
+#[cfg(feature = "glx")]
+#[cfg(feature = "glx")]
+use std::ffi::CStr;
 use std::ffi::CString;
+#[cfg(feature = "glx")]
+use std::sync::Arc;
 use std::sync::Mutex;
 
 use libc;
 
 use super::ffi;
+#[cfg(feature = "egl")]
 use api::egl::ffi::egl::Egl;
 use api::dlopen;
+#[cfg(feature = "glx")]
+use api::extensions::Extensions;
+use leaks;
+use libraries::{self, Library};
 
 /// A connection to an X server.
 pub struct XConnection {
@@ -16,8 +28,16 @@ pub struct XConnection {
     pub xf86vmode: ffi::Xf86vmode,
     pub xcursor: ffi::Xcursor,
     pub xinput2: ffi::XInput2,
-    pub glx: Option<ffi::glx::Glx>,
-    pub egl: Option<Egl>,
+    /// `None` if libXrandr isn't installed, it is only used for optional queries.
+    pub xrandr: Option<ffi::Xrandr>,
+    // `None` until the library is first needed
+    #[cfg(feature = "glx")]
+    glx: Mutex<Option<Option<ffi::glx::Glx>>>,
+    // the GLX extensions of each screen, queried when first needed
+    #[cfg(feature = "glx")]
+    glx_extensions: Mutex<HashMap<libc::c_int, Arc<Extensions>>>,
+    #[cfg(feature = "egl")]
+    egl: Mutex<Option<Option<Egl>>>,
     pub display: *mut ffi::Display,
     pub latest_error: Mutex<Option<XError>>,
 }
@@ -34,44 +54,11 @@ impl XConnection {
         let xcursor = try!(ffi::Xcursor::open());
         let xf86vmode = try!(ffi::Xf86vmode::open());
         let xinput2 = try!(ffi::XInput2::open());
+        let xrandr = ffi::Xrandr::open().ok();
 
         unsafe { (xlib.XInitThreads)() };
         unsafe { (xlib.XSetErrorHandler)(error_handler) };
 
-        // TODO: use something safer than raw "dlopen"
-        let glx = {
-            let mut libglx = unsafe { dlopen::dlopen(b"libGL.so.1\0".as_ptr() as *const _, dlopen::RTLD_NOW) };
-            if libglx.is_null() {
-                libglx = unsafe { dlopen::dlopen(b"libGL.so\0".as_ptr() as *const _, dlopen::RTLD_NOW) };
-            }
-
-            if libglx.is_null() {
-                None
-            } else {
-                Some(ffi::glx::Glx::load_with(|sym| {
-                    let sym = CString::new(sym).unwrap();
-                    unsafe { dlopen::dlsym(libglx, sym.as_ptr()) }
-                }))
-            }
-        };
-
-        // TODO: use something safer than raw "dlopen"
-        let egl = {
-            let mut libegl = unsafe { dlopen::dlopen(b"libEGL.so.1\0".as_ptr() as *const _, dlopen::RTLD_NOW) };
-            if libegl.is_null() {
-                libegl = unsafe { dlopen::dlopen(b"libEGL.so\0".as_ptr() as *const _, dlopen::RTLD_NOW) };
-            }
-
-            if libegl.is_null() {
-                None
-            } else {
-                Some(Egl::load_with(|sym| {
-                    let sym = CString::new(sym).unwrap();
-                    unsafe { dlopen::dlsym(libegl, sym.as_ptr()) }
-                }))
-            }
-        };
-
         // calling XOpenDisplay
         let display = unsafe {
             let display = (xlib.XOpenDisplay)(ptr::null());
@@ -86,8 +73,13 @@ impl XConnection {
             xf86vmode: xf86vmode,
             xcursor: xcursor,
             xinput2: xinput2,
-            glx: glx,
-            egl: egl,
+            xrandr: xrandr,
+            #[cfg(feature = "glx")]
+            glx: Mutex::new(None),
+            #[cfg(feature = "glx")]
+            glx_extensions: Mutex::new(HashMap::new()),
+            #[cfg(feature = "egl")]
+            egl: Mutex::new(None),
             display: display,
             latest_error: Mutex::new(None),
         })
@@ -110,15 +102,132 @@ impl XConnection {
     pub fn ignore_error(&self) {
         *self.latest_error.lock().unwrap() = None;
     }
+
+    /// Returns the GLX functions, or `None` if GLX is not available.
+    ///
+    /// The GL library is only loaded the first time this is called.
+    #[cfg(feature = "glx")]
+    pub fn glx(&self) -> Option<ffi::glx::Glx> {
+        let mut glx = self.glx.lock().unwrap();
+        if glx.is_none() {
+            *glx = Some(load_glx());
+        }
+        glx.as_ref().unwrap().clone()
+    }
+
+    /// Returns the GLX extensions of `screen`, which are only queried and parsed the first time
+    /// they are needed.
+    #[cfg(feature = "glx")]
+    pub fn glx_extensions(&self, glx: &ffi::glx::Glx, screen: libc::c_int) -> Arc<Extensions> {
+        let mut cache = self.glx_extensions.lock().unwrap();
+        cache.entry(screen).or_insert_with(|| {
+            // This is completely ridiculous, but VirtualBox's OpenGL driver needs some call
+            // handled by *it* (i.e. not Mesa) to occur before anything else can happen. That is
+            // because VirtualBox's OpenGL driver is going to apply binary patches to Mesa in the
+            // DLL constructor and until it's loaded it won't have a chance to do that.
+            //
+            // The easiest way to do this is to just call `glXQueryVersion()` before doing
+            // anything else. See: https://www.virtualbox.org/ticket/8293
+            let (mut major, mut minor) = (0, 0);
+            unsafe {
+                glx.QueryVersion(self.display as *mut _, &mut major, &mut minor);
+
+                let list = glx.QueryExtensionsString(self.display as *mut _, screen);
+                Arc::new(if list.is_null() {
+                    Extensions::default()
+                } else {
+                    Extensions::parse(&CStr::from_ptr(list).to_string_lossy())
+                })
+            }
+        }).clone()
+    }
+
+    /// Returns the EGL functions, or `None` if EGL is not available.
+    ///
+    /// The EGL library is only loaded the first time this is called.
+    #[cfg(feature = "egl")]
+    pub fn egl(&self) -> Option<Egl> {
+        let mut egl = self.egl.lock().unwrap();
+        if egl.is_none() {
+            *egl = Some(load_egl());
+        }
+        egl.as_ref().unwrap().clone()
+    }
 }
 
 impl Drop for XConnection {
     #[inline]
     fn drop(&mut self) {
+        leaks::check_display(self.display as usize);
         unsafe { (self.xlib.XCloseDisplay)(self.display) };
     }
 }
 
+// TODO: use something safer than raw "dlopen"
+#[cfg(feature = "glx")]
+fn load_glx() -> Option<ffi::glx::Glx> {
+    let libglx = open_library(Library::Gl, dlopen::RTLD_NOW);
+
+    if libglx.is_null() {
+        None
+    } else {
+        Some(ffi::glx::Glx::load_with(|sym| {
+            let sym = CString::new(sym).unwrap();
+            unsafe { dlopen::dlsym(libglx, sym.as_ptr()) }
+        }))
+    }
+}
+
+#[cfg(all(feature = "egl", feature = "static-egl"))]
+#[inline]
+fn load_egl() -> Option<Egl> {
+    Some(Egl)
+}
+
+// TODO: use something safer than raw "dlopen"
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+fn load_egl() -> Option<Egl> {
+    if let Some(loader) = libraries::egl_loader() {
+        return Some(Egl::load_with(|sym| loader(sym)));
+    }
+
+    // loading a user-provided GLES library first, so that the EGL implementation
+    // resolves to it instead of the system one
+    open_library(Library::GlesV2, dlopen::RTLD_NOW | dlopen::RTLD_GLOBAL);
+
+    let libegl = open_library(Library::Egl, dlopen::RTLD_NOW);
+
+    if libegl.is_null() {
+        None
+    } else {
+        Some(Egl::load_with(|sym| {
+            let sym = CString::new(sym).unwrap();
+            unsafe { dlopen::dlsym(libegl, sym.as_ptr()) }
+        }))
+    }
+}
+
+/// Opens the first library that can be loaded among the candidates for `library`.
+///
+/// Returns null if none of them could be loaded.
+fn open_library(library: Library, flags: libc::c_int) -> *mut libc::c_void {
+    use std::os::unix::ffi::OsStrExt;
+
+    for path in libraries::candidates(library) {
+        let path = match CString::new(path.as_os_str().as_bytes()) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+
+        let lib = unsafe { dlopen::dlopen(path.as_ptr(), flags) };
+        if !lib.is_null() {
+            return lib;
+        }
+    }
+
+    ptr::null_mut()
+}
+
 /// Error triggered by xlib.
 #[derive(Debug, Clone)]
 pub struct XError {
@@ -149,6 +258,8 @@ pub enum XNotSupported {
     LibraryOpenError(ffi::OpenError),
     /// Connecting to the X server with `XOpenDisplay` failed.
     XOpenDisplayFailed,     // TODO: add better message
+    /// The session doesn't have an X server, and xlib hasn't been loaded.
+    NoX11Session,
 }
 
 impl From<ffi::OpenError> for XNotSupported {
@@ -164,11 +275,12 @@ impl Error for XNotSupported {
         match *self {
             XNotSupported::LibraryOpenError(_) => "Failed to load one of xlib's shared libraries",
             XNotSupported::XOpenDisplayFailed => "Failed to open connection to X server",
+            XNotSupported::NoX11Session => "The DISPLAY environment variable is not set",
         }
     }
 
     #[inline]
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(Error + 'static)> {
         match *self {
             XNotSupported::LibraryOpenError(ref err) => Some(err),
             _ => None
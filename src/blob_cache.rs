@@ -0,0 +1,102 @@
+//! The cache of compiled shaders that the EGL drivers keep through `EGL_ANDROID_blob_cache`.
+//!
+//! The drivers that support the extension hand their compiled shaders and programs to the
+//! application as opaque blobs, and look them up again before compiling anything. Without a
+//! cache, which only the application can provide, every launch compiles all the shaders again.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use cache_file;
+
+/// Returns the blob stored for a key, if any.
+pub type BlobGet = Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync;
+
+/// Stores a blob for a key, replacing the previous one.
+pub type BlobSet = Fn(&[u8], &[u8]) + Send + Sync;
+
+lazy_static! {
+    static ref CALLBACKS: Mutex<Option<(Arc<BlobGet>, Arc<BlobSet>)>> = Mutex::new(None);
+}
+
+/// Makes the EGL displays give the blobs of their drivers to `set`, and look them up with
+/// `get`, for the drivers that support `EGL_ANDROID_blob_cache`.
+///
+/// The functions are called from any thread, including the threads of the driver, and may be
+/// called while a shader is compiled or a program is linked, so they should be fast. They can
+/// lose any blob, the driver then compiles the shader again. A panic aborts the process, as
+/// they are called by the driver.
+///
+/// EGL only accepts the functions once per display, therefore this must be called before the
+/// first window or headless context is built. It has no effect on the displays that have already
+/// been initialized, even once they're terminated and initialized again.
+pub fn set_egl_blob_cache<G, S>(get: G, set: S)
+    where G: Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+          S: Fn(&[u8], &[u8]) + Send + Sync + 'static
+{
+    *CALLBACKS.lock().unwrap() = Some((Arc::new(get), Arc::new(set)));
+}
+
+/// Like `set_egl_blob_cache`, with functions that store each blob in a file of `dir`.
+///
+/// The directory must exist and should be private to the application, for example its cache
+/// directory on Android. A blob whose file can't be read or written is compiled again.
+pub fn set_egl_blob_cache_dir(dir: PathBuf) {
+    let get_dir = dir.clone();
+    set_egl_blob_cache(move |key| load(&get_dir, key), move |key, value| store(&dir, key, value));
+}
+
+/// Like `set_egl_blob_cache_dir`, unless the application has already set functions.
+pub fn set_default_dir(dir: &Path) {
+    let mut callbacks = CALLBACKS.lock().unwrap();
+    if callbacks.is_none() {
+        let (get_dir, set_dir) = (dir.to_owned(), dir.to_owned());
+        *callbacks = Some((Arc::new(move |key: &[u8]| load(&get_dir, key)),
+                           Arc::new(move |key: &[u8], value: &[u8]| store(&set_dir, key, value))));
+    }
+}
+
+/// Returns the functions set with `set_egl_blob_cache`, if any.
+#[allow(dead_code)]
+pub fn callbacks() -> Option<(Arc<BlobGet>, Arc<BlobSet>)> {
+    CALLBACKS.lock().unwrap().clone()
+}
+
+fn load(dir: &PathBuf, key: &[u8]) -> Option<Vec<u8>> {
+    let mut content = Vec::new();
+    let path = cache_file::path(dir, "egl-blob", key);
+    if File::open(path).and_then(|mut f| f.read_to_end(&mut content)).is_err() {
+        return None;
+    }
+
+    // the file starts with the whole key in case two keys have the same hash
+    if content.len() < 4 {
+        return None;
+    }
+    let key_len = content[0] as usize | (content[1] as usize) << 8 | (content[2] as usize) << 16 |
+                  (content[3] as usize) << 24;
+    if content.len() < 4 + key_len || &content[4 .. 4 + key_len] != key {
+        return None;
+    }
+
+    Some(content.split_off(4 + key_len))
+}
+
+fn store(dir: &PathBuf, key: &[u8], value: &[u8]) {
+    let path = cache_file::path(dir, "egl-blob", key);
+    let key_len = key.len() as u32;
+    let header = [key_len as u8, (key_len >> 8) as u8, (key_len >> 16) as u8,
+                  (key_len >> 24) as u8];
+
+    // the driver may look the blob up from another thread while it is written
+    let written = cache_file::write_atomically(&path, |f| {
+        try!(f.write_all(&header));
+        try!(f.write_all(key));
+        f.write_all(value)
+    });
+    if let Err(_err) = written {
+        debug_log!("couldn't store the EGL blob in {:?}: {}", path, _err);
+    }
+}
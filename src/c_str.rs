@@ -0,0 +1,28 @@
+//! Conversion of the names passed to the native functions, without allocating for the short ones.
+//!
+//! `get_proc_address` is called for every function while the bindings are loaded, and some
+//! programs keep calling it afterwards, so its names are copied on the stack instead of into a
+//! `CString`.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+/// The strings that fit in this buffer with their terminator are converted on the stack, which
+/// covers all the OpenGL, EGL, GLX and WGL function names.
+const STACK_LEN: usize = 128;
+
+/// Calls `f` with a nul-terminated copy of `s`.
+///
+/// Panics if `s` contains a nul byte.
+pub fn with_c_str<F, T>(s: &str, f: F) -> T where F: FnOnce(*const c_char) -> T {
+    let bytes = s.as_bytes();
+    if bytes.len() >= STACK_LEN {
+        let c_str = CString::new(bytes).unwrap();
+        return f(c_str.as_ptr());
+    }
+
+    assert!(!bytes.contains(&0), "{:?} contains a nul byte", s);
+    let mut buffer = [0u8; STACK_LEN];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    f(buffer.as_ptr() as *const c_char)
+}
@@ -0,0 +1,36 @@
+//! The files of the caches that the application keeps in its own directories, shared by the
+//! config cache and by the blob cache of the EGL backend.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// the temporary files are numbered, so that two threads never write into the same one
+static NEXT_TEMPORARY: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the path of the file of `key` in `dir`, named after `prefix` and the hash of the key.
+/// The file should contain the whole key too, in case two keys have the same hash.
+pub fn path<K: ?Sized + Hash>(dir: &Path, prefix: &str, key: &K) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    dir.join(format!("{}-{:016x}", prefix, hasher.finish()))
+}
+
+/// Writes the file at `path` with `write`. Other threads and processes may read the file while
+/// it's written, therefore `write` writes a temporary file that is renamed once complete.
+pub fn write_atomically<F>(path: &Path, write: F) -> io::Result<()>
+    where F: FnOnce(&mut File) -> io::Result<()>
+{
+    let temporary = path.with_extension(format!("{}-{}.tmp", process::id(),
+                                                NEXT_TEMPORARY.fetch_add(1, Ordering::Relaxed)));
+    let written = File::create(&temporary).and_then(|mut f| write(&mut f))
+                                          .and_then(|_| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
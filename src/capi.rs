@@ -0,0 +1,293 @@
+//! A C interface for the engines written in C or C++, enabled by the `capi` feature. The
+//! declarations are in `include/glutin.h`.
+//!
+//! The windows and the headless contexts are behind the opaque `GlutinContext` pointer. The
+//! functions that fail return a null pointer or a non-zero value, and `glutin_last_error`
+//! describes the failure. A panic is caught and reported as a failure, as unwinding into C is
+//! undefined behavior.
+//!
+//! Cargo only builds this crate as a Rust library, so embedders link it through a wrapper crate
+//! whose `crate-type` is `staticlib` or `cdylib`, or with
+//! `cargo rustc --release --features capi -- --crate-type staticlib`.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::fmt::Display;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use Event;
+use GlContext;
+use HeadlessContext;
+use HeadlessRendererBuilder;
+use Window;
+use WindowBuilder;
+
+/// A window or a headless context created by `glutin_window_new` or `glutin_headless_new`.
+pub enum GlutinContext {
+    Window(Window),
+    Headless(HeadlessContext),
+}
+
+impl GlutinContext {
+    #[inline]
+    fn context(&self) -> &GlContext {
+        match *self {
+            GlutinContext::Window(ref window) => window,
+            GlutinContext::Headless(ref headless) => headless,
+        }
+    }
+}
+
+thread_local! {
+    // the message of the last failure in this thread, which stays valid until the next one
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error<E>(error: E) where E: Display {
+    // a message can't contain a null byte for C
+    let message = format!("{}", error).replace('\0', " ");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+/// Calls `f`, and returns `failure` if it panics.
+fn guard<T, F>(failure: T, f: F) -> T where F: FnOnce() -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(_) => {
+            set_last_error("glutin panicked");
+            failure
+        },
+    }
+}
+
+/// Returns the context behind `context`, or stores an error and returns `None` if it's null.
+unsafe fn as_context<'a>(context: *mut GlutinContext) -> Option<&'a GlutinContext> {
+    if context.is_null() {
+        set_last_error("the context is null");
+        None
+    } else {
+        Some(&*context)
+    }
+}
+
+/// Returns 0 for `Ok`, and stores the error and returns -1 otherwise.
+fn status<E>(result: Result<(), E>) -> c_int where E: Display {
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            set_last_error(err);
+            -1
+        },
+    }
+}
+
+/// Returns the message of the last failure of a function of this module in this thread, or
+/// null if none failed. The string belongs to glutin and is valid until the next failure.
+#[no_mangle]
+pub extern "C" fn glutin_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map(|m| m.as_ptr()).unwrap_or(ptr::null()))
+}
+
+/// Creates a window of `width` by `height` pixels with an OpenGL context, and returns null if
+/// it fails. `title` is a UTF-8 string, or null for the default title.
+///
+/// # Safety
+///
+/// `title` must be null or point to a string that ends with a null byte.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_window_new(width: u32, height: u32, title: *const c_char)
+                                           -> *mut GlutinContext
+{
+    guard(ptr::null_mut(), || {
+        let mut builder = WindowBuilder::new().with_dimensions(width, height);
+        if !title.is_null() {
+            builder = builder.with_title(CStr::from_ptr(title).to_string_lossy().into_owned());
+        }
+
+        match builder.build() {
+            Ok(window) => Box::into_raw(Box::new(GlutinContext::Window(window))),
+            Err(err) => {
+                set_last_error(err);
+                ptr::null_mut()
+            },
+        }
+    })
+}
+
+/// Creates a headless context that renders into `width` by `height` pixels, and returns null if
+/// it fails.
+#[no_mangle]
+pub extern "C" fn glutin_headless_new(width: u32, height: u32) -> *mut GlutinContext {
+    guard(ptr::null_mut(), || {
+        match HeadlessRendererBuilder::new(width, height).build() {
+            Ok(headless) => Box::into_raw(Box::new(GlutinContext::Headless(headless))),
+            Err(err) => {
+                set_last_error(err);
+                ptr::null_mut()
+            },
+        }
+    })
+}
+
+/// Destroys a context created by this module. Does nothing with null.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet. It
+/// can't be used anymore afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_destroy(context: *mut GlutinContext) {
+    if !context.is_null() {
+        guard((), || drop(Box::from_raw(context)));
+    }
+}
+
+/// Makes the context current in this thread. Returns 0 on success.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_make_current(context: *mut GlutinContext) -> c_int {
+    guard(-1, || match as_context(context) {
+        Some(context) => status(context.context().make_current()),
+        None => -1,
+    })
+}
+
+/// Returns 1 if the context is current in this thread, and 0 otherwise.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_is_current(context: *mut GlutinContext) -> c_int {
+    guard(0, || match as_context(context) {
+        Some(context) if context.context().is_current() => 1,
+        _ => 0,
+    })
+}
+
+/// Swaps the buffers of the context, which must be current. Returns 0 on success.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_swap_buffers(context: *mut GlutinContext) -> c_int {
+    guard(-1, || match as_context(context) {
+        Some(context) => status(context.context().swap_buffers()),
+        None => -1,
+    })
+}
+
+/// Sets the swap interval of the context, see `GlContext::set_swap_interval`. Returns 0 on
+/// success.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_set_swap_interval(context: *mut GlutinContext, interval: c_int)
+                                                  -> c_int
+{
+    guard(-1, || match as_context(context) {
+        Some(context) => status(context.context().set_swap_interval(interval)),
+        None => -1,
+    })
+}
+
+/// Returns the address of the OpenGL function `name`, or null if the context doesn't have it.
+/// The context must be current on some platforms.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet, and
+/// `name` must be null or point to a string that ends with a null byte.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_get_proc_address(context: *mut GlutinContext,
+                                                 name: *const c_char) -> *const c_void
+{
+    guard(ptr::null(), || {
+        let context = match as_context(context) {
+            Some(context) => context,
+            None => return ptr::null(),
+        };
+        if name.is_null() {
+            set_last_error("the name is null");
+            return ptr::null();
+        }
+
+        match CStr::from_ptr(name).to_str() {
+            Ok(name) => context.context().get_proc_address(name) as *const c_void,
+            Err(_) => {
+                set_last_error("the name isn't UTF-8");
+                ptr::null()
+            },
+        }
+    })
+}
+
+/// Stores the dimensions of the inside of the window in pixels. Returns 0 on success, and fails
+/// if the window doesn't exist anymore or if the context is headless.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet, and
+/// `width` and `height` must be null or point to writable integers.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_get_inner_size(context: *mut GlutinContext, width: *mut u32,
+                                               height: *mut u32) -> c_int
+{
+    guard(-1, || {
+        let context = match as_context(context) {
+            Some(context) => context,
+            None => return -1,
+        };
+        if width.is_null() || height.is_null() {
+            set_last_error("the width or the height is null");
+            return -1;
+        }
+
+        let size = match *context {
+            GlutinContext::Window(ref window) => window.get_inner_size_pixels(),
+            GlutinContext::Headless(_) => None,
+        };
+
+        match size {
+            Some((w, h)) => {
+                *width = w;
+                *height = h;
+                0
+            },
+            None => {
+                set_last_error("the context has no window");
+                -1
+            },
+        }
+    })
+}
+
+/// Processes the events of the window that are pending, without waiting. Returns 1 if the
+/// window was asked to close, and 0 otherwise or if the context is headless.
+///
+/// # Safety
+///
+/// `context` must be null or a context that this module created and hasn't destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn glutin_poll_events(context: *mut GlutinContext) -> c_int {
+    guard(0, || match as_context(context) {
+        Some(&GlutinContext::Window(ref window)) => {
+            let mut closed = 0;
+            for event in window.poll_events() {
+                if let Event::Closed = event {
+                    closed = 1;
+                }
+            }
+            closed
+        },
+        Some(&GlutinContext::Headless(_)) | None => 0,
+    })
+}
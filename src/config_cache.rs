@@ -0,0 +1,73 @@
+//! The configs chosen by the EGL backend, remembered across launches in a directory provided by
+//! the application.
+//!
+//! Choosing a config reads the attributes of every config that `eglChooseConfig` returns, which
+//! takes a noticeable part of the startup with some Android drivers. With a cache directory, the
+//! id of the chosen config is stored under a key made of the driver and of the requirements, and
+//! the next launches only check that config again.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use cache_file;
+
+lazy_static! {
+    static ref DIRECTORY: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+/// Makes the EGL backend remember the configs that it chooses in `dir`, or stops it with
+/// `None`.
+///
+/// The directory must exist and should be private to the application, for example its cache
+/// directory on Android. The configs are keyed by the vendor and the version of the driver, by
+/// the pixel format requirements and by the active quirks, therefore an update of the driver or
+/// a change of the quirks just chooses them again.
+/// A cached config is checked against the requirements before being used. Requirements with a
+/// `scoring` function are never cached, as the function may change between builds.
+pub fn set_egl_config_cache_dir(dir: Option<PathBuf>) {
+    *DIRECTORY.lock().unwrap() = dir;
+}
+
+/// Returns the directory set with `set_egl_config_cache_dir`, if any.
+pub fn get_egl_config_cache_dir() -> Option<PathBuf> {
+    DIRECTORY.lock().unwrap().clone()
+}
+
+/// Returns the path of the file of `key`, or `None` without a cache directory.
+fn path(key: &str) -> Option<PathBuf> {
+    get_egl_config_cache_dir().map(|dir| cache_file::path(&dir, "egl-config", key))
+}
+
+/// Returns the id of the config stored for `key`.
+#[allow(dead_code)]
+pub fn load(key: &str) -> Option<i32> {
+    let mut content = String::new();
+    match path(key).map(|p| File::open(p).and_then(|mut f| f.read_to_string(&mut content))) {
+        Some(Ok(_)) => (),
+        _ => return None,
+    }
+
+    // the file starts with the id, followed by the whole key in case two keys have the same
+    // hash
+    let mut lines = content.splitn(2, '\n');
+    match (lines.next().and_then(|id| id.parse().ok()), lines.next()) {
+        (Some(id), Some(stored)) if stored == key => Some(id),
+        _ => None,
+    }
+}
+
+/// Stores the id of the config chosen for `key`. Failures are ignored, the config is chosen
+/// again next time.
+#[allow(dead_code)]
+pub fn store(key: &str, id: i32) {
+    let path = match path(key) {
+        Some(path) => path,
+        None => return,
+    };
+
+    if let Err(_err) = cache_file::write_atomically(&path, |f| write!(f, "{}\n{}", id, key)) {
+        debug_log!("couldn't store the EGL config in {:?}: {}", path, _err);
+    }
+}
@@ -0,0 +1,45 @@
+//! Contexts that share their objects without borrowing each other. See `ContextGroup`.
+
+use std::sync::Arc;
+
+use CreationError;
+use Window;
+use WindowBuilder;
+
+/// A set of contexts that share their textures, buffers and programs.
+///
+/// The objects belong to a hidden window that the group keeps alive, and the windows built with
+/// `WindowBuilder::with_context_group` share with it. Contrary to `with_shared_lists`, the group
+/// isn't borrowed by the windows, so any of them can be destroyed without losing the objects
+/// and new windows can join it at any time.
+///
+/// Cloning a group is cheap and gives another handle to the same group, which can be sent to
+/// other threads. The hidden window is destroyed with the last handle.
+#[derive(Clone)]
+pub struct ContextGroup {
+    root: Arc<Window>,
+}
+
+impl ContextGroup {
+    /// Creates a group whose contexts have the API and the pixel format of `builder`.
+    ///
+    /// The windows of the group should request the same API and pixel format, otherwise some
+    /// platforms refuse to share objects between them.
+    pub fn new(builder: WindowBuilder) -> Result<ContextGroup, CreationError> {
+        let root = try!(builder.with_visibility(false).with_dimensions(1, 1).build());
+        Ok(ContextGroup { root: Arc::new(root) })
+    }
+
+    /// Returns true if both handles refer to the same group.
+    #[inline]
+    pub fn is_same_group(&self, other: &ContextGroup) -> bool {
+        Arc::ptr_eq(&self.root, &other.root)
+    }
+
+    /// Returns the hidden window that the windows of the group share with.
+    #[doc(hidden)]
+    #[inline]
+    pub fn get_root(&self) -> &Window {
+        &self.root
+    }
+}
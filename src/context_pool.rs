@@ -0,0 +1,123 @@
+//! Contexts that share their objects with a window, for worker threads. See `ContextPool`.
+
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex};
+
+use ContextError;
+use CreationError;
+use Window;
+use WindowBuilder;
+
+/// A fixed number of contexts that share their textures, buffers and programs with a primary
+/// window, handed out to the threads that decode and upload resources in parallel.
+///
+/// The contexts belong to hidden windows, which are the only offscreen contexts that can share
+/// with a window on every platform. They should render into framebuffer objects, or not render
+/// at all. The pool is therefore only available on the platforms where a window is `Send`: X11,
+/// win32, Android and Haiku. On macOS and iOS the windows can't leave the main thread.
+///
+/// A context can only be current in one thread at a time. `acquire` makes a free context current
+/// in the calling thread, and the context is released when the returned `PooledContext` is
+/// dropped, which can't happen in another thread. Releasing a context flushes its commands, but
+/// an upload is only guaranteed to be complete for the other contexts once the worker has
+/// called `glFinish` or waited for a fence.
+pub struct ContextPool {
+    free: Mutex<Vec<Window>>,
+    condvar: Condvar,
+    size: usize,
+}
+
+impl ContextPool {
+    /// Creates `size` contexts that share with `primary`, with the attributes of `builder`.
+    ///
+    /// The contexts should request the same API and pixel format as `primary`, otherwise some
+    /// platforms refuse to share objects between them.
+    pub fn new<'a>(primary: &'a Window, size: usize, builder: WindowBuilder<'a>)
+                   -> Result<ContextPool, CreationError>
+    {
+        let builder = builder.with_visibility(false).with_shared_lists(primary);
+
+        let mut contexts = Vec::with_capacity(size);
+        for _ in 0 .. size {
+            contexts.push(try!(builder.clone().build()));
+        }
+
+        Ok(ContextPool {
+            free: Mutex::new(contexts),
+            condvar: Condvar::new(),
+            size: size,
+        })
+    }
+
+    /// Returns the number of contexts of the pool.
+    #[inline]
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// Waits until a context is free and makes it current in this thread.
+    pub fn acquire(&self) -> Result<PooledContext, ContextError> {
+        let mut free = self.free.lock().unwrap();
+        loop {
+            if let Some(window) = free.pop() {
+                drop(free);
+                return self.make_current(window);
+            }
+            free = self.condvar.wait(free).unwrap();
+        }
+    }
+
+    /// Makes a free context current in this thread, or returns `None` if they are all in use.
+    pub fn try_acquire(&self) -> Option<Result<PooledContext, ContextError>> {
+        let window = self.free.lock().unwrap().pop();
+        window.map(|window| self.make_current(window))
+    }
+
+    fn make_current(&self, window: Window) -> Result<PooledContext, ContextError> {
+        if let Err(err) = unsafe { window.make_current() } {
+            self.release(window);
+            return Err(err);
+        }
+
+        Ok(PooledContext {
+            pool: self,
+            window: Some(window),
+            marker: PhantomData,
+        })
+    }
+
+    fn release(&self, window: Window) {
+        self.free.lock().unwrap().push(window);
+        self.condvar.notify_one();
+    }
+}
+
+/// A context of a `ContextPool` that is current in this thread.
+///
+/// The context is released and given back to the pool when this is dropped.
+pub struct PooledContext<'a> {
+    pool: &'a ContextPool,
+    window: Option<Window>,
+    // the context is current in this thread, so this must not be sent to another one
+    marker: PhantomData<*const ()>,
+}
+
+impl<'a> Deref for PooledContext<'a> {
+    type Target = Window;
+
+    #[inline]
+    fn deref(&self) -> &Window {
+        self.window.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledContext<'a> {
+    fn drop(&mut self) {
+        let window = self.window.take().unwrap();
+        // a context that can't be released is given back anyway, the next thread that acquires
+        // it gets the error of `make_current` instead of waiting forever
+        let _ = unsafe { window.make_not_current() };
+        self.pool.release(window);
+    }
+}
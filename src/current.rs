@@ -0,0 +1,44 @@
+//! The context and surfaces that glutin has made current in each thread.
+//!
+//! `eglMakeCurrent` and its equivalents are expensive with some drivers even when nothing
+//! changes, and many programs make their context current every frame. The EGL, GLX and WGL
+//! backends record the binding of their successful calls here and skip the next ones that would
+//! bind the same context and surfaces. Every other call that changes the current context must
+//! forget the binding, and a context that is bound behind the back of glutin needs
+//! `GlContext::make_current_forced`.
+
+use std::cell::Cell;
+
+thread_local!(static CURRENT: Cell<Option<(usize, usize, usize)>> = Cell::new(None));
+
+/// Returns true if `context` has been made current with these surfaces in this thread, and
+/// nothing else since.
+#[inline]
+pub fn is_current(context: usize, draw: usize, read: usize) -> bool {
+    CURRENT.with(|c| c.get() == Some((context, draw, read)))
+}
+
+/// Records a successful call that made `context` current.
+#[inline]
+pub fn set(context: usize, draw: usize, read: usize) {
+    CURRENT.with(|c| c.set(Some((context, draw, read))));
+}
+
+/// Forgets the binding of this thread, after a call that may have changed it.
+#[inline]
+pub fn forget() {
+    CURRENT.with(|c| c.set(None));
+}
+
+/// Forgets the binding of this thread if it uses `object`, a context or a surface that is being
+/// destroyed. Another object could be created with the same handle afterwards.
+#[inline]
+pub fn forget_object(object: usize) {
+    CURRENT.with(|c| {
+        if let Some((context, draw, read)) = c.get() {
+            if context == object || draw == object || read == object {
+                c.set(None);
+            }
+        }
+    });
+}
@@ -0,0 +1,58 @@
+//! Contexts that aren't current in any thread, see `Window::send_to_thread`.
+
+use ContextError;
+use HeadlessContext;
+use Window;
+
+/// A window whose context has been released, to be moved to another thread.
+///
+/// It is `Send` on the platforms where a window may be used from another thread than the one
+/// that created it, which excludes macOS and iOS: their windows belong to the main thread.
+pub struct DetachedWindow {
+    window: Window,
+}
+
+impl DetachedWindow {
+    #[doc(hidden)]
+    #[inline]
+    pub fn new(window: Window) -> DetachedWindow {
+        DetachedWindow { window: window }
+    }
+
+    /// Makes the context current in this thread and returns the window.
+    pub fn attach(self) -> Result<Window, ContextError> {
+        try!(unsafe { self.window.make_current() });
+        Ok(self.window)
+    }
+
+    /// Returns the window without making its context current.
+    #[inline]
+    pub fn into_inner(self) -> Window {
+        self.window
+    }
+}
+
+/// A headless context that has been released, to be moved to another thread.
+pub struct DetachedHeadlessContext {
+    context: HeadlessContext,
+}
+
+impl DetachedHeadlessContext {
+    #[doc(hidden)]
+    #[inline]
+    pub fn new(context: HeadlessContext) -> DetachedHeadlessContext {
+        DetachedHeadlessContext { context: context }
+    }
+
+    /// Makes the context current in this thread and returns it.
+    pub fn attach(self) -> Result<HeadlessContext, ContextError> {
+        try!(unsafe { self.context.make_current() });
+        Ok(self.context)
+    }
+
+    /// Returns the context without making it current.
+    #[inline]
+    pub fn into_inner(self) -> HeadlessContext {
+        self.context
+    }
+}
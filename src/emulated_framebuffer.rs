@@ -0,0 +1,541 @@
+//! A framebuffer object that stands in for the default framebuffer of a headless context, see
+//! `HeadlessRendererBuilder::with_emulated_framebuffer`.
+
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+
+use ContextError;
+use GlContext;
+use PixelFormatRequirements;
+use gl_version::{self, GlVersion};
+
+const FRAMEBUFFER: u32 = 0x8D40;
+const READ_FRAMEBUFFER: u32 = 0x8CA8;
+const DRAW_FRAMEBUFFER: u32 = 0x8CA9;
+const RENDERBUFFER: u32 = 0x8D41;
+const COLOR_ATTACHMENT0: u32 = 0x8CE0;
+const DEPTH_ATTACHMENT: u32 = 0x8D00;
+const STENCIL_ATTACHMENT: u32 = 0x8D20;
+const DEPTH_STENCIL_ATTACHMENT: u32 = 0x821A;
+const FRAMEBUFFER_COMPLETE: u32 = 0x8CD5;
+const DRAW_FRAMEBUFFER_BINDING: u32 = 0x8CA6;
+const READ_FRAMEBUFFER_BINDING: u32 = 0x8CAA;
+const RGBA4: u32 = 0x8056;
+const RGB565: u32 = 0x8D62;
+// `GL_RGBA8_OES` and `GL_DEPTH24_STENCIL8_OES` on OpenGL ES 2.0 have the same values
+const RGBA8: u32 = 0x8058;
+const DEPTH_COMPONENT16: u32 = 0x81A5;
+const DEPTH_COMPONENT24: u32 = 0x81A6;
+const DEPTH24_STENCIL8: u32 = 0x88F0;
+const STENCIL_INDEX8: u32 = 0x8D48;
+const COLOR_BUFFER_BIT: u32 = 0x4000;
+const NEAREST: u32 = 0x2600;
+const TEXTURE_2D: u32 = 0x0DE1;
+const RGBA: u32 = 0x1908;
+const UNSIGNED_BYTE: u32 = 0x1401;
+
+/// The functions of `GL_ARB_framebuffer_object`, which is part of OpenGL 3.0 and OpenGL ES 3.0.
+/// OpenGL ES 2.0 has all of them except multisampling and blitting.
+#[derive(Copy, Clone)]
+struct FramebufferFunctions {
+    get_integer: extern "system" fn(u32, *mut i32),
+    gen_framebuffers: extern "system" fn(i32, *mut u32),
+    bind_framebuffer: extern "system" fn(u32, u32),
+    framebuffer_renderbuffer: extern "system" fn(u32, u32, u32, u32),
+    check_framebuffer_status: extern "system" fn(u32) -> u32,
+    gen_renderbuffers: extern "system" fn(i32, *mut u32),
+    bind_renderbuffer: extern "system" fn(u32, u32),
+    renderbuffer_storage: extern "system" fn(u32, u32, i32, i32),
+    renderbuffer_storage_multisample: Option<extern "system" fn(u32, i32, u32, i32, i32)>,
+    // the function of `GL_NV_framebuffer_multisample_coverage` or of
+    // `GL_AMD_framebuffer_multisample_advanced`, which take the coverage then the color samples
+    renderbuffer_storage_multisample_coverage:
+        Option<extern "system" fn(u32, i32, i32, u32, i32, i32)>,
+    blit_framebuffer:
+        Option<extern "system" fn(i32, i32, i32, i32, i32, i32, i32, i32, u32, u32)>,
+}
+
+/// The functions of `GL_EXT_multisampled_render_to_texture`, and the texture functions that it
+/// needs.
+#[derive(Copy, Clone)]
+struct ImplicitResolveFunctions {
+    gen_textures: extern "system" fn(i32, *mut u32),
+    bind_texture: extern "system" fn(u32, u32),
+    tex_image_2d: extern "system" fn(u32, i32, i32, i32, i32, i32, u32, u32, *const c_void),
+    framebuffer_texture_2d_multisample: extern "system" fn(u32, u32, u32, u32, i32, i32),
+    renderbuffer_storage_multisample: extern "system" fn(u32, i32, u32, i32, i32),
+}
+
+/// The formats of the renderbuffers.
+#[derive(Copy, Clone)]
+struct Formats {
+    color: u32,
+    // a depth buffer, or a packed depth24/stencil8 buffer
+    depth_stencil: Option<u32>,
+    // a separate stencil buffer
+    stencil: Option<u32>,
+    samples: i32,
+    // more than `samples` with coverage sampling, `samples` otherwise
+    coverage_samples: i32,
+}
+
+/// A framebuffer object with color, depth and stencil renderbuffers.
+///
+/// With multisampling, the context draws into a multisampled framebuffer and reads from a
+/// single-sampled one, which `resolve` updates. This is what `swap_buffers` does with the
+/// default framebuffer of a pbuffer.
+///
+/// With `GL_EXT_multisampled_render_to_texture`, which the drivers of tiled GPUs provide, the
+/// color buffer is a single-sampled texture instead, and the samples are resolved implicitly
+/// when the tiles are written back to memory.
+///
+/// The objects belong to the context and are destroyed with it.
+pub struct EmulatedFramebuffer {
+    functions: FramebufferFunctions,
+    formats: Formats,
+    framebuffer: u32,
+    // the color buffer is a texture with an implicit resolve, then the depth and the stencil
+    renderbuffers: (u32, Option<u32>, Option<u32>),
+    resolve: Option<(u32, u32)>,
+    implicit: Option<ImplicitResolveFunctions>,
+    dimensions: (u32, u32),
+    // OpenGL ES 2.0 has no separate read and draw framebuffers, nor
+    // `GL_DEPTH_STENCIL_ATTACHMENT`
+    full: bool,
+}
+
+impl EmulatedFramebuffer {
+    /// Creates the framebuffer with `context`, which must be current, and binds it.
+    pub fn new<C>(context: &C, dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements)
+                  -> Result<EmulatedFramebuffer, ContextError> where C: GlContext
+    {
+        let get_string = context.get_proc_address("glGetString");
+        if get_string.is_null() {
+            return Err(ContextError::NotSupported("glGetString couldn't be loaded"));
+        }
+        let get_string = unsafe { mem::transmute(get_string) };
+
+        let (full, gles) = match unsafe { GlVersion::query(get_string) } {
+            Some(v) if v.gles && v.number >= (3, 0) => (true, true),
+            Some(v) if v.gles && v.number >= (2, 0) => (false, true),
+            Some(v) if v.number >= (3, 0) => (true, false),
+            Some(_) if unsafe {
+                gl_version::has_extension(get_string, "GL_ARB_framebuffer_object")
+            } => (true, false),
+            _ => {
+                return Err(ContextError::NotSupported(
+                           "framebuffer objects need OpenGL 3.0, OpenGL ES 2.0 or \
+                            GL_ARB_framebuffer_object"));
+            },
+        };
+
+        let samples = match pf_reqs.color_samples() {
+            Some(samples) if samples > 1 => samples as i32,
+            _ => 0,
+        };
+        let implicit = samples > 0 && gles && unsafe {
+            gl_version::has_extension(get_string, "GL_EXT_multisampled_render_to_texture")
+        };
+        if samples > 0 && !implicit && !full {
+            return Err(ContextError::NotSupported(
+                       "a multisampled framebuffer object needs OpenGL ES 3.0 or \
+                        GL_EXT_multisampled_render_to_texture"));
+        }
+
+        let has_extension = |name| unsafe { gl_version::has_extension(get_string, name) };
+        let depth = !pf_reqs.depth_bits.accepts(0) || pf_reqs.depth_bits.accepts(24);
+        let stencil = !pf_reqs.stencil_bits.accepts(0);
+        let packed = (depth || stencil) && pf_reqs.packed_depth_stencil != Some(false) &&
+                     (full || has_extension("GL_OES_packed_depth_stencil"));
+        let depth_format = if full || has_extension("GL_OES_depth24") {
+            DEPTH_COMPONENT24
+        } else {
+            DEPTH_COMPONENT16
+        };
+        let mut formats = Formats {
+            // OpenGL ES 2.0 needs `GL_OES_rgb8_rgba8` for 8 bits per component, and desktop
+            // OpenGL only requires RGB565 renderbuffers since 4.1
+            color: if implicit {
+                RGBA8
+            } else if gles && pf_reqs.color_bits.accepts(16) && pf_reqs.alpha_bits.accepts(0) {
+                RGB565
+            } else if gles && !full && !has_extension("GL_OES_rgb8_rgba8") {
+                RGBA4
+            } else {
+                RGBA8
+            },
+            depth_stencil: match (packed, depth) {
+                (true, _) => Some(DEPTH24_STENCIL8),
+                (false, true) => Some(depth_format),
+                (false, false) => None,
+            },
+            stencil: if stencil && !packed { Some(STENCIL_INDEX8) } else { None },
+            samples: samples,
+            coverage_samples: samples,
+        };
+
+        if pf_reqs.strict {
+            let (color_bits, alpha_bits, depth_bits, stencil_bits) = formats.bits();
+            if !(pf_reqs.color_bits.accepts(color_bits) && pf_reqs.alpha_bits.accepts(alpha_bits) &&
+                 pf_reqs.depth_bits.accepts(depth_bits) &&
+                 pf_reqs.stencil_bits.accepts(stencil_bits))
+            {
+                debug_log!("the framebuffer object would have {}/{}/{}/{} bits of color, alpha, \
+                            depth and stencil", color_bits, alpha_bits, depth_bits, stencil_bits);
+                return Err(ContextError::NotSupported(
+                           "the framebuffer object can't have the strict numbers of bits"));
+            }
+        }
+
+        let load = |name| {
+            let address = context.get_proc_address(name);
+            if address.is_null() {
+                debug_log!("{} couldn't be loaded", name);
+            }
+            address
+        };
+        let required = [load("glGetIntegerv"), load("glGenFramebuffers"), load("glBindFramebuffer"),
+                        load("glFramebufferRenderbuffer"), load("glCheckFramebufferStatus"),
+                        load("glGenRenderbuffers"), load("glBindRenderbuffer"),
+                        load("glRenderbufferStorage")];
+        if required.iter().any(|f| f.is_null()) {
+            return Err(ContextError::NotSupported("the framebuffer functions couldn't be loaded"));
+        }
+        let multisample = if full { load("glRenderbufferStorageMultisample") } else { ptr::null() };
+        let blit = if full { load("glBlitFramebuffer") } else { ptr::null() };
+
+        // without one of the extensions, this is multisampling with the color samples
+        let coverage = match pf_reqs.coverage_samples {
+            Some(s) if samples > 0 && s.coverage as i32 > samples && full && !gles => unsafe {
+                if gl_version::has_extension(get_string, "GL_NV_framebuffer_multisample_coverage") {
+                    load("glRenderbufferStorageMultisampleCoverageNV")
+                } else if gl_version::has_extension(get_string,
+                                                    "GL_AMD_framebuffer_multisample_advanced") {
+                    load("glRenderbufferStorageMultisampleAdvancedAMD")
+                } else {
+                    ptr::null()
+                }
+            },
+            _ => ptr::null(),
+        };
+        if let (Some(s), false) = (pf_reqs.coverage_samples, coverage.is_null()) {
+            formats.coverage_samples = s.coverage as i32;
+        }
+
+        let functions = unsafe {
+            FramebufferFunctions {
+                get_integer: mem::transmute(required[0]),
+                gen_framebuffers: mem::transmute(required[1]),
+                bind_framebuffer: mem::transmute(required[2]),
+                framebuffer_renderbuffer: mem::transmute(required[3]),
+                check_framebuffer_status: mem::transmute(required[4]),
+                gen_renderbuffers: mem::transmute(required[5]),
+                bind_renderbuffer: mem::transmute(required[6]),
+                renderbuffer_storage: mem::transmute(required[7]),
+                renderbuffer_storage_multisample:
+                    if multisample.is_null() { None } else { Some(mem::transmute(multisample)) },
+                renderbuffer_storage_multisample_coverage:
+                    if coverage.is_null() { None } else { Some(mem::transmute(coverage)) },
+                blit_framebuffer: if blit.is_null() { None } else { Some(mem::transmute(blit)) },
+            }
+        };
+
+        let implicit = if implicit {
+            let required = [load("glGenTextures"), load("glBindTexture"), load("glTexImage2D"),
+                            load("glFramebufferTexture2DMultisampleEXT"),
+                            load("glRenderbufferStorageMultisampleEXT")];
+            if required.iter().any(|f| f.is_null()) {
+                return Err(ContextError::NotSupported(
+                           "the functions of GL_EXT_multisampled_render_to_texture couldn't be \
+                            loaded"));
+            }
+
+            unsafe {
+                Some(ImplicitResolveFunctions {
+                    gen_textures: mem::transmute(required[0]),
+                    bind_texture: mem::transmute(required[1]),
+                    tex_image_2d: mem::transmute(required[2]),
+                    framebuffer_texture_2d_multisample: mem::transmute(required[3]),
+                    renderbuffer_storage_multisample: mem::transmute(required[4]),
+                })
+            }
+        } else {
+            None
+        };
+
+        if samples > 0 && implicit.is_none() &&
+           (functions.renderbuffer_storage_multisample.is_none() ||
+            functions.blit_framebuffer.is_none())
+        {
+            return Err(ContextError::NotSupported(
+                       "glRenderbufferStorageMultisample couldn't be loaded"));
+        }
+
+        let framebuffer = functions.gen_framebuffer();
+        let color = match implicit {
+            Some(ref f) => {
+                let mut texture = 0;
+                (f.gen_textures)(1, &mut texture);
+                texture
+            },
+            None => functions.gen_renderbuffer(),
+        };
+        let depth_stencil = formats.depth_stencil.map(|_| functions.gen_renderbuffer());
+        let stencil = formats.stencil.map(|_| functions.gen_renderbuffer());
+        let resolve = if samples > 0 && implicit.is_none() {
+            Some((functions.gen_framebuffer(), functions.gen_renderbuffer()))
+        } else {
+            None
+        };
+
+        let mut framebuffer = EmulatedFramebuffer {
+            functions: functions,
+            formats: formats,
+            framebuffer: framebuffer,
+            renderbuffers: (color, depth_stencil, stencil),
+            resolve: resolve,
+            implicit: implicit,
+            dimensions: dimensions,
+            full: full,
+        };
+        if let Err(err) = framebuffer.allocate() {
+            // some drivers of OpenGL ES 2.0 can't combine separate depth and stencil buffers
+            if framebuffer.formats.stencil.is_none() || pf_reqs.strict {
+                return Err(err);
+            }
+            debug_log!("the framebuffer object is incomplete, trying again without stencil");
+            (framebuffer.functions.framebuffer_renderbuffer)(FRAMEBUFFER, STENCIL_ATTACHMENT,
+                                                             RENDERBUFFER, 0);
+            framebuffer.formats.stencil = None;
+            framebuffer.renderbuffers.2 = None;
+            try!(framebuffer.allocate());
+        }
+        framebuffer.bind();
+        Ok(framebuffer)
+    }
+
+    /// Returns the name of the framebuffer object that the context draws into.
+    #[inline]
+    pub fn get_name(&self) -> u32 {
+        self.framebuffer
+    }
+
+    /// Returns the number of samples per pixel, and whether they are resolved implicitly.
+    #[inline]
+    pub fn get_multisampling(&self) -> (Option<u16>, bool) {
+        if self.formats.samples == 0 {
+            return (None, false);
+        }
+        (Some(self.formats.samples as u16), self.implicit.is_some())
+    }
+
+    /// Returns the number of coverage samples per pixel, if there are more than color samples.
+    #[inline]
+    pub fn get_coverage_samples(&self) -> Option<u16> {
+        if self.formats.coverage_samples > self.formats.samples {
+            Some(self.formats.coverage_samples as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the depth and the stencil buffers are a single depth24/stencil8
+    /// renderbuffer.
+    #[inline]
+    pub fn is_depth_stencil_packed(&self) -> bool {
+        self.formats.depth_stencil == Some(DEPTH24_STENCIL8)
+    }
+
+    /// Returns the numbers of bits of color, alpha, depth and stencil that have been allocated,
+    /// which can be less than requested on OpenGL ES 2.0.
+    #[inline]
+    pub fn get_bits(&self) -> (u8, u8, u8, u8) {
+        self.formats.bits()
+    }
+
+    /// Binds the framebuffer for drawing and reading.
+    pub fn bind(&self) {
+        match self.resolve {
+            Some((resolve, _)) => {
+                (self.functions.bind_framebuffer)(DRAW_FRAMEBUFFER, self.framebuffer);
+                (self.functions.bind_framebuffer)(READ_FRAMEBUFFER, resolve);
+            },
+            None => (self.functions.bind_framebuffer)(FRAMEBUFFER, self.framebuffer),
+        }
+    }
+
+    /// Reallocates the renderbuffers with new dimensions. Their content is lost, and the
+    /// framebuffers that were bound stay bound.
+    pub fn resize(&mut self, dimensions: (u32, u32)) -> Result<(), ContextError> {
+        if dimensions == self.dimensions {
+            return Ok(());
+        }
+
+        let bindings = self.bindings();
+        self.dimensions = dimensions;
+        let result = self.allocate();
+        self.restore(bindings);
+        result
+    }
+
+    /// Copies the samples of the multisampled framebuffer into the one that is read from.
+    pub fn resolve(&self) {
+        let resolve = match self.resolve {
+            Some((resolve, _)) => resolve,
+            None => return,
+        };
+
+        let bindings = self.bindings();
+        let (width, height) = (self.dimensions.0 as i32, self.dimensions.1 as i32);
+        (self.functions.bind_framebuffer)(READ_FRAMEBUFFER, self.framebuffer);
+        (self.functions.bind_framebuffer)(DRAW_FRAMEBUFFER, resolve);
+        (self.functions.blit_framebuffer.unwrap())(0, 0, width, height, 0, 0, width, height,
+                                                   COLOR_BUFFER_BIT, NEAREST);
+        self.restore(bindings);
+    }
+
+    /// Returns the framebuffers that are bound for drawing and for reading.
+    fn bindings(&self) -> (u32, u32) {
+        let (mut draw, mut read) = (0, 0);
+        (self.functions.get_integer)(DRAW_FRAMEBUFFER_BINDING, &mut draw);
+        if self.full {
+            (self.functions.get_integer)(READ_FRAMEBUFFER_BINDING, &mut read);
+        } else {
+            read = draw;
+        }
+        (draw as u32, read as u32)
+    }
+
+    /// Binds the framebuffers returned by `bindings` again.
+    fn restore(&self, (draw, read): (u32, u32)) {
+        if self.full {
+            (self.functions.bind_framebuffer)(DRAW_FRAMEBUFFER, draw);
+            (self.functions.bind_framebuffer)(READ_FRAMEBUFFER, read);
+        } else {
+            (self.functions.bind_framebuffer)(FRAMEBUFFER, draw);
+        }
+    }
+
+    /// Allocates the storage of the renderbuffers and checks that the framebuffers are complete.
+    fn allocate(&self) -> Result<(), ContextError> {
+        let functions = &self.functions;
+        let (width, height) = (self.dimensions.0 as i32, self.dimensions.1 as i32);
+        let coverage_samples = self.formats.coverage_samples;
+        let storage = |renderbuffer, format, samples| {
+            (functions.bind_renderbuffer)(RENDERBUFFER, renderbuffer);
+            match (self.implicit, functions.renderbuffer_storage_multisample) {
+                _ if samples > 0 && coverage_samples > samples => {
+                    (functions.renderbuffer_storage_multisample_coverage.unwrap())(
+                        RENDERBUFFER, coverage_samples, samples, format, width, height)
+                },
+                (Some(f), _) if samples > 0 => {
+                    (f.renderbuffer_storage_multisample)(RENDERBUFFER, samples, format, width,
+                                                         height)
+                },
+                (None, Some(f)) if samples > 0 => {
+                    f(RENDERBUFFER, samples, format, width, height)
+                },
+                _ => (functions.renderbuffer_storage)(RENDERBUFFER, format, width, height),
+            }
+            (functions.bind_renderbuffer)(RENDERBUFFER, 0);
+        };
+
+        match self.implicit {
+            Some(f) => {
+                // the unsized format is the only one that OpenGL ES 2.0 accepts for textures
+                (f.bind_texture)(TEXTURE_2D, self.renderbuffers.0);
+                (f.tex_image_2d)(TEXTURE_2D, 0, RGBA as i32, width, height, 0, RGBA,
+                                 UNSIGNED_BYTE, ptr::null());
+                (f.bind_texture)(TEXTURE_2D, 0);
+                (functions.bind_framebuffer)(FRAMEBUFFER, self.framebuffer);
+                (f.framebuffer_texture_2d_multisample)(FRAMEBUFFER, COLOR_ATTACHMENT0, TEXTURE_2D,
+                                                       self.renderbuffers.0, 0,
+                                                       self.formats.samples);
+            },
+            None => {
+                storage(self.renderbuffers.0, self.formats.color, self.formats.samples);
+                (functions.bind_framebuffer)(FRAMEBUFFER, self.framebuffer);
+                (functions.framebuffer_renderbuffer)(FRAMEBUFFER, COLOR_ATTACHMENT0, RENDERBUFFER,
+                                                     self.renderbuffers.0);
+            },
+        }
+        if let (Some(renderbuffer), Some(format)) =
+            (self.renderbuffers.1, self.formats.depth_stencil)
+        {
+            storage(renderbuffer, format, self.formats.samples);
+            match (format, self.full) {
+                (DEPTH24_STENCIL8, true) => {
+                    (functions.framebuffer_renderbuffer)(FRAMEBUFFER, DEPTH_STENCIL_ATTACHMENT,
+                                                         RENDERBUFFER, renderbuffer);
+                },
+                (DEPTH24_STENCIL8, false) => {
+                    (functions.framebuffer_renderbuffer)(FRAMEBUFFER, DEPTH_ATTACHMENT,
+                                                         RENDERBUFFER, renderbuffer);
+                    (functions.framebuffer_renderbuffer)(FRAMEBUFFER, STENCIL_ATTACHMENT,
+                                                         RENDERBUFFER, renderbuffer);
+                },
+                _ => {
+                    (functions.framebuffer_renderbuffer)(FRAMEBUFFER, DEPTH_ATTACHMENT,
+                                                         RENDERBUFFER, renderbuffer);
+                },
+            }
+        }
+        if let (Some(renderbuffer), Some(format)) = (self.renderbuffers.2, self.formats.stencil) {
+            storage(renderbuffer, format, self.formats.samples);
+            (functions.framebuffer_renderbuffer)(FRAMEBUFFER, STENCIL_ATTACHMENT, RENDERBUFFER,
+                                                 renderbuffer);
+        }
+        try!(self.check("the framebuffer object is incomplete"));
+
+        if let Some((resolve, color)) = self.resolve {
+            storage(color, self.formats.color, 0);
+            (functions.bind_framebuffer)(FRAMEBUFFER, resolve);
+            (functions.framebuffer_renderbuffer)(FRAMEBUFFER, COLOR_ATTACHMENT0, RENDERBUFFER,
+                                                 color);
+            try!(self.check("the resolve framebuffer object is incomplete"));
+        }
+
+        Ok(())
+    }
+
+    fn check(&self, message: &'static str) -> Result<(), ContextError> {
+        if (self.functions.check_framebuffer_status)(FRAMEBUFFER) != FRAMEBUFFER_COMPLETE {
+            return Err(ContextError::NotSupported(message));
+        }
+        Ok(())
+    }
+}
+
+impl Formats {
+    /// Returns the numbers of bits of color, alpha, depth and stencil of the formats.
+    fn bits(&self) -> (u8, u8, u8, u8) {
+        let (color, alpha) = match self.color {
+            RGB565 => (16, 0),
+            RGBA4 => (12, 4),
+            _ => (24, 8),
+        };
+        let (depth, packed_stencil) = match self.depth_stencil {
+            Some(DEPTH24_STENCIL8) => (24, 8),
+            Some(DEPTH_COMPONENT24) => (24, 0),
+            Some(_) => (16, 0),
+            None => (0, 0),
+        };
+        let stencil = if self.stencil.is_some() { 8 } else { packed_stencil };
+        (color, alpha, depth, stencil)
+    }
+}
+
+impl FramebufferFunctions {
+    fn gen_framebuffer(&self) -> u32 {
+        let mut name = 0;
+        (self.gen_framebuffers)(1, &mut name);
+        name
+    }
+
+    fn gen_renderbuffer(&self) -> u32 {
+        let mut name = 0;
+        (self.gen_renderbuffers)(1, &mut name);
+        name
+    }
+}
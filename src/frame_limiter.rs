@@ -0,0 +1,60 @@
+//! Caps the frame rate of a window, enabled with `GlContext::set_max_frame_rate`.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Spaces the swaps of a window by at least the interval of the maximum frame rate.
+pub struct FrameLimiter {
+    interval: Duration,
+    deadline: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// Returns `None` if `max_frame_rate` is zero, which means no limit.
+    pub fn new(max_frame_rate: u32) -> Option<FrameLimiter> {
+        if max_frame_rate == 0 {
+            return None;
+        }
+
+        Some(FrameLimiter {
+            interval: Duration::new(1, 0) / max_frame_rate,
+            deadline: None,
+        })
+    }
+
+    /// Waits until the next frame may be presented. Called right before swapping the buffers.
+    pub fn wait(&mut self) {
+        if let Some(deadline) = self.deadline {
+            sleep_until(deadline);
+        }
+
+        // a late frame starts a new schedule, instead of letting the next ones catch up
+        let now = Instant::now();
+        self.deadline = Some(match self.deadline {
+            Some(deadline) if now < deadline + self.interval => deadline + self.interval,
+            _ => now + self.interval,
+        });
+    }
+}
+
+/// Sleeps until `deadline`.
+///
+/// `thread::sleep` can oversleep by a whole tick of the scheduler, which is up to 15ms on
+/// Windows, therefore the last part of the wait yields in a loop instead.
+fn sleep_until(deadline: Instant) {
+    let margin = Duration::from_millis(2);
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+
+        let remaining = deadline - now;
+        if remaining > margin {
+            thread::sleep(remaining - margin);
+        } else {
+            thread::yield_now();
+        }
+    }
+}
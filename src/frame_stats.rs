@@ -0,0 +1,150 @@
+//! Timing of the frames presented by `Window::swap_buffers`, enabled with
+//! `Window::set_frame_stats`.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ContextError;
+
+/// The timing of a single frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// The time between the return of the previous `swap_buffers` and the call to this one,
+    /// which is the time the CPU spent building and submitting the frame.
+    pub submit_time: Duration,
+
+    /// The time spent in `swap_buffers`, which includes the wait for vsync and for the frame
+    /// rate limit.
+    pub swap_time: Duration,
+
+    /// The time between the return of `swap_buffers` and the frame reaching the screen. `None`
+    /// if the platform doesn't report it, which is currently the case everywhere.
+    pub present_time: Option<Duration>,
+}
+
+impl FrameTiming {
+    /// Returns the total duration of the frame, from the end of the previous swap to the end of
+    /// this one.
+    #[inline]
+    pub fn frame_time(&self) -> Duration {
+        self.submit_time + self.swap_time
+    }
+}
+
+/// A summary of the last frames recorded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameStatsSummary {
+    /// The number of frames that the summary covers.
+    pub frames: usize,
+
+    /// The average duration of a frame.
+    pub average_frame_time: Duration,
+
+    /// The duration of the longest frame, which reveals stutter that the average hides.
+    pub max_frame_time: Duration,
+
+    /// The average time the CPU spent building and submitting a frame.
+    pub average_submit_time: Duration,
+
+    /// The average time spent in `swap_buffers`.
+    pub average_swap_time: Duration,
+
+    /// The longest time spent in `swap_buffers`.
+    pub max_swap_time: Duration,
+
+    /// The average presentation time of the frames that have one.
+    pub average_present_time: Option<Duration>,
+}
+
+/// Records the timing of the last `history` frames, at least one.
+pub struct FrameStats {
+    history: usize,
+    frames: VecDeque<FrameTiming>,
+    last_swap_end: Option<Instant>,
+}
+
+impl FrameStats {
+    pub fn new(history: usize) -> FrameStats {
+        let history = cmp::max(history, 1);
+        FrameStats {
+            history: history,
+            frames: VecDeque::with_capacity(history),
+            last_swap_end: None,
+        }
+    }
+
+    /// Calls `swap` and records the timing of the frame that it presents.
+    ///
+    /// The first frame only starts the measurements, as there is no previous swap to measure
+    /// its submit time from.
+    pub fn record<F>(&mut self, swap: F) -> Result<(), ContextError>
+        where F: FnOnce() -> Result<(), ContextError>
+    {
+        let swap_start = Instant::now();
+        let result = swap();
+        let swap_end = Instant::now();
+
+        if let Some(last_swap_end) = self.last_swap_end {
+            if self.frames.len() == self.history {
+                self.frames.pop_front();
+            }
+
+            self.frames.push_back(FrameTiming {
+                submit_time: swap_start - last_swap_end,
+                swap_time: swap_end - swap_start,
+                present_time: None,
+            });
+        }
+
+        self.last_swap_end = Some(swap_end);
+        result
+    }
+
+    /// Returns the recorded frames, from the oldest to the most recent.
+    #[inline]
+    pub fn frames(&self) -> Vec<FrameTiming> {
+        self.frames.iter().cloned().collect()
+    }
+
+    /// Returns a summary of the recorded frames, or `None` if no frame has been recorded yet.
+    pub fn summary(&self) -> Option<FrameStatsSummary> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        let count = self.frames.len() as u32;
+        let mut total_submit = Duration::new(0, 0);
+        let mut total_swap = Duration::new(0, 0);
+        let mut max_frame = Duration::new(0, 0);
+        let mut max_swap = Duration::new(0, 0);
+        let mut total_present = Duration::new(0, 0);
+        let mut presented = 0;
+
+        for frame in &self.frames {
+            total_submit += frame.submit_time;
+            total_swap += frame.swap_time;
+            max_frame = max_frame.max(frame.frame_time());
+            max_swap = max_swap.max(frame.swap_time);
+
+            if let Some(present) = frame.present_time {
+                total_present += present;
+                presented += 1;
+            }
+        }
+
+        Some(FrameStatsSummary {
+            frames: self.frames.len(),
+            average_frame_time: (total_submit + total_swap) / count,
+            max_frame_time: max_frame,
+            average_submit_time: total_submit / count,
+            average_swap_time: total_swap / count,
+            max_swap_time: max_swap,
+            average_present_time: if presented != 0 {
+                Some(total_present / presented)
+            } else {
+                None
+            },
+        })
+    }
+}
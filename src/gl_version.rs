@@ -0,0 +1,64 @@
+//! Version and extensions of the current context, for the features that glutin implements with
+//! OpenGL itself.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+const VERSION: u32 = 0x1F02;
+const EXTENSIONS: u32 = 0x1F03;
+
+/// The signature of `glGetString`.
+pub type GetStringFn = extern "system" fn(u32) -> *const c_char;
+
+/// The version of the current context.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GlVersion {
+    /// True for OpenGL ES.
+    pub gles: bool,
+    /// The `(major, minor)` version.
+    pub number: (u32, u32),
+}
+
+impl GlVersion {
+    /// Queries the version of the current context, or returns `None` if `GL_VERSION` can't be
+    /// parsed.
+    pub unsafe fn query(get_string: GetStringFn) -> Option<GlVersion> {
+        let version = get_string(VERSION);
+        if version.is_null() {
+            return None;
+        }
+        let version = CStr::from_ptr(version).to_string_lossy();
+
+        parse_version(&version).map(|number| GlVersion {
+            gles: version.starts_with("OpenGL ES"),
+            number: number,
+        })
+    }
+}
+
+/// Returns true if the current context has the extension `name`.
+///
+/// The extensions can only be queried with `glGetString` before OpenGL 3.2, which is enough for
+/// the extensions that became core in earlier versions.
+pub unsafe fn has_extension(get_string: GetStringFn, name: &str) -> bool {
+    let extensions = get_string(EXTENSIONS);
+    if extensions.is_null() {
+        return false;
+    }
+    CStr::from_ptr(extensions).to_string_lossy().split(' ').any(|e| e == name)
+}
+
+/// Parses the `major.minor` part of a `GL_VERSION` string, like `4.6.0 NVIDIA 535.54` or
+/// `OpenGL ES 3.2 Mesa 23.1.0`.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let number = match version.split(' ').find(|w| w.starts_with(|c: char| c.is_digit(10))) {
+        Some(n) => n,
+        None => return None,
+    };
+
+    let mut parts = number.split('.').map(|p| p.parse::<u32>().ok());
+    match (parts.next(), parts.next()) {
+        (Some(Some(major)), Some(Some(minor))) => Some((major, minor)),
+        _ => None,
+    }
+}
@@ -1,15 +1,38 @@
 use Api;
+use BitsRequirement;
+use CoverageSamples;
+use ConfigLimits;
+use RendererInfo;
+use Rect;
+use HdrMetadata;
 use ContextError;
+use ContextPriority;
 use CreationError;
+use DetachedHeadlessContext;
+use FailedVersion;
 use GlAttributes;
 use GlProfile;
 use GlRequest;
 use GlContext;
+use PBuffer;
 use PixelFormat;
 use PixelFormatRequirements;
 use Robustness;
+use Surface;
+use adjust_robustness;
+use validate_attributes;
 
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::Mutex;
+
+use current;
+use emulated_framebuffer::EmulatedFramebuffer;
+use libraries;
+use multiview;
 use platform;
+use shader_cache;
 
 /// Object that allows you to build headless contexts.
 #[derive(Clone)]
@@ -25,6 +48,9 @@ pub struct HeadlessRendererBuilder<'a> {
 
     /// Platform-specific configuration.
     platform_specific: platform::PlatformSpecificHeadlessBuilderAttributes,
+
+    // See `with_emulated_framebuffer`.
+    emulated_framebuffer: bool,
 }
 
 impl<'a> HeadlessRendererBuilder<'a> {
@@ -36,6 +62,7 @@ impl<'a> HeadlessRendererBuilder<'a> {
             pf_reqs: Default::default(),
             opengl: Default::default(),
             platform_specific: Default::default(),
+            emulated_framebuffer: false,
         }
     }
 
@@ -70,15 +97,186 @@ impl<'a> HeadlessRendererBuilder<'a> {
         self
     }
 
+    /// Makes the drivers keep the shaders and programs that they compile in `dir`.
+    ///
+    /// See `WindowBuilder::with_shader_cache_dir`.
+    #[inline]
+    pub fn with_shader_cache_dir(mut self, dir: PathBuf) -> HeadlessRendererBuilder<'a> {
+        self.opengl.shader_cache_dir = Some(dir);
+        self
+    }
+
+    /// See `WindowBuilder::with_multiview`.
+    #[inline]
+    pub fn with_multiview(mut self, multiview: bool) -> HeadlessRendererBuilder<'a> {
+        self.opengl.multiview = multiview;
+        self
+    }
+
+    /// See `WindowBuilder::with_context_priority`.
+    #[inline]
+    pub fn with_context_priority(mut self, priority: ContextPriority)
+                                 -> HeadlessRendererBuilder<'a>
+    {
+        self.opengl.priority = Some(priority);
+        self
+    }
+
+    /// Requests a 16 bits RGB565 color buffer without alpha.
+    ///
+    /// See `WindowBuilder::with_rgb565`.
+    #[inline]
+    pub fn with_rgb565(mut self) -> HeadlessRendererBuilder<'a> {
+        self.pf_reqs.color_bits = BitsRequirement::Exact(16);
+        self.pf_reqs.alpha_bits = BitsRequirement::Exact(0);
+        self
+    }
+
+    /// Requests coverage sampling with at least `color` color samples and `coverage` coverage
+    /// samples per pixel.
+    ///
+    /// See `CoverageSamples`.
+    #[inline]
+    pub fn with_coverage_samples(mut self, color: u16, coverage: u16)
+                                 -> HeadlessRendererBuilder<'a>
+    {
+        self.pf_reqs.coverage_samples = Some(CoverageSamples { color: color, coverage: coverage });
+        self
+    }
+
+    /// Sets whether the depth and the stencil buffers must be packed in a single depth24/stencil8
+    /// buffer, or must be separate buffers.
+    ///
+    /// See `PixelFormatRequirements::packed_depth_stencil`.
+    #[inline]
+    pub fn with_packed_depth_stencil(mut self, packed: bool) -> HeadlessRendererBuilder<'a> {
+        self.pf_reqs.packed_depth_stencil = Some(packed);
+        self
+    }
+
+    /// Sets whether context creation must fail instead of using a pixel format that doesn't
+    /// fulfill all the requirements.
+    #[inline]
+    pub fn with_strict_pixel_format(mut self, strict: bool) -> HeadlessRendererBuilder<'a> {
+        self.pf_reqs.strict = strict;
+        self
+    }
+
+    /// Sets whether only the configs that the driver reports as conformant for the requested
+    /// API may be used.
+    #[inline]
+    pub fn with_conformant_pixel_format(mut self, conformant: bool)
+                                        -> HeadlessRendererBuilder<'a>
+    {
+        self.pf_reqs.conformant = conformant;
+        self
+    }
+
+    /// Sets whether a config that the driver reports as slow or non-conformant may be used.
+    #[inline]
+    pub fn with_caveat_allowed(mut self, allow_caveat: bool) -> HeadlessRendererBuilder<'a> {
+        self.pf_reqs.allow_caveat = allow_caveat;
+        self
+    }
+
+    /// Sets the function that ranks the pixel formats. See `PixelFormatRequirements::scoring`.
+    #[inline]
+    pub fn with_pixel_format_scoring(mut self, scoring: fn(&PixelFormat) -> Option<i32>)
+                                     -> HeadlessRendererBuilder<'a>
+    {
+        self.pf_reqs.scoring = Some(scoring);
+        self
+    }
+
+    /// Requests exactly `format`, for example to reproduce the format of a bug report read with
+    /// `PixelFormat::from_descriptor`. This replaces the pixel format requirements set before.
+    ///
+    /// See `PixelFormatRequirements::exactly`.
+    #[inline]
+    pub fn with_exact_pixel_format(mut self, format: &PixelFormat) -> HeadlessRendererBuilder<'a> {
+        self.pf_reqs = PixelFormatRequirements::exactly(format);
+        self
+    }
+
+    /// Sets whether the context renders into a framebuffer object that stands in for its
+    /// default framebuffer.
+    ///
+    /// The framebuffer object has the dimensions of the context and the multisampling, depth and
+    /// stencil buffers of the pixel format requirements. It's created and bound the first time
+    /// the context is made current, and resolved by `swap_buffers` when it's multisampled, so
+    /// that code written for the default framebuffer of a window renders headlessly without
+    /// changes. `set_dimensions` resizes it. Neither changes which framebuffers are bound.
+    ///
+    /// OpenGL ES 2.0 can have fewer bits of color, depth or stencil than requested, depending on
+    /// `GL_OES_rgb8_rgba8`, `GL_OES_depth24` and `GL_OES_packed_depth_stencil`. `get_pixel_format`
+    /// returns the bits that have been allocated, and with `with_strict_pixel_format` making the
+    /// context current fails instead.
+    ///
+    /// On OpenGL ES, the samples are resolved implicitly with
+    /// `GL_EXT_multisampled_render_to_texture` when the driver supports it, which avoids the
+    /// bandwidth of a multisampled buffer on tiled GPUs. `PixelFormat::implicit_resolve` is then
+    /// true once the framebuffer object has been created.
+    ///
+    /// Code that binds the framebuffer `0` to go back to the default framebuffer must bind
+    /// `get_default_framebuffer` instead. The default is `false`.
+    #[inline]
+    pub fn with_emulated_framebuffer(mut self, emulated: bool) -> HeadlessRendererBuilder<'a> {
+        self.emulated_framebuffer = emulated;
+        self
+    }
+
+    /// Chooses the config that `build` would choose, and returns its limits without creating
+    /// the context.
+    ///
+    /// Only the configs of EGL can be chosen without a context, therefore this returns
+    /// `NotSupported` with OSMesa, WGL, CGL, and on the platforms without headless contexts.
+    pub fn query_config_limits(&self) -> Result<ConfigLimits, CreationError> {
+        let mut opengl = self.opengl.clone();
+        try!(validate_attributes(&opengl, &self.pf_reqs));
+        adjust_robustness(&mut opengl);
+        multiview::adjust_request(&mut opengl);
+
+        platform::HeadlessContext::query_config_limits(&self.pf_reqs, &opengl,
+                                                       &self.platform_specific)
+    }
+
     /// Builds the headless context.
     ///
     /// Error should be very rare and only occur in case of permission denied, incompatible system,
     ///  out of memory, etc.
     #[inline]
-    pub fn build(self) -> Result<HeadlessContext, CreationError> {
-        platform::HeadlessContext::new(self.dimensions, &self.pf_reqs, &self.opengl,
-                                       &self.platform_specific)
-                .map(|w| HeadlessContext { context: w })
+    pub fn build(mut self) -> Result<HeadlessContext, CreationError> {
+        try!(validate_attributes(&self.opengl, &self.pf_reqs));
+        adjust_robustness(&mut self.opengl);
+        multiview::adjust_request(&mut self.opengl);
+
+        if let Some(ref dir) = self.opengl.shader_cache_dir {
+            shader_cache::use_dir(dir);
+        }
+
+        let context = try!(platform::HeadlessContext::new(self.dimensions, &self.pf_reqs,
+                                                          &self.opengl,
+                                                          &self.platform_specific));
+        let emulation = if self.emulated_framebuffer {
+            Some(FramebufferEmulation {
+                dimensions: Mutex::new(self.dimensions),
+                pf_reqs: self.pf_reqs,
+                framebuffer: Mutex::new(None),
+            })
+        } else {
+            None
+        };
+
+        let context = HeadlessContext {
+            context: context,
+            emulation: emulation,
+        };
+
+        if self.opengl.multiview {
+            try!(multiview::check(&context));
+        }
+
+        Ok(context)
     }
 
     /// Builds the headless context.
@@ -94,14 +292,116 @@ impl<'a> HeadlessRendererBuilder<'a> {
 /// Represents a headless OpenGL context.
 pub struct HeadlessContext {
     context: platform::HeadlessContext,
+    emulation: Option<FramebufferEmulation>,
+}
+
+/// The framebuffer object of `with_emulated_framebuffer`, created by the first `make_current`.
+struct FramebufferEmulation {
+    dimensions: Mutex<(u32, u32)>,
+    pf_reqs: PixelFormatRequirements,
+    framebuffer: Mutex<Option<EmulatedFramebuffer>>,
 }
 
 impl HeadlessContext {
     /// Creates a new OpenGL context
     /// Sets the context as the current context.
-    #[inline]
+    ///
+    /// With `with_emulated_framebuffer`, the first call also creates and binds the framebuffer
+    /// object. The next calls leave the bound framebuffers untouched.
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.context.make_current()
+        try!(self.context.make_current());
+
+        let emulation = match self.emulation {
+            Some(ref emulation) => emulation,
+            None => return Ok(()),
+        };
+
+        let mut framebuffer = emulation.framebuffer.lock().unwrap();
+        if framebuffer.is_some() {
+            return Ok(());
+        }
+
+        let dimensions = *emulation.dimensions.lock().unwrap();
+        *framebuffer = Some(try!(EmulatedFramebuffer::new(self, dimensions, &emulation.pf_reqs)));
+        Ok(())
+    }
+
+    /// See `GlContext::make_current_forced`.
+    #[inline]
+    pub unsafe fn make_current_forced(&self) -> Result<(), ContextError> {
+        current::forget();
+        self.make_current()
+    }
+
+    /// Returns the name of the framebuffer object that stands in for the default framebuffer,
+    /// or `0` if the context renders into its real default framebuffer.
+    ///
+    /// The framebuffer object only exists once the context has been made current.
+    pub fn get_default_framebuffer(&self) -> u32 {
+        match self.emulation {
+            Some(ref emulation) => {
+                emulation.framebuffer.lock().unwrap().as_ref().map(|f| f.get_name()).unwrap_or(0)
+            },
+            None => 0,
+        }
+    }
+
+    /// Resizes the framebuffer object of `with_emulated_framebuffer`, whose content is lost.
+    ///
+    /// The context must be current if the framebuffer object has been created already. Returns
+    /// an error if the context doesn't emulate its default framebuffer, because the dimensions
+    /// of a real one can't change.
+    pub fn set_dimensions(&self, width: u32, height: u32) -> Result<(), ContextError> {
+        let emulation = match self.emulation {
+            Some(ref emulation) => emulation,
+            None => {
+                return Err(ContextError::NotSupported(
+                           "the default framebuffer of a headless context can't be resized"));
+            },
+        };
+
+        *emulation.dimensions.lock().unwrap() = (width, height);
+        match *emulation.framebuffer.lock().unwrap() {
+            Some(ref mut framebuffer) => framebuffer.resize((width, height)),
+            None => Ok(()),
+        }
+    }
+
+    /// Swaps the buffers of the context, resolving the framebuffer object of
+    /// `with_emulated_framebuffer` first if it's multisampled.
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        if let Some(ref emulation) = self.emulation {
+            if let Some(ref framebuffer) = *emulation.framebuffer.lock().unwrap() {
+                framebuffer.resolve();
+            }
+        }
+        self.context.swap_buffers()
+    }
+
+    /// Releases the context if it's the current context of this thread.
+    ///
+    /// See `GlContext::make_not_current`.
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.context.make_not_current()
+    }
+
+    /// Releases the context so that it can be moved to another thread.
+    ///
+    /// See `Window::send_to_thread`.
+    pub fn send_to_thread(self) -> Result<DetachedHeadlessContext, ContextError> {
+        if self.is_current() {
+            try!(unsafe { self.make_not_current() });
+        }
+        Ok(DetachedHeadlessContext::new(self))
+    }
+
+    /// Destroys the context and returns the errors that `Drop` has to ignore.
+    ///
+    /// See `Window::destroy`.
+    #[inline]
+    pub fn destroy(self) -> Result<(), ContextError> {
+        unsafe { self.context.destroy_context() }
     }
 
     /// Returns true if this context is the current one in this thread.
@@ -113,8 +413,13 @@ impl HeadlessContext {
     /// Returns the address of an OpenGL function.
     ///
     /// Contrary to `wglGetProcAddress`, all available OpenGL functions return an address.
+    ///
+    /// Returns null for the functions that `set_allowed_symbols` doesn't allow.
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
+        if !libraries::is_symbol_allowed(addr) {
+            return ptr::null();
+        }
         self.context.get_proc_address(addr)
     }
 
@@ -126,6 +431,43 @@ impl HeadlessContext {
         self.context.get_api()
     }
 
+    /// Creates an offscreen surface of the given dimensions that renders with this context.
+    ///
+    /// See `Window::create_pbuffer`.
+    #[inline]
+    pub fn create_pbuffer(&self, width: u32, height: u32) -> Result<PBuffer, CreationError> {
+        self.context.create_pbuffer((width, height)).map(|p| PBuffer {
+            pbuffer: p,
+            dimensions: (width, height),
+            marker: PhantomData,
+        })
+    }
+
+    /// Makes this context current, drawing into `draw` and reading from `read`.
+    ///
+    /// See `Window::make_current_with_surfaces`.
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Surface, read: Surface)
+                                             -> Result<(), ContextError>
+    {
+        self.context.make_current_with_surfaces(draw.to_platform(), read.to_platform())
+    }
+
+    /// Swaps the buffers of `surface`, which should be the surface this context draws into.
+    ///
+    /// See `Window::swap_surface_buffers`.
+    #[inline]
+    pub fn swap_surface_buffers(&self, surface: Surface) -> Result<(), ContextError> {
+        match surface {
+            Surface::Default => self.swap_buffers(),
+            Surface::Window(window) => window.swap_buffers(),
+            Surface::PBuffer(pbuffer) => pbuffer.swap_buffers(),
+            #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "netbsd"))]
+            Surface::Pixmap(pixmap) => pixmap.swap_buffers(),
+        }
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
     }
@@ -134,7 +476,7 @@ impl HeadlessContext {
 impl GlContext for HeadlessContext {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.context.make_current()
+        HeadlessContext::make_current(self)
     }
 
     #[inline]
@@ -144,12 +486,12 @@ impl GlContext for HeadlessContext {
 
     #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
-        self.context.get_proc_address(addr)
+        HeadlessContext::get_proc_address(self, addr)
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.context.swap_buffers()
+        HeadlessContext::swap_buffers(self)
     }
 
     #[inline]
@@ -157,9 +499,73 @@ impl GlContext for HeadlessContext {
         self.context.get_api()
     }
 
-    #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        self.context.get_pixel_format()
+        let mut pixel_format = self.context.get_pixel_format();
+        if let Some(ref emulation) = self.emulation {
+            if let Some(ref framebuffer) = *emulation.framebuffer.lock().unwrap() {
+                let (multisampling, implicit_resolve) = framebuffer.get_multisampling();
+                let (color_bits, alpha_bits, depth_bits, stencil_bits) = framebuffer.get_bits();
+                pixel_format.color_bits = color_bits;
+                pixel_format.alpha_bits = alpha_bits;
+                pixel_format.depth_bits = depth_bits;
+                pixel_format.stencil_bits = stencil_bits;
+                pixel_format.multisampling = multisampling;
+                pixel_format.implicit_resolve = implicit_resolve;
+                pixel_format.packed_depth_stencil = framebuffer.is_depth_stencil_packed();
+                pixel_format.coverage_samples = framebuffer.get_coverage_samples();
+            }
+        }
+        pixel_format
+    }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.context.get_failed_versions()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.context.get_config_limits()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.context.get_renderer_info()
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.context.make_not_current()
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        self.context.destroy_context()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.context.is_back_buffer_preserved()
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        self.context.is_front_buffer_rendering()
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.context.set_damage_region(rects)
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        self.context.set_hdr_metadata(metadata)
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        self.context.set_swap_interval(interval)
     }
 }
 
@@ -0,0 +1,120 @@
+//! Detection of leaked contexts and surfaces, enabled with the `leak-detection` feature.
+//!
+//! Every context and surface of the EGL, GLX and WGL backends holds a `Tracker`, which registers
+//! the native object while it's alive. The objects that are still registered when their display
+//! is terminated, or when the process exits, are reported as leaks: with the `log` crate if the
+//! `log` feature is enabled, on the standard error otherwise.
+//!
+//! Without the feature, the tracker is empty and nothing is recorded.
+
+#[cfg(feature = "leak-detection")]
+use std::collections::HashMap;
+#[cfg(feature = "leak-detection")]
+use std::sync::{Mutex, Once};
+#[cfg(feature = "leak-detection")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "leak-detection")]
+use std::thread;
+
+#[cfg(feature = "leak-detection")]
+use libc;
+
+/// A native object that is reported if it's still alive when its display is terminated or when
+/// the process exits. Dropping the tracker forgets the object.
+pub struct Tracker {
+    #[cfg(feature = "leak-detection")]
+    id: usize,
+}
+
+#[cfg(feature = "leak-detection")]
+struct Entry {
+    kind: &'static str,
+    handle: usize,
+    display: usize,
+    thread: Option<String>,
+}
+
+#[cfg(feature = "leak-detection")]
+lazy_static! {
+    static ref LIVE: Mutex<HashMap<usize, Entry>> = Mutex::new(HashMap::new());
+}
+
+#[cfg(feature = "leak-detection")]
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl Tracker {
+    /// Registers `handle`, a native object described by `kind` that has been created on
+    /// `display`. The display is zero for the backends without one, whose objects are only
+    /// checked when the process exits.
+    #[cfg(feature = "leak-detection")]
+    pub fn new(kind: &'static str, handle: usize, display: usize) -> Tracker {
+        static AT_EXIT: Once = Once::new();
+        AT_EXIT.call_once(|| unsafe { libc::atexit(check_at_exit); });
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        LIVE.lock().unwrap().insert(id, Entry {
+            kind: kind,
+            handle: handle,
+            display: display,
+            thread: thread::current().name().map(|n| n.to_owned()),
+        });
+        Tracker { id: id }
+    }
+
+    #[cfg(not(feature = "leak-detection"))]
+    #[inline]
+    pub fn new(_: &'static str, _: usize, _: usize) -> Tracker {
+        Tracker {}
+    }
+
+    /// Forgets the object, which has been destroyed before the tracker is dropped.
+    #[inline]
+    pub fn release(&self) {
+        #[cfg(feature = "leak-detection")]
+        LIVE.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl Drop for Tracker {
+    #[inline]
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Reports the objects of `display` that are still alive. Must be called right before the
+/// display is terminated, which destroys them behind the back of their owners.
+#[inline]
+pub fn check_display(display: usize) {
+    #[cfg(feature = "leak-detection")]
+    {
+        let live = LIVE.lock().unwrap();
+        for entry in live.values().filter(|e| e.display == display) {
+            report(entry, "is still alive when its display is terminated");
+        }
+    }
+    #[cfg(not(feature = "leak-detection"))]
+    let _ = display;
+}
+
+#[cfg(feature = "leak-detection")]
+extern "C" fn check_at_exit() {
+    // a thread that panicked while holding the lock can't be trusted to have left it consistent
+    if let Ok(live) = LIVE.lock() {
+        for entry in live.values() {
+            report(entry, "has never been destroyed");
+        }
+    }
+}
+
+#[cfg(feature = "leak-detection")]
+fn report(entry: &Entry, problem: &str) {
+    let thread = entry.thread.as_ref().map(|n| &n[..]).unwrap_or("<unnamed>");
+    let message = format!("{} {:#x}, created in the thread {}, {}", entry.kind, entry.handle,
+                          thread, problem);
+
+    #[cfg(feature = "log")]
+    warn!("{}", message);
+    #[cfg(not(feature = "log"))]
+    eprintln!("[glutin] leak: {}", message);
+}
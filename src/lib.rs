@@ -13,6 +13,15 @@
 //! The second way allows you to customize the way your window and GL context
 //!  will look and behave.
 //!
+//! # Choosing the API at runtime
+//!
+//! The same binary can create OpenGL contexts and OpenGL ES contexts, for example to let the
+//! user pick a renderer. The API is chosen with `with_gl` for each context, and
+//! `GlContext::get_api` tells which one a context got. The libraries of a backend are only
+//! loaded when the first context that needs them is built, so the libraries of an API that is
+//! never requested don't have to be installed. Contexts of different APIs can't share their
+//! objects.
+//!
 //! # Features
 //!
 //! This crate has two Cargo features: `window` and `headless`.
@@ -22,6 +31,32 @@
 //!     the `HeadlessRendererBuilder` object.
 //!
 //! By default only `window` is enabled.
+//!
+//! When the `log` feature is enabled, the attributes passed to the driver and the pixel format
+//! that it selected are recorded at the debug level with the `log` crate.
+//!
+//! The backends can also be compiled out with the `x11`, `glx`, `egl`, `wgl` and `osmesa`
+//! features, which are all enabled by default. Building without a backend removes its code and
+//! its system library requirements. On X11, at least one of `glx` and `egl` must be enabled.
+//!
+//! The `static-egl` feature links `libEGL` and `libGLESv2` at compile-time instead of loading
+//! them at runtime. The `GLUTIN_EGL_LIB_DIR` environment variable adds a directory to the
+//! linker search path, and `GLUTIN_EGL_LINK_KIND` can be set to `static` to link static
+//! archives instead of shared libraries. With this feature, `set_library_path` and
+//! `set_egl_loader` have no effect on EGL.
+//!
+//! On Linux and FreeBSD, the `angle` feature makes glutin try the `libEGL.so` and `libGLESv2.so`
+//! of an ANGLE build in the directory of the executable before the system EGL, which gives the
+//! same OpenGL ES implementation on every machine. An ANGLE build elsewhere can be used without
+//! the feature by giving its paths to `set_library_path`. See `EglPlatform::Angle`.
+//!
+//! The `leak-detection` feature is meant for debug builds. It records the EGL, GLX and WGL
+//! contexts and surfaces that are alive, and reports the ones that haven't been destroyed when
+//! their display is terminated or when the process exits, with the `log` crate if the `log`
+//! feature is enabled and on the standard error otherwise.
+//!
+//! The Haiku backend is experimental and needs the `haiku` feature, without which Haiku is an
+//! unsupported platform. Its C++ part is compiled by the build script with the `CXX` compiler.
 
 #[macro_use]
 extern crate lazy_static;
@@ -29,6 +64,10 @@ extern crate lazy_static;
 #[macro_use]
 extern crate shared_library;
 
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
 extern crate libc;
 
 #[cfg(target_os = "windows")]
@@ -54,33 +93,110 @@ extern crate cocoa;
 extern crate core_foundation;
 #[cfg(target_os = "macos")]
 extern crate core_graphics;
-#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#[cfg(all(feature = "x11",
+          any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd")))]
 extern crate x11_dl;
 #[cfg(all(feature = "image",
-          any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd")))]
+          any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd")))]
 extern crate image;
 
+pub use blob_cache::{set_egl_blob_cache, set_egl_blob_cache_dir};
+pub use config_cache::{get_egl_config_cache_dir, set_egl_config_cache_dir};
+pub use context_group::ContextGroup;
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+          target_os = "openbsd", target_os = "netbsd", target_os = "windows",
+          target_os = "android", target_os = "haiku"))]
+pub use context_pool::{ContextPool, PooledContext};
+pub use detached::{DetachedHeadlessContext, DetachedWindow};
 pub use events::*;
+pub use frame_stats::{FrameStatsSummary, FrameTiming};
 pub use headless::{HeadlessRendererBuilder, HeadlessContext};
 pub use window::{WindowProxy, PollEventsIterator, WaitEventsIterator};
 pub use window::{AvailableMonitorsIter, MonitorId, get_available_monitors, get_primary_monitor};
 pub use native_monitor::NativeMonitorId;
+pub use resilient::ResilientContext;
+pub use swap_token::SwapToken;
+#[cfg(feature = "virtual-contexts")]
+pub use virtual_context::{VirtualContext, VirtualContextHost};
+pub use libraries::{Library, get_library_path, set_library_path, set_egl_loader};
+pub use libraries::{get_glx_vendor, set_glx_vendor};
+pub use libraries::{is_library_loading_sealed, is_symbol_allowed, seal_library_loading};
+pub use libraries::set_allowed_symbols;
 
 use std::io;
+use std::marker::PhantomData;
 #[cfg(not(target_os = "macos"))]
 use std::cmp::Ordering;
 use std::path::PathBuf;
+use std::sync::Mutex;
+
+// Emits a debug record with the `log` crate, or nothing if the `log` feature is disabled.
+#[cfg(feature = "log")]
+macro_rules! debug_log {
+    ($($arg:tt)*) => (debug!($($arg)*))
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! debug_log {
+    ($($arg:tt)*) => (())
+}
 
 mod api;
 mod platform;
+mod blob_cache;
+mod c_str;
+mod cache_file;
+mod config_cache;
+mod context_group;
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+          target_os = "openbsd", target_os = "netbsd", target_os = "windows",
+          target_os = "android", target_os = "haiku"))]
+mod context_pool;
+mod current;
+mod detached;
+mod emulated_framebuffer;
 mod events;
+mod frame_limiter;
+mod frame_stats;
+mod gl_version;
 mod headless;
+mod leaks;
+mod libraries;
+mod multiview;
+mod pbuffer;
+mod presenter;
+mod resilient;
+mod shader_cache;
+mod surface;
+mod swap_token;
+#[cfg(feature = "virtual-contexts")]
+mod virtual_context;
 mod window;
 
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod os;
+pub mod quirks;
 
 /// Represents an OpenGL context and the Window or environment around it.
 ///
+/// # Threads
+///
+/// A window is `Send` and `Sync` on the platforms whose windowing API is thread-safe: X11, win32
+/// and Haiku. On Android it is only `Send`, because its events are received through a channel,
+/// and on macOS and iOS it is neither, because AppKit and UIKit must be used from the main
+/// thread. The state of the EGL, GLX and WGL contexts that changes after their creation is
+/// atomic or behind a mutex, which is what makes them `Sync`.
+///
+/// A headless context is `Send` everywhere, and `Sync` everywhere except on macOS, where an
+/// NSOpenGLContext can't be used from two threads at once without `CGLLockContext`.
+///
+/// A context can only be current in one thread at a time, and most drivers refuse to make a
+/// context current while it's current in another thread. `send_to_thread` releases the context
+/// before the window is moved, and `DetachedWindow::attach` makes it current in the new thread.
+///
 /// # Example
 ///
 /// ```ignore
@@ -103,7 +219,19 @@ pub mod os;
 /// }
 /// ```
 pub struct Window {
+    // declared first, because the thread must stop swapping before the window is destroyed
+    swap: Mutex<SwapState>,
     window: platform::Window,
+    fence_functions: Mutex<Option<swap_token::FenceFunctions>>,
+}
+
+/// What `Window::swap_buffers` does besides swapping, behind a single lock so that a frame
+/// only locks once.
+#[derive(Default)]
+struct SwapState {
+    presenter: Option<presenter::Presenter>,
+    frame_stats: Option<frame_stats::FrameStats>,
+    frame_limiter: Option<frame_limiter::FrameLimiter>,
 }
 
 /// Object that allows you to build windows.
@@ -120,11 +248,122 @@ pub struct WindowBuilder<'a> {
 
     /// Platform-specific configuration.
     platform_specific: platform::PlatformSpecificWindowBuilderAttributes,
+
+    /// The group whose objects the window shares, instead of `opengl.sharing`.
+    group: Option<ContextGroup>,
+
+    /// Whether the build fails if `swap_buffers_async` isn't supported.
+    fence_sync: bool,
+}
+
+/// An offscreen surface that renders with the OpenGL context of the window or headless context
+/// that created it.
+///
+/// Making the pbuffer current binds the context of its parent to the pbuffer, therefore the
+/// textures, buffers and programs of the parent can be used while drawing into the pbuffer, and
+/// what has been drawn can be copied into a texture with `glCopyTexSubImage2D`. This is useful on
+/// drivers that don't support framebuffer objects.
+///
+/// Created with `Window::create_pbuffer` or `HeadlessContext::create_pbuffer`.
+pub struct PBuffer<'a> {
+    pbuffer: platform::PBuffer,
+    dimensions: (u32, u32),
+    marker: PhantomData<&'a ()>,
+}
+
+/// A surface that renders into an X `Pixmap` with the OpenGL context of the window that
+/// created it.
+///
+/// Created with `os::unix::WindowExt::create_pixmap_surface`.
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+          target_os = "openbsd", target_os = "netbsd"))]
+pub struct PixmapSurface<'a> {
+    surface: platform::PixmapSurface,
+    marker: PhantomData<&'a ()>,
+}
+
+/// A texture exported as dma-bufs, which another process can import after receiving the file
+/// descriptors of its planes, for example through a Unix socket with `SCM_RIGHTS`.
+///
+/// Created with `os::unix::WindowExt::export_texture_dma_buf`.
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+          target_os = "openbsd", target_os = "netbsd"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DmaBufTexture {
+    /// The width and the height of the texture, in pixels.
+    pub dimensions: (u32, u32),
+
+    /// The DRM fourcc code of the format of the pixels, like `DRM_FORMAT_ABGR8888`.
+    pub fourcc: u32,
+
+    /// The DRM modifier that describes the tiling of the planes, or `None` if the driver
+    /// doesn't report it, in which case the importer has to guess it.
+    pub modifier: Option<u64>,
+
+    /// Between one and four planes, depending on the format.
+    pub planes: Vec<DmaBufPlane>,
+}
+
+/// A plane of a `DmaBufTexture`.
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+          target_os = "openbsd", target_os = "netbsd"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmaBufPlane {
+    /// The file descriptor of the dma-buf that holds the plane.
+    pub fd: std::os::unix::io::RawFd,
+
+    /// The offset of the plane in the dma-buf, in bytes.
+    pub offset: u32,
+
+    /// The number of bytes between the starts of two rows.
+    pub stride: u32,
+}
+
+/// Something that OpenGL can draw into: a window, a pbuffer or a pixmap.
+///
+/// The surfaces are owned by the objects they borrow, but any of them can be used with the
+/// context of another window through `Window::make_current_with_surfaces`.
+///
+/// A window still owns both its surface and its context, and handles the loss of its surface on
+/// Android itself.
+#[derive(Clone, Copy)]
+pub enum Surface<'a> {
+    /// The surface that the context has been created with, which is the window itself or the
+    /// buffer of the headless context.
+    Default,
+    /// The surface of a window, which lets a single context draw into several windows.
+    Window(&'a Window),
+    /// A pbuffer.
+    PBuffer(&'a PBuffer<'a>),
+    /// A surface that renders into an X pixmap.
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+              target_os = "openbsd", target_os = "netbsd"))]
+    Pixmap(&'a PixmapSurface<'a>),
+}
+
+impl<'a> Surface<'a> {
+    /// Returns the platform objects behind the surface.
+    #[inline]
+    fn to_platform(&self)
+                   -> api::Drawable<'a, platform::Window, platform::PBuffer, platform::PixmapSurface>
+    {
+        match *self {
+            Surface::Default => api::Drawable::Default,
+            Surface::Window(window) => api::Drawable::Window(&window.window),
+            Surface::PBuffer(pbuffer) => api::Drawable::PBuffer(&pbuffer.pbuffer),
+            #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "netbsd"))]
+            Surface::Pixmap(pixmap) => api::Drawable::Pixmap(&pixmap.surface),
+        }
+    }
 }
 
 /// Trait that describes objects that have access to an OpenGL context.
 pub trait GlContext {
     /// Sets the context as the current context.
+    ///
+    /// With EGL, GLX and WGL, nothing is done if glutin has already made this context current in
+    /// this thread with the same surfaces. See `make_current_forced`.
     unsafe fn make_current(&self) -> Result<(), ContextError>;
 
     /// Returns true if this context is the current one in this thread.
@@ -148,6 +387,122 @@ pub trait GlContext {
 
     /// Returns the pixel format of the main framebuffer of the context.
     fn get_pixel_format(&self) -> PixelFormat;
+
+    /// Returns the versions that were tried, and failed, before the version of this context
+    /// could be created. This is how `GlRequest::Latest` and `GlRequest::Range` end up with an
+    /// older version than expected.
+    ///
+    /// Empty if the first attempt succeeded, or if the backend lets the driver choose the
+    /// version.
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        Vec::new()
+    }
+
+    /// Returns the limits of the config that the context has been created with.
+    ///
+    /// `HeadlessRendererBuilder::query_config_limits` returns them before the context exists,
+    /// with EGL only. There is no such query for the windows, whose config depends on the native
+    /// window.
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        ConfigLimits::default()
+    }
+
+    /// Returns information about the driver and the GPU.
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        RendererInfo::default()
+    }
+
+    /// Releases the context if it's the current context of this thread, which lets another
+    /// thread make it current. Does nothing if another context is current.
+    ///
+    /// Returns an error if the backend can't release its contexts.
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        Err(ContextError::NotSupported("releasing the context isn't supported by this backend"))
+    }
+
+    /// Sets the context as the current context, even if glutin believes it already is.
+    ///
+    /// This is needed after another library has changed the current context of this thread
+    /// without glutin, for example by calling `eglMakeCurrent` directly.
+    #[inline]
+    unsafe fn make_current_forced(&self) -> Result<(), ContextError> {
+        current::forget();
+        self.make_current()
+    }
+
+    /// Releases the context if it's current in this thread, then destroys the context and its
+    /// surfaces and returns the errors of the driver. The context must not be used afterwards,
+    /// and dropping it doesn't destroy anything a second time.
+    ///
+    /// Backends that don't report errors leave everything to the destructor and return `Ok`.
+    #[doc(hidden)]
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        Ok(())
+    }
+
+    /// Returns true if the content of the back buffer survives `swap_buffers`. See
+    /// `PixelFormatRequirements::preserve_back_buffer`.
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        false
+    }
+
+    /// Tells the platform which parts of the back buffer the next frame is going to modify,
+    /// which lets tile-based GPUs avoid reloading the others. Must be called after making the
+    /// context current and before rendering the frame, the region is reset by `swap_buffers`.
+    ///
+    /// The content of the back buffer outside of the region is undefined afterwards, unless the
+    /// back buffer is preserved. Backends that don't support it ignore the region.
+    #[inline]
+    fn set_damage_region(&self, _rects: &[Rect]) -> Result<(), ContextError> {
+        Ok(())
+    }
+
+    /// Returns true if the context draws directly into the buffer that is displayed. See
+    /// `PixelFormatRequirements::front_buffer_rendering`.
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        false
+    }
+
+    /// Caps the number of frames per second that `swap_buffers` presents by sleeping before
+    /// swapping, which saves power in menus and background windows. `None` or `Some(0)` removes
+    /// the limit.
+    ///
+    /// Only windows are limited, this does nothing for the other contexts.
+    #[inline]
+    fn set_max_frame_rate(&self, _max_frame_rate: Option<u32>) {
+    }
+
+    /// Attaches HDR metadata to the surface, which applies to the frames presented afterwards.
+    ///
+    /// This needs `EGL_EXT_surface_SMPTE2086_metadata`, and `EGL_EXT_surface_CTA861_3_metadata`
+    /// for the light levels. WGL has no equivalent. Returns an error if the backend doesn't
+    /// support it.
+    #[inline]
+    fn set_hdr_metadata(&self, _metadata: &HdrMetadata) -> Result<(), ContextError> {
+        Err(ContextError::NotSupported("HDR metadata isn't supported by this backend"))
+    }
+
+    /// Sets the number of refreshes of the display that `swap_buffers` waits for: 0 disables
+    /// vsync, 1 enables it, 2 presents at half the refresh rate, and so on.
+    /// `ConfigLimits::swap_interval_range` tells which intervals the platform accepts.
+    ///
+    /// A negative interval enables adaptive vsync: `swap_buffers` waits like with the opposite
+    /// interval, unless the frame is late, in which case it's presented right away and may
+    /// tear. This needs `GLX_EXT_swap_control_tear` or `WGL_EXT_swap_control_tear`.
+    ///
+    /// The context must be current. Returns an error if the backend can't set this interval,
+    /// instead of presenting with another one.
+    #[inline]
+    fn set_swap_interval(&self, _interval: i32) -> Result<(), ContextError> {
+        Err(ContextError::NotSupported("the swap interval can't be changed with this backend"))
+    }
 }
 
 /// Error that can happen while creating a window or a headless renderer.
@@ -160,12 +515,61 @@ pub enum CreationError {
     RobustnessNotSupported,
     OpenGlVersionNotSupported,
     NoAvailablePixelFormat,
+    TransparencyNotSupported,
+    /// None of the versions that glutin tried could be created. Contains every attempt, in
+    /// order.
+    NoVersionAvailable(Vec<FailedVersion>),
+    /// A function of EGL, GLX or WGL failed with an error code.
+    NativeError(NativeError),
+    /// The attributes of the builder contradict each other. Contains the explanation.
+    InvalidAttributes(String),
+}
+
+/// An error code returned by EGL, GLX or WGL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeError {
+    /// The function that failed, for example `"eglSwapBuffers"`.
+    pub function: &'static str,
+    /// The code of the error: the value returned by `eglGetError` with EGL, the X error code
+    /// with GLX and the value returned by `GetLastError` with WGL. Zero if the function failed
+    /// without reporting a code.
+    pub code: u32,
+    /// The name of the code, for example `"EGL_BAD_SURFACE"`. `None` if glutin doesn't know it.
+    pub name: Option<&'static str>,
+}
+
+impl std::error::Error for NativeError {
+    fn description(&self) -> &str {
+        "A function of the platform failed"
+    }
+}
+
+impl std::fmt::Display for NativeError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self.name {
+            Some(name) => write!(formatter, "{} failed with {} (0x{:x})", self.function, name,
+                                 self.code),
+            None => write!(formatter, "{} failed with 0x{:x}", self.function, self.code),
+        }
+    }
+}
+
+/// A version of OpenGL or OpenGL ES that glutin failed to create a context with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedVersion {
+    /// The API that was tried.
+    pub api: Api,
+    /// The version that was tried.
+    pub version: (u8, u8),
+    /// Why the context couldn't be created.
+    pub reason: String,
 }
 
 impl CreationError {
     fn to_string(&self) -> &str {
         match *self {
             CreationError::OsError(ref text) => &text,
+            CreationError::InvalidAttributes(ref text) => &text,
             CreationError::NotSupported => "Some of the requested attributes are not supported",
             CreationError::NoBackendAvailable(_) => "No backend is available",
             CreationError::RobustnessNotSupported => "You requested robustness, but it is \
@@ -174,13 +578,32 @@ impl CreationError {
                                                          supported.",
             CreationError::NoAvailablePixelFormat => "Couldn't find any pixel format that matches \
                                                       the criterias.",
+            CreationError::TransparencyNotSupported => "You requested a transparent window, but \
+                                                        the platform can't compose it.",
+            CreationError::NoVersionAvailable(_) => "None of the OpenGL versions that were tried \
+                                                     could be created.",
+            CreationError::NativeError(_) => "A function of the platform failed",
         }
     }
 }
 
 impl std::fmt::Display for CreationError {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        formatter.write_str(self.to_string())
+        // the native errors are described entirely by their own message
+        if let CreationError::NativeError(ref error) = *self {
+            return write!(formatter, "{}", error);
+        }
+
+        try!(formatter.write_str(self.to_string()));
+
+        if let CreationError::NoVersionAvailable(ref failed) = *self {
+            for attempt in failed {
+                try!(write!(formatter, " {:?} {}.{}: {}", attempt.api, attempt.version.0,
+                            attempt.version.1, attempt.reason));
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -189,7 +612,9 @@ impl std::error::Error for CreationError {
         self.to_string()
     }
 
-    fn cause(&self) -> Option<&std::error::Error> {
+    /// Returns the error of the backends with `NoBackendAvailable`, which isn't part of the
+    /// message of this error.
+    fn source(&self) -> Option<&(std::error::Error + 'static)> {
         match *self {
             CreationError::NoBackendAvailable(ref err) => Some(&**err),
             _ => None
@@ -201,7 +626,17 @@ impl std::error::Error for CreationError {
 #[derive(Debug)]
 pub enum ContextError {
     IoError(io::Error),
+    /// The context has been lost, with all its textures, buffers and programs. It must be
+    /// destroyed and created again, see `ResilientContext`.
     ContextLost,
+    /// The surface of the context has been destroyed, for example because an Android application
+    /// has been suspended, but the objects of the context are still valid. Only the surface
+    /// needs to be created again, once the native window is available.
+    SurfaceLost,
+    /// A function of EGL, GLX or WGL failed with an error code.
+    NativeError(NativeError),
+    /// The platform, the driver or the kind of context or surface doesn't support the operation.
+    NotSupported(&'static str),
 }
 
 impl ContextError {
@@ -209,14 +644,24 @@ impl ContextError {
         use std::error::Error;
         match *self {
             ContextError::IoError(ref err) => err.description(),
-            ContextError::ContextLost => "Context lost"
+            ContextError::ContextLost => "Context lost",
+            ContextError::SurfaceLost => "Surface lost",
+            ContextError::NativeError(_) => "A function of the platform failed",
+            ContextError::NotSupported(text) => text,
         }
     }
 }
 
 impl std::fmt::Display for ContextError {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        formatter.write_str(self.to_string())
+        match *self {
+            ContextError::IoError(ref error) => write!(formatter, "{}", error),
+            ContextError::NativeError(ref error) => write!(formatter, "{}", error),
+            ContextError::ContextLost | ContextError::SurfaceLost |
+            ContextError::NotSupported(_) => {
+                formatter.write_str(self.to_string())
+            },
+        }
     }
 }
 
@@ -224,6 +669,17 @@ impl std::error::Error for ContextError {
     fn description(&self) -> &str {
         self.to_string()
     }
+
+    /// The I/O and native errors are displayed as this error, therefore this returns their
+    /// own source.
+    fn source(&self) -> Option<&(std::error::Error + 'static)> {
+        match *self {
+            ContextError::IoError(ref error) => error.source(),
+            ContextError::NativeError(ref error) => error.source(),
+            ContextError::ContextLost | ContextError::SurfaceLost |
+            ContextError::NotSupported(_) => None,
+        }
+    }
 }
 
 /// All APIs related to OpenGL that you can possibly get while using glutin.
@@ -262,23 +718,226 @@ pub enum GlRequest {
     /// If OpenGL is available, create an OpenGL context with the specified `opengl_version`.
     /// Else if OpenGL ES or WebGL is available, create a context with the
     /// specified `opengles_version`.
+    ///
+    /// OpenGL is also considered unavailable when the platform provides it but creating the
+    /// context fails, which is common on ARM boards and in virtual machines. Use `get_api()` to
+    /// know which API the context uses.
     GlThenGles {
         /// The version to use for OpenGL.
         opengl_version: (u8, u8),
         /// The version to use for OpenGL ES.
         opengles_version: (u8, u8),
     },
+
+    /// Request the most recent version of `api` between `min` and `max`, both included. If
+    /// `max` is `None`, there is no upper bound.
+    ///
+    /// Example: `GlRequest::Range { api: Api::OpenGlEs, min: (3, 2), max: None }` creates an
+    /// OpenGL ES 3.2 context or better, but never an OpenGL ES 2.0 one.
+    ///
+    /// The minor version can only be enforced when the platform lets glutin pass it, which
+    /// requires `EGL_KHR_create_context` (or EGL 1.5), `GLX_ARB_create_context` or
+    /// `WGL_ARB_create_context`.
+    Range {
+        /// The API to use.
+        api: Api,
+        /// The oldest acceptable version.
+        min: (u8, u8),
+        /// The most recent acceptable version.
+        max: Option<(u8, u8)>,
+    },
 }
 
+/// The versions of OpenGL that exist, from the most recent to the oldest.
+const GL_VERSIONS: &'static [(u8, u8)] = &[(4, 6), (4, 5), (4, 4), (4, 3), (4, 2), (4, 1), (4, 0),
+                                           (3, 3), (3, 2), (3, 1), (3, 0), (2, 1), (2, 0),
+                                           (1, 5), (1, 4), (1, 3), (1, 2), (1, 1), (1, 0)];
+
+/// The versions of OpenGL ES that exist, from the most recent to the oldest.
+const GLES_VERSIONS: &'static [(u8, u8)] = &[(3, 2), (3, 1), (3, 0), (2, 0), (1, 1), (1, 0)];
+
 impl GlRequest {
     /// Extract the desktop GL version, if any.
+    ///
+    /// For a `Range`, this is the oldest acceptable version.
     pub fn to_gl_version(&self) -> Option<(u8, u8)> {
         match self {
             &GlRequest::Specific(Api::OpenGl, version) => Some(version),
             &GlRequest::GlThenGles { opengl_version: version, .. } => Some(version),
+            &GlRequest::Range { api: Api::OpenGl, min, .. } => Some(min),
             _ => None,
         }
     }
+
+    /// Returns the versions that a `Range` request accepts, from the most recent to the oldest.
+    /// Returns an empty list for the other requests.
+    #[allow(dead_code)]
+    fn range_versions(&self) -> Vec<(u8, u8)> {
+        let (api, min, max) = match *self {
+            GlRequest::Range { api, min, max } => (api, min, max),
+            _ => return Vec::new(),
+        };
+
+        if max.map(|max| max < min).unwrap_or(false) {
+            return Vec::new();
+        }
+
+        let known = match api {
+            Api::OpenGl => GL_VERSIONS,
+            Api::OpenGlEs | Api::WebGl => GLES_VERSIONS,
+        };
+
+        // the bounds themselves may be versions that didn't exist when this list was written
+        let mut versions: Vec<(u8, u8)> = known.iter().cloned().filter(|&v| {
+            v >= min && max.map(|max| v <= max).unwrap_or(true)
+        }).collect();
+        if let Some(max) = max {
+            if !versions.contains(&max) { versions.insert(0, max); }
+        }
+        if !versions.contains(&min) { versions.push(min); }
+        versions
+    }
+
+    /// Calls `create` with each version of a `Range` request, from the most recent to the
+    /// oldest, until one of them succeeds. See `create_with_fallbacks`.
+    #[allow(dead_code)]
+    fn create_in_range<T, F>(&self, create: F) -> Result<(T, Vec<FailedVersion>), CreationError>
+        where F: FnMut((u8, u8)) -> Result<T, CreationError>
+    {
+        let api = match *self {
+            GlRequest::Range { api, .. } => api,
+            _ => Api::OpenGl,
+        };
+
+        create_with_fallbacks(api, &self.range_versions(), create)
+    }
+}
+
+/// Calls `create` with each of `versions` in order, until one of them succeeds.
+///
+/// Returns the created value along with the versions that failed before it, or
+/// `NoVersionAvailable` if all of them failed.
+#[allow(dead_code)]
+fn create_with_fallbacks<T, F>(api: Api, versions: &[(u8, u8)], mut create: F)
+                               -> Result<(T, Vec<FailedVersion>), CreationError>
+    where F: FnMut((u8, u8)) -> Result<T, CreationError>
+{
+    let mut failed = Vec::new();
+
+    for &version in versions {
+        match create(version) {
+            Ok(value) => return Ok((value, failed)),
+            Err(err) => {
+                debug_log!("Creating a {:?} {:?} context failed: {}", api, version, err);
+                failed.push(FailedVersion {
+                    api: api,
+                    version: version,
+                    reason: format!("{}", err),
+                });
+            },
+        }
+    }
+
+    Err(CreationError::NoVersionAvailable(failed))
+}
+
+/// Checks a swap interval against the range of the config and the support of adaptive vsync,
+/// as the platforms clamp the unsupported intervals without reporting it.
+#[allow(dead_code)]
+fn check_swap_interval(limits: &ConfigLimits, adaptive_vsync: bool, interval: i32)
+                       -> Result<(), ContextError>
+{
+    let error = |text: String| Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                                       text)));
+
+    if interval < 0 && !adaptive_vsync {
+        return error(format!("adaptive vsync isn't supported by this backend"));
+    }
+
+    // `i32::MIN` has no absolute value in an `i32`
+    let magnitude = (interval as i64).abs();
+    match limits.swap_interval_range {
+        Some((min, max)) if magnitude < min as i64 || magnitude > max as i64 => {
+            error(format!("the swap interval {} is out of the range {} to {} of the config",
+                          interval, min, max))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks the combinations of attributes that no platform can create, so that they are
+/// reported with an explanation instead of as a failure of the driver.
+fn validate_attributes<S>(opengl: &GlAttributes<S>, pf_reqs: &PixelFormatRequirements)
+                          -> Result<(), CreationError>
+{
+    let invalid = |text: &str| Err(CreationError::InvalidAttributes(text.to_owned()));
+
+    // the API if the request allows only one, and the oldest version it allows
+    let (api, min_version) = match opengl.version {
+        GlRequest::Specific(api, version) => (Some(api), Some(version)),
+        GlRequest::Range { api, min, max } => {
+            if max.map(|max| max < min).unwrap_or(false) {
+                return invalid("the maximum version of the range is older than its minimum");
+            }
+            (Some(api), Some(min))
+        },
+        GlRequest::Latest | GlRequest::GlThenGles { .. } => (None, None),
+    };
+    let gles = api == Some(Api::OpenGlEs) || api == Some(Api::WebGl);
+
+    if opengl.profile.is_some() && gles {
+        return invalid("the profiles only exist for OpenGL, not for OpenGL ES or WebGL");
+    }
+
+    // the drivers ignore the profile before OpenGL 3.2
+    if opengl.profile == Some(GlProfile::Core) && api == Some(Api::OpenGl) &&
+       min_version.map(|v| v < (3, 2)).unwrap_or(false)
+    {
+        debug_log!("the core profile doesn't exist before OpenGL 3.2 and is ignored");
+    }
+
+    if opengl.multiview && min_version.map(|v| v < (3, 0)).unwrap_or(false) {
+        return invalid("multiview needs OpenGL 3.0 or OpenGL ES 3.0");
+    }
+
+    if pf_reqs.stereoscopy && gles {
+        return invalid("stereoscopy is only available with OpenGL");
+    }
+
+    if pf_reqs.packed_depth_stencil == Some(true) &&
+       !(pf_reqs.depth_bits.accepts(24) && pf_reqs.stencil_bits.accepts(8))
+    {
+        return invalid("a packed depth/stencil buffer has 24 bits of depth and 8 bits of stencil");
+    }
+
+    if let Some(samples) = pf_reqs.coverage_samples {
+        if samples.color == 0 || samples.coverage < samples.color {
+            return invalid("coverage sampling needs at least one color sample, and at least as \
+                            many coverage samples as color samples");
+        }
+        if pf_reqs.multisampling.map(|s| s != samples.color).unwrap_or(false) {
+            return invalid("multisampling and coverage sampling request different numbers of \
+                            color samples");
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces `Robustness::NoError` with `Robustness::NotRobust` if the debug flag is set, because
+/// `GL_KHR_no_error` makes the creation fail with it.
+fn adjust_robustness<S>(opengl: &mut GlAttributes<S>) {
+    if opengl.robustness == Robustness::NoError && opengl.debug {
+        debug_log!("Robustness::NoError is replaced by Robustness::NotRobust for a debug context");
+        opengl.robustness = Robustness::NotRobust;
+    }
+}
+
+/// Tells whether a format with these buffers packs them in a depth24/stencil8 buffer, which is
+/// the only packed format that the configs of the platforms offer.
+#[allow(dead_code)]
+fn is_packed_depth_stencil(depth_bits: u8, stencil_bits: u8) -> bool {
+    depth_bits == 24 && stencil_bits == 8
 }
 
 /// The minimum core profile GL context. Useful for getting the minimum
@@ -299,6 +958,9 @@ pub enum Robustness {
     ///
     /// Since this option is purely an optimisation, no error will be returned if the backend
     /// doesn't support it. Instead it will automatically fall back to `NotRobust`.
+    ///
+    /// It also falls back to `NotRobust` if the debug flag is set, which is the default in debug
+    /// builds, as the two can't be combined.
     NoError,
 
     /// Everything is checked to avoid any crash. The driver will attempt to avoid any problem,
@@ -330,6 +992,119 @@ pub enum ReleaseBehavior {
     Flush,
 }
 
+/// The platform that is requested from EGL when creating the EGL display.
+///
+/// Only used by the backends that create their context with EGL.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EglPlatform {
+    /// Uses `eglGetPlatformDisplay` with the platform of the native display if the EGL
+    /// implementation supports it, and `eglGetDisplay` otherwise.
+    Auto,
+
+    /// Always uses `eglGetDisplay`, and lets the EGL implementation guess the platform of the
+    /// native display.
+    Unspecified,
+
+    /// Requires `EGL_PLATFORM_X11_KHR` or `EGL_PLATFORM_X11_EXT`.
+    X11,
+
+    /// Requires `EGL_PLATFORM_XCB_EXT`. Only available for the windows that are given an XCB
+    /// connection with `WindowBuilderExt::with_xcb_connection`.
+    Xcb,
+
+    /// Requires `EGL_PLATFORM_GBM_KHR`.
+    Gbm,
+
+    /// Requires `EGL_PLATFORM_DEVICE_EXT`.
+    Device,
+
+    /// Requires `EGL_PLATFORM_ANDROID_KHR`.
+    Android,
+
+    /// Requires `EGL_ANGLE_platform_angle`, which only the ANGLE implementation of EGL provides,
+    /// and creates an ANGLE display on top of the X11 display. `Auto` does the same when the
+    /// EGL implementation is ANGLE. The renderer of ANGLE, for example Vulkan or desktop OpenGL,
+    /// is the default one of the build, or the one of the `ANGLE_DEFAULT_PLATFORM` environment
+    /// variable.
+    Angle,
+}
+
+impl Default for EglPlatform {
+    #[inline]
+    fn default() -> EglPlatform {
+        EglPlatform::Auto
+    }
+}
+
+/// How the presentation of the frames is synchronized with the display when vsync is enabled.
+///
+/// Only used on Windows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Uses `DwmFlush` when the desktop window manager is compositing and the window is not
+    /// fullscreen, and the swap interval otherwise.
+    Auto,
+
+    /// Always uses `wglSwapIntervalEXT` or `eglSwapInterval`.
+    ///
+    /// When the desktop window manager is compositing, this adds a frame of latency and may
+    /// stutter.
+    SwapInterval,
+
+    /// Waits for the desktop window manager to compose the frame with `DwmFlush` after
+    /// swapping the buffers, instead of using the swap interval.
+    ///
+    /// Falls back to the swap interval when the desktop window manager is not compositing.
+    DwmFlush,
+}
+
+impl Default for PresentMode {
+    #[inline]
+    fn default() -> PresentMode {
+        PresentMode::Auto
+    }
+}
+
+/// The GPU that renders the window on the machines that have two of them, usually laptops
+/// with an integrated and a discrete GPU.
+///
+/// Only used on Linux and the BSDs, see `os::unix::set_gpu_preference`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GpuPreference {
+    /// Lets the driver and the environment choose.
+    Default,
+
+    /// The GPU that drives the display, usually the integrated one.
+    LowPower,
+
+    /// The discrete GPU, which renders the frames and copies them to the GPU that drives the
+    /// display.
+    HighPerformance,
+}
+
+impl Default for GpuPreference {
+    #[inline]
+    fn default() -> GpuPreference {
+        GpuPreference::Default
+    }
+}
+
+/// The priority of the commands of a context on the GPU, relative to the other contexts of the
+/// system.
+///
+/// Only used with EGL, where it is requested with `EGL_IMG_context_priority`. The driver may
+/// grant another priority, for example only give a high priority to privileged processes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContextPriority {
+    /// For the background work that must not delay the other applications.
+    Low,
+    /// The priority of the contexts that don't request one.
+    Medium,
+    /// The priority of the compositors and of the VR renderers, whose frames must not wait for
+    /// those of the other applications.
+    High,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum MouseCursor {
     /// The platform-dependent default cursor.
@@ -416,6 +1191,264 @@ pub struct PixelFormat {
     pub double_buffer: bool,
     pub multisampling: Option<u16>,
     pub srgb: bool,
+    /// True if the samples are resolved implicitly when the tiles of a tiled GPU are written back
+    /// to memory, like with `GL_EXT_multisampled_render_to_texture`. The multisampled buffer
+    /// doesn't exist in memory then, so it can't be blitted or read, but multisampling costs no
+    /// extra bandwidth. False when the backend doesn't know.
+    pub implicit_resolve: bool,
+    /// True if the depth and the stencil buffers are packed in a single depth24/stencil8
+    /// buffer, which is how the framebuffer objects of the application should allocate them to
+    /// match the default framebuffer. The configs of EGL, GLX, WGL and CGL don't tell how the
+    /// driver stores them, so this is inferred from their sizes.
+    pub packed_depth_stencil: bool,
+    /// The number of samples per pixel that are tested for coverage, if it is higher than the
+    /// number of color samples in `multisampling`. See `CoverageSamples`.
+    pub coverage_samples: Option<u16>,
+}
+
+/// The version prefix of the descriptors of `PixelFormat::to_descriptor`.
+const DESCRIPTOR_PREFIX: &'static str = "glutin-pf1";
+
+impl PixelFormat {
+    /// Describes the pixel format in a single line of text, for example to include the config
+    /// that a user got in a bug report. `from_descriptor` reads it back, and
+    /// `PixelFormatRequirements::exactly` requests the same format on another machine:
+    ///
+    /// `glutin-pf1 hw=1 color=24 alpha=8 depth=24 stencil=8 stereo=0 double=1 samples=4 srgb=0
+    /// resolve=0 packed=1 coverage=none`
+    pub fn to_descriptor(&self) -> String {
+        fn flag(value: bool) -> &'static str { if value { "1" } else { "0" } }
+        fn samples(value: Option<u16>) -> String {
+            value.map(|s| s.to_string()).unwrap_or_else(|| "none".to_owned())
+        }
+
+        format!("{} hw={} color={} alpha={} depth={} stencil={} stereo={} double={} samples={} \
+                 srgb={} resolve={} packed={} coverage={}", DESCRIPTOR_PREFIX,
+                flag(self.hardware_accelerated), self.color_bits, self.alpha_bits,
+                self.depth_bits, self.stencil_bits, flag(self.stereoscopy),
+                flag(self.double_buffer), samples(self.multisampling), flag(self.srgb),
+                flag(self.implicit_resolve), flag(self.packed_depth_stencil),
+                samples(self.coverage_samples))
+    }
+
+    /// Parses a descriptor of `to_descriptor`. Returns `None` if it is malformed, was written
+    /// by another version of the format, or lacks some of the values.
+    pub fn from_descriptor(descriptor: &str) -> Option<PixelFormat> {
+        fn flag(value: &str) -> Option<bool> {
+            match value { "1" => Some(true), "0" => Some(false), _ => None }
+        }
+        fn samples(value: &str) -> Option<Option<u16>> {
+            if value == "none" { Some(None) } else { value.parse().ok().map(Some) }
+        }
+
+        let mut words = descriptor.split_whitespace();
+        if words.next() != Some(DESCRIPTOR_PREFIX) {
+            return None;
+        }
+
+        let mut format = PixelFormat {
+            hardware_accelerated: false,
+            color_bits: 0,
+            alpha_bits: 0,
+            depth_bits: 0,
+            stencil_bits: 0,
+            stereoscopy: false,
+            double_buffer: false,
+            multisampling: None,
+            srgb: false,
+            implicit_resolve: false,
+            packed_depth_stencil: false,
+            coverage_samples: None,
+        };
+
+        // one bit per key, which must all be present exactly once
+        let mut seen = 0u16;
+        for word in words {
+            let mut parts = word.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => return None,
+            };
+
+            let (bit, parsed) = match key {
+                "hw" => (0, flag(value).map(|v| format.hardware_accelerated = v)),
+                "color" => (1, value.parse().ok().map(|v| format.color_bits = v)),
+                "alpha" => (2, value.parse().ok().map(|v| format.alpha_bits = v)),
+                "depth" => (3, value.parse().ok().map(|v| format.depth_bits = v)),
+                "stencil" => (4, value.parse().ok().map(|v| format.stencil_bits = v)),
+                "stereo" => (5, flag(value).map(|v| format.stereoscopy = v)),
+                "double" => (6, flag(value).map(|v| format.double_buffer = v)),
+                "samples" => (7, samples(value).map(|v| format.multisampling = v)),
+                "srgb" => (8, flag(value).map(|v| format.srgb = v)),
+                "resolve" => (9, flag(value).map(|v| format.implicit_resolve = v)),
+                "packed" => (10, flag(value).map(|v| format.packed_depth_stencil = v)),
+                "coverage" => (11, samples(value).map(|v| format.coverage_samples = v)),
+                _ => return None,
+            };
+
+            if parsed.is_none() || seen & (1 << bit) != 0 {
+                return None;
+            }
+            seen |= 1 << bit;
+        }
+
+        if seen == (1 << 12) - 1 { Some(format) } else { None }
+    }
+}
+
+/// Limits of the config (EGL), framebuffer config (GLX) or pixel format (WGL) of a context.
+///
+/// They are read from the config when it is chosen, which allows sizing the pbuffers before
+/// creating them. `None` means that the backend doesn't report the value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigLimits {
+    /// The maximum width and height of a pbuffer.
+    pub max_pbuffer_dimensions: Option<(u32, u32)>,
+
+    /// The maximum number of pixels of a pbuffer, which can be less than the product of the
+    /// maximum dimensions.
+    pub max_pbuffer_pixels: Option<u32>,
+
+    /// The minimum and maximum values of the swap interval, which tell whether the display can
+    /// be presented without vsync (0) or at a fraction of the refresh rate (2 or more).
+    ///
+    /// EGL reads them from `EGL_MIN_SWAP_INTERVAL` and `EGL_MAX_SWAP_INTERVAL` of the config,
+    /// GLX from `GLX_MAX_SWAP_INTERVAL_EXT` of the window, and the `NSOpenGLContext` of macOS
+    /// only has intervals 0 and 1. `WGL_EXT_swap_control` silently clamps the interval to a
+    /// maximum that it doesn't report, so this is `None` with WGL.
+    pub swap_interval_range: Option<(u32, u32)>,
+
+    /// True if the native rendering APIs of the platform can draw into the surfaces too, for
+    /// example Xlib for `EGL_NATIVE_RENDERABLE` and `GLX_X_RENDERABLE`.
+    pub native_renderable: Option<bool>,
+}
+
+/// Information about the driver and the GPU behind a context, for example to pick the default
+/// settings of an application before rendering anything.
+///
+/// It comes from `GLX_MESA_query_renderer` with GLX, and from the EGL device of the display
+/// with EGL. `None` means that the backend doesn't report the value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RendererInfo {
+    /// The name of the vendor of the driver or of the GPU.
+    pub vendor: Option<String>,
+
+    /// The name of the device, for example the path of the DRM device with EGL.
+    pub device: Option<String>,
+
+    /// The PCI vendor id of the GPU.
+    pub vendor_id: Option<u32>,
+
+    /// The PCI device id of the GPU.
+    pub device_id: Option<u32>,
+
+    /// The amount of video memory, in megabytes.
+    pub video_memory: Option<u32>,
+
+    /// True if the GPU shares the memory of the CPU, like integrated GPUs.
+    pub unified_memory: Option<bool>,
+
+    /// False for software renderers.
+    pub accelerated: Option<bool>,
+
+    /// The highest version of the core profile of OpenGL that the driver supports.
+    pub max_core_profile_version: Option<(u8, u8)>,
+
+    /// The highest version of the compatibility profile of OpenGL that the driver supports.
+    pub max_compatibility_profile_version: Option<(u8, u8)>,
+
+    /// The highest version of OpenGL ES 2 and later that the driver supports.
+    pub max_gles2_version: Option<(u8, u8)>,
+
+    /// The version of Mesa, as major, minor and patch numbers, if the driver is part of Mesa.
+    pub mesa_version: Option<(u32, u32, u32)>,
+}
+
+/// A rectangle of a surface, in pixels. Like in OpenGL, the origin is the bottom-left corner of
+/// the surface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Metadata of HDR content, which lets the display pipeline map its luminance to what the
+/// screen can show. The chromaticities are CIE 1931 xy coordinates and the luminances are in
+/// cd/m².
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HdrMetadata {
+    /// The red, green and blue primaries of the mastering display (SMPTE ST 2086).
+    pub display_primaries: [(f32, f32); 3],
+
+    /// The white point of the mastering display (SMPTE ST 2086).
+    pub white_point: (f32, f32),
+
+    /// The maximum luminance of the mastering display (SMPTE ST 2086).
+    pub max_luminance: f32,
+
+    /// The minimum luminance of the mastering display (SMPTE ST 2086).
+    pub min_luminance: f32,
+
+    /// The luminance of the brightest pixel of the content (CTA-861.3).
+    pub max_content_light_level: Option<f32>,
+
+    /// The highest average luminance of a frame of the content (CTA-861.3).
+    pub max_frame_average_light_level: Option<f32>,
+}
+
+/// Requirement on the number of bits of one of the buffers of a pixel format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BitsRequirement {
+    /// Any number of bits is acceptable, including zero.
+    DontCare,
+
+    /// The buffer must have at least this number of bits. A pixel format with more bits is
+    /// acceptable, for example a 32 bits depth buffer for `AtLeast(24)`.
+    AtLeast(u8),
+
+    /// The buffer must have exactly this number of bits.
+    Exact(u8),
+}
+
+impl BitsRequirement {
+    /// Returns the minimum number of bits, or `None` if this is `DontCare`.
+    #[inline]
+    pub fn min(&self) -> Option<u8> {
+        match *self {
+            BitsRequirement::DontCare => None,
+            BitsRequirement::AtLeast(bits) => Some(bits),
+            BitsRequirement::Exact(bits) => Some(bits),
+        }
+    }
+
+    /// Returns true if a buffer with `bits` bits fulfills the requirement.
+    #[inline]
+    pub fn accepts(&self, bits: u8) -> bool {
+        match *self {
+            BitsRequirement::DontCare => true,
+            BitsRequirement::AtLeast(min) => bits >= min,
+            BitsRequirement::Exact(exact) => bits == exact,
+        }
+    }
+}
+
+/// The numbers of samples per pixel of coverage sampling, which tests more samples for the
+/// coverage of the primitives than it stores colors for, like CSAA on NVIDIA and EQAA on AMD.
+/// It gives an antialiasing close to multisampling with `coverage` samples for the memory and
+/// the bandwidth of multisampling with `color` samples.
+///
+/// The default framebuffer gets it from `EGL_NV_coverage_sample`, `GLX_NV_multisample_coverage`
+/// and `WGL_NV_multisample_coverage`. The emulated framebuffer of a headless context uses
+/// `GL_NV_framebuffer_multisample_coverage` or `GL_AMD_framebuffer_multisample_advanced`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CoverageSamples {
+    /// The number of samples that store a color, which are the samples of multisampling.
+    pub color: u16,
+
+    /// The number of samples that are tested for coverage, at least `color`.
+    pub coverage: u16,
 }
 
 /// Describes how the backend should choose a pixel format.
@@ -426,26 +1459,24 @@ pub struct PixelFormatRequirements {
     /// renderers. `None` means "don't care". Default is `Some(true)`.
     pub hardware_accelerated: Option<bool>,
 
-    /// Minimum number of bits for the color buffer, excluding alpha. `None` means "don't care".
-    /// The default is `Some(24)`.
-    pub color_bits: Option<u8>,
+    /// Number of bits for the color buffer, excluding alpha. The default is `AtLeast(24)`.
+    pub color_bits: BitsRequirement,
 
     /// If true, the color buffer must be in a floating point format. Default is `false`.
     ///
     /// Using floating points allows you to write values outside of the `[0.0, 1.0]` range.
     pub float_color_buffer: bool,
 
-    /// Minimum number of bits for the alpha in the color buffer. `None` means "don't care".
-    /// The default is `Some(8)`.
-    pub alpha_bits: Option<u8>,
+    /// Number of bits for the alpha in the color buffer. The default is `AtLeast(8)`.
+    pub alpha_bits: BitsRequirement,
 
-    /// Minimum number of bits for the depth buffer. `None` means "don't care".
-    /// The default value is `Some(24)`.
-    pub depth_bits: Option<u8>,
+    /// Number of bits for the depth buffer. `Exact(0)` requests no depth buffer at all.
+    /// The default value is `AtLeast(24)`.
+    pub depth_bits: BitsRequirement,
 
-    /// Minimum number of bits for the depth buffer. `None` means "don't care".
-    /// The default value is `Some(8)`.
-    pub stencil_bits: Option<u8>,
+    /// Number of bits for the stencil buffer. `Exact(0)` requests no stencil buffer at all.
+    /// The default value is `AtLeast(8)`.
+    pub stencil_bits: BitsRequirement,
 
     /// If true, only double-buffered formats will be considered. If false, only single-buffer
     /// formats. `None` means "don't care". The default is `Some(true)`.
@@ -462,10 +1493,242 @@ pub struct PixelFormatRequirements {
 
     /// If true, only sRGB-capable formats will be considered. If false, don't care.
     /// The default is `false`.
+    ///
+    /// EGL needs EGL 1.5 or `EGL_KHR_gl_colorspace` and 8 bits per channel, and the surfaces
+    /// are created with `EGL_GL_COLORSPACE_SRGB`. Without them, the format isn't sRGB, which
+    /// `strict` turns into an error.
     pub srgb: bool,
 
     /// The behavior when changing the current context. Default is `Flush`.
     pub release_behavior: ReleaseBehavior,
+
+    /// If true, context creation fails with `NoAvailablePixelFormat` instead of silently using
+    /// a pixel format that doesn't fulfill all the requirements above, for example when a
+    /// backend can't provide sRGB or multisampling. The default is `false`.
+    pub strict: bool,
+
+    /// If true, only configs that are conformant for the requested API will be considered.
+    /// Some drivers don't filter on `EGL_CONFORMANT` in `eglChooseConfig`, so each config is
+    /// checked again before being selected. Only has an effect with EGL and GLX. The default
+    /// is `false`.
+    pub conformant: bool,
+
+    /// If false, configs that have a caveat are skipped: `EGL_SLOW_CONFIG` and
+    /// `EGL_NON_CONFORMANT_CONFIG` with EGL, `GLX_SLOW_CONFIG` and `GLX_NON_CONFORMANT_CONFIG`
+    /// with GLX, and formats that aren't fully accelerated with WGL. Unlike
+    /// `hardware_accelerated`, this also applies when it is `None`. The default is `true`.
+    pub allow_caveat: bool,
+
+    /// If true, the content of the back buffer should survive `swap_buffers`, which lets
+    /// incremental redraws only paint what changed. This uses `EGL_BUFFER_PRESERVED` with EGL
+    /// and the copy swap method with WGL. It is only a preference unless `strict` is true, use
+    /// `GlContext::is_back_buffer_preserved` to know whether it was granted. The default is
+    /// `false`.
+    pub preserve_back_buffer: bool,
+
+    /// If true, the window should draw directly into the buffer that is displayed, which saves
+    /// the latency of a swap. This uses `EGL_KHR_mutable_render_buffer`, which Android supports
+    /// for VR. The rendering appears when the commands are flushed, `swap_buffers` only flushes
+    /// them, therefore the application has to synchronize with the display itself. It is only
+    /// a preference unless `strict` is true, use `GlContext::is_front_buffer_rendering` to know
+    /// whether it was granted. The default is `false`.
+    pub front_buffer_rendering: bool,
+
+    /// Ranks the formats that fulfill the requirements above, for applications whose priorities
+    /// differ from the order of the platform. It returns `None` to reject a format, or a score
+    /// that is added to the score of glutin, which is `0` unless a quirk of the driver prefers
+    /// some formats. The format with the highest score is chosen, and the platform order breaks
+    /// the ties. Only has an effect with EGL, GLX and `WGL_ARB_pixel_format`. The default is
+    /// `None`.
+    pub scoring: Option<fn(&PixelFormat) -> Option<i32>>,
+
+    /// If true, the depth and the stencil buffers must be packed in a single depth24/stencil8
+    /// buffer. If false, they must be separate buffers. `None` means "don't care". Some drivers
+    /// only offer one of the arrangements, therefore this is a preference unless `strict` is
+    /// true, and `PixelFormat::packed_depth_stencil` tells which one was granted. The default
+    /// is `None`.
+    pub packed_depth_stencil: Option<bool>,
+
+    /// Contains the minimum numbers of samples per pixel of coverage sampling. The color samples
+    /// replace `multisampling`, which must be `None` or the same number. Without support for
+    /// coverage sampling, the backends fall back to multisampling with the color samples unless
+    /// `strict` is true. The default is `None`.
+    pub coverage_samples: Option<CoverageSamples>,
+}
+
+impl PixelFormatRequirements {
+    /// Sets whether the pixel format must fulfill all the requirements. See the `strict` field.
+    #[inline]
+    pub fn strict(mut self, strict: bool) -> PixelFormatRequirements {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets whether only conformant configs are considered. See the `conformant` field.
+    #[inline]
+    pub fn conformant(mut self, conformant: bool) -> PixelFormatRequirements {
+        self.conformant = conformant;
+        self
+    }
+
+    /// Sets whether configs with a caveat may be chosen. See the `allow_caveat` field.
+    #[inline]
+    pub fn allow_caveat(mut self, allow_caveat: bool) -> PixelFormatRequirements {
+        self.allow_caveat = allow_caveat;
+        self
+    }
+
+    /// Sets whether the back buffer should be preserved when swapping. See the
+    /// `preserve_back_buffer` field.
+    #[inline]
+    pub fn preserve_back_buffer(mut self, preserve: bool) -> PixelFormatRequirements {
+        self.preserve_back_buffer = preserve;
+        self
+    }
+
+    /// Sets whether the window should draw into its front buffer. See the
+    /// `front_buffer_rendering` field.
+    #[inline]
+    pub fn front_buffer_rendering(mut self, front_buffer: bool) -> PixelFormatRequirements {
+        self.front_buffer_rendering = front_buffer;
+        self
+    }
+
+    /// Sets the function that ranks the formats. See the `scoring` field.
+    #[inline]
+    pub fn scoring(mut self, scoring: fn(&PixelFormat) -> Option<i32>) -> PixelFormatRequirements {
+        self.scoring = Some(scoring);
+        self
+    }
+
+    /// Sets whether the depth and the stencil buffers must be packed. See the
+    /// `packed_depth_stencil` field.
+    #[inline]
+    pub fn packed_depth_stencil(mut self, packed: bool) -> PixelFormatRequirements {
+        self.packed_depth_stencil = Some(packed);
+        self
+    }
+
+    /// Sets the numbers of samples of coverage sampling. See the `coverage_samples` field.
+    #[inline]
+    pub fn coverage_samples(mut self, color: u16, coverage: u16) -> PixelFormatRequirements {
+        self.coverage_samples = Some(CoverageSamples { color: color, coverage: coverage });
+        self
+    }
+
+    /// Returns the requirements that select `format` again, for example a format read by
+    /// `PixelFormat::from_descriptor` from a bug report. The requirements are strict, and every
+    /// property is exact except the numbers of samples, which platforms only let filter by
+    /// minimum, and `implicit_resolve`, which can't be requested.
+    pub fn exactly(format: &PixelFormat) -> PixelFormatRequirements {
+        let samples = format.multisampling.unwrap_or(0);
+        PixelFormatRequirements {
+            hardware_accelerated: Some(format.hardware_accelerated),
+            color_bits: BitsRequirement::Exact(format.color_bits),
+            alpha_bits: BitsRequirement::Exact(format.alpha_bits),
+            depth_bits: BitsRequirement::Exact(format.depth_bits),
+            stencil_bits: BitsRequirement::Exact(format.stencil_bits),
+            double_buffer: Some(format.double_buffer),
+            multisampling: Some(samples),
+            stereoscopy: format.stereoscopy,
+            srgb: format.srgb,
+            strict: true,
+            packed_depth_stencil: Some(format.packed_depth_stencil),
+            coverage_samples: format.coverage_samples.map(|coverage| {
+                CoverageSamples { color: samples, coverage: coverage }
+            }),
+            .. Default::default()
+        }
+    }
+
+    /// Returns the number of color samples to request, from `coverage_samples` or
+    /// `multisampling`.
+    #[allow(dead_code)]
+    fn color_samples(&self) -> Option<u16> {
+        self.coverage_samples.map(|samples| samples.color).or(self.multisampling)
+    }
+
+    /// Returns true if `format` fulfills all the requirements.
+    fn is_satisfied_by(&self, format: &PixelFormat) -> bool {
+        if let Some(hardware_accelerated) = self.hardware_accelerated {
+            if format.hardware_accelerated != hardware_accelerated {
+                return false;
+            }
+        }
+
+        if !self.bits_satisfied_by(format) {
+            return false;
+        }
+
+        if let Some(double_buffer) = self.double_buffer {
+            if format.double_buffer != double_buffer {
+                return false;
+            }
+        }
+
+        match self.color_samples() {
+            Some(0) if format.multisampling.is_some() => return false,
+            Some(samples) if format.multisampling.unwrap_or(0) < samples => return false,
+            _ => ()
+        }
+
+        if let Some(samples) = self.coverage_samples {
+            let coverage = format.coverage_samples.or(format.multisampling).unwrap_or(0);
+            if coverage < samples.coverage {
+                return false;
+            }
+        }
+
+        if format.stereoscopy != self.stereoscopy {
+            return false;
+        }
+
+        if self.srgb && !format.srgb {
+            return false;
+        }
+
+        if let Some(packed) = self.packed_depth_stencil {
+            if format.packed_depth_stencil != packed {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Returns true if the number of bits of each buffer of `format` fulfills the requirements.
+    ///
+    /// The backends let the platform filter the formats by minimum number of bits, and use
+    /// this to pick the first of them that also matches the `Exact` requirements.
+    #[allow(dead_code)]
+    fn bits_satisfied_by(&self, format: &PixelFormat) -> bool {
+        self.color_bits.accepts(format.color_bits) &&
+        self.alpha_bits.accepts(format.alpha_bits) &&
+        self.depth_bits.accepts(format.depth_bits) &&
+        self.stencil_bits.accepts(format.stencil_bits)
+    }
+
+    /// Adds the score of the `scoring` function to `base`, the score of glutin, or returns
+    /// `None` if the function rejects `format`.
+    #[allow(dead_code)]
+    fn score(&self, format: &PixelFormat, base: i32) -> Option<i32> {
+        match self.scoring {
+            Some(scoring) => scoring(format).map(|score| base.saturating_add(score)),
+            None => Some(base),
+        }
+    }
+
+    /// Returns `NoAvailablePixelFormat` if in strict mode and `format` doesn't fulfill all the
+    /// requirements.
+    #[allow(dead_code)]
+    fn check_strict(&self, format: &PixelFormat) -> Result<(), CreationError> {
+        if self.strict && !self.is_satisfied_by(format) {
+            debug_log!("{:?} doesn't fulfill the strict requirements {:?}", format, self);
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for PixelFormatRequirements {
@@ -473,16 +1736,24 @@ impl Default for PixelFormatRequirements {
     fn default() -> PixelFormatRequirements {
         PixelFormatRequirements {
             hardware_accelerated: Some(true),
-            color_bits: Some(24),
+            color_bits: BitsRequirement::AtLeast(24),
             float_color_buffer: false,
-            alpha_bits: Some(8),
-            depth_bits: Some(24),
-            stencil_bits: Some(8),
+            alpha_bits: BitsRequirement::AtLeast(8),
+            depth_bits: BitsRequirement::AtLeast(24),
+            stencil_bits: BitsRequirement::AtLeast(8),
             double_buffer: None,
             multisampling: None,
             stereoscopy: false,
             srgb: false,
             release_behavior: ReleaseBehavior::Flush,
+            strict: false,
+            conformant: false,
+            allow_caveat: true,
+            preserve_back_buffer: false,
+            front_buffer_rendering: false,
+            scoring: None,
+            packed_depth_stencil: None,
+            coverage_samples: None,
         }
     }
 }
@@ -620,6 +1891,23 @@ pub struct GlAttributes<S> {
     ///
     /// The default is `false`.
     pub vsync: bool,
+
+    /// A directory where the drivers should keep the shaders and programs that they compile, so
+    /// that the next launches don't compile them again. See `WindowBuilder::with_shader_cache_dir`.
+    ///
+    /// The default is `None`, which leaves the caches where the drivers put them.
+    pub shader_cache_dir: Option<PathBuf>,
+
+    /// Whether the context must support `GL_OVR_multiview2`, which renders the views of both
+    /// eyes in a single pass. See `WindowBuilder::with_multiview`.
+    ///
+    /// The default is `false`.
+    pub multiview: bool,
+
+    /// The priority of the context on the GPU. See `ContextPriority`.
+    ///
+    /// The default is `None`, which lets the driver choose.
+    pub priority: Option<ContextPriority>,
 }
 
 impl<S> GlAttributes<S> {
@@ -633,6 +1921,9 @@ impl<S> GlAttributes<S> {
             debug: self.debug,
             robustness: self.robustness,
             vsync: self.vsync,
+            shader_cache_dir: self.shader_cache_dir,
+            multiview: self.multiview,
+            priority: self.priority,
         }
     }
 }
@@ -647,6 +1938,9 @@ impl<S> Default for GlAttributes<S> {
             debug: cfg!(debug_assertions),
             robustness: Robustness::NotRobust,
             vsync: false,
+            shader_cache_dir: None,
+            multiview: false,
+            priority: None,
         }
     }
 }
@@ -666,3 +1960,228 @@ mod native_monitor {
         Unavailable
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_requirement_accepts() {
+        assert!(BitsRequirement::DontCare.accepts(0));
+        assert!(BitsRequirement::DontCare.accepts(32));
+        assert!(BitsRequirement::AtLeast(24).accepts(24));
+        assert!(BitsRequirement::AtLeast(24).accepts(32));
+        assert!(!BitsRequirement::AtLeast(24).accepts(16));
+        assert!(BitsRequirement::Exact(0).accepts(0));
+        assert!(!BitsRequirement::Exact(0).accepts(8));
+        assert!(!BitsRequirement::Exact(24).accepts(32));
+    }
+
+    #[test]
+    fn bits_requirement_min() {
+        assert_eq!(BitsRequirement::DontCare.min(), None);
+        assert_eq!(BitsRequirement::AtLeast(8).min(), Some(8));
+        assert_eq!(BitsRequirement::Exact(0).min(), Some(0));
+    }
+
+    #[test]
+    fn range_versions() {
+        let range = |api, min, max| GlRequest::Range { api: api, min: min, max: max };
+
+        assert_eq!(range(Api::OpenGlEs, (3, 0), None).range_versions(),
+                   vec![(3, 2), (3, 1), (3, 0)]);
+        assert_eq!(range(Api::OpenGl, (3, 2), Some((4, 0))).range_versions(),
+                   vec![(4, 0), (3, 3), (3, 2)]);
+        // bounds that aren't known versions are tried too
+        assert_eq!(range(Api::OpenGlEs, (2, 5), Some((3, 5))).range_versions(),
+                   vec![(3, 5), (3, 2), (3, 1), (3, 0), (2, 5)]);
+        assert_eq!(range(Api::OpenGl, (3, 0), Some((2, 1))).range_versions(), vec![]);
+        assert_eq!(GlRequest::Latest.range_versions(), vec![]);
+    }
+
+    #[test]
+    fn create_in_range_falls_back() {
+        let request = GlRequest::Range { api: Api::OpenGlEs, min: (3, 0), max: None };
+
+        let mut tried = Vec::new();
+        let (version, failed) = request.create_in_range(|version| {
+            tried.push(version);
+            if version > (3, 0) {
+                Err(CreationError::OpenGlVersionNotSupported)
+            } else {
+                Ok(version)
+            }
+        }).unwrap();
+        assert_eq!(version, (3, 0));
+        assert_eq!(tried, vec![(3, 2), (3, 1), (3, 0)]);
+        assert_eq!(failed.iter().map(|f| (f.api, f.version)).collect::<Vec<_>>(),
+                   vec![(Api::OpenGlEs, (3, 2)), (Api::OpenGlEs, (3, 1))]);
+
+        match request.create_in_range(|_| -> Result<(), _> {
+            Err(CreationError::OpenGlVersionNotSupported)
+        }) {
+            Err(CreationError::NoVersionAvailable(failed)) => assert_eq!(failed.len(), 3),
+            _ => panic!("the creation should have failed"),
+        }
+    }
+
+    fn pixel_format() -> PixelFormat {
+        PixelFormat {
+            hardware_accelerated: true,
+            color_bits: 24,
+            alpha_bits: 8,
+            depth_bits: 24,
+            stencil_bits: 8,
+            stereoscopy: false,
+            double_buffer: true,
+            multisampling: Some(4),
+            srgb: true,
+            implicit_resolve: false,
+            packed_depth_stencil: true,
+            coverage_samples: Some(8),
+        }
+    }
+
+    #[test]
+    fn pixel_format_descriptor_round_trip() {
+        let descriptor = pixel_format().to_descriptor();
+        assert_eq!(descriptor, "glutin-pf1 hw=1 color=24 alpha=8 depth=24 stencil=8 stereo=0 \
+                                double=1 samples=4 srgb=1 resolve=0 packed=1 coverage=8");
+
+        let format = PixelFormat::from_descriptor(&descriptor).unwrap();
+        assert_eq!(format.to_descriptor(), descriptor);
+        assert_eq!(format.multisampling, Some(4));
+        assert_eq!(format.coverage_samples, Some(8));
+
+        let format = PixelFormat { multisampling: None, coverage_samples: None, .. format };
+        let descriptor = format.to_descriptor();
+        let parsed = PixelFormat::from_descriptor(&descriptor).unwrap();
+        assert_eq!(parsed.multisampling, None);
+        assert_eq!(parsed.coverage_samples, None);
+        assert_eq!(parsed.to_descriptor(), descriptor);
+
+        // the order of the values doesn't matter
+        let reordered = "glutin-pf1 coverage=none packed=0 resolve=1 srgb=0 samples=none \
+                         double=0 stereo=1 stencil=0 depth=16 alpha=0 color=16 hw=0";
+        let parsed = PixelFormat::from_descriptor(reordered).unwrap();
+        assert_eq!(parsed.color_bits, 16);
+        assert!(parsed.stereoscopy && parsed.implicit_resolve && !parsed.hardware_accelerated);
+    }
+
+    #[test]
+    fn pixel_format_descriptor_rejects() {
+        let descriptor = pixel_format().to_descriptor();
+        let rejected = [
+            String::new(),
+            descriptor.replace("glutin-pf1", "glutin-pf2"),
+            descriptor.replace(" coverage=8", ""),
+            format!("{} color=24", descriptor),
+            format!("{} unknown=1", descriptor),
+            format!("{} color", descriptor),
+            descriptor.replace("hw=1", "hw=2"),
+            descriptor.replace("color=24", "color=256"),
+            descriptor.replace("samples=4", "samples=four"),
+        ];
+        for descriptor in &rejected {
+            assert!(PixelFormat::from_descriptor(descriptor).is_none(), "{:?}", descriptor);
+        }
+    }
+
+    #[test]
+    fn exactly_is_satisfied_by_its_format() {
+        let format = pixel_format();
+        let reqs = PixelFormatRequirements::exactly(&format);
+        assert!(reqs.is_satisfied_by(&format));
+        assert!(!reqs.is_satisfied_by(&PixelFormat { depth_bits: 32, .. format.clone() }));
+        assert!(!reqs.is_satisfied_by(&PixelFormat { srgb: false, .. format }));
+    }
+
+    fn attributes(version: GlRequest) -> GlAttributes<()> {
+        GlAttributes { version: version, .. Default::default() }
+    }
+
+    fn is_invalid(opengl: &GlAttributes<()>, pf_reqs: &PixelFormatRequirements) -> bool {
+        match validate_attributes(opengl, pf_reqs) {
+            Err(CreationError::InvalidAttributes(_)) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn validate_attributes_accepts_the_defaults() {
+        let pf_reqs = Default::default();
+        assert!(validate_attributes(&attributes(GlRequest::Latest), &pf_reqs).is_ok());
+
+        // the core profile is ignored before OpenGL 3.2, not rejected
+        let opengl = GlAttributes {
+            profile: Some(GlProfile::Core),
+            .. attributes(GlRequest::Specific(Api::OpenGl, (2, 1)))
+        };
+        assert!(validate_attributes(&opengl, &pf_reqs).is_ok());
+    }
+
+    #[test]
+    fn validate_attributes_rejects_the_invalid_combinations() {
+        let pf_reqs = PixelFormatRequirements::default();
+        let gles = attributes(GlRequest::Specific(Api::OpenGlEs, (3, 0)));
+
+        let range = attributes(GlRequest::Range { api: Api::OpenGl, min: (4, 0),
+                                                  max: Some((3, 3)) });
+        assert!(is_invalid(&range, &pf_reqs));
+
+        let profile = GlAttributes { profile: Some(GlProfile::Core), .. gles.clone() };
+        assert!(is_invalid(&profile, &pf_reqs));
+
+        let multiview = GlAttributes {
+            multiview: true,
+            .. attributes(GlRequest::Specific(Api::OpenGlEs, (2, 0)))
+        };
+        assert!(is_invalid(&multiview, &pf_reqs));
+
+        let stereo = PixelFormatRequirements { stereoscopy: true, .. Default::default() };
+        assert!(is_invalid(&gles, &stereo));
+
+        let packed = PixelFormatRequirements {
+            packed_depth_stencil: Some(true),
+            depth_bits: BitsRequirement::Exact(32),
+            .. Default::default()
+        };
+        assert!(is_invalid(&gles, &packed));
+
+        let coverage = |color, coverage, multisampling| PixelFormatRequirements {
+            coverage_samples: Some(CoverageSamples { color: color, coverage: coverage }),
+            multisampling: multisampling,
+            .. Default::default()
+        };
+        assert!(!is_invalid(&gles, &coverage(4, 8, Some(4))));
+        assert!(is_invalid(&gles, &coverage(0, 8, None)));
+        assert!(is_invalid(&gles, &coverage(8, 4, None)));
+        assert!(is_invalid(&gles, &coverage(4, 8, Some(2))));
+    }
+
+    #[test]
+    fn adjust_robustness_of_a_debug_context() {
+        let mut opengl = GlAttributes {
+            robustness: Robustness::NoError,
+            debug: true,
+            .. attributes(GlRequest::Latest)
+        };
+        adjust_robustness(&mut opengl);
+        assert_eq!(opengl.robustness, Robustness::NotRobust);
+
+        opengl.robustness = Robustness::NoError;
+        opengl.debug = false;
+        adjust_robustness(&mut opengl);
+        assert_eq!(opengl.robustness, Robustness::NoError);
+    }
+
+    #[test]
+    fn swap_interval_outside_of_the_range() {
+        let limits = ConfigLimits { swap_interval_range: Some((0, 4)), .. Default::default() };
+        assert!(check_swap_interval(&limits, false, 1).is_ok());
+        assert!(check_swap_interval(&limits, false, 5).is_err());
+        assert!(check_swap_interval(&limits, true, -4).is_ok());
+        assert!(check_swap_interval(&limits, true, i32::min_value()).is_err());
+        assert!(check_swap_interval(&limits, false, -1).is_err());
+    }
+}
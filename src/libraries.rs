@@ -0,0 +1,302 @@
+//! Location of the shared libraries that glutin loads at runtime.
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::os::raw::c_void;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use Api;
+
+/// Function that returns the address of an EGL function given its name.
+pub type EglLoader = Fn(&str) -> *const c_void + Send + Sync;
+
+/// A shared library that glutin may load at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Library {
+    /// The EGL implementation. `libEGL.so.1` on Unix (`libEGL.so` on OpenBSD and NetBSD),
+    /// `libEGL.dll` on Windows and the `libEGL.dylib` of ANGLE on macOS. With the `angle`
+    /// feature, the `libEGL.so` in the directory of the executable is tried first on Linux and
+    /// FreeBSD.
+    ///
+    /// Can also be set with the `GLUTIN_EGL_LIBRARY` environment variable.
+    Egl,
+
+    /// The OpenGL ES implementation. `libGLESv2.so.2` on Unix and `libGLESv2.dll` on Windows.
+    ///
+    /// This library is only loaded when a path is provided, or with the `angle` feature from the
+    /// directory of the executable. It is then loaded right before EGL, so that an EGL
+    /// implementation like ANGLE or SwiftShader picks it up instead of the system one.
+    ///
+    /// It is also loaded when an OpenGL ES context is first created with an EGL implementation
+    /// older than EGL 1.5 that lacks `EGL_KHR_get_all_proc_addresses`, to look up the core
+    /// functions that `eglGetProcAddress` doesn't return. `libGLESv2.so.2` is then tried after
+    /// the path, and after the directory of the `Library::Egl` path if one is set.
+    ///
+    /// Can also be set with the `GLUTIN_GLES_LIBRARY` environment variable.
+    GlesV2,
+
+    /// The desktop OpenGL implementation. `libGL.so.1` (GLX) on Unix (`libGL.so` on OpenBSD and
+    /// NetBSD) and `opengl32.dll` on Windows.
+    ///
+    /// On Linux and FreeBSD, the `libGLX.so.0` dispatch library of libglvnd is tried before
+    /// `libGL.so.1` when it is installed. It picks the vendor library of each X screen, which
+    /// can be forced with `set_glx_vendor`, instead of whichever driver installed `libGL.so.1`
+    /// last. `libEGL.so.1` is already the dispatch library of libglvnd on these systems.
+    ///
+    /// On Windows the WGL functions are linked at compile-time, therefore this only changes
+    /// where the OpenGL 1.1 functions returned by `get_proc_address` are looked up.
+    ///
+    /// Can also be set with the `GLUTIN_GL_LIBRARY` environment variable.
+    Gl,
+}
+
+impl Library {
+    /// Name of the environment variable that overrides the path of this library.
+    #[inline]
+    pub fn env_var(&self) -> &'static str {
+        match *self {
+            Library::Egl => "GLUTIN_EGL_LIBRARY",
+            Library::GlesV2 => "GLUTIN_GLES_LIBRARY",
+            Library::Gl => "GLUTIN_GL_LIBRARY",
+        }
+    }
+
+    /// Names that are tried, in order, when no path has been provided.
+    fn default_names(&self) -> &'static [&'static str] {
+        if cfg!(target_os = "windows") {
+            match *self {
+                Library::Egl => &["libEGL.dll"],
+                Library::GlesV2 => &[],
+                Library::Gl => &["opengl32.dll"],
+            }
+        } else if cfg!(target_os = "macos") {
+            // there is no system EGL, and CGL is linked as a framework
+            match *self {
+                Library::Egl => &["libEGL.dylib"],
+                Library::GlesV2 => &[],
+                Library::Gl => &[],
+            }
+        } else if cfg!(any(target_os = "openbsd", target_os = "netbsd")) {
+            // the sonames of the base system (xenocara and xsrc) carry a different major
+            // version than Mesa on Linux, the runtime linker picks the latest one when given
+            // the unversioned name
+            match *self {
+                Library::Egl => &["libEGL.so", "libEGL.so.1"],
+                Library::GlesV2 => &[],
+                Library::Gl => &["libGL.so", "libGL.so.3", "libGL.so.1"],
+            }
+        } else {
+            match *self {
+                Library::Egl => &["libEGL.so.1", "libEGL.so"],
+                Library::GlesV2 => &[],
+                Library::Gl => &["libGLX.so.0", "libGL.so.1", "libGL.so"],
+            }
+        }
+    }
+}
+
+lazy_static! {
+    static ref OVERRIDES: Mutex<HashMap<Library, PathBuf>> = Mutex::new(HashMap::new());
+    static ref EGL_LOADER: Mutex<Option<Arc<EglLoader>>> = Mutex::new(None);
+    static ref ALLOWED_SYMBOLS: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+}
+
+static LOADING_SEALED: AtomicBool = AtomicBool::new(false);
+
+/// Name of the environment variable that libglvnd reads to force a GLX vendor library.
+pub const GLX_VENDOR_ENV_VAR: &'static str = "__GLX_VENDOR_LIBRARY_NAME";
+
+/// Sets the path glutin uses to load `library`, instead of the system default.
+///
+/// This takes priority over the environment variable of the library. When a path is set, the
+/// default names of the library are not tried anymore.
+///
+/// Libraries are loaded once per process, the first time a window or a headless context is
+/// built. Calling this function afterwards has no effect.
+pub fn set_library_path<P>(library: Library, path: P) where P: Into<PathBuf> {
+    OVERRIDES.lock().unwrap().insert(library, path.into());
+}
+
+/// Returns the path that has been set for `library`, either with `set_library_path` or with
+/// the environment variable of the library.
+pub fn get_library_path(library: Library) -> Option<PathBuf> {
+    if let Some(path) = OVERRIDES.lock().unwrap().get(&library) {
+        return Some(path.clone());
+    }
+
+    env::var_os(library.env_var()).and_then(|p| if p.is_empty() { None } else { Some(p.into()) })
+}
+
+/// Returns the list of paths to try, in order, when loading `library`.
+///
+/// The list is empty once `seal_library_loading` has been called.
+#[allow(dead_code)]
+pub fn candidates(library: Library) -> Vec<PathBuf> {
+    if is_library_loading_sealed() {
+        debug_log!("{:?} isn't loaded, library loading has been sealed", library);
+        return Vec::new();
+    }
+
+    match get_library_path(library) {
+        Some(path) => vec![path],
+        None => {
+            let mut paths = bundled_paths(library);
+            paths.extend(library.default_names().iter().map(PathBuf::from));
+            paths
+        },
+    }
+}
+
+/// Returns the list of paths to try, in order, when loading the library that contains the core
+/// functions of `api` for an EGL context.
+///
+/// The library of OpenGL ES comes first from the path of `Library::GlesV2`, then from the
+/// directory of the EGL library, which may not be the system one. The list is empty once
+/// `seal_library_loading` has been called.
+#[allow(dead_code)]
+pub fn client_api_candidates(api: Api) -> Vec<PathBuf> {
+    if is_library_loading_sealed() {
+        debug_log!("the library of {:?} isn't loaded, library loading has been sealed", api);
+        return Vec::new();
+    }
+
+    let (library, names): (_, &[&str]) = match api {
+        Api::OpenGlEs if cfg!(target_os = "windows") => (Library::GlesV2, &["libGLESv2.dll"]),
+        Api::OpenGlEs if cfg!(target_os = "macos") => (Library::GlesV2, &["libGLESv2.dylib"]),
+        Api::OpenGlEs => (Library::GlesV2, &["libGLESv2.so.2", "libGLESv2.so"]),
+        // libglvnd splits the OpenGL functions from the GLX ones
+        Api::OpenGl if cfg!(target_os = "windows") => (Library::Gl, &["opengl32.dll"]),
+        Api::OpenGl if cfg!(target_os = "macos") => return Vec::new(),
+        Api::OpenGl => (Library::Gl, &["libOpenGL.so.0", "libGL.so.1", "libGL.so"]),
+        Api::WebGl => return Vec::new(),
+    };
+
+    let mut paths = Vec::new();
+    paths.extend(get_library_path(library));
+    paths.extend(bundled_paths(library));
+    if let Some(dir) = get_library_path(Library::Egl).as_ref().and_then(|p| p.parent()) {
+        paths.extend(names.iter().map(|name| dir.join(name)));
+    }
+    paths.extend(names.iter().map(PathBuf::from));
+    paths
+}
+
+/// Returns the paths of the ANGLE libraries that may be shipped next to the executable.
+#[cfg(all(feature = "angle", any(target_os = "linux", target_os = "freebsd")))]
+fn bundled_paths(library: Library) -> Vec<PathBuf> {
+    let name = match library {
+        Library::Egl => "libEGL.so",
+        Library::GlesV2 => "libGLESv2.so",
+        Library::Gl => return Vec::new(),
+    };
+
+    match env::current_exe() {
+        Ok(exe) => exe.parent().map(|dir| vec![dir.join(name)]).unwrap_or(Vec::new()),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(not(all(feature = "angle", any(target_os = "linux", target_os = "freebsd"))))]
+#[inline]
+#[allow(dead_code)]
+fn bundled_paths(_: Library) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Makes glutin obtain the EGL functions from `loader` instead of loading `libEGL` itself.
+///
+/// The loader receives the name of an EGL function (for example `"eglGetDisplay"`) and must
+/// return its address, or null if it is not available. This is useful in sandboxes that forbid
+/// `dlopen`, where the function pointers are obtained from a host process or a custom loader.
+///
+/// When a loader is set, the paths of `Library::Egl` and `Library::GlesV2` are ignored.
+///
+/// Like `set_library_path`, this must be called before the first window or headless context
+/// is built. On Android the EGL functions are linked at compile-time and the loader is ignored.
+///
+/// The loader is set for the whole process instead of being passed to the builders. glutin
+/// loads the EGL functions once: the X11 connection and the Windows backend share them between
+/// all the windows and headless contexts, and EGL contexts can only share their objects when
+/// they come from the same implementation. To hand over pre-loaded pointers, the loader can
+/// simply look the name up in them. The contexts of UWP applications, which don't share their
+/// functions with the Windows backend, take the loader as an argument of
+/// `os::windows::CoreWindowContext::with_egl_loader` instead.
+pub fn set_egl_loader<F>(loader: F) where F: Fn(&str) -> *const c_void + Send + Sync + 'static {
+    *EGL_LOADER.lock().unwrap() = Some(Arc::new(loader));
+}
+
+/// Makes libglvnd use the GLX vendor library `name` for every X screen, for example `"mesa"` or
+/// `"nvidia"` to pick between both drivers when both are installed.
+///
+/// libglvnd only reads the choice from the `__GLX_VENDOR_LIBRARY_NAME` environment variable,
+/// which this function sets, replacing the value of the user. It has no effect if the GL
+/// library isn't libglvnd, or once the first window has been built.
+///
+/// # Safety
+///
+/// Changing the environment while another thread reads it is undefined behavior with most C
+/// libraries, therefore this must be called when the process starts, before it creates any
+/// thread. The variable is inherited by the child processes.
+pub unsafe fn set_glx_vendor<S>(name: S) where S: Into<String> {
+    env::set_var(GLX_VENDOR_ENV_VAR, name.into());
+}
+
+/// Returns the GLX vendor library that has been chosen with the `__GLX_VENDOR_LIBRARY_NAME`
+/// environment variable, possibly by `set_glx_vendor`.
+pub fn get_glx_vendor() -> Option<String> {
+    env::var(GLX_VENDOR_ENV_VAR).ok().and_then(|n| if n.is_empty() { None } else { Some(n) })
+}
+
+/// Returns the loader that has been set with `set_egl_loader`, if any.
+#[allow(dead_code)]
+pub fn egl_loader() -> Option<Arc<EglLoader>> {
+    EGL_LOADER.lock().unwrap().clone()
+}
+
+/// Restricts the functions that `get_proc_address` resolves to the names in `symbols`.
+///
+/// `get_proc_address` returns null for any other name without asking the driver, which keeps
+/// the entry points a sandboxed process can reach to a vetted list. The list must contain every
+/// GL and EGL extension function the application loads, and the functions that glutin loads
+/// through `get_proc_address` for its own features: `glGetString` and `glFlush`, the fence
+/// functions of `swap_buffers_async` and the framebuffer functions of
+/// `with_emulated_framebuffer`. The EGL and GLX functions that glutin links against are not
+/// affected.
+///
+/// Calling this again replaces the list.
+pub fn set_allowed_symbols<I, S>(symbols: I) where I: IntoIterator<Item = S>, S: Into<String> {
+    let symbols = symbols.into_iter().map(Into::into).collect();
+    *ALLOWED_SYMBOLS.lock().unwrap() = Some(symbols);
+}
+
+/// Returns true if `get_proc_address` may resolve `name`, which is always the case unless
+/// `set_allowed_symbols` has been called.
+pub fn is_symbol_allowed(name: &str) -> bool {
+    match *ALLOWED_SYMBOLS.lock().unwrap() {
+        Some(ref symbols) => symbols.contains(name),
+        None => true,
+    }
+}
+
+/// Prevents glutin from loading any shared library from now on.
+///
+/// This is meant for processes that restrict their system calls with seccomp or `pledge` after
+/// initialization: build the windows or headless contexts first, which loads the libraries of
+/// their backends, then seal the loading and lock the process down. Building a context that
+/// needs a library that isn't loaded yet, like EGL on X11 when only GLX has been used so far,
+/// fails afterwards instead of calling `dlopen`. The `set_egl_loader` loader is still used.
+///
+/// Drivers may still load their own modules, for example when `eglInitialize` is called for a
+/// new display, which glutin can't prevent.
+///
+/// The loading can't be unsealed.
+pub fn seal_library_loading() {
+    LOADING_SEALED.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if `seal_library_loading` has been called.
+pub fn is_library_loading_sealed() -> bool {
+    LOADING_SEALED.load(Ordering::SeqCst)
+}
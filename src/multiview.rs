@@ -0,0 +1,69 @@
+//! The context requirements of `GL_OVR_multiview2`, which VR renderers use to draw both eyes in
+//! a single pass, see `WindowBuilder::with_multiview`.
+//!
+//! The extension needs the texture arrays of OpenGL 3.0 and OpenGL ES 3.0. Nothing in the
+//! configs of EGL, GLX or WGL tells whether a driver supports it, therefore the context is
+//! checked once it's created.
+
+use std::mem;
+
+use CreationError;
+use GlAttributes;
+use GlContext;
+use GlRequest;
+use gl_version::{self, GlVersion};
+
+const MAX_VIEWS_OVR: u32 = 0x9631;
+
+/// Asks for OpenGL 3.0 or OpenGL ES 3.0 instead of `GlRequest::Latest`, if `multiview` is set.
+///
+/// EGL only chooses the configs of OpenGL ES 3.0 when the request has that version, and the
+/// older contexts can't have the extension.
+pub fn adjust_request<S>(opengl: &mut GlAttributes<S>) {
+    if let (true, GlRequest::Latest) = (opengl.multiview, opengl.version) {
+        opengl.version = GlRequest::GlThenGles {
+            opengl_version: (3, 0),
+            opengles_version: (3, 0),
+        };
+    }
+}
+
+/// Makes `context` current and checks that it supports `GL_OVR_multiview2` with two views at
+/// least.
+pub fn check<C>(context: &C) -> Result<(), CreationError> where C: GlContext {
+    try!(unsafe { context.make_current() }.map_err(|err| {
+        CreationError::OsError(format!("the new context can't be made current: {}", err))
+    }));
+
+    let get_string = context.get_proc_address("glGetString");
+    let get_integer = context.get_proc_address("glGetIntegerv");
+    if get_string.is_null() || get_integer.is_null() {
+        return Err(CreationError::OsError(format!("glGetString or glGetIntegerv couldn't be \
+                                                   loaded")));
+    }
+
+    unsafe {
+        let get_string = mem::transmute(get_string);
+        match GlVersion::query(get_string) {
+            Some(v) if v.number >= (3, 0) => (),
+            _ => return Err(CreationError::OpenGlVersionNotSupported),
+        }
+
+        // `GL_OVR_multiview` alone doesn't let the shaders use the view index outside of the
+        // position
+        if !gl_version::has_extension(get_string, "GL_OVR_multiview2") {
+            debug_log!("the context doesn't support GL_OVR_multiview2");
+            return Err(CreationError::NotSupported);
+        }
+
+        let get_integer: extern "system" fn(u32, *mut i32) = mem::transmute(get_integer);
+        let mut max_views = 0;
+        get_integer(MAX_VIEWS_OVR, &mut max_views);
+        if max_views < 2 {
+            debug_log!("the context supports {} views with GL_OVR_multiview2", max_views);
+            return Err(CreationError::NotSupported);
+        }
+    }
+
+    Ok(())
+}
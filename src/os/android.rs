@@ -0,0 +1,169 @@
+#![cfg(target_os = "android")]
+
+use std::io;
+use std::os::raw::c_void;
+
+use ContextError;
+use CreationError;
+use Window;
+use WindowBuilder;
+use api::android::image_reader::ImageReader;
+
+/// Additional methods on `Window` that are specific to Android.
+pub trait WindowExt {
+    /// Creates a 2D texture in the context of this window with the content of an
+    /// `AHardwareBuffer`, and returns its name. It's left bound to `GL_TEXTURE_2D` of the active
+    /// texture unit.
+    ///
+    /// This is how another process shares its textures: it allocates the buffer with
+    /// `AHardwareBuffer_allocate` and `AHARDWAREBUFFER_USAGE_GPU_SAMPLED_IMAGE`, imports it in
+    /// its own context, and sends it with `AHardwareBuffer_sendHandleToUnixSocket`. This process
+    /// receives it with `AHardwareBuffer_recvHandleFromUnixSocket`.
+    ///
+    /// The context must be current. Needs `EGL_ANDROID_get_native_client_buffer` and
+    /// `EGL_ANDROID_image_native_buffer`, which Android provides since 8.0.
+    ///
+    /// # Unsafety
+    ///
+    /// `buffer` must be a valid `AHardwareBuffer`. The texture holds a reference to it, so the
+    /// caller can release its own.
+    unsafe fn import_texture_hardware_buffer(&self, buffer: *mut c_void)
+                                             -> Result<u32, ContextError>;
+
+    /// Like `import_texture_hardware_buffer`, with a texture bound to `GL_TEXTURE_EXTERNAL_OES`
+    /// instead, which YUV buffers like those of `MediaCodec` and of `ImageReader` need. Shaders
+    /// sample it with `samplerExternalOES`.
+    ///
+    /// Also needs `GL_OES_EGL_image_external`.
+    unsafe fn import_external_texture_hardware_buffer(&self, buffer: *mut c_void)
+                                                      -> Result<u32, ContextError>;
+
+    /// Creates a texture bound to `GL_TEXTURE_EXTERNAL_OES` without storage, and returns its
+    /// name, for the `SurfaceTexture` that a video decoder or the camera renders into. Each
+    /// `SurfaceTexture.updateTexImage` binds the latest frame to the texture without copying
+    /// it.
+    ///
+    /// The context must be current. Needs `GL_OES_EGL_image_external`.
+    fn create_external_texture(&self) -> Result<u32, ContextError>;
+
+    /// Creates an `ANativeWindow` whose frames are bound to `texture`, an external texture
+    /// created with `create_external_texture`, which is what a Java `SurfaceTexture` does. The
+    /// window is what `AMediaCodec_configure` and `ACameraOutputTarget_create` take, and
+    /// `ANativeWindow_toSurface` turns it into a Java `Surface`.
+    ///
+    /// The frames must have the given dimensions. This needs Android 8.0, and returns
+    /// `CreationError::NotSupported` before.
+    fn create_texture_surface(&self, texture: u32, width: u32, height: u32)
+                              -> Result<TextureSurface, CreationError>;
+}
+
+impl WindowExt for Window {
+    #[inline]
+    unsafe fn import_texture_hardware_buffer(&self, buffer: *mut c_void)
+                                             -> Result<u32, ContextError>
+    {
+        self.window.import_texture_hardware_buffer(buffer)
+    }
+
+    #[inline]
+    unsafe fn import_external_texture_hardware_buffer(&self, buffer: *mut c_void)
+                                                      -> Result<u32, ContextError>
+    {
+        self.window.import_external_texture_hardware_buffer(buffer)
+    }
+
+    #[inline]
+    fn create_external_texture(&self) -> Result<u32, ContextError> {
+        self.window.create_external_texture()
+    }
+
+    #[inline]
+    fn create_texture_surface(&self, texture: u32, width: u32, height: u32)
+                              -> Result<TextureSurface, CreationError>
+    {
+        Ok(TextureSurface {
+            window: self,
+            reader: try!(ImageReader::new((width, height))),
+            texture: texture,
+        })
+    }
+}
+
+/// Additional methods on `WindowBuilder` that are specific to Android.
+pub trait WindowBuilderExt<'a> {
+    /// If true, the context and the surface of the window are protected with
+    /// `EGL_EXT_protected_content`, so that they can render the protected buffers that the
+    /// decoders of DRM media produce, for example with a texture of `create_texture_surface`.
+    /// The default value is `false`.
+    ///
+    /// The window creation returns `CreationError::NotSupported` if the driver doesn't support
+    /// the extension. The content of a protected surface is only shown on secure outputs, and
+    /// the activity should also have `FLAG_SECURE`, see `with_secure_activity`.
+    fn with_protected_content(self, protected: bool) -> WindowBuilder<'a>;
+
+    /// Gives `FLAG_SECURE` to the window of `activity` before its surface is created, which
+    /// keeps its content out of screenshots, screen recordings and non-secure displays.
+    ///
+    /// # Unsafety
+    ///
+    /// `activity` must be the `ANativeActivity` of the window, and the window must be built on
+    /// its main thread.
+    unsafe fn with_secure_activity(self, activity: *mut c_void) -> WindowBuilder<'a>;
+}
+
+impl<'a> WindowBuilderExt<'a> for WindowBuilder<'a> {
+    #[inline]
+    fn with_protected_content(mut self, protected: bool) -> WindowBuilder<'a> {
+        self.platform_specific.protected_content = protected;
+        self
+    }
+
+    #[inline]
+    unsafe fn with_secure_activity(mut self, activity: *mut c_void) -> WindowBuilder<'a> {
+        self.platform_specific.secure_activity = Some(activity as *mut _);
+        self
+    }
+}
+
+/// A native window whose frames are bound to an external texture of the context of a window,
+/// for the camera preview and the output of `MediaCodec`.
+///
+/// Created with `WindowExt::create_texture_surface`. The producer must be stopped before this
+/// is dropped, which destroys the native window. The texture belongs to the context.
+pub struct TextureSurface<'a> {
+    window: &'a Window,
+    reader: ImageReader,
+    texture: u32,
+}
+
+impl<'a> TextureSurface<'a> {
+    /// Returns the `ANativeWindow` that the producer renders into. It's destroyed with this
+    /// surface.
+    #[inline]
+    pub fn get_native_window(&self) -> *mut c_void {
+        self.reader.get_native_window()
+    }
+
+    /// Returns the external texture that the frames are bound to.
+    #[inline]
+    pub fn get_texture(&self) -> u32 {
+        self.texture
+    }
+
+    /// Binds the latest frame of the producer to the texture, like
+    /// `SurfaceTexture.updateTexImage`, and returns false if there is no new frame since the
+    /// last call. The older frames are dropped. The texture is left bound to
+    /// `GL_TEXTURE_EXTERNAL_OES` of the active texture unit after a new frame.
+    ///
+    /// The context of the window must be current.
+    pub fn update_tex_image(&self) -> Result<bool, ContextError> {
+        if !self.window.is_current() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                       "the context must be current to update the texture")));
+        }
+
+        self.reader.acquire_latest(|buffer| unsafe {
+            self.window.window.update_external_texture_hardware_buffer(self.texture, buffer)
+        })
+    }
+}
@@ -3,7 +3,7 @@
 use std::convert::From;
 use std::os::raw::c_void;
 use cocoa::appkit::NSApplicationActivationPolicy;
-use {Window, WindowBuilder};
+use {ContextError, Window, WindowBuilder};
 
 /// Additional methods on `Window` that are specific to MacOS.
 pub trait WindowExt {
@@ -11,6 +11,20 @@ pub trait WindowExt {
     ///
     /// The pointer will become invalid when the glutin `Window` is destroyed.
     fn get_nswindow(&self) -> *mut c_void;
+
+    /// Returns the `CGLShareGroupObj` of the context of this window.
+    ///
+    /// This is what OpenCL and the texture caches of Core Video need to share objects with the
+    /// context.
+    fn get_cgl_share_group(&self) -> *mut c_void;
+
+    /// Blocks until the next refresh of the screen that the window is on, as reported by a
+    /// `CVDisplayLink`.
+    ///
+    /// Unlike the swap interval, this follows the actual refresh rate of ProMotion screens and
+    /// of the screen the window moved to. Apps that pace their frames with this usually disable
+    /// vsync. The wait gives up after 100ms, since a display that sleeps doesn't refresh.
+    fn wait_for_vblank(&self) -> Result<(), ContextError>;
 }
 
 impl WindowExt for Window {
@@ -18,6 +32,16 @@ impl WindowExt for Window {
     fn get_nswindow(&self) -> *mut c_void {
         self.window.platform_window() as *mut c_void
     }
+
+    #[inline]
+    fn get_cgl_share_group(&self) -> *mut c_void {
+        self.window.get_cgl_share_group() as *mut c_void
+    }
+
+    #[inline]
+    fn wait_for_vblank(&self) -> Result<(), ContextError> {
+        self.window.wait_for_vblank()
+    }
 }
 
 /// Corresponds to `NSApplicationActivationPolicy`.
@@ -50,10 +74,28 @@ impl From<ActivationPolicy> for NSApplicationActivationPolicy {
     }
 }
 
+/// The color space that the content of a window is interpreted in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    /// Corresponds to `[NSColorSpace sRGBColorSpace]`.
+    Srgb,
+    /// Corresponds to `[NSColorSpace displayP3ColorSpace]`. Requires macOS 10.12.
+    DisplayP3,
+    /// Corresponds to `[NSColorSpace extendedSRGBColorSpace]`. Requires macOS 10.12.
+    ///
+    /// Values outside of `[0, 1]` are only preserved if the pixel format has a floating-point
+    /// color buffer, see `PixelFormatRequirements::float_color_buffer`.
+    ExtendedSrgb,
+}
+
 /// Additional methods on `WindowBuilder` that are specific to MacOS.
 pub trait WindowBuilderExt<'a> {
     fn with_activation_policy(mut self, activation_policy: ActivationPolicy) -> WindowBuilder<'a>;
     fn with_app_name(mut self, app_name: String) -> WindowBuilder<'a>;
+    fn with_color_space(mut self, color_space: ColorSpace) -> WindowBuilder<'a>;
+    fn with_disallow_hidpi(mut self, disallow_hidpi: bool) -> WindowBuilder<'a>;
+    fn with_layer_backed_view(mut self, layer_backed: bool) -> WindowBuilder<'a>;
+    unsafe fn with_cgl_share_context(mut self, context: *mut c_void) -> WindowBuilder<'a>;
 }
 
 impl<'a> WindowBuilderExt<'a> for WindowBuilder<'a> {
@@ -70,4 +112,49 @@ impl<'a> WindowBuilderExt<'a> for WindowBuilder<'a> {
         self.platform_specific.app_name = Some(app_name);
         self
     }
+
+    /// Sets the color space of the window, instead of the color space of the screen it is on.
+    ///
+    /// Creation fails if the color space isn't supported by the running version of macOS.
+    #[inline]
+    fn with_color_space(mut self, color_space: ColorSpace) -> WindowBuilder<'a> {
+        self.platform_specific.color_space = Some(color_space);
+        self
+    }
+
+    /// If true, the OpenGL surface has one pixel per point instead of the native resolution of
+    /// Retina screens, and the OS scales it up. This sets `wantsBestResolutionOpenGLSurface`
+    /// of the view to `NO`, and `Window::hidpi_factor` then returns 1.
+    #[inline]
+    fn with_disallow_hidpi(mut self, disallow_hidpi: bool) -> WindowBuilder<'a> {
+        self.platform_specific.disallow_hidpi = disallow_hidpi;
+        self
+    }
+
+    /// If true, the content is presented through a `CAOpenGLLayer` of the view instead of a
+    /// surface that covers the window. The OpenGL content then composites with the other views
+    /// of the window, like vibrancy effects, rounded corners and child views, at the cost of
+    /// an extra copy per frame.
+    #[inline]
+    fn with_layer_backed_view(mut self, layer_backed: bool) -> WindowBuilder<'a> {
+        self.platform_specific.layer_backed = layer_backed;
+        self
+    }
+
+    /// Creates the context in the share group of `context`, a `CGLContextObj`, so that they
+    /// share their textures, buffers and other objects.
+    ///
+    /// CGL can't create a context from a bare `CGLShareGroupObj`, therefore this takes any
+    /// context of the group, like the one that a Core Video or AVFoundation pipeline was set up
+    /// with. The share group of the window can be retrieved with
+    /// `WindowExt::get_cgl_share_group`.
+    ///
+    /// # Unsafety
+    ///
+    /// `context` must be a valid `CGLContextObj` until the window has been built.
+    #[inline]
+    unsafe fn with_cgl_share_context(mut self, context: *mut c_void) -> WindowBuilder<'a> {
+        self.platform_specific.share_context = Some(context as *mut _);
+        self
+    }
 }
@@ -2,10 +2,12 @@
 //!
 //! Contains the follow modules:
 //!
+//!  - `android`
 //!  - `macos`
 //!  - `unix`
 //!  - `windows`
 //!
+pub mod android;
 pub mod macos;
 pub mod unix;
 pub mod windows;
@@ -1,10 +1,45 @@
-#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+           target_os = "openbsd", target_os = "netbsd"))]
 
 use libc;
+use Api;
+use ContextError;
+use CreationError;
+use EglPlatform;
+use GlContext;
+use GpuPreference;
+use PixelFormat;
 use Window;
+use platform::PixmapSurface as LinuxPixmapSurface;
 use platform::Window as LinuxWindow;
 use WindowBuilder;
 
+use std::env;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::os::unix::io::RawFd;
+
+pub use {DmaBufPlane, DmaBufTexture, PixmapSurface};
+
+/// How the OpenGL commands of a window reach the GPU, see
+/// `WindowExt::get_rendering_environment`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct RenderingEnvironment {
+    /// True if the X server runs on another machine, for example with `ssh -X`.
+    pub remote_display: bool,
+
+    /// True if the process runs under VirtualGL, which renders on the GPU of this machine and
+    /// sends the frames to the X server as images.
+    pub virtualgl: bool,
+
+    /// True if the GLX context sends the OpenGL commands through the X server.
+    pub indirect: bool,
+
+    /// True if the renderer is known to run on the CPU, like llvmpipe. Only reported by the
+    /// drivers that support `GLX_MESA_query_renderer`.
+    pub software: bool,
+}
+
 /// Additional methods on `Window` that are specific to Unix.
 pub trait WindowExt {
     /// Returns a pointer to the `Window` object of xlib that is used by this window.
@@ -20,12 +55,100 @@ pub trait WindowExt {
     ///
     /// The pointer will become invalid when the glutin `Window` is destroyed.
     fn get_xlib_display(&self) -> Option<*mut libc::c_void>;
+
+    /// Returns a pointer to the `XVisualInfo` of the visual that the window has been created
+    /// with, which other X windows must use to be made current with the context of this window.
+    ///
+    /// Returns `None` if the window doesn't use xlib (if it uses wayland for example).
+    ///
+    /// The pointer will become invalid when the glutin `Window` is destroyed.
+    fn get_xlib_visual_info(&self) -> Option<*const libc::c_void>;
+
+    /// Returns the id of the visual that the window has been created with.
+    ///
+    /// Returns `None` if the window doesn't use xlib (if it uses wayland for example).
+    fn get_xlib_visual_id(&self) -> Option<libc::c_ulong>;
+
+    /// Returns the `GLXFBConfig` that the context of the window has been created with.
+    ///
+    /// Returns `None` if the context doesn't use GLX.
+    fn get_glx_fbconfig(&self) -> Option<*mut libc::c_void>;
+
+    /// Returns whether the GLX context of the window renders directly to the GPU.
+    ///
+    /// Indirect contexts send the OpenGL commands through the X server, for example over a
+    /// forwarded SSH connection, and are limited to OpenGL 1.4. Returns `None` if the context
+    /// doesn't use GLX.
+    fn is_direct(&self) -> Option<bool>;
+
+    /// Returns whether the window renders over the network or without a GPU, which is
+    /// typically slow.
+    ///
+    /// Returns `None` if the window doesn't use xlib (if it uses wayland for example).
+    fn get_rendering_environment(&self) -> Option<RenderingEnvironment>;
+
+    /// Returns the file descriptor of a native fence that signals once the GPU has executed
+    /// the commands submitted so far, which makes it possible to synchronize explicitly with a
+    /// compositor, a video encoder or Vulkan after `swap_buffers`. The caller owns the file
+    /// descriptor and must close it.
+    ///
+    /// The context must be current. Needs an EGL context and `EGL_ANDROID_native_fence_sync`,
+    /// which Mesa and the NVIDIA driver provide.
+    fn create_native_fence_fd(&self) -> Result<RawFd, ContextError>;
+
+    /// Exports a 2D texture of the context of this window as dma-bufs, for another process
+    /// that imports it with `import_texture_dma_buf`. The dimensions are those of the texture,
+    /// which OpenGL ES can't query. The caller owns the file descriptors of the planes and must
+    /// close them once they have been sent.
+    ///
+    /// The texture must not be resized or deleted while the other process uses it, and the
+    /// processes must synchronize their access, for example with `create_native_fence_fd`.
+    ///
+    /// The context must be current. Needs an EGL context, `EGL_KHR_gl_texture_2D_image` and
+    /// `EGL_MESA_image_dma_buf_export`, which Mesa provides.
+    fn export_texture_dma_buf(&self, texture: u32, width: u32, height: u32)
+                              -> Result<DmaBufTexture, ContextError>;
+
+    /// Creates a 2D texture in the context of this window with the content of dma-bufs, for
+    /// example exported by another process, and returns its name. It's left bound to
+    /// `GL_TEXTURE_2D` of the active texture unit. The file descriptors still belong to the
+    /// caller, which can close them afterwards.
+    ///
+    /// The context must be current. Needs an EGL context and `EGL_EXT_image_dma_buf_import`,
+    /// and `EGL_EXT_image_dma_buf_import_modifiers` for a modifier or a fourth plane.
+    fn import_texture_dma_buf(&self, texture: &DmaBufTexture) -> Result<u32, ContextError>;
+
+    /// Like `import_texture_dma_buf`, with a texture bound to `GL_TEXTURE_EXTERNAL_OES`
+    /// instead, which is how the frames that VA-API and V4L2 decoders export as dma-bufs are
+    /// composited without a readback. Shaders sample it with `samplerExternalOES`, which
+    /// converts YUV formats like NV12 to RGB.
+    ///
+    /// Also needs `GL_OES_EGL_image_external`.
+    fn import_external_texture_dma_buf(&self, texture: &DmaBufTexture)
+                                       -> Result<u32, ContextError>;
+
+    /// Creates a surface that renders into an X `Pixmap` with the OpenGL context of this window,
+    /// for example to compose the result with XRender.
+    ///
+    /// Pixmaps are single-buffered, calling `swap_buffers` on the surface waits until the
+    /// rendering is finished so that it is visible to the X requests that follow.
+    ///
+    /// Returns `CreationError::NotSupported` if the window doesn't use xlib, or if its pixel
+    /// format can't be used for pixmaps.
+    ///
+    /// # Unsafety
+    ///
+    /// The pixmap must have the depth of the visual of the window, and must continue to exist
+    /// as long as the surface exists.
+    unsafe fn create_pixmap_surface(&self, pixmap: libc::c_ulong)
+                                    -> Result<PixmapSurface, CreationError>;
 }
 
 impl WindowExt for Window {
     #[inline]
     fn get_xlib_window(&self) -> Option<*mut libc::c_void> {
         match self.window {
+            #[cfg(feature = "x11")]
             LinuxWindow::X(ref w) => Some(w.get_xlib_window()),
         }
     }
@@ -33,15 +156,278 @@ impl WindowExt for Window {
     #[inline]
     fn get_xlib_display(&self) -> Option<*mut libc::c_void> {
         match self.window {
+            #[cfg(feature = "x11")]
             LinuxWindow::X(ref w) => Some(w.get_xlib_display()),
         }
     }
+
+    #[inline]
+    fn get_xlib_visual_info(&self) -> Option<*const libc::c_void> {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => {
+                Some(w.get_xlib_visual_info() as *const _ as *const libc::c_void)
+            },
+        }
+    }
+
+    #[inline]
+    fn get_xlib_visual_id(&self) -> Option<libc::c_ulong> {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => Some(w.get_xlib_visual_info().visualid as libc::c_ulong),
+        }
+    }
+
+    #[inline]
+    fn get_glx_fbconfig(&self) -> Option<*mut libc::c_void> {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => w.get_glx_fbconfig(),
+        }
+    }
+
+    #[inline]
+    fn is_direct(&self) -> Option<bool> {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => w.is_direct(),
+        }
+    }
+
+    fn get_rendering_environment(&self) -> Option<RenderingEnvironment> {
+        let info = self.get_renderer_info();
+
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => Some(RenderingEnvironment {
+                remote_display: w.is_remote_display(),
+                virtualgl: w.is_virtualgl(),
+                indirect: w.is_direct() == Some(false),
+                software: info.accelerated == Some(false),
+            }),
+        }
+    }
+
+    #[inline]
+    fn create_native_fence_fd(&self) -> Result<RawFd, ContextError> {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => w.create_native_fence_fd(),
+        }
+    }
+
+    #[inline]
+    fn export_texture_dma_buf(&self, texture: u32, width: u32, height: u32)
+                              -> Result<DmaBufTexture, ContextError>
+    {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => w.export_texture_dma_buf(texture, (width, height)),
+        }
+    }
+
+    #[inline]
+    fn import_texture_dma_buf(&self, texture: &DmaBufTexture) -> Result<u32, ContextError> {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => w.import_texture_dma_buf(texture),
+        }
+    }
+
+    #[inline]
+    fn import_external_texture_dma_buf(&self, texture: &DmaBufTexture)
+                                       -> Result<u32, ContextError>
+    {
+        match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => w.import_external_texture_dma_buf(texture),
+        }
+    }
+
+    #[inline]
+    unsafe fn create_pixmap_surface(&self, pixmap: libc::c_ulong)
+                                    -> Result<PixmapSurface, CreationError>
+    {
+        let surface = match self.window {
+            #[cfg(feature = "x11")]
+            LinuxWindow::X(ref w) => {
+                try!(w.create_pixmap_surface(pixmap).map(LinuxPixmapSurface::X))
+            },
+        };
+
+        Ok(PixmapSurface {
+            surface: surface,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<'a> PixmapSurface<'a> {
+    /// Returns the dimensions of the pixmap in pixels.
+    #[inline]
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        self.surface.get_dimensions()
+    }
+}
+
+impl<'a> GlContext for PixmapSurface<'a> {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.surface.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.surface.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.surface.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.surface.swap_buffers()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.surface.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.surface.get_pixel_format()
+    }
 }
 
 /// Additional methods on `WindowBuilder` that are specific to Unix.
-pub trait WindowBuilderExt {
+pub trait WindowBuilderExt<'a> {
+    /// Sets the platform to request from EGL, if the context is created with EGL.
+    ///
+    /// The default value is `EglPlatform::Auto`. Requesting a platform explicitly makes the
+    /// window creation fail if the EGL implementation doesn't support it, instead of letting
+    /// the implementation guess the platform of the X11 display.
+    fn with_egl_platform(self, platform: EglPlatform) -> WindowBuilder<'a>;
+
+    /// If true, the window creation fails instead of returning an indirect GLX context. The
+    /// default value is `false`.
+    ///
+    /// See `WindowExt::is_direct`.
+    fn with_direct_rendering_required(self, required: bool) -> WindowBuilder<'a>;
+
+    /// Sets the `xcb_connection_t` of a toolkit that doesn't use xlib, which owns the parent
+    /// window given to `with_parent` as an `xcb_window_t`.
+    ///
+    /// With EGL, the context renders through this connection with `EGL_EXT_platform_xcb` if
+    /// the implementation supports it, and through the xlib display of glutin otherwise unless
+    /// `EglPlatform::Xcb` has been requested. GLX always uses the xlib display of glutin, which
+    /// libX11 implements on top of XCB. Both ways work because the ids of the windows are the
+    /// same for all the connections to an X server.
+    ///
+    /// # Unsafety
+    ///
+    /// The connection must continue to exist as long as the window exists.
+    unsafe fn with_xcb_connection(self, connection: *mut libc::c_void) -> WindowBuilder<'a>;
+
+    /// If true and the X server is remote or indirect rendering is forced, a window whose
+    /// requested version can't be created gets a context of the latest version available
+    /// instead, usually OpenGL 1.4 or 2.1. The default value is `false`.
+    ///
+    /// The versions that failed are returned by `GlContext::get_failed_versions`.
+    fn with_remote_fallback(self, fallback: bool) -> WindowBuilder<'a>;
+}
+
+impl<'a> WindowBuilderExt<'a> for WindowBuilder<'a> {
+    #[inline]
+    fn with_egl_platform(mut self, platform: EglPlatform) -> WindowBuilder<'a> {
+        self.platform_specific.egl_platform = platform;
+        self
+    }
 
+    #[inline]
+    fn with_direct_rendering_required(mut self, required: bool) -> WindowBuilder<'a> {
+        self.platform_specific.require_direct_rendering = required;
+        self
+    }
+
+    #[inline]
+    unsafe fn with_xcb_connection(mut self, connection: *mut libc::c_void) -> WindowBuilder<'a> {
+        self.platform_specific.xcb_connection = Some(connection);
+        self
+    }
+
+    #[inline]
+    fn with_remote_fallback(mut self, fallback: bool) -> WindowBuilder<'a> {
+        self.platform_specific.remote_fallback = fallback;
+        self
+    }
 }
 
-impl<'a> WindowBuilderExt for WindowBuilder<'a> {
+/// Sets the GPU that renders the windows and the headless contexts on machines with two GPUs.
+///
+/// The drivers read the choice from environment variables when they are loaded for an X
+/// display, which happens when the first window is built: `DRI_PRIME` for Mesa and
+/// `__NV_PRIME_RENDER_OFFLOAD` for the proprietary NVIDIA driver. This function sets both, and
+/// `GpuPreference::Default` leaves them unchanged.
+///
+/// # Safety
+///
+/// Changing the environment while another thread reads it is undefined behavior with most C
+/// libraries, therefore this must be called when the process starts, before it creates any
+/// thread. The variables are inherited by the child processes.
+pub unsafe fn set_gpu_preference(preference: GpuPreference) {
+    let value = match preference {
+        GpuPreference::Default => return,
+        GpuPreference::LowPower => "0",
+        GpuPreference::HighPerformance => "1",
+    };
+
+    env::set_var("DRI_PRIME", value);
+    env::set_var("__NV_PRIME_RENDER_OFFLOAD", value);
+}
+
+/// Enables or disables the threaded OpenGL dispatch of Mesa (`mesa_glthread`), which runs the
+/// driver on a separate thread and speeds up the applications that are limited by the CPU. By
+/// default, the choice is left to the environment and to the driconf files.
+///
+/// Mesa only reads the option from the `mesa_glthread` environment variable, which this function
+/// sets, when it loads its driver for an X screen. The choice therefore applies to the whole
+/// process and can't differ between contexts. Neither EGL nor GLX has a context attribute for
+/// it, which is why there's no builder option. Drivers other than Mesa ignore it, see
+/// `RendererInfo::mesa_version`.
+///
+/// # Safety
+///
+/// Like `set_gpu_preference`, this must be called when the process starts, before it creates
+/// any thread. The variable is inherited by the child processes.
+pub unsafe fn set_mesa_glthread(enabled: bool) {
+    env::set_var("mesa_glthread", if enabled { "true" } else { "false" });
+}
+
+/// Makes Mesa and the proprietary NVIDIA driver keep the shaders and programs that they compile
+/// in `dir`, like `WindowBuilder::with_shader_cache_dir` does for the EGL drivers.
+///
+/// These drivers only read the directory from their environment variables when they are
+/// loaded, which this function sets. Mesa appends `mesa_shader_cache` to the directory, and its
+/// older versions only read the GLSL variable. The variables that are already set, by the user
+/// or by a previous call, are kept.
+///
+/// # Safety
+///
+/// Like `set_gpu_preference`, this must be called when the process starts, before it creates
+/// any thread. The variables are inherited by the child processes.
+pub unsafe fn set_driver_shader_cache_dir(dir: &Path) {
+    for name in &["MESA_SHADER_CACHE_DIR", "MESA_GLSL_CACHE_DIR",
+                  "__GL_SHADER_DISK_CACHE_PATH"]
+    {
+        if env::var_os(name).is_none() {
+            env::set_var(name, dir);
+        }
+    }
+    if env::var_os("__GL_SHADER_DISK_CACHE").is_none() {
+        env::set_var("__GL_SHADER_DISK_CACHE", "1");
+    }
 }
@@ -1,6 +1,12 @@
 #![cfg(target_os = "windows")]
 
 use libc;
+use ContextError;
+use PBuffer;
+use PresentMode;
+
+#[cfg(feature = "egl")]
+pub use platform::CoreWindowContext;
 use Window;
 use WindowBuilder;
 
@@ -21,10 +27,81 @@ impl WindowExt for Window {
     }
 }
 
+/// Additional methods on `PBuffer` that are specific to Windows.
+pub trait PBufferExt {
+    /// Returns true if the pbuffer can be bound as a texture, which requires
+    /// `WGL_ARB_render_texture` and a pixel format that supports it.
+    fn can_bind_to_texture(&self) -> bool;
+
+    /// Binds the color buffer of the pbuffer to the `GL_TEXTURE_2D` texture that is currently
+    /// bound in the context of the parent, like `glTexImage2D` would without copying the
+    /// pixels. The back buffer is used if the pixel format is double-buffered.
+    ///
+    /// The context must be current on its window or headless context, not on the pbuffer.
+    unsafe fn bind_to_texture(&self) -> Result<(), ContextError>;
+
+    /// Releases the color buffer bound with `bind_to_texture`, which must be done before
+    /// drawing into the pbuffer again.
+    unsafe fn release_from_texture(&self) -> Result<(), ContextError>;
+}
+
+impl<'a> PBufferExt for PBuffer<'a> {
+    #[inline]
+    fn can_bind_to_texture(&self) -> bool {
+        self.pbuffer.can_bind_to_texture()
+    }
+
+    #[inline]
+    unsafe fn bind_to_texture(&self) -> Result<(), ContextError> {
+        self.pbuffer.bind_to_texture()
+    }
+
+    #[inline]
+    unsafe fn release_from_texture(&self) -> Result<(), ContextError> {
+        self.pbuffer.release_from_texture()
+    }
+}
+
 /// Additional methods on `WindowBuilder` that are specific to Windows.
-pub trait WindowBuilderExt {
+pub trait WindowBuilderExt<'a> {
+    /// Sets how the presentation of the frames is synchronized with the display when vsync is
+    /// enabled. The default is `PresentMode::Auto`.
+    fn with_present_mode(self, mode: PresentMode) -> WindowBuilder<'a>;
+
+    /// Requests exclusive fullscreen presentation when the window is fullscreen.
+    ///
+    /// The pixel format is then chosen with the exchange swap method, so that the driver can
+    /// flip the buffers instead of copying them and the frames don't go through the desktop
+    /// window manager. The default is `false`.
+    fn with_exclusive_fullscreen(self, exclusive: bool) -> WindowBuilder<'a>;
 
+    /// Creates the window with the `WS_EX_LAYERED` style, which overlays and widgets need to be
+    /// drawn above the other windows with per-pixel transparency.
+    ///
+    /// This implies `with_transparency(true)`. Requires Windows 8 or later, because earlier
+    /// versions can't render OpenGL into layered windows. The default is `false`.
+    fn with_layered_window(self, layered: bool) -> WindowBuilder<'a>;
 }
 
-impl<'a> WindowBuilderExt for WindowBuilder<'a> {
+impl<'a> WindowBuilderExt<'a> for WindowBuilder<'a> {
+    #[inline]
+    fn with_present_mode(mut self, mode: PresentMode) -> WindowBuilder<'a> {
+        self.platform_specific.present_mode = mode;
+        self
+    }
+
+    #[inline]
+    fn with_exclusive_fullscreen(mut self, exclusive: bool) -> WindowBuilder<'a> {
+        self.platform_specific.exclusive_fullscreen = exclusive;
+        self
+    }
+
+    #[inline]
+    fn with_layered_window(mut self, layered: bool) -> WindowBuilder<'a> {
+        self.platform_specific.layered = layered;
+        if layered {
+            self.window.transparent = true;
+        }
+        self
+    }
 }
@@ -0,0 +1,75 @@
+use Api;
+use ContextError;
+use GlContext;
+use PBuffer;
+use PixelFormat;
+
+impl<'a> PBuffer<'a> {
+    /// Binds the context of the parent to this pbuffer and makes it current.
+    #[inline]
+    pub unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.pbuffer.make_current()
+    }
+
+    /// Returns true if the context of the parent is current and bound to this pbuffer.
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        self.pbuffer.is_current()
+    }
+
+    /// Returns the address of an OpenGL function.
+    #[inline]
+    pub fn get_proc_address(&self, addr: &str) -> *const () {
+        self.pbuffer.get_proc_address(addr)
+    }
+
+    /// Returns the API of the context of the parent.
+    #[inline]
+    pub fn get_api(&self) -> Api {
+        self.pbuffer.get_api()
+    }
+
+    /// Returns the pixel format of the pbuffer, which is the one of its parent.
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.pbuffer.get_pixel_format()
+    }
+
+    /// Returns the dimensions of the pbuffer in pixels.
+    #[inline]
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+}
+
+impl<'a> GlContext for PBuffer<'a> {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.pbuffer.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.pbuffer.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.pbuffer.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.pbuffer.swap_buffers()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.pbuffer.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pbuffer.get_pixel_format()
+    }
+}
@@ -1,3 +1,4 @@
 #![cfg(target_os = "android")]
 
 pub use api::android::*;
+pub use api::NoPixmap as PixmapSurface;
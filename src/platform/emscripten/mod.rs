@@ -1,15 +1,19 @@
 #![cfg(target_os = "emscripten")]
 
 use Api;
+use ConfigLimits;
 use ContextError;
 use CreationError;
 use GlAttributes;
 use GlContext;
 use PixelFormat;
 use PixelFormatRequirements;
+use api::Drawable;
 
 pub use api::emscripten::{Window, WindowProxy, MonitorId, get_available_monitors};
 pub use api::emscripten::{get_primary_monitor, WaitEventsIterator, PollEventsIterator};
+pub use api::emscripten::PBuffer;
+pub use api::NoPixmap as PixmapSurface;
 
 pub struct HeadlessContext(Window);
 
@@ -21,6 +25,33 @@ impl HeadlessContext {
     {
         unimplemented!()
     }
+
+    /// There are no headless contexts with emscripten.
+    #[inline]
+    pub fn query_config_limits(_: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+                               _: &PlatformSpecificHeadlessBuilderAttributes)
+                               -> Result<ConfigLimits, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
 }
 
 impl GlContext for HeadlessContext {
@@ -55,9 +86,6 @@ impl GlContext for HeadlessContext {
     }
 }
 
-unsafe impl Send for HeadlessContext {}
-unsafe impl Sync for HeadlessContext {}
-
 #[derive(Clone, Default)]
 pub struct PlatformSpecificWindowBuilderAttributes;
 #[derive(Clone, Default)]
@@ -0,0 +1,92 @@
+#![cfg(target_os = "haiku")]
+
+use Api;
+use ConfigLimits;
+use ContextError;
+use CreationError;
+use GlAttributes;
+use GlContext;
+use PixelFormat;
+use PixelFormatRequirements;
+use api::Drawable;
+
+pub use api::haiku::{Window, WindowProxy, MonitorId, get_available_monitors, get_primary_monitor};
+pub use api::haiku::{WaitEventsIterator, PollEventsIterator, PBuffer};
+pub use api::haiku::PlatformSpecificWindowBuilderAttributes;
+pub use api::NoPixmap as PixmapSurface;
+
+#[derive(Clone, Default)]
+pub struct PlatformSpecificHeadlessBuilderAttributes;
+
+/// `BGLView` can only render into a window, there is no offscreen context on Haiku.
+pub struct HeadlessContext(Window);
+
+impl HeadlessContext {
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn new(_: (u32, u32), _: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+               _: &PlatformSpecificHeadlessBuilderAttributes)
+               -> Result<HeadlessContext, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
+
+    /// There are no headless contexts on Haiku.
+    #[inline]
+    pub fn query_config_limits(_: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+                               _: &PlatformSpecificHeadlessBuilderAttributes)
+                               -> Result<ConfigLimits, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
+}
+
+impl GlContext for HeadlessContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.0.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.0.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.0.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.0.swap_buffers()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.0.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.0.get_pixel_format()
+    }
+}
@@ -1,12 +1,15 @@
 #![cfg(target_os = "ios")]
 
+use ConfigLimits;
 use GlAttributes;
 use CreationError;
 use PixelFormat;
 use PixelFormatRequirements;
 use ContextError;
+use api::Drawable;
 
 pub use api::ios::*;
+pub use api::NoPixmap as PixmapSurface;
 
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
@@ -22,6 +25,15 @@ impl HeadlessContext {
         unimplemented!()
     }
 
+    /// The headless contexts of iOS aren't implemented.
+    #[inline]
+    pub fn query_config_limits(_: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+                               _: &PlatformSpecificHeadlessBuilderAttributes)
+                               -> Result<ConfigLimits, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
+
     /// See the docs in the crate root file.
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         unimplemented!()
@@ -48,7 +60,22 @@ impl HeadlessContext {
     pub fn get_pixel_format(&self) -> PixelFormat {
         unimplemented!();
     }
-}
 
-unsafe impl Send for HeadlessContext {}
-unsafe impl Sync for HeadlessContext {}
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        // there are no pbuffers, and a context can't draw into the surface of another window
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported(
+                       "a context can only draw into its own surface on this platform"));
+        }
+
+        self.make_current()
+    }
+}
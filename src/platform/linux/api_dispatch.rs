@@ -2,11 +2,18 @@
 pub use api::x11::{WaitEventsIterator, PollEventsIterator};*/
 
 use std::collections::VecDeque;
+use std::env;
 use std::sync::Arc;
 
+use ConfigLimits;
+use RendererInfo;
+use Rect;
+use HdrMetadata;
 use ContextError;
 use CreationError;
 use CursorState;
+use EglPlatform;
+use FailedVersion;
 use Event;
 use GlAttributes;
 use GlContext;
@@ -14,23 +21,50 @@ use MouseCursor;
 use PixelFormat;
 use PixelFormatRequirements;
 use WindowAttributes;
+use api::Drawable;
+use presenter::SwapFn;
 use libc;
 
+#[cfg(feature = "x11")]
 use api::x11;
+#[cfg(feature = "x11")]
 use api::x11::XConnection;
+#[cfg(feature = "x11")]
 use api::x11::XError;
+#[cfg(feature = "x11")]
 use api::x11::XNotSupported;
 
 #[derive(Clone, Default)]
-pub struct PlatformSpecificWindowBuilderAttributes;
+pub struct PlatformSpecificWindowBuilderAttributes {
+    pub egl_platform: EglPlatform,
+    pub require_direct_rendering: bool,
+    pub xcb_connection: Option<*mut libc::c_void>,
+    pub remote_fallback: bool,
+}
 
 enum Backend {
+    #[cfg(feature = "x11")]
     X(Arc<XConnection>),
+    #[cfg(feature = "x11")]
     Error(XNotSupported),
+    /// glutin has been built without any windowing backend.
+    #[cfg(not(feature = "x11"))]
+    None,
 }
 
+#[cfg(not(feature = "x11"))]
+lazy_static!(
+    static ref BACKEND: Backend = Backend::None;
+);
+
+#[cfg(feature = "x11")]
 lazy_static!(
     static ref BACKEND: Backend = {
+        // checking the session type first, so that xlib isn't loaded when there is no X server
+        if !has_x11_session() {
+            return Backend::Error(XNotSupported::NoX11Session);
+        }
+
         match XConnection::new(Some(x_error_callback)) {
             Ok(x) => Backend::X(Arc::new(x)),
             Err(e) => Backend::Error(e),
@@ -38,22 +72,31 @@ lazy_static!(
     };
 );
 
+/// Returns true if the session has an X server, including Xwayland.
+#[cfg(feature = "x11")]
+fn has_x11_session() -> bool {
+    env::var_os("DISPLAY").map(|d| !d.is_empty()).unwrap_or(false)
+}
+
 pub enum Window {
     #[doc(hidden)]
+    #[cfg(feature = "x11")]
     X(x11::Window),
 }
 
 #[derive(Clone)]
 pub enum WindowProxy {
     #[doc(hidden)]
+    #[cfg(feature = "x11")]
     X(x11::WindowProxy),
 }
 
 impl WindowProxy {
     #[inline]
     pub fn wakeup_event_loop(&self) {
-        match self {
-            &WindowProxy::X(ref wp) => wp.wakeup_event_loop(),
+        match *self {
+            #[cfg(feature = "x11")]
+            WindowProxy::X(ref wp) => wp.wakeup_event_loop(),
         }
     }
 }
@@ -61,6 +104,7 @@ impl WindowProxy {
 #[derive(Clone)]
 pub enum MonitorId {
     #[doc(hidden)]
+    #[cfg(feature = "x11")]
     X(x11::MonitorId),
     #[doc(hidden)]
     None,
@@ -69,193 +113,293 @@ pub enum MonitorId {
 #[inline]
 pub fn get_available_monitors() -> VecDeque<MonitorId> {
     match *BACKEND {
+        #[cfg(feature = "x11")]
         Backend::X(ref connec) => x11::get_available_monitors(connec)
                                     .into_iter()
                                     .map(MonitorId::X)
                                     .collect(),
-        Backend::Error(_) => { let mut d = VecDeque::new(); d.push_back(MonitorId::None); d},
+        _ => { let mut d = VecDeque::new(); d.push_back(MonitorId::None); d},
     }
 }
 
 #[inline]
 pub fn get_primary_monitor() -> MonitorId {
     match *BACKEND {
+        #[cfg(feature = "x11")]
         Backend::X(ref connec) => MonitorId::X(x11::get_primary_monitor(connec)),
-        Backend::Error(_) => MonitorId::None,
+        _ => MonitorId::None,
     }
 }
 
 impl MonitorId {
     #[inline]
     pub fn get_name(&self) -> Option<String> {
-        match self {
-            &MonitorId::X(ref m) => m.get_name(),
-            &MonitorId::None => None,
+        match *self {
+            #[cfg(feature = "x11")]
+            MonitorId::X(ref m) => m.get_name(),
+            MonitorId::None => None,
         }
     }
 
     #[inline]
     pub fn get_native_identifier(&self) -> ::native_monitor::NativeMonitorId {
-        match self {
-            &MonitorId::X(ref m) => m.get_native_identifier(),
-            &MonitorId::None => unimplemented!()        // FIXME:
+        match *self {
+            #[cfg(feature = "x11")]
+            MonitorId::X(ref m) => m.get_native_identifier(),
+            MonitorId::None => unimplemented!()        // FIXME:
         }
     }
 
     #[inline]
     pub fn get_dimensions(&self) -> (u32, u32) {
-        match self {
-            &MonitorId::X(ref m) => m.get_dimensions(),
-            &MonitorId::None => (800, 600),     // FIXME:
+        match *self {
+            #[cfg(feature = "x11")]
+            MonitorId::X(ref m) => m.get_dimensions(),
+            MonitorId::None => (800, 600),     // FIXME:
         }
     }
 }
 
 
+#[cfg(feature = "x11")]
 pub use api::x11::{PollEventsIterator, WaitEventsIterator};
 
+/// Never produces any event, as no window can be built without a windowing backend.
+#[cfg(not(feature = "x11"))]
+pub struct PollEventsIterator<'a>(::std::marker::PhantomData<&'a Window>);
+
+#[cfg(not(feature = "x11"))]
+impl<'a> Iterator for PollEventsIterator<'a> {
+    type Item = Event;
+
+    #[inline]
+    fn next(&mut self) -> Option<Event> {
+        None
+    }
+}
+
+/// Never produces any event, as no window can be built without a windowing backend.
+#[cfg(not(feature = "x11"))]
+pub struct WaitEventsIterator<'a>(::std::marker::PhantomData<&'a Window>);
+
+#[cfg(not(feature = "x11"))]
+impl<'a> Iterator for WaitEventsIterator<'a> {
+    type Item = Event;
+
+    #[inline]
+    fn next(&mut self) -> Option<Event> {
+        None
+    }
+}
+
 impl Window {
     #[inline]
     pub fn new(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               opengl: &GlAttributes<&Window>, pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
         match *BACKEND {
+            #[cfg(feature = "x11")]
             Backend::X(ref connec) => {
                 let opengl = opengl.clone().map_sharing(|w| match w {
                     &Window::X(ref w) => w,
                 });
 
-                x11::Window::new(connec, window, pf_reqs, &opengl).map(Window::X)
+                x11::Window::new(connec, window, pf_reqs, &opengl, pl_attribs).map(Window::X)
             },
 
-            Backend::Error(ref error) => Err(CreationError::NoBackendAvailable(Box::new(error.clone())))
+            #[cfg(feature = "x11")]
+            Backend::Error(ref error) => Err(CreationError::NoBackendAvailable(Box::new(error.clone()))),
+
+            #[cfg(not(feature = "x11"))]
+            Backend::None => Err(CreationError::NotSupported),
         }
     }
 
     #[inline]
     pub fn set_title(&self, title: &str) {
-        match self {
-            &Window::X(ref w) => w.set_title(title),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_title(title),
         }
     }
 
     #[inline]
     pub fn show(&self) {
-        match self {
-            &Window::X(ref w) => w.show(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.show(),
         }
     }
 
     #[inline]
     pub fn hide(&self) {
-        match self {
-            &Window::X(ref w) => w.hide(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.hide(),
         }
     }
 
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
-        match self {
-            &Window::X(ref w) => w.get_position(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_position(),
         }
     }
 
     #[inline]
     pub fn set_position(&self, x: i32, y: i32) {
-        match self {
-            &Window::X(ref w) => w.set_position(x, y),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_position(x, y),
         }
     }
 
     #[inline]
     pub fn get_inner_size(&self) -> Option<(u32, u32)> {
-        match self {
-            &Window::X(ref w) => w.get_inner_size(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_inner_size(),
         }
     }
 
     #[inline]
     pub fn get_outer_size(&self) -> Option<(u32, u32)> {
-        match self {
-            &Window::X(ref w) => w.get_outer_size(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_outer_size(),
         }
     }
 
     #[inline]
     pub fn set_inner_size(&self, x: u32, y: u32) {
-        match self {
-            &Window::X(ref w) => w.set_inner_size(x, y),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_inner_size(x, y),
         }
     }
 
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
-        match self {
-            &Window::X(ref w) => WindowProxy::X(w.create_window_proxy()),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => WindowProxy::X(w.create_window_proxy()),
         }
     }
 
     #[inline]
     pub fn poll_events(&self) -> PollEventsIterator {
-        match self {
-            &Window::X(ref w) => w.poll_events(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.poll_events(),
         }
     }
 
     #[inline]
     pub fn wait_events(&self) -> WaitEventsIterator {
-        match self {
-            &Window::X(ref w) => w.wait_events(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.wait_events(),
         }
     }
 
     #[inline]
     pub fn set_window_resize_callback(&mut self, callback: Option<fn(u32, u32)>) {
-        match self {
-            &mut Window::X(ref mut w) => w.set_window_resize_callback(callback),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref mut w) => w.set_window_resize_callback(callback),
         }
     }
 
     #[inline]
     pub fn set_cursor(&self, cursor: MouseCursor) {
-        match self {
-            &Window::X(ref w) => w.set_cursor(cursor),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_cursor(cursor),
         }
     }
 
     #[inline]
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        match self {
-            &Window::X(ref w) => w.set_cursor_state(state),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_cursor_state(state),
         }
     }
 
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
-       match self {
-            &Window::X(ref w) => w.hidpi_factor(),
+       match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.hidpi_factor(),
+        }
+    }
+
+    #[inline]
+    pub fn get_swapper(&self) -> Option<SwapFn> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_swapper(),
+        }
+    }
+
+    #[inline]
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.supports_variable_refresh_rate(),
         }
     }
 
     #[inline]
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
-        match self {
-            &Window::X(ref w) => w.set_cursor_position(x, y),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_cursor_position(x, y),
         }
     }
 
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
-        match self {
-            &Window::X(ref w) => w.platform_display(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.platform_display(),
         }
     }
 
     #[inline]
     pub fn platform_window(&self) -> *mut libc::c_void {
-        match self {
-            &Window::X(ref w) => w.platform_window(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.platform_window(),
+        }
+    }
+
+    #[inline]
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.create_pbuffer(dimensions).map(PBuffer::X),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer, PixmapSurface>,
+                                             read: Drawable<Window, PBuffer, PixmapSurface>)
+                                             -> Result<(), ContextError>
+    {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => {
+                let draw = try!(draw.map(|w| match *w { Window::X(ref w) => Ok(w) },
+                                         |p| match *p { PBuffer::X(ref p) => Ok(p) },
+                                         |x| match *x { PixmapSurface::X(ref x) => Ok(x) }));
+                let read = try!(read.map(|w| match *w { Window::X(ref w) => Ok(w) },
+                                         |p| match *p { PBuffer::X(ref p) => Ok(p) },
+                                         |x| match *x { PixmapSurface::X(ref x) => Ok(x) }));
+                w.make_current_with_surfaces(draw, read)
+            },
         }
     }
 }
@@ -263,47 +407,256 @@ impl Window {
 impl GlContext for Window {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        match self {
-            &Window::X(ref w) => w.make_current(),
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.make_current(),
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.is_current(),
+        }
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_proc_address(addr),
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_api(),
+        }
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_pixel_format(),
+        }
+    }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_failed_versions(),
+        }
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_config_limits(),
+        }
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.get_renderer_info(),
+        }
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.make_not_current(),
+        }
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.destroy_context(),
+        }
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.is_back_buffer_preserved(),
+        }
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.is_front_buffer_rendering(),
+        }
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_damage_region(rects),
+        }
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_hdr_metadata(metadata),
+        }
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X(ref w) => w.set_swap_interval(interval),
+        }
+    }
+}
+
+pub enum PBuffer {
+    #[doc(hidden)]
+    #[cfg(feature = "x11")]
+    X(x11::PBuffer),
+}
+
+impl GlContext for PBuffer {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            PBuffer::X(ref p) => p.make_current(),
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            PBuffer::X(ref p) => p.is_current(),
+        }
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        match *self {
+            #[cfg(feature = "x11")]
+            PBuffer::X(ref p) => p.get_proc_address(addr),
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            PBuffer::X(ref p) => p.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        match *self {
+            #[cfg(feature = "x11")]
+            PBuffer::X(ref p) => p.get_api(),
+        }
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {
+            #[cfg(feature = "x11")]
+            PBuffer::X(ref p) => p.get_pixel_format(),
+        }
+    }
+}
+
+pub enum PixmapSurface {
+    #[doc(hidden)]
+    #[cfg(feature = "x11")]
+    X(x11::PixmapSurface),
+}
+
+impl PixmapSurface {
+    #[inline]
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            PixmapSurface::X(ref p) => p.get_dimensions(),
+        }
+    }
+}
+
+impl GlContext for PixmapSurface {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {
+            #[cfg(feature = "x11")]
+            PixmapSurface::X(ref p) => p.make_current(),
         }
     }
 
     #[inline]
     fn is_current(&self) -> bool {
-        match self {
-            &Window::X(ref w) => w.is_current(),
+        match *self {
+            #[cfg(feature = "x11")]
+            PixmapSurface::X(ref p) => p.is_current(),
         }
     }
 
     #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
-        match self {
-            &Window::X(ref w) => w.get_proc_address(addr),
+        match *self {
+            #[cfg(feature = "x11")]
+            PixmapSurface::X(ref p) => p.get_proc_address(addr),
         }
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
-        match self {
-            &Window::X(ref w) => w.swap_buffers(),
+        match *self {
+            #[cfg(feature = "x11")]
+            PixmapSurface::X(ref p) => p.swap_buffers(),
         }
     }
 
     #[inline]
     fn get_api(&self) -> ::Api {
-        match self {
-            &Window::X(ref w) => w.get_api(),
+        match *self {
+            #[cfg(feature = "x11")]
+            PixmapSurface::X(ref p) => p.get_api(),
         }
     }
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        match self {
-            &Window::X(ref w) => w.get_pixel_format(),
+        match *self {
+            #[cfg(feature = "x11")]
+            PixmapSurface::X(ref p) => p.get_pixel_format(),
         }
     }
 }
 
+#[cfg(feature = "x11")]
 unsafe extern "C" fn x_error_callback(dpy: *mut x11::ffi::Display, event: *mut x11::ffi::XErrorEvent)
                                       -> libc::c_int
 {
@@ -1,24 +1,31 @@
-#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+           target_os = "openbsd", target_os = "netbsd"))]
 
 use Api;
+use ConfigLimits;
 use ContextError;
 use CreationError;
 use GlAttributes;
 use GlContext;
 use PixelFormat;
 use PixelFormatRequirements;
+use api::Drawable;
 
+#[cfg(feature = "osmesa")]
 use api::osmesa::{self, OsMesaContext};
 
 pub use self::api_dispatch::{Window, WindowProxy, MonitorId, get_available_monitors, get_primary_monitor};
-pub use self::api_dispatch::{WaitEventsIterator, PollEventsIterator};
+pub use self::api_dispatch::{WaitEventsIterator, PollEventsIterator, PBuffer, PixmapSurface};
 pub use self::api_dispatch::PlatformSpecificWindowBuilderAttributes;
 mod api_dispatch;
 
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
-pub struct HeadlessContext(OsMesaContext);
+pub enum HeadlessContext {
+    #[cfg(feature = "osmesa")]
+    OsMesa(OsMesaContext),
+}
 
 impl HeadlessContext {
     pub fn new(dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements,
@@ -26,46 +33,97 @@ impl HeadlessContext {
                _: &PlatformSpecificHeadlessBuilderAttributes)
                -> Result<HeadlessContext, CreationError>
     {
-        let opengl = opengl.clone().map_sharing(|c| &c.0);
+        #[cfg(feature = "osmesa")]
+        {
+            let opengl = opengl.clone().map_sharing(|c| match *c {
+                HeadlessContext::OsMesa(ref c) => c,
+            });
+
+            match OsMesaContext::new(dimensions, pf_reqs, &opengl) {
+                Ok(c) => return Ok(HeadlessContext::OsMesa(c)),
+                Err(osmesa::OsMesaCreationError::NotSupported) => (),
+                Err(osmesa::OsMesaCreationError::CreationError(e)) => return Err(e),
+            };
+        }
 
-        match OsMesaContext::new(dimensions, pf_reqs, &opengl) {
-            Ok(c) => return Ok(HeadlessContext(c)),
-            Err(osmesa::OsMesaCreationError::NotSupported) => (),
-            Err(osmesa::OsMesaCreationError::CreationError(e)) => return Err(e),
-        };
+        Err(CreationError::NotSupported)
+    }
+
+    /// OSMesa has no configs.
+    #[inline]
+    pub fn query_config_limits(_: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+                               _: &PlatformSpecificHeadlessBuilderAttributes)
+                               -> Result<ConfigLimits, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
 
+    /// OSMesa renders into a buffer in memory, which can't be shared with another surface.
+    #[inline]
+    pub fn create_pbuffer(&self, _: (u32, u32)) -> Result<PBuffer, CreationError> {
         Err(CreationError::NotSupported)
     }
+
+    /// The buffer of OSMesa can't be combined with the surfaces of windows, so only the default
+    /// surfaces can be used.
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer, PixmapSurface>,
+                                             read: Drawable<Window, PBuffer, PixmapSurface>)
+                                             -> Result<(), ContextError>
+    {
+        if !draw.is_default() || !read.is_default() {
+            return Err(ContextError::NotSupported("the surface uses another API"));
+        }
+
+        self.make_current()
+    }
 }
 
 impl GlContext for HeadlessContext {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.0.make_current()
+        match *self {
+            #[cfg(feature = "osmesa")]
+            HeadlessContext::OsMesa(ref c) => c.make_current(),
+        }
     }
 
     #[inline]
     fn is_current(&self) -> bool {
-        self.0.is_current()
+        match *self {
+            #[cfg(feature = "osmesa")]
+            HeadlessContext::OsMesa(ref c) => c.is_current(),
+        }
     }
 
     #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
-        self.0.get_proc_address(addr)
+        match *self {
+            #[cfg(feature = "osmesa")]
+            HeadlessContext::OsMesa(ref c) => c.get_proc_address(addr),
+        }
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.0.swap_buffers()
+        match *self {
+            #[cfg(feature = "osmesa")]
+            HeadlessContext::OsMesa(ref c) => c.swap_buffers(),
+        }
     }
 
     #[inline]
     fn get_api(&self) -> Api {
-        self.0.get_api()
+        match *self {
+            #[cfg(feature = "osmesa")]
+            HeadlessContext::OsMesa(ref c) => c.get_api(),
+        }
     }
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        self.0.get_pixel_format()
+        match *self {
+            #[cfg(feature = "osmesa")]
+            HeadlessContext::OsMesa(ref c) => c.get_pixel_format(),
+        }
     }
 }
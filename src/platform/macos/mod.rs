@@ -1,3 +1,4 @@
 #![cfg(target_os = "macos")]
 
 pub use api::cocoa::*;
+pub use api::NoPixmap as PixmapSurface;
@@ -3,58 +3,136 @@
 pub use api::win32;
 pub use api::win32::{MonitorId, get_available_monitors, get_primary_monitor};
 pub use api::win32::{WindowProxy, PollEventsIterator, WaitEventsIterator};
+pub use api::win32::PBuffer;
+pub use api::NoPixmap as PixmapSurface;
 
 use Api;
+use ConfigLimits;
+use RendererInfo;
+use Rect;
+use HdrMetadata;
 use ContextError;
 use CreationError;
+use FailedVersion;
+#[cfg(feature = "egl")]
+use EglPlatform;
 use PixelFormat;
 use PixelFormatRequirements;
+use PresentMode;
 use GlAttributes;
 use GlContext;
+#[cfg(feature = "egl")]
+use GlRequest;
 use WindowAttributes;
+use api::Drawable;
 
-use api::egl::ffi::egl::Egl;
+use api::win32::Egl;
+#[cfg(feature = "egl")]
 use api::egl;
+#[cfg(feature = "egl")]
 use api::egl::Context as EglContext;
 
 use std::ffi::CString;
+#[cfg(feature = "egl")]
+use std::os::raw::c_void;
 use std::ops::{Deref, DerefMut};
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
 use kernel32;
+use winapi;
+
+use libraries::{self, Library};
 
 /// Stupid wrapper because `*const libc::c_void` doesn't implement `Sync`.
+#[cfg(feature = "egl")]
 struct EglWrapper(Egl);
+#[cfg(feature = "egl")]
 unsafe impl Sync for EglWrapper {}
 
+#[cfg(feature = "egl")]
 lazy_static! {
     // An EGL implementation available on the system.
-    static ref EGL: Option<EglWrapper> = {
-        // the ATI drivers provide an EGL implementation in their DLLs
-        let ati_dll_name = if cfg!(target_pointer_width = "64") {
-            b"atio6axx.dll\0"
-        } else {
-            b"atioglxx.dll\0"
-        };
+    static ref EGL: Option<EglWrapper> = load_egl().map(EglWrapper);
+}
 
-        for dll_name in &[b"libEGL.dll\0" as &[u8], ati_dll_name] {
-            let dll = unsafe { kernel32::LoadLibraryA(dll_name.as_ptr() as *const _) };
-            if dll.is_null() {
-                continue;
-            }
+/// Returns the EGL implementation available on the system, if any.
+#[cfg(feature = "egl")]
+#[inline]
+fn egl() -> Option<&'static Egl> {
+    EGL.as_ref().map(|w| &w.0)
+}
 
-            let egl = Egl::load_with(|name| {
-                let name = CString::new(name).unwrap();
-                unsafe { kernel32::GetProcAddress(dll, name.as_ptr()) as *const _ }
-            });
+#[cfg(not(feature = "egl"))]
+#[inline]
+fn egl() -> Option<&'static Egl> {
+    None
+}
+
+/// Returns the EGL implementation that was linked at compile-time.
+#[cfg(all(feature = "egl", feature = "static-egl"))]
+#[inline]
+fn load_egl() -> Option<Egl> {
+    Some(Egl)
+}
+
+/// Loads the EGL implementation, if any.
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+fn load_egl() -> Option<Egl> {
+    if let Some(loader) = libraries::egl_loader() {
+        return Some(Egl::load_with(|name| loader(name)));
+    }
 
-            return Some(EglWrapper(egl))
+    // loading a user-provided GLES library first, so that the EGL implementation
+    // resolves to it instead of the system one
+    for path in libraries::candidates(Library::GlesV2) {
+        if !load_library(&path).is_null() {
+            break;
         }
+    }
+
+    let mut candidates = libraries::candidates(Library::Egl);
+
+    // the ATI drivers provide an EGL implementation in their DLLs
+    if libraries::get_library_path(Library::Egl).is_none() &&
+       !libraries::is_library_loading_sealed()
+    {
+        candidates.push(PathBuf::from(if cfg!(target_pointer_width = "64") {
+            "atio6axx.dll"
+        } else {
+            "atioglxx.dll"
+        }));
+    }
+
+    for path in &candidates {
+        let dll = load_library(path);
+        if dll.is_null() {
+            continue;
+        }
+
+        let egl = Egl::load_with(|name| {
+            let name = CString::new(name).unwrap();
+            unsafe { kernel32::GetProcAddress(dll, name.as_ptr()) as *const _ }
+        });
+
+        return Some(egl);
+    }
+
+    None
+}
 
-        None
-    };
+/// Calls `LoadLibraryW` with the given path.
+#[cfg(all(feature = "egl", not(feature = "static-egl")))]
+fn load_library(path: &Path) -> winapi::HMODULE {
+    let name = path.as_os_str().encode_wide().chain(Some(0).into_iter()).collect::<Vec<_>>();
+    unsafe { kernel32::LoadLibraryW(name.as_ptr()) }
 }
 
 #[derive(Clone, Default)]
-pub struct PlatformSpecificWindowBuilderAttributes;
+pub struct PlatformSpecificWindowBuilderAttributes {
+    pub present_mode: PresentMode,
+    pub exclusive_fullscreen: bool,
+    pub layered: bool,
+}
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
@@ -65,11 +143,23 @@ impl Window {
     /// See the docs in the crate root file.
     #[inline]
     pub fn new(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               opengl: &GlAttributes<&Window>, pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
-        win32::Window::new(window, pf_reqs, &opengl.clone().map_sharing(|w| &w.0),
-                           EGL.as_ref().map(|w| &w.0)).map(|w| Window(w))
+        win32::Window::new(window, pf_reqs, &opengl.clone().map_sharing(|w| &w.0), egl(),
+                           pl_attribs)
+            .map(|w| Window(w))
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        let draw = try!(draw.map(|w| Ok(&w.0), |p| Ok(p), |x| Ok(x)));
+        let read = try!(read.map(|w| Ok(&w.0), |p| Ok(p), |x| Ok(x)));
+        self.0.make_current_with_surfaces(draw, read)
     }
 }
 
@@ -94,6 +184,7 @@ pub enum HeadlessContext {
     /// A regular window, but invisible.
     HiddenWindow(win32::Window),
     /// An EGL pbuffer.
+    #[cfg(feature = "egl")]
     EglPbuffer(EglContext),
 }
 
@@ -105,22 +196,77 @@ impl HeadlessContext {
     {
         // if EGL is available, we try using EGL first
         // if EGL returns an error, we try the hidden window method
-        if let &Some(ref egl) = &*EGL {
-            let context = EglContext::new(egl.0.clone(), pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),       // TODO: 
-                                          egl::NativeDisplay::Other(None))
-                                .and_then(|prototype| prototype.finish_pbuffer(dimensions))
-                                .map(|ctxt| HeadlessContext::EglPbuffer(ctxt));
-
-            if let Ok(context) = context {
-                return Ok(context);
+        #[cfg(feature = "egl")]
+        {
+            if let Some(egl) = egl() {
+                let context = EglContext::new(egl.clone(), pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),       // TODO: 
+                                              egl::NativeDisplay::Other(None), EglPlatform::Auto)
+                                    .and_then(|prototype| prototype.finish_pbuffer(dimensions))
+                                    .map(|ctxt| HeadlessContext::EglPbuffer(ctxt));
+
+                if let Ok(context) = context {
+                    return Ok(context);
+                }
             }
         }
 
         let window = try!(win32::Window::new(&WindowAttributes { visible: false, .. Default::default() },
                                              pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),            //TODO:
-                                             EGL.as_ref().map(|w| &w.0)));
+                                             egl(), &Default::default()));
         Ok(HeadlessContext::HiddenWindow(window))
     }
+
+    /// Chooses the EGL config like `new` does, without creating the context. The pixel formats
+    /// of WGL can't be queried without a context.
+    pub fn query_config_limits(pf_reqs: &PixelFormatRequirements,
+                               opengl: &GlAttributes<&HeadlessContext>,
+                               _: &PlatformSpecificHeadlessBuilderAttributes)
+                               -> Result<ConfigLimits, CreationError>
+    {
+        #[cfg(feature = "egl")]
+        {
+            if let (Some(egl), None) = (egl(), opengl.sharing) {
+                let opengl = opengl.clone().map_sharing(|_| unreachable!());
+                let prototype = try!(EglContext::new(egl.clone(), pf_reqs, &opengl,
+                                                     egl::NativeDisplay::Other(None),
+                                                     EglPlatform::Auto));
+                return Ok(prototype.get_config_limits());
+            }
+        }
+
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn create_pbuffer(&self, dimensions: (u32, u32)) -> Result<PBuffer, CreationError> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.create_pbuffer(dimensions),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => {
+                ctxt.create_pbuffer(dimensions).map(PBuffer::Egl)
+            },
+        }
+    }
+
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Drawable<Window, PBuffer>,
+                                             read: Drawable<Window, PBuffer>)
+                                             -> Result<(), ContextError>
+    {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => {
+                let draw = try!(draw.map(|w| Ok(&w.0), |p| Ok(p), |x| Ok(x)));
+                let read = try!(read.map(|w| Ok(&w.0), |p| Ok(p), |x| Ok(x)));
+                ctxt.make_current_with_surfaces(draw, read)
+            },
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => {
+                let draw = try!(draw.map(|w| w.0.egl_context(), PBuffer::as_egl, |x| match *x {}));
+                let read = try!(read.map(|w| w.0.egl_context(), PBuffer::as_egl, |x| match *x {}));
+                ctxt.make_current_with_surfaces(draw, read)
+            },
+        }
+    }
 }
 
 impl GlContext for HeadlessContext {
@@ -128,6 +274,7 @@ impl GlContext for HeadlessContext {
     unsafe fn make_current(&self) -> Result<(), ContextError> {
         match self {
             &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.make_current(),
+            #[cfg(feature = "egl")]
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.make_current(),
         }
     }
@@ -136,6 +283,7 @@ impl GlContext for HeadlessContext {
     fn is_current(&self) -> bool {
         match self {
             &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.is_current(),
+            #[cfg(feature = "egl")]
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.is_current(),
         }
     }
@@ -144,6 +292,7 @@ impl GlContext for HeadlessContext {
     fn get_proc_address(&self, addr: &str) -> *const () {
         match self {
             &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.get_proc_address(addr),
+            #[cfg(feature = "egl")]
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_proc_address(addr),
         }
     }
@@ -152,6 +301,7 @@ impl GlContext for HeadlessContext {
     fn swap_buffers(&self) -> Result<(), ContextError> {
         match self {
             &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.swap_buffers(),
+            #[cfg(feature = "egl")]
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.swap_buffers(),
         }
     }
@@ -160,6 +310,7 @@ impl GlContext for HeadlessContext {
     fn get_api(&self) -> Api {
         match self {
             &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.get_api(),
+            #[cfg(feature = "egl")]
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_api(),
         }
     }
@@ -168,7 +319,243 @@ impl GlContext for HeadlessContext {
     fn get_pixel_format(&self) -> PixelFormat {
         match self {
             &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.get_pixel_format(),
+            #[cfg(feature = "egl")]
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_pixel_format(),
         }
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.get_failed_versions(),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_failed_versions(),
+        }
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.get_config_limits(),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_config_limits(),
+        }
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.get_renderer_info(),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_renderer_info(),
+        }
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.make_not_current(),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.make_not_current(),
+        }
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.destroy_context(),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.destroy_context(),
+        }
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.is_back_buffer_preserved(),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.is_back_buffer_preserved(),
+        }
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.is_front_buffer_rendering(),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.is_front_buffer_rendering(),
+        }
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.set_damage_region(rects),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.set_damage_region(rects),
+        }
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.set_hdr_metadata(metadata),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.set_hdr_metadata(metadata),
+        }
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.set_swap_interval(interval),
+            #[cfg(feature = "egl")]
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.set_swap_interval(interval),
+        }
+    }
+}
+
+/// An EGL context that renders into a `CoreWindow` or a `SwapChainPanel` of a UWP application.
+///
+/// This requires an EGL implementation that supports WinRT windows, like ANGLE. Because UWP
+/// applications can't load libraries from arbitrary paths, the EGL functions usually have to come
+/// from `set_egl_loader`.
+#[cfg(feature = "egl")]
+pub struct CoreWindowContext(EglContext);
+
+#[cfg(feature = "egl")]
+impl CoreWindowContext {
+    /// Creates a context that renders into `window`, which must be a pointer to the
+    /// `IInspectable` interface of a `CoreWindow`, of a `SwapChainPanel` or of a `PropertySet`
+    /// that contains one of them.
+    ///
+    /// # Unsafety
+    ///
+    /// The window must continue to exist as long as the resulting context exists.
+    pub unsafe fn new(window: *mut c_void, version: GlRequest)
+                      -> Result<CoreWindowContext, CreationError>
+    {
+        let egl = match egl() {
+            Some(egl) => egl,
+            None => return Err(CreationError::NotSupported),
+        };
+
+        CoreWindowContext::with_egl(egl.clone(), window, version)
+    }
+
+    /// Like `new`, but obtains the EGL functions from `loader` instead of `set_egl_loader` or
+    /// the EGL library of the application, for example from the function table of a host
+    /// process.
+    ///
+    /// The loader receives the name of an EGL function and must return its address, or null if
+    /// it is not available. It's only called while the context is created. With the
+    /// `static-egl` feature, the EGL functions are linked at compile-time and the loader is
+    /// ignored.
+    ///
+    /// # Unsafety
+    ///
+    /// Same as `new`. The functions must also stay valid as long as the context exists.
+    pub unsafe fn with_egl_loader<F>(window: *mut c_void, version: GlRequest, loader: F)
+                                     -> Result<CoreWindowContext, CreationError>
+        where F: Fn(&str) -> *const c_void
+    {
+        #[cfg(not(feature = "static-egl"))]
+        let egl = Egl::load_with(|name| loader(name));
+        #[cfg(feature = "static-egl")]
+        let egl = { let _ = loader; Egl };
+        CoreWindowContext::with_egl(egl, window, version)
+    }
+
+    unsafe fn with_egl(egl: Egl, window: *mut c_void, version: GlRequest)
+                       -> Result<CoreWindowContext, CreationError>
+    {
+        let opengl = GlAttributes { version: version, .. Default::default() };
+        EglContext::new(egl, &Default::default(), &opengl, egl::NativeDisplay::Other(None),
+                        EglPlatform::Auto)
+            .and_then(|prototype| prototype.finish(window as _))
+            .map(CoreWindowContext)
+    }
+}
+
+#[cfg(feature = "egl")]
+impl GlContext for CoreWindowContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.0.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.0.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.0.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.0.swap_buffers()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.0.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.0.get_pixel_format()
+    }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.0.get_failed_versions()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.0.get_config_limits()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.0.get_renderer_info()
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.0.make_not_current()
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        self.0.destroy_context()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.0.is_back_buffer_preserved()
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        self.0.is_front_buffer_rendering()
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.0.set_damage_region(rects)
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        self.0.set_hdr_metadata(metadata)
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        self.0.set_swap_interval(interval)
+    }
 }
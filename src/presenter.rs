@@ -0,0 +1,101 @@
+//! Swaps the buffers of a window on another thread, enabled with `Window::set_async_swap`.
+
+use std::io;
+use std::mem;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use ContextError;
+
+/// Swaps the buffers of a window from any thread. Only the backends that don't need the context
+/// to be current in the calling thread can provide one.
+pub type SwapFn = Box<FnMut() -> Result<(), ContextError> + Send>;
+
+/// A thread that swaps the buffers of a window, one frame at a time.
+pub struct Presenter {
+    requests: Option<SyncSender<()>>,
+    results: Receiver<Result<(), ContextError>>,
+    pending: bool,
+    flush: extern "system" fn(),
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Presenter {
+    /// Starts the thread. `flush` is the address of `glFlush`.
+    pub fn new(swap: SwapFn, flush: *const ()) -> Result<Presenter, ContextError> {
+        if flush.is_null() {
+            return Err(ContextError::NotSupported("glFlush couldn't be loaded"));
+        }
+
+        // at most one swap is in progress, and bounded channels don't allocate for each message
+        let (requests, requests_receiver) = mpsc::sync_channel(1);
+        let (results_sender, results) = mpsc::sync_channel(1);
+
+        let mut swap = swap;
+        let thread = try!(thread::Builder::new().name("glutin presentation".to_owned())
+                                                .spawn(move || {
+            for () in requests_receiver.iter() {
+                if results_sender.send(swap()).is_err() {
+                    break;
+                }
+            }
+        }).map_err(ContextError::IoError));
+
+        Ok(Presenter {
+            requests: Some(requests),
+            results: results,
+            pending: false,
+            flush: unsafe { mem::transmute(flush) },
+            thread: Some(thread),
+        })
+    }
+
+    /// Waits for the swap of the previous frame, then flushes the commands of this frame and
+    /// hands its swap to the thread.
+    ///
+    /// Returns the error of the previous swap, if any.
+    pub fn swap(&mut self) -> Result<(), ContextError> {
+        try!(self.finish());
+
+        // the thread doesn't use the context, which must have sent the frame to the GPU first
+        (self.flush)();
+
+        if self.requests.as_ref().unwrap().send(()).is_err() {
+            return Err(stopped());
+        }
+        self.pending = true;
+        Ok(())
+    }
+
+    /// Waits for the swap in progress, if any, and returns its result.
+    pub fn finish(&mut self) -> Result<(), ContextError> {
+        if !self.pending {
+            return Ok(());
+        }
+
+        self.pending = false;
+        match self.results.recv() {
+            Ok(result) => result,
+            Err(_) => Err(stopped()),
+        }
+    }
+}
+
+impl Drop for Presenter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+
+        // closing the channel stops the thread
+        self.requests = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The thread only stops early if the swap panicked.
+#[inline]
+fn stopped() -> ContextError {
+    ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                         "the presentation thread has stopped"))
+}
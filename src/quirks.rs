@@ -0,0 +1,269 @@
+//! Workarounds for the bugs of some drivers, applied automatically when a context is created.
+//!
+//! A quirk is applied when the vendor and version strings of the EGL or GLX implementation
+//! contain the strings of one of its rules. These are the only strings that are known before
+//! the pixel format is chosen: `EGL_VENDOR` and `EGL_VERSION` with EGL, and the `GLX_VENDOR`
+//! and `GLX_VERSION` of the client library with GLX.
+//!
+//! The rules and the quirks that are enabled are read when a context is created, therefore
+//! changing them doesn't affect the existing contexts.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use BitsRequirement;
+use CreationError;
+use PixelFormat;
+use PixelFormatRequirements;
+
+/// A workaround for a driver bug.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Quirk {
+    /// Pbuffers are reported as not supported, for drivers that advertise them but crash or
+    /// render garbage into them.
+    NoPbuffers,
+
+    /// The pixel format is restricted to 8 bits per channel and 8 bits of alpha, for drivers
+    /// whose other formats are slow or broken. Not applied if more bits or a floating-point
+    /// color buffer are explicitly requested.
+    ForceRgba8,
+
+    /// sRGB pixel formats are never chosen, for drivers whose sRGB framebuffers give wrong
+    /// colors. A strict request of sRGB fails with `NoAvailablePixelFormat` instead.
+    NoSrgb,
+
+    /// The EGL configs whose `EGL_NATIVE_VISUAL_ID` is a window format are preferred, for
+    /// platforms whose compositor converts the buffers of the other configs on the GPU instead
+    /// of scanning them out.
+    PreferNativeVisual,
+
+    /// The config that exceeds the requested number of bits by the least is preferred over the
+    /// one with the most bits, which EGL lists first, for tiled GPUs where every extra bit costs
+    /// bandwidth.
+    PreferSmallestConfig,
+}
+
+/// Makes a quirk apply to the drivers whose strings contain `vendor` and `version`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuirkRule {
+    /// The quirk to apply.
+    pub quirk: Quirk,
+
+    /// Must be part of the vendor string of the implementation.
+    pub vendor: String,
+
+    /// Must be part of the version string of the implementation. `None` matches any version.
+    pub version: Option<String>,
+}
+
+impl QuirkRule {
+    #[inline]
+    fn matches(&self, vendor: &str, version: &str) -> bool {
+        vendor.contains(&*self.vendor) &&
+            self.version.as_ref().map(|v| version.contains(&**v)).unwrap_or(true)
+    }
+}
+
+struct Quirks {
+    enabled: bool,
+    disabled: HashSet<Quirk>,
+    rules: Vec<QuirkRule>,
+}
+
+lazy_static! {
+    static ref QUIRKS: Mutex<Quirks> = Mutex::new(Quirks {
+        enabled: true,
+        disabled: HashSet::new(),
+        rules: default_rules(),
+    });
+}
+
+/// The rules of the drivers that glutin knows about.
+fn default_rules() -> Vec<QuirkRule> {
+    let rule = |quirk, vendor: &str, version: Option<&str>| QuirkRule {
+        quirk: quirk,
+        vendor: vendor.to_owned(),
+        version: version.map(|v| v.to_owned()),
+    };
+
+    vec![
+        // the Utgard generation of Mali GPUs (Mali-400 and Mali-450)
+        rule(Quirk::NoPbuffers, "ARM", Some("Utgard")),
+        // Adreno prefers 16 bits or 10 bits per channel formats when they come first
+        rule(Quirk::ForceRgba8, "Qualcomm", None),
+        rule(Quirk::NoSrgb, "Vivante", None),
+        // the EGL loader of Android reports its own vendor whatever the GPU
+        rule(Quirk::PreferNativeVisual, "Android", None),
+        rule(Quirk::PreferSmallestConfig, "ARM", None),
+        rule(Quirk::PreferSmallestConfig, "Imagination", None),
+    ]
+}
+
+/// Adds a rule to the list, in addition to the rules of glutin.
+pub fn add_rule(rule: QuirkRule) {
+    QUIRKS.lock().unwrap().rules.push(rule);
+}
+
+/// Enables or disables a quirk for all the drivers, whatever the rules say. All the quirks are
+/// enabled by default.
+pub fn set_quirk_enabled(quirk: Quirk, enabled: bool) {
+    let mut quirks = QUIRKS.lock().unwrap();
+    if enabled {
+        quirks.disabled.remove(&quirk);
+    } else {
+        quirks.disabled.insert(quirk);
+    }
+}
+
+/// Enables or disables all the quirks, including those of the rules added with `add_rule`.
+pub fn set_quirks_enabled(enabled: bool) {
+    QUIRKS.lock().unwrap().enabled = enabled;
+}
+
+/// Returns the quirks that apply to a driver, given its vendor and version strings.
+pub fn active_quirks(vendor: &str, version: &str) -> Vec<Quirk> {
+    let quirks = QUIRKS.lock().unwrap();
+    if !quirks.enabled {
+        return Vec::new();
+    }
+
+    let mut active = Vec::new();
+    for rule in &quirks.rules {
+        if rule.matches(vendor, version) && !quirks.disabled.contains(&rule.quirk) &&
+           !active.contains(&rule.quirk)
+        {
+            active.push(rule.quirk);
+        }
+    }
+    active
+}
+
+/// Returns the requirements to choose the pixel format with once the quirks are applied, or
+/// an error if a quirk can't be applied to strict requirements.
+#[doc(hidden)]
+pub fn apply_to_pixel_format(quirks: &[Quirk], reqs: &PixelFormatRequirements)
+                             -> Result<PixelFormatRequirements, CreationError>
+{
+    let mut reqs = reqs.clone();
+
+    if quirks.contains(&Quirk::ForceRgba8) && !reqs.float_color_buffer &&
+       reqs.color_bits.accepts(24) && reqs.alpha_bits.accepts(8)
+    {
+        reqs.color_bits = BitsRequirement::Exact(24);
+        reqs.alpha_bits = BitsRequirement::Exact(8);
+    }
+
+    if quirks.contains(&Quirk::NoSrgb) && reqs.srgb {
+        if reqs.strict {
+            debug_log!("sRGB is requested strictly, but the driver has the NoSrgb quirk");
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+        reqs.srgb = false;
+    }
+
+    Ok(reqs)
+}
+
+/// Ranks a pixel format that fulfills the requirements, the highest score being the best. The
+/// backends keep the order of the platform between the formats with the same score.
+///
+/// `native_visual` tells whether the format has a native visual, which only EGL reports.
+#[doc(hidden)]
+pub fn score_pixel_format(quirks: &[Quirk], reqs: &PixelFormatRequirements,
+                          format: &PixelFormat, native_visual: bool) -> i32
+{
+    let mut score = 0;
+
+    if quirks.contains(&Quirk::PreferNativeVisual) && !native_visual {
+        score -= 1000;
+    }
+
+    if quirks.contains(&Quirk::PreferSmallestConfig) {
+        let extra = |bits: u8, req: &BitsRequirement| {
+            bits.saturating_sub(req.min().unwrap_or(0)) as i32
+        };
+        score -= extra(format.color_bits, &reqs.color_bits) +
+                 extra(format.alpha_bits, &reqs.alpha_bits) +
+                 extra(format.depth_bits, &reqs.depth_bits) +
+                 extra(format.stencil_bits, &reqs.stencil_bits);
+    }
+
+    // the packing is only a preference unless the requirements are strict, so the formats that
+    // have the other one come last instead of being skipped
+    if let Some(packed) = reqs.packed_depth_stencil {
+        if format.packed_depth_stencil != packed {
+            score -= 100;
+        }
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_quirks_follow_the_rules() {
+        assert_eq!(active_quirks("ARM", "1.4 Midgard"), vec![Quirk::PreferSmallestConfig]);
+        assert_eq!(active_quirks("ARM", "1.4 Utgard"),
+                   vec![Quirk::NoPbuffers, Quirk::PreferSmallestConfig]);
+        assert_eq!(active_quirks("Mesa Project", "1.4"), vec![]);
+
+        // a vendor that only this test uses, as the rules are global
+        add_rule(QuirkRule {
+            quirk: Quirk::NoSrgb,
+            vendor: "glutin test".to_owned(),
+            version: Some("broken".to_owned()),
+        });
+        add_rule(QuirkRule {
+            quirk: Quirk::NoSrgb,
+            vendor: "glutin test".to_owned(),
+            version: None,
+        });
+        assert_eq!(active_quirks("glutin test", "1.5 broken"), vec![Quirk::NoSrgb]);
+        assert_eq!(active_quirks("glutin test", "1.5"), vec![Quirk::NoSrgb]);
+
+        set_quirk_enabled(Quirk::NoSrgb, false);
+        assert_eq!(active_quirks("glutin test", "1.5"), vec![]);
+        set_quirk_enabled(Quirk::NoSrgb, true);
+
+        set_quirks_enabled(false);
+        assert_eq!(active_quirks("ARM", "1.4 Utgard"), vec![]);
+        set_quirks_enabled(true);
+        assert_eq!(active_quirks("glutin test", "1.5"), vec![Quirk::NoSrgb]);
+    }
+
+    #[test]
+    fn apply_to_pixel_format() {
+        let reqs = PixelFormatRequirements { srgb: true, .. Default::default() };
+
+        let applied = super::apply_to_pixel_format(&[], &reqs).unwrap();
+        assert_eq!(applied.color_bits, reqs.color_bits);
+        assert!(applied.srgb);
+
+        let applied = super::apply_to_pixel_format(&[Quirk::ForceRgba8, Quirk::NoSrgb], &reqs)
+            .unwrap();
+        assert_eq!(applied.color_bits, BitsRequirement::Exact(24));
+        assert_eq!(applied.alpha_bits, BitsRequirement::Exact(8));
+        assert!(!applied.srgb);
+
+        let strict = PixelFormatRequirements { strict: true, .. reqs.clone() };
+        match super::apply_to_pixel_format(&[Quirk::NoSrgb], &strict) {
+            Err(CreationError::NoAvailablePixelFormat) => (),
+            _ => panic!("a strict request of sRGB must fail with NoSrgb"),
+        }
+
+        // the explicit requests of more bits or of floats are kept
+        let deep = PixelFormatRequirements {
+            color_bits: BitsRequirement::AtLeast(30),
+            .. Default::default()
+        };
+        let applied = super::apply_to_pixel_format(&[Quirk::ForceRgba8], &deep).unwrap();
+        assert_eq!(applied.color_bits, BitsRequirement::AtLeast(30));
+
+        let float = PixelFormatRequirements { float_color_buffer: true, .. Default::default() };
+        let applied = super::apply_to_pixel_format(&[Quirk::ForceRgba8], &float).unwrap();
+        assert_eq!(applied.color_bits, float.color_bits);
+    }
+}
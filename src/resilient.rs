@@ -0,0 +1,128 @@
+//! Recreation of the contexts that have been lost, see `ResilientContext`.
+
+use ContextError;
+use CreationError;
+use GlContext;
+
+/// A context that is created again when it is lost.
+///
+/// EGL loses the contexts of an Android application when it's suspended, and the robust
+/// contexts of the other platforms are lost after a GPU reset. `make_current` and
+/// `swap_buffers` return `ContextError::ContextLost` in that case, which `ResilientContext`
+/// handles by destroying the context, creating a new one with the function that created the
+/// first one, making it current and calling the restore callback so that it uploads the
+/// textures, buffers and programs again. The callback is called for the first context too,
+/// which lets the initialization of the GL state live in a single place.
+///
+/// If the new context can't be created yet, for example because an Android application has no
+/// window until it's resumed, `ContextLost` is returned and the next call tries again.
+///
+/// `ContextError::SurfaceLost` is returned as is, because the objects of the context are still
+/// valid and recreating the context would needlessly upload them again.
+///
+/// # Example
+///
+/// ```ignore
+/// let builder = WindowBuilder::new().with_gl_robustness(Robustness::RobustLoseContextOnReset);
+/// let mut context = try!(ResilientContext::new(move || builder.clone().build(),
+///                                              |window| upload_resources(window)));
+///
+/// loop {
+///     // draw everything here
+///
+///     let _ = context.swap_buffers();
+/// }
+/// ```
+pub struct ResilientContext<C> {
+    context: Option<C>,
+    create: Box<FnMut() -> Result<C, CreationError>>,
+    restore: Box<FnMut(&C)>,
+    recoveries: u32,
+}
+
+impl<C> ResilientContext<C> where C: GlContext {
+    /// Creates the context with `create`, makes it current and calls `restore` with it.
+    pub fn new<F, R>(create: F, restore: R) -> Result<ResilientContext<C>, CreationError>
+        where F: FnMut() -> Result<C, CreationError> + 'static, R: FnMut(&C) + 'static
+    {
+        let mut create = create;
+        let context = try!(create());
+        try!(unsafe { context.make_current() }.map_err(|err| {
+            CreationError::OsError(format!("the new context can't be made current: {}", err))
+        }));
+
+        let mut restore = restore;
+        restore(&context);
+
+        Ok(ResilientContext {
+            context: Some(context),
+            create: Box::new(create),
+            restore: Box::new(restore),
+            recoveries: 0,
+        })
+    }
+
+    /// Returns the current context, or `None` if it has been lost and couldn't be created again
+    /// yet.
+    #[inline]
+    pub fn get_context(&self) -> Option<&C> {
+        self.context.as_ref()
+    }
+
+    /// Returns the number of times the context has been created again.
+    #[inline]
+    pub fn get_recoveries(&self) -> u32 {
+        self.recoveries
+    }
+
+    /// Sets the context as the current context, creating it again if it has been lost.
+    pub unsafe fn make_current(&mut self) -> Result<(), ContextError> {
+        let result = match self.context {
+            Some(ref context) => context.make_current(),
+            None => Err(ContextError::ContextLost),
+        };
+
+        match result {
+            Err(ContextError::ContextLost) => self.recover(),
+            result => result,
+        }
+    }
+
+    /// Swaps the buffers of the context, creating it again if it has been lost.
+    ///
+    /// The frame is dropped when the context is lost, and `Ok` is returned once the new context
+    /// is current and restored.
+    pub fn swap_buffers(&mut self) -> Result<(), ContextError> {
+        let result = match self.context {
+            Some(ref context) => context.swap_buffers(),
+            None => Err(ContextError::ContextLost),
+        };
+
+        match result {
+            Err(ContextError::ContextLost) => self.recover(),
+            result => result,
+        }
+    }
+
+    /// Replaces the lost context with a new one.
+    fn recover(&mut self) -> Result<(), ContextError> {
+        // the old context is destroyed first, because an Android window can only have one
+        // surface at a time
+        self.context = None;
+
+        let context = match (self.create)() {
+            Ok(context) => context,
+            Err(_err) => {
+                debug_log!("the lost context couldn't be created again: {}", _err);
+                return Err(ContextError::ContextLost);
+            },
+        };
+
+        try!(unsafe { context.make_current() });
+        (self.restore)(&context);
+
+        self.context = Some(context);
+        self.recoveries += 1;
+        Ok(())
+    }
+}
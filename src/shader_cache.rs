@@ -0,0 +1,19 @@
+//! The disk caches of compiled shaders of the drivers, moved to a directory of the application.
+//!
+//! Each vendor has its own way to choose where its cache lives: Mesa and the proprietary NVIDIA
+//! driver read environment variables when they initialize, which only the application can set
+//! safely, see `os::unix::set_driver_shader_cache_dir`. The EGL drivers that support
+//! `EGL_ANDROID_blob_cache` leave the storage to the application.
+
+use std::path::Path;
+
+use blob_cache;
+
+/// Points the `EGL_ANDROID_blob_cache` caches at `dir`, before the first context is created.
+///
+/// The functions of `set_egl_blob_cache` are kept if they are set. Therefore the first directory
+/// applies to the whole process.
+#[inline]
+pub fn use_dir(dir: &Path) {
+    blob_cache::set_default_dir(dir);
+}
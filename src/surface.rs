@@ -0,0 +1,50 @@
+use ContextError;
+use GlContext;
+use PixelFormat;
+use Surface;
+
+impl<'a> Surface<'a> {
+    /// Returns the dimensions of the surface in pixels.
+    ///
+    /// Returns `None` for `Surface::Default`, or if the window has been closed.
+    pub fn get_dimensions(&self) -> Option<(u32, u32)> {
+        match *self {
+            Surface::Default => None,
+            Surface::Window(window) => window.get_inner_size_pixels(),
+            Surface::PBuffer(pbuffer) => Some(pbuffer.get_dimensions()),
+            #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "netbsd"))]
+            Surface::Pixmap(pixmap) => Some(pixmap.get_dimensions()),
+        }
+    }
+
+    /// Returns the pixel format of the surface.
+    ///
+    /// Returns `None` for `Surface::Default`.
+    pub fn get_pixel_format(&self) -> Option<PixelFormat> {
+        match *self {
+            Surface::Default => None,
+            Surface::Window(window) => Some(window.get_pixel_format()),
+            Surface::PBuffer(pbuffer) => Some(pbuffer.get_pixel_format()),
+            #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "netbsd"))]
+            Surface::Pixmap(pixmap) => Some(pixmap.get_pixel_format()),
+        }
+    }
+
+    /// Resizes the surface to the given dimensions in pixels.
+    ///
+    /// Only windows can be resized, the size of pbuffers and pixmaps is fixed when they are
+    /// created and an error is returned for them.
+    pub fn resize(&self, width: u32, height: u32) -> Result<(), ContextError> {
+        match *self {
+            Surface::Window(window) => {
+                let hidpi = window.hidpi_factor();
+                window.set_inner_size((width as f32 / hidpi) as u32,
+                                      (height as f32 / hidpi) as u32);
+                Ok(())
+            },
+            _ => Err(ContextError::NotSupported("only windows can be resized")),
+        }
+    }
+}
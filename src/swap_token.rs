@@ -0,0 +1,118 @@
+//! Notification of the frames that the GPU has finished, see `Window::swap_buffers_async`.
+
+use std::io;
+use std::mem;
+use std::os::raw::c_void;
+use std::time::Duration;
+
+use ContextError;
+use GlContext;
+use gl_version::{self, GetStringFn, GlVersion};
+
+type GLsync = *const c_void;
+
+const SYNC_GPU_COMMANDS_COMPLETE: u32 = 0x9117;
+const SYNC_FLUSH_COMMANDS_BIT: u32 = 0x1;
+const ALREADY_SIGNALED: u32 = 0x911A;
+const CONDITION_SATISFIED: u32 = 0x911C;
+
+/// The functions of `GL_ARB_sync`, which is part of OpenGL 3.2 and OpenGL ES 3.0.
+#[derive(Copy, Clone)]
+pub struct FenceFunctions {
+    fence_sync: extern "system" fn(u32, u32) -> GLsync,
+    client_wait_sync: extern "system" fn(GLsync, u32, u64) -> u32,
+    delete_sync: extern "system" fn(GLsync),
+}
+
+impl FenceFunctions {
+    /// Loads the functions with `context`, which must be current.
+    pub fn load<C>(context: &C) -> Result<FenceFunctions, ContextError> where C: GlContext {
+        let get_string = context.get_proc_address("glGetString");
+        if get_string.is_null() || unsafe { !supports_fences(mem::transmute(get_string)) } {
+            return Err(ContextError::NotSupported(
+                       "fences need OpenGL 3.2, OpenGL ES 3.0 or GL_ARB_sync"));
+        }
+
+        let fence_sync = context.get_proc_address("glFenceSync");
+        let client_wait_sync = context.get_proc_address("glClientWaitSync");
+        let delete_sync = context.get_proc_address("glDeleteSync");
+        if fence_sync.is_null() || client_wait_sync.is_null() || delete_sync.is_null() {
+            return Err(ContextError::NotSupported("the fence functions couldn't be loaded"));
+        }
+
+        unsafe {
+            Ok(FenceFunctions {
+                fence_sync: mem::transmute(fence_sync),
+                client_wait_sync: mem::transmute(client_wait_sync),
+                delete_sync: mem::transmute(delete_sync),
+            })
+        }
+    }
+}
+
+/// Tells when the GPU has finished a frame, which is when its buffer has been handed to the
+/// compositor or the display.
+///
+/// The token is a fence inserted right after the swap, therefore it must be used and dropped
+/// in the thread where the context of the window is current.
+pub struct SwapToken {
+    sync: GLsync,
+    functions: FenceFunctions,
+}
+
+impl SwapToken {
+    /// Inserts a fence in the commands of the current context.
+    #[doc(hidden)]
+    pub fn new(functions: FenceFunctions) -> Result<SwapToken, ContextError> {
+        let sync = (functions.fence_sync)(SYNC_GPU_COMMANDS_COMPLETE, 0);
+        if sync.is_null() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                            "glFenceSync failed")));
+        }
+
+        Ok(SwapToken {
+            sync: sync,
+            functions: functions,
+        })
+    }
+
+    /// Returns true if the GPU has finished the frame, without waiting.
+    #[inline]
+    pub fn is_retired(&self) -> bool {
+        self.client_wait(0, 0)
+    }
+
+    /// Waits until the GPU has finished the frame, for at most `timeout`. Returns false if the
+    /// frame isn't finished yet.
+    #[inline]
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let nanos = timeout.as_secs().saturating_mul(1_000_000_000)
+                                     .saturating_add(timeout.subsec_nanos() as u64);
+        // flushing makes sure that the fence reaches the GPU, otherwise it could never signal
+        self.client_wait(SYNC_FLUSH_COMMANDS_BIT, nanos)
+    }
+
+    fn client_wait(&self, flags: u32, timeout: u64) -> bool {
+        match (self.functions.client_wait_sync)(self.sync, flags, timeout) {
+            ALREADY_SIGNALED | CONDITION_SATISFIED => true,
+            _ => false,
+        }
+    }
+}
+
+impl Drop for SwapToken {
+    #[inline]
+    fn drop(&mut self) {
+        (self.functions.delete_sync)(self.sync);
+    }
+}
+
+/// Checks the version and the extensions of the current context.
+unsafe fn supports_fences(get_string: GetStringFn) -> bool {
+    match GlVersion::query(get_string) {
+        Some(v) if v.gles => return v.number >= (3, 0),
+        Some(v) if v.number >= (3, 2) => return true,
+        _ => (),
+    }
+    gl_version::has_extension(get_string, "GL_ARB_sync")
+}
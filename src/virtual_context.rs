@@ -0,0 +1,446 @@
+//! Lightweight contexts multiplexed over one native context, see `VirtualContextHost`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+
+use Api;
+use ConfigLimits;
+use ContextError;
+use GlContext;
+use PixelFormat;
+use Rect;
+use RendererInfo;
+use gl_version::{self, GlVersion};
+
+const FRAMEBUFFER: u32 = 0x8D40;
+const READ_FRAMEBUFFER: u32 = 0x8CA8;
+const DRAW_FRAMEBUFFER: u32 = 0x8CA9;
+const FRAMEBUFFER_BINDING: u32 = 0x8CA6;
+const READ_FRAMEBUFFER_BINDING: u32 = 0x8CAA;
+const RENDERBUFFER: u32 = 0x8D41;
+const RENDERBUFFER_BINDING: u32 = 0x8CA7;
+const CURRENT_PROGRAM: u32 = 0x8B8D;
+const ARRAY_BUFFER: u32 = 0x8892;
+const ELEMENT_ARRAY_BUFFER: u32 = 0x8893;
+const ARRAY_BUFFER_BINDING: u32 = 0x8894;
+const ELEMENT_ARRAY_BUFFER_BINDING: u32 = 0x8895;
+const VERTEX_ARRAY_BINDING: u32 = 0x85B5;
+const TEXTURE0: u32 = 0x84C0;
+const ACTIVE_TEXTURE: u32 = 0x84E0;
+const TEXTURE_2D: u32 = 0x0DE1;
+const TEXTURE_BINDING_2D: u32 = 0x8069;
+const VIEWPORT: u32 = 0x0BA2;
+const SCISSOR_BOX: u32 = 0x0C10;
+const BLEND_DST_RGB: u32 = 0x80C8;
+const BLEND_SRC_RGB: u32 = 0x80C9;
+const BLEND_DST_ALPHA: u32 = 0x80CA;
+const BLEND_SRC_ALPHA: u32 = 0x80CB;
+const DEPTH_FUNC: u32 = 0x0B74;
+const COLOR_CLEAR_VALUE: u32 = 0x0C22;
+const COLOR_WRITEMASK: u32 = 0x0C23;
+const DEPTH_WRITEMASK: u32 = 0x0B72;
+
+/// The capabilities of `glEnable` that are switched with the contexts.
+const CAPABILITIES: [u32; 5] = [
+    0x0BE2, // GL_BLEND
+    0x0B44, // GL_CULL_FACE
+    0x0B71, // GL_DEPTH_TEST
+    0x0C11, // GL_SCISSOR_TEST
+    0x0B90, // GL_STENCIL_TEST
+];
+
+/// The number of texture units whose 2D textures are switched with the contexts, which is the
+/// minimum of OpenGL ES 2.0.
+const TEXTURE_UNITS: usize = 8;
+
+/// The functions that read and set the switched state.
+#[derive(Copy, Clone)]
+struct StateFunctions {
+    get_integerv: extern "system" fn(u32, *mut i32),
+    get_floatv: extern "system" fn(u32, *mut f32),
+    get_booleanv: extern "system" fn(u32, *mut u8),
+    is_enabled: extern "system" fn(u32) -> u8,
+    enable: extern "system" fn(u32),
+    disable: extern "system" fn(u32),
+    bind_framebuffer: extern "system" fn(u32, u32),
+    bind_renderbuffer: extern "system" fn(u32, u32),
+    use_program: extern "system" fn(u32),
+    bind_buffer: extern "system" fn(u32, u32),
+    active_texture: extern "system" fn(u32),
+    bind_texture: extern "system" fn(u32, u32),
+    viewport: extern "system" fn(i32, i32, i32, i32),
+    scissor: extern "system" fn(i32, i32, i32, i32),
+    blend_func_separate: extern "system" fn(u32, u32, u32, u32),
+    depth_func: extern "system" fn(u32),
+    clear_color: extern "system" fn(f32, f32, f32, f32),
+    color_mask: extern "system" fn(u8, u8, u8, u8),
+    depth_mask: extern "system" fn(u8),
+    // `glBindVertexArray` of OpenGL 3.0 and OpenGL ES 3.0, or of `GL_OES_vertex_array_object`
+    bind_vertex_array: Option<extern "system" fn(u32)>,
+}
+
+/// The state of a virtual context while another one uses the native context.
+#[derive(Clone)]
+struct State {
+    draw_framebuffer: u32,
+    // only different from `draw_framebuffer` with separate read and draw framebuffers
+    read_framebuffer: u32,
+    renderbuffer: u32,
+    program: u32,
+    array_buffer: u32,
+    // the element array buffer belongs to the vertex array, when there are vertex arrays
+    element_array_buffer: u32,
+    vertex_array: u32,
+    active_texture: u32,
+    textures: [u32; TEXTURE_UNITS],
+    viewport: [i32; 4],
+    scissor_box: [i32; 4],
+    capabilities: [bool; 5],
+    // source and destination factors of the color, then of the alpha
+    blend_func: [u32; 4],
+    depth_func: u32,
+    clear_color: [f32; 4],
+    color_mask: [u8; 4],
+    depth_mask: u8,
+}
+
+/// Which virtual context owns the native context, and the saved state of the others.
+struct Switch {
+    current: Option<usize>,
+    saved: HashMap<usize, State>,
+    next_id: usize,
+}
+
+/// A native context shared by many `VirtualContext`s, which are much cheaper to switch than
+/// native contexts with some mobile drivers.
+///
+/// Making a virtual context current saves a defined subset of the OpenGL state of the previous
+/// one and restores its own:
+///
+/// - the bound framebuffers, renderbuffer, program, array buffer and vertex array, and the
+///   element array buffer without vertex arrays,
+/// - the active texture unit and the 2D textures bound to the first 8 units,
+/// - the viewport and the scissor box,
+/// - `GL_BLEND`, `GL_CULL_FACE`, `GL_DEPTH_TEST`, `GL_SCISSOR_TEST` and `GL_STENCIL_TEST`,
+/// - the blend factors, the depth function, the clear color and the color and depth masks.
+///
+/// Everything else is shared, so the code that renders with a virtual context must set the other
+/// state that it needs, like vertex attributes without vertex arrays, before drawing. The
+/// virtual contexts also share their objects, as if they were created with `with_shared_lists`,
+/// including their vertex arrays, and they draw into the surface of the native context, unless
+/// they bind framebuffer objects.
+///
+/// A virtual context starts with the state that the native context had when the host was
+/// created. The host isn't `Sync`, and the native context should only be made current through
+/// the virtual contexts, otherwise the state that they save belongs to someone else.
+pub struct VirtualContextHost<C> where C: GlContext {
+    context: C,
+    functions: StateFunctions,
+    separate_read: bool,
+    initial: State,
+    switch: RefCell<Switch>,
+}
+
+impl<C> VirtualContextHost<C> where C: GlContext {
+    /// Makes `context` current and creates a host for it.
+    ///
+    /// Returns an error if the context isn't OpenGL 2.0 or OpenGL ES 2.0, or newer.
+    pub fn new(context: C) -> Result<VirtualContextHost<C>, ContextError> {
+        try!(unsafe { context.make_current() });
+
+        let get_string = context.get_proc_address("glGetString");
+        if get_string.is_null() {
+            return Err(ContextError::NotSupported("glGetString couldn't be loaded"));
+        }
+        let get_string = unsafe { mem::transmute(get_string) };
+
+        let version = match unsafe { GlVersion::query(get_string) } {
+            Some(v) if v.number >= (2, 0) => v,
+            _ => {
+                return Err(ContextError::NotSupported(
+                           "virtual contexts need OpenGL 2.0 or OpenGL ES 2.0"));
+            },
+        };
+        let has_extension = |name| unsafe { gl_version::has_extension(get_string, name) };
+        let separate_read = version.number >= (3, 0) ||
+                            !version.gles && has_extension("GL_ARB_framebuffer_object");
+
+        let load = |name| {
+            let address = context.get_proc_address(name);
+            if address.is_null() {
+                debug_log!("{} couldn't be loaded", name);
+            }
+            address
+        };
+        let required = [load("glGetIntegerv"), load("glGetFloatv"), load("glGetBooleanv"),
+                        load("glIsEnabled"), load("glEnable"), load("glDisable"),
+                        load("glBindFramebuffer"), load("glBindRenderbuffer"),
+                        load("glUseProgram"), load("glBindBuffer"), load("glActiveTexture"),
+                        load("glBindTexture"), load("glViewport"), load("glScissor"),
+                        load("glBlendFuncSeparate"), load("glDepthFunc"), load("glClearColor"),
+                        load("glColorMask"), load("glDepthMask")];
+        if required.iter().any(|f| f.is_null()) {
+            return Err(ContextError::NotSupported("the state functions couldn't be loaded"));
+        }
+        let bind_vertex_array = if version.number >= (3, 0) {
+            load("glBindVertexArray")
+        } else if version.gles && has_extension("GL_OES_vertex_array_object") {
+            load("glBindVertexArrayOES")
+        } else if !version.gles && has_extension("GL_ARB_vertex_array_object") {
+            load("glBindVertexArray")
+        } else {
+            ptr::null()
+        };
+
+        let functions = unsafe {
+            StateFunctions {
+                get_integerv: mem::transmute(required[0]),
+                get_floatv: mem::transmute(required[1]),
+                get_booleanv: mem::transmute(required[2]),
+                is_enabled: mem::transmute(required[3]),
+                enable: mem::transmute(required[4]),
+                disable: mem::transmute(required[5]),
+                bind_framebuffer: mem::transmute(required[6]),
+                bind_renderbuffer: mem::transmute(required[7]),
+                use_program: mem::transmute(required[8]),
+                bind_buffer: mem::transmute(required[9]),
+                active_texture: mem::transmute(required[10]),
+                bind_texture: mem::transmute(required[11]),
+                viewport: mem::transmute(required[12]),
+                scissor: mem::transmute(required[13]),
+                blend_func_separate: mem::transmute(required[14]),
+                depth_func: mem::transmute(required[15]),
+                clear_color: mem::transmute(required[16]),
+                color_mask: mem::transmute(required[17]),
+                depth_mask: mem::transmute(required[18]),
+                bind_vertex_array: if bind_vertex_array.is_null() {
+                    None
+                } else {
+                    Some(mem::transmute(bind_vertex_array))
+                },
+            }
+        };
+
+        let initial = save(&functions, separate_read);
+        Ok(VirtualContextHost {
+            context: context,
+            functions: functions,
+            separate_read: separate_read,
+            initial: initial,
+            switch: RefCell::new(Switch {
+                current: None,
+                saved: HashMap::new(),
+                next_id: 0,
+            }),
+        })
+    }
+
+    /// Creates a virtual context, with the state that the native context had when the host was
+    /// created.
+    pub fn create_context(&self) -> VirtualContext<C> {
+        let mut switch = self.switch.borrow_mut();
+        let id = switch.next_id;
+        switch.next_id += 1;
+        switch.saved.insert(id, self.initial.clone());
+
+        VirtualContext {
+            host: self,
+            id: id,
+        }
+    }
+
+    /// Returns the native context.
+    #[inline]
+    pub fn get_native_context(&self) -> &C {
+        &self.context
+    }
+}
+
+/// A context of a `VirtualContextHost`, which saves and restores its state when it's made
+/// current instead of switching native contexts.
+///
+/// The virtual context is destroyed when this is dropped. Its objects belong to the native
+/// context, and must be deleted before if they aren't needed anymore.
+pub struct VirtualContext<'a, C> where C: GlContext + 'a {
+    host: &'a VirtualContextHost<C>,
+    id: usize,
+}
+
+impl<'a, C> GlContext for VirtualContext<'a, C> where C: GlContext {
+    /// Makes the native context current, then switches its state to this virtual context if
+    /// another one used it last.
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        try!(self.host.context.make_current());
+
+        let mut switch = self.host.switch.borrow_mut();
+        if switch.current == Some(self.id) {
+            return Ok(());
+        }
+
+        let (functions, separate_read) = (&self.host.functions, self.host.separate_read);
+        if let Some(previous) = switch.current {
+            switch.saved.insert(previous, save(functions, separate_read));
+        }
+        if let Some(state) = switch.saved.remove(&self.id) {
+            restore(functions, separate_read, &state);
+        }
+        switch.current = Some(self.id);
+        Ok(())
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.host.switch.borrow().current == Some(self.id) && self.host.context.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.host.context.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.host.context.swap_buffers()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.host.context.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.host.context.get_pixel_format()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.host.context.get_config_limits()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.host.context.get_renderer_info()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.host.context.is_back_buffer_preserved()
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        self.host.context.is_front_buffer_rendering()
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.host.context.set_damage_region(rects)
+    }
+}
+
+impl<'a, C> Drop for VirtualContext<'a, C> where C: GlContext {
+    fn drop(&mut self) {
+        // the next virtual context restores all of its state over the one left by this one
+        let mut switch = self.host.switch.borrow_mut();
+        switch.saved.remove(&self.id);
+        if switch.current == Some(self.id) {
+            switch.current = None;
+        }
+    }
+}
+
+/// Reads the switched state of the current context.
+fn save(f: &StateFunctions, separate_read: bool) -> State {
+    let integer = |name| {
+        let mut value = 0;
+        (f.get_integerv)(name, &mut value);
+        value as u32
+    };
+
+    let draw_framebuffer = integer(FRAMEBUFFER_BINDING);
+    let active_texture = integer(ACTIVE_TEXTURE);
+    let mut textures = [0; TEXTURE_UNITS];
+    for (unit, texture) in textures.iter_mut().enumerate() {
+        (f.active_texture)(TEXTURE0 + unit as u32);
+        *texture = integer(TEXTURE_BINDING_2D);
+    }
+    (f.active_texture)(active_texture);
+
+    let mut state = State {
+        draw_framebuffer: draw_framebuffer,
+        read_framebuffer: if separate_read {
+            integer(READ_FRAMEBUFFER_BINDING)
+        } else {
+            draw_framebuffer
+        },
+        renderbuffer: integer(RENDERBUFFER_BINDING),
+        program: integer(CURRENT_PROGRAM),
+        array_buffer: integer(ARRAY_BUFFER_BINDING),
+        element_array_buffer: integer(ELEMENT_ARRAY_BUFFER_BINDING),
+        vertex_array: if f.bind_vertex_array.is_some() { integer(VERTEX_ARRAY_BINDING) } else { 0 },
+        active_texture: active_texture,
+        textures: textures,
+        viewport: [0; 4],
+        scissor_box: [0; 4],
+        capabilities: [false; 5],
+        blend_func: [integer(BLEND_SRC_RGB), integer(BLEND_DST_RGB), integer(BLEND_SRC_ALPHA),
+                     integer(BLEND_DST_ALPHA)],
+        depth_func: integer(DEPTH_FUNC),
+        clear_color: [0.0; 4],
+        color_mask: [0; 4],
+        depth_mask: 0,
+    };
+
+    (f.get_integerv)(VIEWPORT, state.viewport.as_mut_ptr());
+    (f.get_integerv)(SCISSOR_BOX, state.scissor_box.as_mut_ptr());
+    for (enabled, &capability) in state.capabilities.iter_mut().zip(CAPABILITIES.iter()) {
+        *enabled = (f.is_enabled)(capability) != 0;
+    }
+    (f.get_floatv)(COLOR_CLEAR_VALUE, state.clear_color.as_mut_ptr());
+    (f.get_booleanv)(COLOR_WRITEMASK, state.color_mask.as_mut_ptr());
+    (f.get_booleanv)(DEPTH_WRITEMASK, &mut state.depth_mask);
+    state
+}
+
+/// Sets the switched state of the current context.
+fn restore(f: &StateFunctions, separate_read: bool, state: &State) {
+    if separate_read {
+        (f.bind_framebuffer)(DRAW_FRAMEBUFFER, state.draw_framebuffer);
+        (f.bind_framebuffer)(READ_FRAMEBUFFER, state.read_framebuffer);
+    } else {
+        (f.bind_framebuffer)(FRAMEBUFFER, state.draw_framebuffer);
+    }
+    (f.bind_renderbuffer)(RENDERBUFFER, state.renderbuffer);
+    (f.use_program)(state.program);
+
+    // the vertex array is bound first, as it holds the element array buffer
+    if let Some(bind_vertex_array) = f.bind_vertex_array {
+        bind_vertex_array(state.vertex_array);
+    } else {
+        (f.bind_buffer)(ELEMENT_ARRAY_BUFFER, state.element_array_buffer);
+    }
+    (f.bind_buffer)(ARRAY_BUFFER, state.array_buffer);
+
+    for (unit, &texture) in state.textures.iter().enumerate() {
+        (f.active_texture)(TEXTURE0 + unit as u32);
+        (f.bind_texture)(TEXTURE_2D, texture);
+    }
+    (f.active_texture)(state.active_texture);
+
+    let (viewport, scissor_box) = (state.viewport, state.scissor_box);
+    (f.viewport)(viewport[0], viewport[1], viewport[2], viewport[3]);
+    (f.scissor)(scissor_box[0], scissor_box[1], scissor_box[2], scissor_box[3]);
+    for (&enabled, &capability) in state.capabilities.iter().zip(CAPABILITIES.iter()) {
+        if enabled { (f.enable)(capability) } else { (f.disable)(capability) }
+    }
+
+    let (blend, clear, mask) = (state.blend_func, state.clear_color, state.color_mask);
+    (f.blend_func_separate)(blend[0], blend[1], blend[2], blend[3]);
+    (f.depth_func)(state.depth_func);
+    (f.clear_color)(clear[0], clear[1], clear[2], clear[3]);
+    (f.color_mask)(mask[0], mask[1], mask[2], mask[3]);
+    (f.depth_mask)(state.depth_mask);
+}
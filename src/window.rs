@@ -1,26 +1,56 @@
 use std::collections::vec_deque::IntoIter as VecDequeIter;
 use std::default::Default;
+use std::marker::PhantomData;
 use std::path::PathBuf;
+use std::ptr;
+use std::sync::Mutex;
 
 use Api;
+use BitsRequirement;
+use ConfigLimits;
+use CoverageSamples;
+use RendererInfo;
+use Rect;
+use HdrMetadata;
 use ContextError;
+use ContextGroup;
+use GlAttributes;
 use CreationError;
+use ContextPriority;
 use CursorState;
+use DetachedWindow;
 use Event;
+use FailedVersion;
+use FrameStatsSummary;
+use FrameTiming;
 use GlContext;
 use GlProfile;
 use GlRequest;
 use MouseCursor;
+use PBuffer;
 use PixelFormat;
+use PixelFormatRequirements;
 use Robustness;
+use Surface;
+use SwapState;
+use SwapToken;
 use Window;
 use WindowID;
 use WindowAttributes;
 use WindowBuilder;
+use adjust_robustness;
+use validate_attributes;
 use native_monitor::NativeMonitorId;
 
+use frame_limiter::FrameLimiter;
+use frame_stats::FrameStats;
+use libraries;
+use multiview;
+use presenter::Presenter;
+use swap_token::FenceFunctions;
 use libc;
 use platform;
+use shader_cache;
 
 impl<'a> WindowBuilder<'a> {
     /// Initializes a new `WindowBuilder` with default values.
@@ -31,6 +61,8 @@ impl<'a> WindowBuilder<'a> {
             window: Default::default(),
             opengl: Default::default(),
             platform_specific: Default::default(),
+            group: None,
+            fence_sync: false,
         }
     }
 
@@ -87,6 +119,15 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// The created window will share all its OpenGL objects with the windows of `group`.
+    ///
+    /// This replaces `with_shared_lists`. See `ContextGroup`.
+    #[inline]
+    pub fn with_context_group(mut self, group: &ContextGroup) -> WindowBuilder<'a> {
+        self.group = Some(group.clone());
+        self
+    }
+
     /// Sets how the backend should choose the OpenGL API and version.
     #[inline]
     pub fn with_gl(mut self, request: GlRequest) -> WindowBuilder<'a> {
@@ -125,6 +166,71 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// Makes the drivers keep the shaders and programs that they compile in `dir`, so that the
+    /// next launches don't compile them again.
+    ///
+    /// The directory must exist and should be private to the application. It's given to the EGL
+    /// drivers through `EGL_ANDROID_blob_cache`. Mesa and the NVIDIA driver only read it from
+    /// environment variables, see `os::unix::set_driver_shader_cache_dir`. The drivers of the
+    /// other platforms keep their own caches.
+    ///
+    /// The drivers read it when they are initialized, therefore it only applies if the first
+    /// context of the process is built with it. The functions of `set_egl_blob_cache` take
+    /// precedence.
+    #[inline]
+    pub fn with_shader_cache_dir(mut self, dir: PathBuf) -> WindowBuilder<'a> {
+        self.opengl.shader_cache_dir = Some(dir);
+        self
+    }
+
+    /// Requires a context that supports `GL_OVR_multiview2` with two views at least, which VR
+    /// renderers use to draw both eyes in a single pass into the layers of a texture array.
+    ///
+    /// Without an explicit version, an OpenGL 3.0 or OpenGL ES 3.0 context is requested, as the
+    /// extension needs one of them. The driver support can only be known once the context
+    /// exists, therefore `build` makes the context current in the calling thread to check it,
+    /// and returns `CreationError::NotSupported` without the extension.
+    #[inline]
+    pub fn with_multiview(mut self, multiview: bool) -> WindowBuilder<'a> {
+        self.opengl.multiview = multiview;
+        self
+    }
+
+    /// Requests a priority for the commands of the context on the GPU. See `ContextPriority`.
+    #[inline]
+    pub fn with_context_priority(mut self, priority: ContextPriority) -> WindowBuilder<'a> {
+        self.opengl.priority = Some(priority);
+        self
+    }
+
+    /// If true, the build fails unless the context supports `swap_buffers_async`, whose fences
+    /// are loaded by `build` instead of by the first frame. The check makes the context current
+    /// in the calling thread. The default value is `false`.
+    #[inline]
+    pub fn with_fence_sync(mut self, required: bool) -> WindowBuilder<'a> {
+        self.fence_sync = required;
+        self
+    }
+
+    /// Sets up the window for the low latency renderers of VR headsets that are driven by a
+    /// phone, like GearVR and Cardboard:
+    ///
+    /// - a high priority context, see `with_context_priority`,
+    /// - rendering into the front buffer, see `with_front_buffer_rendering`,
+    /// - no vsync, as the renderer times the frames with the display itself,
+    /// - fences, see `with_fence_sync`.
+    ///
+    /// The front buffer and the priority are only granted by the drivers that support them, the
+    /// build fails without fences. Each setting can be changed after this one.
+    #[inline]
+    pub fn with_low_latency_vr(mut self) -> WindowBuilder<'a> {
+        self.opengl.priority = Some(ContextPriority::High);
+        self.opengl.vsync = false;
+        self.pf_reqs.front_buffer_rendering = true;
+        self.fence_sync = true;
+        self
+    }
+
     /// Sets whether the window will be initially hidden or visible.
     #[inline]
     pub fn with_visibility(mut self, visible: bool) -> WindowBuilder<'a> {
@@ -144,25 +250,90 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
-    /// Sets the number of bits in the depth buffer.
+    /// Requests coverage sampling with at least `color` color samples and `coverage` coverage
+    /// samples per pixel, which replaces `with_multisampling`.
+    ///
+    /// See `CoverageSamples`.
+    #[inline]
+    pub fn with_coverage_samples(mut self, color: u16, coverage: u16) -> WindowBuilder<'a> {
+        self.pf_reqs.multisampling = None;
+        self.pf_reqs.coverage_samples = Some(CoverageSamples { color: color, coverage: coverage });
+        self
+    }
+
+    /// Sets the minimum number of bits in the depth buffer.
+    ///
+    /// A value of `0` requests a pixel format without any depth buffer.
     #[inline]
     pub fn with_depth_buffer(mut self, bits: u8) -> WindowBuilder<'a> {
-        self.pf_reqs.depth_bits = Some(bits);
+        self.pf_reqs.depth_bits = no_buffer_or_at_least(bits);
         self
     }
 
-    /// Sets the number of bits in the stencil buffer.
+    /// Sets the minimum number of bits in the stencil buffer.
+    ///
+    /// A value of `0` requests a pixel format without any stencil buffer.
     #[inline]
     pub fn with_stencil_buffer(mut self, bits: u8) -> WindowBuilder<'a> {
-        self.pf_reqs.stencil_bits = Some(bits);
+        self.pf_reqs.stencil_bits = no_buffer_or_at_least(bits);
         self
     }
 
-    /// Sets the number of bits in the color buffer.
+    /// Sets whether the depth and the stencil buffers must be packed in a single depth24/stencil8
+    /// buffer, or must be separate buffers.
+    ///
+    /// See `PixelFormatRequirements::packed_depth_stencil`.
+    #[inline]
+    pub fn with_packed_depth_stencil(mut self, packed: bool) -> WindowBuilder<'a> {
+        self.pf_reqs.packed_depth_stencil = Some(packed);
+        self
+    }
+
+    /// Sets the minimum number of bits in the color buffer.
     #[inline]
     pub fn with_pixel_format(mut self, color_bits: u8, alpha_bits: u8) -> WindowBuilder<'a> {
-        self.pf_reqs.color_bits = Some(color_bits);
-        self.pf_reqs.alpha_bits = Some(alpha_bits);
+        self.pf_reqs.color_bits = BitsRequirement::AtLeast(color_bits);
+        self.pf_reqs.alpha_bits = BitsRequirement::AtLeast(alpha_bits);
+        self
+    }
+
+    /// Sets the requirement on the number of bits in the depth buffer.
+    #[inline]
+    pub fn with_depth_bits(mut self, bits: BitsRequirement) -> WindowBuilder<'a> {
+        self.pf_reqs.depth_bits = bits;
+        self
+    }
+
+    /// Sets the requirement on the number of bits in the stencil buffer.
+    #[inline]
+    pub fn with_stencil_bits(mut self, bits: BitsRequirement) -> WindowBuilder<'a> {
+        self.pf_reqs.stencil_bits = bits;
+        self
+    }
+
+    /// Sets the requirement on the number of bits in the color buffer, excluding alpha.
+    #[inline]
+    pub fn with_color_bits(mut self, bits: BitsRequirement) -> WindowBuilder<'a> {
+        self.pf_reqs.color_bits = bits;
+        self
+    }
+
+    /// Requests a 16 bits RGB565 color buffer without alpha, which halves the memory and the
+    /// bandwidth of the framebuffer on memory-constrained devices. `get_pixel_format` reports
+    /// 16 color bits once it has been granted.
+    ///
+    /// This is a shortcut for `with_color_bits(Exact(16))` and `with_alpha_bits(Exact(0))`.
+    #[inline]
+    pub fn with_rgb565(mut self) -> WindowBuilder<'a> {
+        self.pf_reqs.color_bits = BitsRequirement::Exact(16);
+        self.pf_reqs.alpha_bits = BitsRequirement::Exact(0);
+        self
+    }
+
+    /// Sets the requirement on the number of bits of alpha in the color buffer.
+    #[inline]
+    pub fn with_alpha_bits(mut self, bits: BitsRequirement) -> WindowBuilder<'a> {
+        self.pf_reqs.alpha_bits = bits;
         self
     }
 
@@ -180,7 +351,72 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// Sets whether context creation must fail instead of using a pixel format that doesn't
+    /// fulfill all the requirements, like sRGB or the number of samples.
+    #[inline]
+    pub fn with_strict_pixel_format(mut self, strict: bool) -> WindowBuilder<'a> {
+        self.pf_reqs.strict = strict;
+        self
+    }
+
+    /// Sets whether only the configs that the driver reports as conformant for the requested
+    /// API may be used. Some drivers advertise non-conformant configs that render incorrectly.
+    #[inline]
+    pub fn with_conformant_pixel_format(mut self, conformant: bool) -> WindowBuilder<'a> {
+        self.pf_reqs.conformant = conformant;
+        self
+    }
+
+    /// Sets whether a config that the driver reports as slow or non-conformant may be used.
+    /// Forbidding it avoids ending up on a software renderer without any indication.
+    #[inline]
+    pub fn with_caveat_allowed(mut self, allow_caveat: bool) -> WindowBuilder<'a> {
+        self.pf_reqs.allow_caveat = allow_caveat;
+        self
+    }
+
+    /// Sets the function that ranks the pixel formats. See `PixelFormatRequirements::scoring`.
+    #[inline]
+    pub fn with_pixel_format_scoring(mut self, scoring: fn(&PixelFormat) -> Option<i32>)
+                                     -> WindowBuilder<'a>
+    {
+        self.pf_reqs.scoring = Some(scoring);
+        self
+    }
+
+    /// Requests the content of the back buffer to be preserved by `swap_buffers`. Use
+    /// `is_back_buffer_preserved` to know whether the platform granted it.
+    #[inline]
+    pub fn with_preserved_back_buffer(mut self, preserve: bool) -> WindowBuilder<'a> {
+        self.pf_reqs.preserve_back_buffer = preserve;
+        self
+    }
+
+    /// Requests the window to draw directly into the buffer that is displayed. Use
+    /// `is_front_buffer_rendering` to know whether the platform granted it.
+    ///
+    /// See `PixelFormatRequirements::front_buffer_rendering`.
+    #[inline]
+    pub fn with_front_buffer_rendering(mut self, front_buffer: bool) -> WindowBuilder<'a> {
+        self.pf_reqs.front_buffer_rendering = front_buffer;
+        self
+    }
+
+    /// Requests exactly `format`, for example to reproduce the format of a bug report read with
+    /// `PixelFormat::from_descriptor`. This replaces the pixel format requirements set before.
+    ///
+    /// See `PixelFormatRequirements::exactly`.
+    #[inline]
+    pub fn with_exact_pixel_format(mut self, format: &PixelFormat) -> WindowBuilder<'a> {
+        self.pf_reqs = PixelFormatRequirements::exactly(format);
+        self
+    }
+
     /// Sets whether the background of the window should be transparent.
+    ///
+    /// The pixel format then has an alpha channel, which is used by the window manager to
+    /// blend the window with what is behind it. Building the window returns
+    /// `TransparencyNotSupported` if the platform can't compose it.
     #[inline]
     pub fn with_transparency(mut self, transparent: bool) -> WindowBuilder<'a> {
         self.window.transparent = transparent;
@@ -219,6 +455,20 @@ impl<'a> WindowBuilder<'a> {
     /// Error should be very rare and only occur in case of permission denied, incompatible system,
     /// out of memory, etc.
     pub fn build(mut self) -> Result<Window, CreationError> {
+        try!(validate_attributes(&self.opengl, &self.pf_reqs));
+        adjust_robustness(&mut self.opengl);
+
+        // the alpha channel is what makes the window transparent
+        if self.window.transparent {
+            self.pf_reqs.alpha_bits = match self.pf_reqs.alpha_bits {
+                BitsRequirement::Exact(0) => return Err(CreationError::TransparencyNotSupported),
+                BitsRequirement::DontCare | BitsRequirement::AtLeast(0) => {
+                    BitsRequirement::AtLeast(8)
+                },
+                bits => bits,
+            };
+        }
+
         // resizing the window to the dimensions of the monitor when fullscreen
         if self.window.dimensions.is_none() && self.window.monitor.is_some() {
             self.window.dimensions = Some(self.window.monitor.as_ref().unwrap().get_dimensions())
@@ -229,9 +479,43 @@ impl<'a> WindowBuilder<'a> {
             self.window.dimensions = Some((1024, 768));
         }
 
+        multiview::adjust_request(&mut self.opengl);
+
+        // the windows of a group share with its root, which lives as long as the group
+        let mut opengl: GlAttributes<&platform::Window> = self.opengl.clone();
+        if let Some(ref group) = self.group {
+            opengl.sharing = Some(&group.get_root().window);
+        }
+
+        if let Some(ref dir) = self.opengl.shader_cache_dir {
+            shader_cache::use_dir(dir);
+        }
+
         // building
-        platform::Window::new(&self.window, &self.pf_reqs, &self.opengl, &self.platform_specific)
-                            .map(|w| Window { window: w })
+        let window = try!(platform::Window::new(&self.window, &self.pf_reqs, &opengl,
+                                                &self.platform_specific)
+                            .map(|w| Window {
+                                swap: Mutex::new(Default::default()),
+                                window: w,
+                                fence_functions: Mutex::new(None),
+                            }));
+
+        if self.opengl.multiview {
+            try!(multiview::check(&window));
+        }
+
+        if self.fence_sync {
+            try!(unsafe { window.make_current() }.map_err(|err| {
+                CreationError::OsError(format!("the new context can't be made current: {}", err))
+            }));
+            let functions = try!(FenceFunctions::load(&window).map_err(|_err| {
+                debug_log!("{}", _err);
+                CreationError::NotSupported
+            }));
+            *window.fence_functions.lock().unwrap() = Some(functions);
+        }
+
+        Ok(window)
     }
 
     /// Builds the window.
@@ -244,6 +528,16 @@ impl<'a> WindowBuilder<'a> {
     }
 }
 
+// Zero bits means that the buffer must not exist at all, rather than "any number of bits".
+#[inline]
+fn no_buffer_or_at_least(bits: u8) -> BitsRequirement {
+    if bits == 0 {
+        BitsRequirement::Exact(0)
+    } else {
+        BitsRequirement::AtLeast(bits)
+    }
+}
+
 
 impl Default for Window {
     #[inline]
@@ -401,11 +695,21 @@ impl Window {
     }
 
     /// Sets the context as the current context.
+    ///
+    /// See `GlContext::make_current`.
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         self.window.make_current()
     }
 
+    /// See `GlContext::make_current_forced`.
+    #[inline]
+    pub unsafe fn make_current_forced(&self) -> Result<(), ContextError> {
+        self.window.make_current_forced()
+    }
+
+    /// Releases the context if it's the current context of this thread.
+    ///
     /// Returns true if this context is the current one in this thread.
     #[inline]
     pub fn is_current(&self) -> bool {
@@ -415,8 +719,13 @@ impl Window {
     /// Returns the address of an OpenGL function.
     ///
     /// Contrary to `wglGetProcAddress`, all available OpenGL functions return an address.
+    ///
+    /// Returns null for the functions that `set_allowed_symbols` doesn't allow.
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
+        if !libraries::is_symbol_allowed(addr) {
+            return ptr::null();
+        }
         self.window.get_proc_address(addr)
     }
 
@@ -428,9 +737,120 @@ impl Window {
     /// **Warning**: if you enabled vsync, this function will block until the next time the screen
     /// is refreshed. However drivers can choose to override your vsync settings, which means that
     /// you can't know in advance whether `swap_buffers` will block or not.
+    ///
+    /// This also waits for the frame rate limit set by `set_max_frame_rate`, and records the
+    /// timing of the frame if `set_frame_stats` enabled it. With `set_async_swap`, this only
+    /// waits for the swap of the previous frame.
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.window.swap_buffers()
+        let mut state = self.swap.lock().unwrap();
+        let SwapState { ref mut presenter, ref mut frame_stats, ref mut frame_limiter } = *state;
+        let mut swap = || {
+            if let Some(ref mut limiter) = *frame_limiter {
+                limiter.wait();
+            }
+            match *presenter {
+                Some(ref mut presenter) => presenter.swap(),
+                None => self.window.swap_buffers(),
+            }
+        };
+
+        match *frame_stats {
+            Some(ref mut stats) => stats.record(swap),
+            None => swap(),
+        }
+    }
+
+    /// Swaps the buffers like `swap_buffers`, and returns a token that tells when the GPU has
+    /// finished the frame, which lets the application throttle its frames precisely.
+    ///
+    /// The token is a fence, which needs OpenGL 3.2, OpenGL ES 3.0 or `GL_ARB_sync`. An error is
+    /// returned without swapping if the context doesn't support them. With `set_async_swap`,
+    /// the fence only covers the rendering of the frame, as the swap happens later.
+    pub fn swap_buffers_async(&self) -> Result<SwapToken, ContextError> {
+        let functions = {
+            let mut functions = self.fence_functions.lock().unwrap();
+            if functions.is_none() {
+                *functions = Some(try!(FenceFunctions::load(self)));
+            }
+            functions.unwrap()
+        };
+
+        try!(self.swap_buffers());
+        SwapToken::new(functions)
+    }
+
+    /// Moves the swaps of `swap_buffers` to a presentation thread, which lets the CPU prepare the
+    /// next frame while the driver presents this one. Disabling it waits for the swap in
+    /// progress and returns its result.
+    ///
+    /// `swap_buffers` flushes the commands of the frame and returns once the swap of the
+    /// previous frame is complete, which limits the frames in flight to one. Drawing into the
+    /// default framebuffer must wait for `finish_async_swap`, or the next frame could end up in
+    /// the buffer that is being presented. Framebuffer objects can be drawn into right away.
+    ///
+    /// Only GLX and WGL can swap the buffers from a thread where the context isn't current,
+    /// an error is returned with the other backends.
+    pub fn set_async_swap(&self, enabled: bool) -> Result<(), ContextError> {
+        let presenter = &mut self.swap.lock().unwrap().presenter;
+        if !enabled {
+            return match presenter.take() {
+                Some(mut presenter) => presenter.finish(),
+                None => Ok(()),
+            };
+        }
+
+        if presenter.is_some() {
+            return Ok(());
+        }
+
+        let swap = match self.window.get_swapper() {
+            Some(swap) => swap,
+            None => {
+                return Err(ContextError::NotSupported(
+                           "asynchronous swaps aren't supported by this backend"));
+            },
+        };
+        *presenter = Some(try!(Presenter::new(swap, self.get_proc_address("glFlush"))));
+        Ok(())
+    }
+
+    /// Waits for the swap started by the last `swap_buffers`, if `set_async_swap` enabled them,
+    /// and returns its result.
+    #[inline]
+    pub fn finish_async_swap(&self) -> Result<(), ContextError> {
+        match self.swap.lock().unwrap().presenter {
+            Some(ref mut presenter) => presenter.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// See `GlContext::set_max_frame_rate`.
+    #[inline]
+    pub fn set_max_frame_rate(&self, max_frame_rate: Option<u32>) {
+        self.swap.lock().unwrap().frame_limiter = max_frame_rate.and_then(FrameLimiter::new);
+    }
+
+    /// Starts recording the timing of the frames presented by `swap_buffers`, keeping the last
+    /// `history` of them, or stops recording with `None`. Any frame recorded before is
+    /// discarded.
+    #[inline]
+    pub fn set_frame_stats(&self, history: Option<usize>) {
+        self.swap.lock().unwrap().frame_stats = history.map(FrameStats::new);
+    }
+
+    /// Returns a summary of the frames recorded since `set_frame_stats`, or `None` if the
+    /// recording isn't enabled or no frame has been recorded yet.
+    #[inline]
+    pub fn get_frame_stats(&self) -> Option<FrameStatsSummary> {
+        self.swap.lock().unwrap().frame_stats.as_ref().and_then(|stats| stats.summary())
+    }
+
+    /// Returns the timing of each recorded frame, from the oldest to the most recent.
+    #[inline]
+    pub fn get_frame_timings(&self) -> Vec<FrameTiming> {
+        let state = self.swap.lock().unwrap();
+        state.frame_stats.as_ref().map(|stats| stats.frames()).unwrap_or(Vec::new())
     }
 
     /// DEPRECATED. Gets the native platform specific display for this window.
@@ -465,6 +885,138 @@ impl Window {
         self.window.get_pixel_format()
     }
 
+    /// Returns the versions of OpenGL that couldn't be created before the one of this window.
+    ///
+    /// See `GlContext::get_failed_versions`.
+    #[inline]
+    pub fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.window.get_failed_versions()
+    }
+
+    /// See `GlContext::get_config_limits`.
+    #[inline]
+    pub fn get_config_limits(&self) -> ConfigLimits {
+        self.window.get_config_limits()
+    }
+
+    /// See `GlContext::get_renderer_info`.
+    #[inline]
+    pub fn get_renderer_info(&self) -> RendererInfo {
+        self.window.get_renderer_info()
+    }
+
+    /// See `GlContext::make_not_current`.
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.window.make_not_current()
+    }
+
+    /// Releases the context so that the window can be moved to another thread, where
+    /// `DetachedWindow::attach` makes it current again.
+    ///
+    /// This must be called in the thread where the context is current, if it's current anywhere.
+    /// An asynchronous swap in progress is finished first.
+    pub fn send_to_thread(self) -> Result<DetachedWindow, ContextError> {
+        try!(self.finish_async_swap());
+        if self.is_current() {
+            try!(unsafe { self.make_not_current() });
+        }
+        Ok(DetachedWindow::new(self))
+    }
+
+    /// Destroys the context and the window, and returns the errors that `Drop` has to ignore.
+    ///
+    /// The context is released first if it's current in this thread, and an asynchronous swap
+    /// in progress is finished. This is the way to make sure that the driver is done with the
+    /// context, for example before unloading the library of a plugin that created it.
+    pub fn destroy(self) -> Result<(), ContextError> {
+        // the thread must stop swapping before the context disappears
+        let swap_result = match self.swap.lock().unwrap().presenter.take() {
+            Some(mut presenter) => presenter.finish(),
+            None => Ok(()),
+        };
+        try!(unsafe { self.window.destroy_context() });
+        swap_result
+    }
+
+    /// See `GlContext::is_back_buffer_preserved`.
+    #[inline]
+    pub fn is_back_buffer_preserved(&self) -> bool {
+        self.window.is_back_buffer_preserved()
+    }
+
+    /// See `GlContext::is_front_buffer_rendering`.
+    #[inline]
+    pub fn is_front_buffer_rendering(&self) -> bool {
+        self.window.is_front_buffer_rendering()
+    }
+
+    /// See `GlContext::set_damage_region`.
+    #[inline]
+    pub fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.window.set_damage_region(rects)
+    }
+
+    /// See `GlContext::set_hdr_metadata`.
+    #[inline]
+    pub fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        self.window.set_hdr_metadata(metadata)
+    }
+
+    /// See `GlContext::set_swap_interval`.
+    #[inline]
+    pub fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        self.window.set_swap_interval(interval)
+    }
+
+    /// Creates an offscreen surface of the given dimensions that renders with the OpenGL context
+    /// of this window.
+    ///
+    /// Returns `CreationError::NotSupported` if the backend, or the pixel format of the window,
+    /// doesn't support pbuffers.
+    #[inline]
+    pub fn create_pbuffer(&self, width: u32, height: u32) -> Result<PBuffer, CreationError> {
+        self.window.create_pbuffer((width, height)).map(|p| PBuffer {
+            pbuffer: p,
+            dimensions: (width, height),
+            marker: PhantomData,
+        })
+    }
+
+    /// Makes the context of this window current, drawing into `draw` and reading from `read`.
+    ///
+    /// The pixels read by commands like `glReadPixels`, `glCopyTexSubImage2D` and
+    /// `glBlitFramebuffer` come from `read`, which allows copying between surfaces without
+    /// going through a framebuffer object.
+    ///
+    /// The surfaces can belong to other windows or to pbuffers created from other contexts,
+    /// which lets a single context render into several windows. They must have been created
+    /// with a pixel format that is compatible with the one of this context, and with the same
+    /// backend (for example GLX or EGL), otherwise an error is returned. Some drivers don't
+    /// support reading from another surface than the one drawn into, for example WGL without
+    /// `WGL_ARB_make_current_read`.
+    #[inline]
+    pub unsafe fn make_current_with_surfaces(&self, draw: Surface, read: Surface)
+                                             -> Result<(), ContextError>
+    {
+        self.window.make_current_with_surfaces(draw.to_platform(), read.to_platform())
+    }
+
+    /// Swaps the buffers of `surface`, which should be the surface this context draws into.
+    ///
+    /// `Surface::Default` is the same as `swap_buffers`.
+    #[inline]
+    pub fn swap_surface_buffers(&self, surface: Surface) -> Result<(), ContextError> {
+        match surface {
+            Surface::Default => self.swap_buffers(),
+            Surface::Window(window) => window.swap_buffers(),
+            Surface::PBuffer(pbuffer) => pbuffer.swap_buffers(),
+            #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd", target_os = "netbsd"))]
+            Surface::Pixmap(pixmap) => pixmap.swap_buffers(),
+        }
+    }
+
     /// Create a window proxy for this window, that can be freely
     /// passed to different threads.
     #[inline]
@@ -496,6 +1048,16 @@ impl Window {
         self.window.hidpi_factor()
     }
 
+    /// Returns whether the monitor of the window supports a variable refresh rate, like
+    /// G-Sync, FreeSync or ProMotion, which lets the frame rate float instead of being capped.
+    ///
+    /// `None` means that the platform doesn't report it. This is currently supported on X11
+    /// through the `vrr_capable` property of RandR, on macOS 12 and on iOS.
+    #[inline]
+    pub fn supports_variable_refresh_rate(&self) -> Option<bool> {
+        self.window.supports_variable_refresh_rate()
+    }
+
     /// Changes the position of the cursor in window coordinates.
     #[inline]
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
@@ -541,6 +1103,61 @@ impl GlContext for Window {
     fn get_pixel_format(&self) -> PixelFormat {
         self.get_pixel_format()
     }
+
+    #[inline]
+    fn get_failed_versions(&self) -> Vec<FailedVersion> {
+        self.get_failed_versions()
+    }
+
+    #[inline]
+    fn get_config_limits(&self) -> ConfigLimits {
+        self.get_config_limits()
+    }
+
+    #[inline]
+    fn get_renderer_info(&self) -> RendererInfo {
+        self.get_renderer_info()
+    }
+
+    #[inline]
+    unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.make_not_current()
+    }
+
+    #[inline]
+    unsafe fn destroy_context(&self) -> Result<(), ContextError> {
+        self.window.destroy_context()
+    }
+
+    #[inline]
+    fn is_back_buffer_preserved(&self) -> bool {
+        self.is_back_buffer_preserved()
+    }
+
+    #[inline]
+    fn is_front_buffer_rendering(&self) -> bool {
+        self.is_front_buffer_rendering()
+    }
+
+    #[inline]
+    fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.set_damage_region(rects)
+    }
+
+    #[inline]
+    fn set_hdr_metadata(&self, metadata: &HdrMetadata) -> Result<(), ContextError> {
+        self.set_hdr_metadata(metadata)
+    }
+
+    #[inline]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), ContextError> {
+        self.set_swap_interval(interval)
+    }
+
+    #[inline]
+    fn set_max_frame_rate(&self, max_frame_rate: Option<u32>) {
+        self.set_max_frame_rate(max_frame_rate)
+    }
 }
 
 /// Represents a thread safe subset of operations that can be called